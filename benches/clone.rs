@@ -0,0 +1,50 @@
+//! Compares cloning a large [`Prompt`] with cloning a [`SharedPrompt`], as in
+//! "clone and tweak" patterns.
+use criterion::{criterion_group, criterion_main, Criterion};
+use misanthropic::{
+    client::Overrides,
+    prompt::{
+        message::{Content, Document, Image, MediaType},
+        Message, SharedPrompt, Temperature,
+    },
+    Prompt,
+};
+use std::hint::black_box;
+
+/// A prompt with ~1 MiB of text, a ~1 MiB text document, and a ~1 MiB image.
+fn prompt() -> Prompt<'static> {
+    let text = "All work and no play makes Jack a dull boy. ".repeat(24000);
+    let mut content = Content::default();
+    content.push(Document::text(text.clone()));
+    content.push(Image::from_compressed(MediaType::Png, vec![0u8; 1 << 20]));
+    content.push(text);
+
+    Prompt::default()
+        .system("You are a helpful assistant.")
+        .add_message(Message {
+            role: misanthropic::prompt::message::Role::User,
+            content,
+        })
+}
+
+fn clone(c: &mut Criterion) {
+    let prompt = prompt();
+    let shared = SharedPrompt::new(prompt.clone());
+    let temperature = Temperature::try_from(1.0).unwrap();
+
+    c.bench_function("Prompt clone and tweak", |b| {
+        b.iter(|| black_box(black_box(&prompt).clone().temperature(None)))
+    });
+    c.bench_function("SharedPrompt clone and tweak", |b| {
+        b.iter(|| {
+            black_box(
+                black_box(&shared)
+                    .clone()
+                    .overrides(Overrides::new().temperature(temperature)),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, clone);
+criterion_main!(benches);
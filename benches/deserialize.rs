@@ -0,0 +1,22 @@
+//! Compares copying and borrowing deserialization of a large response.
+use criterion::{criterion_group, criterion_main, Criterion};
+use misanthropic::response::Message;
+use std::hint::black_box;
+
+mod fixtures;
+
+fn deserialize(c: &mut Criterion) {
+    let json = fixtures::image_response_json();
+
+    c.bench_function("Message owned", |b| {
+        b.iter(|| {
+            black_box(serde_json::from_slice::<Message>(black_box(&json)))
+        })
+    });
+    c.bench_function("Message::from_slice", |b| {
+        b.iter(|| black_box(Message::from_slice(black_box(&json))))
+    });
+}
+
+criterion_group!(benches, deserialize);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+//! Synthetic fixtures shared by the benchmarks. Not every benchmark uses every
+//! fixture.
+#![allow(dead_code)]
+
+use serde_json::json;
+
+/// Words to make text from, about one token each.
+const WORDS: &[&str] = &[
+    "All", " work", " and", " no", " play", " makes", " Jack", " a", " dull",
+    " boy", ".", "\n\n",
+];
+
+/// Text of about `tokens` tokens, with paragraphs.
+pub fn text(tokens: usize) -> String {
+    WORDS.iter().cycle().take(tokens).copied().collect()
+}
+
+/// The data of each event of a synthetic SSE stream, in order, exactly as
+/// the API would send it. There is a text block of `tokens` deltas followed
+/// by a tool use block with input of about `tokens / 10` deltas.
+pub fn sse_events(tokens: usize) -> Vec<String> {
+    let mut events = vec![
+        json!({
+            "type": "message_start",
+            "message": {
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-3-5-sonnet-20240620",
+                "content": [],
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": {"input_tokens": 472, "output_tokens": 1},
+            },
+        }),
+        json!({
+            "type": "content_block_start",
+            "index": 0,
+            "content_block": {"type": "text", "text": ""},
+        }),
+        json!({"type": "ping"}),
+    ];
+    events.extend(WORDS.iter().cycle().take(tokens).map(|word| {
+        json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": {"type": "text_delta", "text": word},
+        })
+    }));
+    events.push(json!({"type": "content_block_stop", "index": 0}));
+
+    let input = json!({"text": text(tokens / 10)}).to_string();
+    events.push(json!({
+        "type": "content_block_start",
+        "index": 1,
+        "content_block": {
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "echo",
+            "input": {},
+        },
+    }));
+    let chars: Vec<char> = input.chars().collect();
+    events.extend(chars.chunks(8).map(|chunk| {
+        json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": {
+                "type": "input_json_delta",
+                "partial_json": chunk.iter().collect::<String>(),
+            },
+        })
+    }));
+    events.push(json!({"type": "content_block_stop", "index": 1}));
+
+    events.push(json!({
+        "type": "message_delta",
+        "delta": {"stop_reason": "tool_use", "stop_sequence": null},
+        "usage": {"output_tokens": tokens + tokens / 10},
+    }));
+    events.push(json!({"type": "message_stop"}));
+
+    events.into_iter().map(|event| event.to_string()).collect()
+}
+
+/// A synthetic SSE stream body of [`sse_events`], with `event`, `data`, and
+/// empty lines, exactly as the API would send it.
+pub fn sse(tokens: usize) -> String {
+    let mut body = String::new();
+    for data in sse_events(tokens) {
+        let event: serde_json::Value = serde_json::from_str(&data).unwrap();
+        body.push_str("event: ");
+        body.push_str(event["type"].as_str().unwrap());
+        body.push_str("\ndata: ");
+        body.push_str(&data);
+        body.push_str("\n\n");
+    }
+
+    body
+}
+
+/// A response with ~256 KiB of text and a ~1 MiB base64 image.
+pub fn image_response_json() -> Vec<u8> {
+    let data = "A".repeat(1 << 20);
+
+    serde_json::to_vec(&json!({
+        "id": "msg_1",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-3-5-sonnet-20240620",
+        "content": [
+            {"type": "text", "text": text(1 << 16)},
+            {"type": "image", "source": {
+                "type": "base64",
+                "media_type": "image/png",
+                "data": data,
+            }},
+        ],
+        "stop_reason": "end_turn",
+        "stop_sequence": null,
+        "usage": {"input_tokens": 1, "output_tokens": 2},
+    }))
+    .unwrap()
+}
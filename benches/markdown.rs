@@ -0,0 +1,40 @@
+//! Rendering of large [`Prompt`]s and responses to Markdown.
+use criterion::{criterion_group, criterion_main, Criterion};
+use misanthropic::{
+    markdown::{Cached, Options, ToMarkdown},
+    prompt::Message,
+    Prompt,
+};
+use std::hint::black_box;
+
+mod fixtures;
+
+fn markdown(c: &mut Criterion) {
+    let text = fixtures::text(10_000);
+    let mut prompt = Prompt::default().system("You are a helpful assistant.");
+    for _ in 0..10 {
+        prompt = prompt
+            .add_message(Message::user(text.clone()))
+            .add_message(Message::assistant(text.clone()));
+    }
+    c.bench_function("Prompt markdown", |b| {
+        b.iter(|| black_box(black_box(&prompt).markdown()))
+    });
+    c.bench_function("Prompt markdown_verbose", |b| {
+        b.iter(|| black_box(black_box(&prompt).markdown_verbose()))
+    });
+    let cached = Cached::new(&prompt, Options::default());
+    c.bench_function("Prompt markdown cached", |b| {
+        b.iter(|| black_box(black_box(&cached).markdown()))
+    });
+
+    let json = fixtures::image_response_json();
+    let message: misanthropic::response::Message =
+        serde_json::from_slice(&json).unwrap();
+    c.bench_function("response Message markdown", |b| {
+        b.iter(|| black_box(black_box(&message).markdown()))
+    });
+}
+
+criterion_group!(benches, markdown);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+//! Serialization of large [`Prompt`]s to request bodies.
+use criterion::{criterion_group, criterion_main, Criterion};
+use misanthropic::{
+    prompt::{
+        message::{Content, Image, MediaType, Role},
+        Message,
+    },
+    Prompt,
+};
+use std::hint::black_box;
+
+mod fixtures;
+
+/// A conversation of 100 turns with ~10k tokens of text in each message.
+fn text_prompt() -> Prompt<'static> {
+    let text = fixtures::text(10_000);
+    let mut prompt = Prompt::default().system("You are a helpful assistant.");
+    for _ in 0..50 {
+        prompt = prompt
+            .add_message(Message::user(text.clone()))
+            .add_message(Message::assistant(text.clone()));
+    }
+
+    prompt
+}
+
+/// A prompt with four ~1 MiB images, which are base64 encoded.
+fn image_prompt() -> Prompt<'static> {
+    let mut content = Content::default();
+    for _ in 0..4 {
+        content
+            .push(Image::from_compressed(MediaType::Png, vec![0u8; 1 << 20]));
+    }
+    content.push("What is in these images?");
+
+    Prompt::default().add_message(Message {
+        role: Role::User,
+        content,
+    })
+}
+
+fn serialize(c: &mut Criterion) {
+    let prompt = text_prompt();
+    c.bench_function("Prompt to_vec text", |b| {
+        b.iter(|| black_box(serde_json::to_vec(black_box(&prompt))))
+    });
+
+    let prompt = image_prompt();
+    c.bench_function("Prompt to_vec images", |b| {
+        b.iter(|| black_box(serde_json::to_vec(black_box(&prompt))))
+    });
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
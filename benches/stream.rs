@@ -0,0 +1,69 @@
+//! Parsing and accumulation of a synthetic 10k token stream.
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use futures::StreamExt;
+use misanthropic::stream::{Accumulator, Event, Stream};
+use std::hint::black_box;
+
+mod fixtures;
+
+/// Number of text deltas in the stream.
+const TOKENS: usize = 10_000;
+
+/// Apply `events` and return the message.
+fn accumulate<'a>(
+    events: impl IntoIterator<Item = Event<'a>>,
+) -> misanthropic::response::Message<'a> {
+    let mut acc = Accumulator::new();
+    for event in events {
+        acc.apply_event(event).unwrap();
+    }
+
+    acc.finish().unwrap()
+}
+
+fn stream(c: &mut Criterion) {
+    let data = fixtures::sse_events(TOKENS);
+    c.bench_function("Event parse 10k tokens", |b| {
+        b.iter(|| {
+            for data in black_box(&data) {
+                black_box(serde_json::from_str::<Event>(data).unwrap());
+            }
+        })
+    });
+
+    let events: Vec<Event<'static>> = data
+        .iter()
+        .map(|data| serde_json::from_str::<Event>(data).unwrap().into_static())
+        .collect();
+    c.bench_function("Accumulator 10k tokens", |b| {
+        b.iter_batched(
+            || events.clone(),
+            |events| black_box(accumulate(events)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // The whole pipeline from bytes, in network sized chunks.
+    let body = bytes::Bytes::from(fixtures::sse(TOKENS));
+    c.bench_function("Stream 10k tokens", |b| {
+        b.iter(|| {
+            use eventsource_stream::Eventsource;
+
+            let chunks: Vec<Result<bytes::Bytes, reqwest::Error>> = (0..body
+                .len())
+                .step_by(1 << 14)
+                .map(|i| Ok(body.slice(i..(i + (1 << 14)).min(body.len()))))
+                .collect();
+            let stream =
+                Stream::new(futures::stream::iter(chunks).eventsource());
+            let events: Vec<Event> = futures::executor::block_on(
+                stream.map(|event| event.unwrap()).collect(),
+            );
+
+            black_box(accumulate(events))
+        })
+    });
+}
+
+criterion_group!(benches, stream);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+//! See `source` for an example of a chat backend with `axum`. Each
+//! conversation is kept in memory as a [`Conversation`] and continued with
+//! [`Client::message`].
+//!
+//! ```sh
+//! curl localhost:3000/chat -H 'content-type: application/json' \
+//!     -d '{"id": "demo", "message": "Hi, I am Ada."}'
+//! ```
+
+// Note: This example uses blocking calls for simplicity such as `println!()`
+// and `stdin().lock()`. In a real application, these should *usually* be
+// replaced with async alternatives.
+use axum::{extract::State, routing::post, Json, Router};
+use clap::Parser;
+use misanthropic::{
+    client,
+    prompt::{self, message::Role},
+    response, Client, Conversation, Model,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    io::{stdin, BufRead},
+    sync::{Arc, Mutex},
+};
+
+/// Serve a `/chat` endpoint which remembers conversations by id.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Address to listen on.
+    #[arg(short, long, default_value = "127.0.0.1:3000")]
+    address: String,
+    /// System prompt for new conversations.
+    #[arg(long, default_value = "You are a helpful assistant.")]
+    system: String,
+}
+
+/// Shared state of the handlers.
+#[derive(Clone)]
+struct AppState {
+    client: Client,
+    system: Arc<str>,
+    conversations: Arc<Mutex<HashMap<String, Conversation<'static>>>>,
+}
+
+/// Body of a `/chat` request.
+#[derive(Deserialize)]
+struct ChatRequest {
+    /// Conversation id. A new conversation is started for a new id.
+    id: String,
+    /// User message.
+    message: String,
+}
+
+/// Add the user message to the conversation, continue it, and add and return
+/// the assistant's response. [`response::Message`] and [`client::Error`]
+/// implement `IntoResponse` with the `axum` feature.
+async fn chat(
+    State(state): State<AppState>,
+    Json(request): Json<ChatRequest>,
+) -> Result<response::Message<'static>, client::Error> {
+    // The lock can't be held across `.await`, so the prompt is built first.
+    let prompt = {
+        let mut conversations = state.conversations.lock().unwrap();
+        let conversation =
+            conversations.entry(request.id.clone()).or_insert_with(|| {
+                Conversation::new(Model::Haiku35)
+                    .system(state.system.to_string())
+            });
+        conversation.push(prompt::Message::from((Role::User, request.message)));
+        conversation.prompt()
+    };
+
+    let message = match state.client.message(prompt).await {
+        Ok(message) => message,
+        Err(error) => {
+            // Remove the unanswered message so the conversation can be
+            // retried.
+            if let Some(conversation) =
+                state.conversations.lock().unwrap().get_mut(&request.id)
+            {
+                conversation.entries.pop();
+            }
+            return Err(error);
+        }
+    };
+
+    if let Some(conversation) =
+        state.conversations.lock().unwrap().get_mut(&request.id)
+    {
+        conversation.push(message.clone());
+    }
+
+    Ok(message)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "log")]
+    env_logger::init();
+
+    // Read the command line arguments.
+    let args = Args::parse();
+
+    // Get API key from stdin.
+    println!("Enter your API key:");
+    let key = stdin().lock().lines().next().unwrap()?;
+
+    // Create a client. `key` will be consumed and zeroized.
+    let state = AppState {
+        client: Client::new(key)?,
+        system: args.system.into(),
+        conversations: Default::default(),
+    };
+
+    let app = Router::new().route("/chat", post(chat)).with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&args.address).await?;
+    println!("Listening on http://{}", args.address);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
@@ -7,7 +7,6 @@
 use std::{
     io::{BufRead, Read, Seek, Write},
     time::Duration,
-    vec,
 };
 
 use clap::Parser;
@@ -16,10 +15,7 @@ use subprocess::{Exec, Redirection};
 use misanthropic::{
     json,
     markdown::ToMarkdown,
-    prompt::{
-        message::{Content, Role},
-        Message,
-    },
+    prompt::{message::Role, Message},
     tool, Client, Model, Prompt, Tool,
 };
 
@@ -197,6 +193,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Model::Haiku30
         })
         .add_tool(Tool {
+            kind: None,
             name: "python".into(),
             description: "Run a Python script.".into(),
             input_schema: json!({
@@ -209,6 +206,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 },
                 "required": ["script"],
             }),
+            extra: Default::default(),
             #[cfg(feature = "prompt-caching")]
             cache_control: None,
         })
@@ -220,32 +218,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // not when it isn't. The more examples here, with more varied prompts,
         // the better the Assistant will be at this.
         .messages([
-            Message {
-                role: Role::User,
-                content: "Write a haiku about Python.".into(),
-            },
-            Message {
-                role: Role::Assistant,
-                content: "Elegant syntax\rPowerful and versatile\nPython, my delight.".into(),
-            },
-            Message {
-                role: Role::User,
-                content: "Count the number of r's in 'strawberry'".into(),
-            },
-            Message {
-                role: Role::Assistant,
-                content: Content::MultiPart(vec![
-                    r#"<thinking>I can't do that myself, but I can run a Python script to count the number of r's in "strawberry". The user did not specify case sensitivity so I will default to case insensitive.</thinking>"#.into(),
-                    tool::Use {
-                        id: "calibration_000".into(),
-                        name: "python".into(),
-                        input: json!({
-                            "script": r#"print("strawberry".lower().count("r"))"#
-                        }),
-                        cache_control: None
-                    }.into()
-                ]),
-            },
+            Message::user("Write a haiku about Python."),
+            Message::assistant("Elegant syntax\rPowerful and versatile\nPython, my delight."),
+            Message::user("Count the number of r's in 'strawberry'"),
+            Message::assistant(
+                r#"<thinking>I can't do that myself, but I can run a Python script to count the number of r's in "strawberry". The user did not specify case sensitivity so I will default to case insensitive.</thinking>"#,
+            )
+            .push_block(tool::Use {
+                id: "calibration_000".into(),
+                name: "python".into(),
+                input: json!({
+                    "script": r#"print("strawberry".lower().count("r"))"#
+                }),
+                cache_control: None
+            }),
             tool::Result {
                 tool_use_id: "calibration_000".into(),
                 content: "3".into(),
@@ -255,20 +241,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }.into(),
             (Role::Assistant, r#"The number of r's in "strawberry" is 3.""#).into(),
             (Role::User, "List the permutations of the first four letters of the alphabet.").into(),
-            Message {
-                role: Role::Assistant,
-                content: Content::MultiPart(vec![
-                    r#"<thinking>This request is complex enough to need Python. I should use the itertools module for this..</thinking>"#.into(),
-                    tool::Use {
-                        id: "calibration_001".into(),
-                        name: "python".into(),
-                        input: json!({
-                            "script": r#"import itertools\nprint(','.join("".join(t) for t in itertools.permutations(('a', 'b', 'c', 'd'))))"#
-                        }),
-                        cache_control: None
-                    }.into()
-                ]),
-            },
+            Message::assistant(
+                r#"<thinking>This request is complex enough to need Python. I should use the itertools module for this..</thinking>"#,
+            )
+            .push_block(tool::Use {
+                id: "calibration_001".into(),
+                name: "python".into(),
+                input: json!({
+                    "script": r#"import itertools\nprint(','.join("".join(t) for t in itertools.permutations(('a', 'b', 'c', 'd'))))"#
+                }),
+                cache_control: None
+            }),
             tool::Result {
                 tool_use_id: "calibration_001".into(),
                 content: "abcd,abdc,acbd,acdb,adbc,adcb,bacd,badc,bcad,bcda,bdac,bdca,cabd,cadb,cbad,cbda,cdab,cdba,dabc,dacb,dbac,dbca,dcab,dcba".into(),
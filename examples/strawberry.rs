@@ -89,6 +89,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // signature and docstring. Like many things in our API, `Tool` is also
     // convertable from a `serde_json::Value`.
     let mut chat = Prompt::default().add_tool(Tool {
+        kind: None,
         name: "count_letters".into(),
         description: "Count the number of letters in a word.".into(),
         input_schema: json!({
@@ -105,6 +106,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             },
             "required": ["letter", "string"],
         }),
+        extra: Default::default(),
         #[cfg(feature = "prompt-caching")]
         cache_control: None,
     // Inform the assistant about their limitations.
@@ -0,0 +1,228 @@
+//! [`IntoResponse`] implementations to serve responses from an [`axum`]
+//! backend. This requires the `axum` feature.
+//!
+//! - A [`Stream`] is sent as Server-Sent Events. See [`stream::to_sse`].
+//! - A [`response::Message`] is sent as JSON.
+//! - A [`client::Error`] is sent as a JSON error in the format of the API,
+//!   with a matching status code.
+//!
+//! [`Prompt`] and [`Conversation`] can be extracted from requests with
+//! [`Json`]. For example, to proxy a prompt and stream the response:
+//!
+//! ```
+//! use axum::{extract::State, routing::post, Json, Router};
+//! use misanthropic::{client, Client, Prompt, Stream};
+//!
+//! async fn stream(
+//!     State(client): State<Client>,
+//!     Json(prompt): Json<Prompt<'static>>,
+//! ) -> Result<Stream<'static>, client::Error> {
+//!     client.stream(prompt).await
+//! }
+//!
+//! # let client = Client::new("x".repeat(108)).unwrap();
+//! let app: Router = Router::new()
+//!     .route("/stream", post(stream))
+//!     .with_state(client);
+//! ```
+//!
+//! See the `axum_chat` example for a chat backend wired to a
+//! [`Conversation`].
+//!
+//! [`axum`]: ::axum
+//! [`Conversation`]: crate::Conversation
+use std::convert::Infallible;
+
+use ::axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{sse, IntoResponse, Response, Sse},
+    Json,
+};
+use futures::StreamExt;
+use serde_json::json;
+
+#[allow(unused_imports)] // `Prompt` is used in docs.
+use crate::{
+    client::{self, AnthropicError, Limit, TooLarge},
+    response, stream, Prompt, Stream,
+};
+
+impl From<stream::sse::Event> for sse::Event {
+    fn from(event: stream::sse::Event) -> Self {
+        let frame = sse::Event::default();
+        // `axum` panics on event names with newlines. The API never sends
+        // them, so they are dropped rather than escaped.
+        let frame = match event.event.contains(['\r', '\n']) {
+            true => frame,
+            false if event.event.is_empty() => frame,
+            false => frame.event(event.event),
+        };
+
+        frame.data(event.data)
+    }
+}
+
+/// Sends the events as Server-Sent Events, in the same format as the API.
+/// Errors are sent as `error` events. See [`stream::to_sse`].
+impl IntoResponse for Stream<'static> {
+    fn into_response(self) -> Response {
+        let events = stream::to_sse(self)
+            .map(|event| Ok::<_, Infallible>(sse::Event::from(event)));
+
+        Sse::new(events).into_response()
+    }
+}
+
+/// Sends the message as JSON.
+impl IntoResponse for response::Message<'_> {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
+/// Sends the error as JSON, in the format of API errors.
+///
+/// API errors keep their status code, except authentication and permission
+/// errors, which are about the backend's key rather than the request, and are
+/// sent as `502 Bad Gateway`. A `retry-after` header is forwarded. Other
+/// errors are sent as `502 Bad Gateway`, `504 Gateway Timeout`, or
+/// `413 Payload Too Large` if the [`Limits::request_body`] was exceeded.
+///
+/// [`Limits::request_body`]: client::Limits::request_body
+impl IntoResponse for client::Error {
+    fn into_response(self) -> Response {
+        let message = self.to_string();
+        let other =
+            |status, kind| (status, json!({"type": kind, "message": message}));
+        let (status, error) = match &self {
+            client::Error::Anthropic {
+                error:
+                    AnthropicError::Authentication { .. }
+                    | AnthropicError::Permission { .. },
+                ..
+            } => other(StatusCode::BAD_GATEWAY, "api_error"),
+            client::Error::Anthropic { error, .. } => (
+                StatusCode::from_u16(error.status().get())
+                    .unwrap_or(StatusCode::BAD_GATEWAY),
+                json!(error),
+            ),
+            client::Error::HTTP(e) if e.is_timeout() => {
+                other(StatusCode::GATEWAY_TIMEOUT, "timeout_error")
+            }
+            client::Error::TooLarge(TooLarge {
+                limit: Limit::RequestBody,
+                ..
+            }) => other(StatusCode::PAYLOAD_TOO_LARGE, "request_too_large"),
+            client::Error::HTTP(_) | client::Error::HttpStatus { .. } => {
+                other(StatusCode::BAD_GATEWAY, "http_error")
+            }
+            client::Error::Parse(_) => {
+                other(StatusCode::BAD_GATEWAY, "parse_error")
+            }
+            client::Error::UnexpectedResponse { .. } => {
+                other(StatusCode::BAD_GATEWAY, "unexpected_response_error")
+            }
+            client::Error::TooLarge(_) => {
+                other(StatusCode::BAD_GATEWAY, "too_large_error")
+            }
+        };
+
+        let mut response =
+            (status, Json(json!({"type": "error", "error": error})))
+                .into_response();
+
+        if let client::Error::Anthropic {
+            retry_after: Some(after),
+            ..
+        } = self
+        {
+            if let Ok(value) =
+                HeaderValue::try_from(after.as_secs().to_string())
+            {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::stream::mock_stream_from_data;
+
+    async fn read_body(response: Response) -> String {
+        let bytes = ::axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_into_response() {
+        let stream = mock_stream_from_data(
+            [
+                r#"{"type": "ping"}"#,
+                r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#,
+            ]
+            .map(String::from),
+        );
+
+        let response = stream.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        assert_eq!(
+            read_body(response).await,
+            "event: ping\ndata: {\"type\":\"ping\"}\n\n\
+             event: error\n\
+             data: {\"error\":{\"message\":\"Overloaded\",\
+             \"type\":\"overloaded_error\"},\"type\":\"error\"}\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_into_response() {
+        let response = client::Error::Anthropic {
+            error: AnthropicError::RateLimit {
+                message: "Slow down.".into(),
+            },
+            retry_after: Some(Duration::from_secs(30)),
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        let body: serde_json::Value =
+            serde_json::from_str(&read_body(response).await).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "type": "error",
+                "error": {"type": "rate_limit_error", "message": "Slow down."}
+            })
+        );
+
+        // The backend's key is not the caller's problem.
+        let response = client::Error::from(AnthropicError::Authentication {
+            message: "Invalid key.".into(),
+        })
+        .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        let body: serde_json::Value =
+            serde_json::from_str(&read_body(response).await).unwrap();
+        assert_eq!(body["error"]["type"], "api_error");
+
+        let response = client::Error::TooLarge(TooLarge {
+            limit: Limit::RequestBody,
+            size: 2,
+            max: 1,
+        })
+        .into_response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
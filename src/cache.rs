@@ -0,0 +1,281 @@
+//! Plan prompt caching breakpoints with a [`Planner`].
+//!
+//! Cache breakpoints apply to the full prefix of a [`Prompt`] in the order of
+//! `tools`, `system`, and `messages`, and the API allows at most
+//! [`MAX_BREAKPOINTS`]. A cached prefix is only reused if it is unchanged, so
+//! the [`Planner`] puts breakpoints at the ends of the parts which change
+//! least often: the tools, the system prompt, the history before the latest
+//! message, and the latest message, for the next request. Prefixes too short
+//! to be cached are skipped.
+//!
+//! ```
+//! use misanthropic::{cache::Planner, prompt::Message, Prompt};
+//!
+//! let mut prompt = Prompt::default()
+//!     .system("You are a helpful assistant. ".repeat(500))
+//!     .add_message(Message::user("Hi!"));
+//!
+//! let plan = Planner::new().plan(&prompt);
+//! assert_eq!(plan.breakpoints.len(), 2);
+//! println!("{} cached, {} fresh", plan.cached_tokens, plan.fresh_tokens);
+//! plan.apply(&mut prompt);
+//! ```
+//!
+//! Tokens are estimated with [`throttle::estimate_input_tokens`].
+use crate::{throttle, Model, Prompt};
+
+/// Maximum number of cache breakpoints in a request.
+pub const MAX_BREAKPOINTS: usize = 4;
+
+/// Where a [`Breakpoint`] is placed. The breakpoint is on the last block of
+/// the part.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Position {
+    /// The last tool.
+    Tools,
+    /// The end of the system prompt.
+    System,
+    /// The end of the message at this index.
+    Message(usize),
+}
+
+/// A cache breakpoint chosen by a [`Planner`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    /// Where the breakpoint is.
+    pub position: Position,
+    /// Estimated tokens in the prefix up to and including the breakpoint.
+    pub tokens: u64,
+}
+
+/// Cache breakpoints for a [`Prompt`], from [`Planner::plan`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Breakpoints, in prompt order.
+    pub breakpoints: Vec<Breakpoint>,
+    /// Estimated tokens which are read from the cache once it is written,
+    /// which is the prefix up to the last breakpoint.
+    pub cached_tokens: u64,
+    /// Estimated tokens after the last breakpoint, which are never cached.
+    pub fresh_tokens: u64,
+}
+
+impl Plan {
+    /// Replace the cache breakpoints in `prompt` with the planned ones. The
+    /// `prompt` should be the one the plan was made for.
+    pub fn apply(&self, prompt: &mut Prompt<'_>) {
+        for tool in prompt.tools.iter_mut().flatten() {
+            tool.cache_control = None;
+        }
+        if let Some(system) = prompt.system.as_mut() {
+            system.uncache();
+        }
+        for message in &mut prompt.messages {
+            message.content.uncache();
+        }
+
+        for breakpoint in &self.breakpoints {
+            match breakpoint.position {
+                Position::Tools => {
+                    if let Some(tool) =
+                        prompt.tools.as_mut().and_then(|tools| tools.last_mut())
+                    {
+                        tool.cache();
+                    }
+                }
+                Position::System => {
+                    if let Some(system) = prompt.system.as_mut() {
+                        system.cache();
+                    }
+                }
+                Position::Message(index) => {
+                    if let Some(message) = prompt.messages.get_mut(index) {
+                        message.content.cache();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Chooses cache breakpoints for a [`Prompt`]. See the [module](self)
+/// documentation.
+#[derive(Clone, Debug)]
+pub struct Planner {
+    max_breakpoints: usize,
+    min_tokens: Option<u64>,
+}
+
+impl Default for Planner {
+    fn default() -> Self {
+        Self {
+            max_breakpoints: MAX_BREAKPOINTS,
+            min_tokens: None,
+        }
+    }
+}
+
+impl Planner {
+    /// Create a [`Planner`] with the defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of breakpoints, for example to leave some for
+    /// manual placement. At most [`MAX_BREAKPOINTS`] are used. If there are
+    /// more candidates, the earlier ones, which change less often, are kept.
+    pub fn max_breakpoints(mut self, max: usize) -> Self {
+        self.max_breakpoints = max.min(MAX_BREAKPOINTS);
+        self
+    }
+
+    /// Set the minimum prefix tokens for a breakpoint. The default is
+    /// [`Planner::min_tokens_for`] the [`Prompt::model`].
+    pub fn min_tokens(mut self, min: u64) -> Self {
+        self.min_tokens = Some(min);
+        self
+    }
+
+    /// Minimum prefix tokens the API caches for a [`Model`]. Shorter
+    /// prefixes are not cached.
+    pub const fn min_tokens_for(model: Model) -> u64 {
+        match model {
+            Model::Haiku30 | Model::Haiku35 | Model::Haiku35_20241022 => 2048,
+            _ => 1024,
+        }
+    }
+
+    /// Choose the breakpoints for `prompt` and estimate the cached and fresh
+    /// tokens.
+    pub fn plan(&self, prompt: &Prompt<'_>) -> Plan {
+        let estimate = |key: &str, value: serde_json::Value| {
+            let mut body = serde_json::Map::new();
+            body.insert(key.to_string(), value);
+            throttle::estimate_input_tokens(&body.into())
+        };
+        let min_tokens = self
+            .min_tokens
+            .unwrap_or_else(|| Self::min_tokens_for(prompt.model));
+
+        // Candidates in prompt order, with their prefix tokens.
+        let mut candidates = vec![];
+        let mut total = 0;
+        if let Some(tools) = prompt.tools.as_ref().filter(|t| !t.is_empty()) {
+            total += estimate("tools", serde_json::json!(tools));
+            candidates.push((Position::Tools, total));
+        }
+        if let Some(system) = prompt.system.as_ref().filter(|s| !s.is_empty()) {
+            total += estimate("system", serde_json::json!(system));
+            candidates.push((Position::System, total));
+        }
+        let count = prompt.messages.len();
+        for (index, message) in prompt.messages.iter().enumerate() {
+            total += estimate("messages", serde_json::json!([message]));
+            // The history before the latest message, and the latest message.
+            if index + 2 >= count {
+                candidates.push((Position::Message(index), total));
+            }
+        }
+
+        let breakpoints: Vec<_> = candidates
+            .into_iter()
+            .filter(|&(_, tokens)| tokens >= min_tokens)
+            .take(self.max_breakpoints)
+            .map(|(position, tokens)| Breakpoint { position, tokens })
+            .collect();
+        let cached_tokens = breakpoints.last().map_or(0, |b| b.tokens);
+
+        Plan {
+            breakpoints,
+            cached_tokens,
+            fresh_tokens: total - cached_tokens,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prompt::Message, Tool};
+
+    fn tool() -> Tool<'static> {
+        Tool::builder("lookup")
+            .description("Look something up. ".repeat(300))
+            .param_string("query", "What to look up.", true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plan() {
+        let long = "All work and no play. ".repeat(300);
+        let mut prompt = Prompt::default()
+            .model(Model::Sonnet35)
+            .add_tool(tool())
+            .system(long.as_str())
+            .add_message(Message::user(long.as_str()))
+            .add_message(Message::assistant("Ok."))
+            .add_message(Message::user("Hi!"))
+            .cache();
+
+        let plan = Planner::new().plan(&prompt);
+        let positions: Vec<_> =
+            plan.breakpoints.iter().map(|b| b.position).collect();
+        assert_eq!(
+            positions,
+            [
+                Position::Tools,
+                Position::System,
+                Position::Message(1),
+                Position::Message(2)
+            ]
+        );
+        assert!(plan
+            .breakpoints
+            .windows(2)
+            .all(|w| w[0].tokens < w[1].tokens));
+        // Everything is before the last breakpoint.
+        assert_eq!(plan.fresh_tokens, 0);
+        assert_eq!(plan.cached_tokens, plan.breakpoints[3].tokens);
+
+        plan.apply(&mut prompt);
+        assert!(prompt.tools.as_ref().unwrap()[0].is_cached());
+        assert!(prompt.system.as_ref().unwrap().last().unwrap().is_cached());
+        assert!(!prompt.messages[0].content.last().unwrap().is_cached());
+        assert!(prompt.messages[1].content.last().unwrap().is_cached());
+        assert!(prompt.messages[2].content.last().unwrap().is_cached());
+
+        // Earlier breakpoints are kept.
+        let plan = Planner::new().max_breakpoints(2).plan(&prompt);
+        assert_eq!(plan.breakpoints[1].position, Position::System);
+        assert!(plan.fresh_tokens > 0);
+        assert_eq!(
+            plan.cached_tokens + plan.fresh_tokens,
+            Planner::new().plan(&prompt).cached_tokens
+        );
+        plan.apply(&mut prompt);
+        assert!(!prompt.messages[2].content.last().unwrap().is_cached());
+    }
+
+    #[test]
+    fn test_plan_min_tokens() {
+        let prompt = Prompt::default()
+            .model(Model::Sonnet35)
+            .system("Short.")
+            .add_message(Message::user("All work and no play. ".repeat(300)));
+
+        // The system prompt is too short.
+        let plan = Planner::new().plan(&prompt);
+        assert_eq!(plan.breakpoints.len(), 1);
+        assert_eq!(plan.breakpoints[0].position, Position::Message(0));
+
+        // Haiku needs more tokens.
+        let plan = Planner::new().plan(&prompt.clone().model(Model::Haiku35));
+        assert_eq!(plan.breakpoints, []);
+        assert_eq!(plan.cached_tokens, 0);
+        assert!(plan.fresh_tokens > 1024);
+
+        let plan = Planner::new().min_tokens(1).plan(&prompt);
+        assert_eq!(plan.breakpoints[0].position, Position::System);
+    }
+}
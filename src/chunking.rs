@@ -0,0 +1,380 @@
+//! Split long texts into [`Block::Text`] chunks for long-context prompts and
+//! retrieval. See Anthropic's [long context tips].
+//!
+//! Chunks are slices of the original text, so joining them gives back the
+//! original. Text is split at sentence and word boundaries when possible.
+//!
+//! ```
+//! use misanthropic::chunking::Chunker;
+//!
+//! let text = "First sentence. Second sentence. Third sentence.";
+//! let chunker = Chunker::sentences(35);
+//! assert_eq!(
+//!     chunker.split(text),
+//!     ["First sentence. Second sentence. ", "Third sentence."]
+//! );
+//!
+//! // Wrapped in `<document index="1">`, etc.
+//! let blocks = chunker.documents(true).source("notes.txt").blocks(text);
+//! assert_eq!(blocks.len(), 2);
+//! ```
+//!
+//! [long context tips]: <https://docs.anthropic.com/en/docs/build-with-claude/prompt-engineering/long-context-tips>
+use crate::prompt::message::Block;
+
+/// Estimates the number of tokens in a text, for [`Chunker::tokens`]. This
+/// is implemented for closures, so a real tokenizer can be plugged in.
+pub trait TokenEstimator {
+    /// Estimate the number of tokens in `text`.
+    fn estimate_tokens(&self, text: &str) -> usize;
+}
+
+impl<F> TokenEstimator for F
+where
+    F: Fn(&str) -> usize,
+{
+    fn estimate_tokens(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+/// Rough token estimate of about four characters per token, which is typical
+/// for English text. Use a real tokenizer if accuracy matters.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// How a [`Chunker`] measures and splits text.
+#[derive(Clone, Copy)]
+enum Split<'e> {
+    /// At most this many [`char`]s, split at words.
+    Chars(usize),
+    /// At most this many [`char`]s, split at sentences.
+    Sentences(usize),
+    /// At most this many estimated tokens, split at sentences.
+    Tokens {
+        max: usize,
+        estimator: &'e dyn TokenEstimator,
+    },
+}
+
+/// Granularity of segments, from coarsest to finest.
+#[derive(Clone, Copy, PartialEq)]
+enum Level {
+    Sentence,
+    Word,
+    Char,
+}
+
+impl Level {
+    fn finer(self) -> Self {
+        match self {
+            Self::Sentence => Self::Word,
+            Self::Word | Self::Char => Self::Char,
+        }
+    }
+}
+
+/// Splits text into chunks. See the [module](self) documentation.
+#[derive(Clone)]
+pub struct Chunker<'e> {
+    split: Split<'e>,
+    documents: bool,
+    source: Option<String>,
+    #[cfg(feature = "prompt-caching")]
+    cache_every: usize,
+}
+
+impl<'e> Chunker<'e> {
+    fn new(split: Split<'e>) -> Self {
+        Self {
+            split,
+            documents: false,
+            source: None,
+            #[cfg(feature = "prompt-caching")]
+            cache_every: 0,
+        }
+    }
+
+    /// Chunks of at most `max` [`char`]s, split at word boundaries when
+    /// possible.
+    pub fn chars(max: usize) -> Self {
+        Self::new(Split::Chars(max))
+    }
+
+    /// Chunks of at most `max` [`char`]s, split at sentence boundaries when
+    /// possible. Sentences end with `.`, `!`, or `?` followed by whitespace,
+    /// or at a line break.
+    pub fn sentences(max: usize) -> Self {
+        Self::new(Split::Sentences(max))
+    }
+
+    /// Chunks of at most `max` tokens as estimated by `estimator`, split at
+    /// sentence boundaries when possible. See [`estimate_tokens`] for a
+    /// rough estimate.
+    pub fn tokens(max: usize, estimator: &'e dyn TokenEstimator) -> Self {
+        Self::new(Split::Tokens { max, estimator })
+    }
+
+    /// Wrap each chunk in `<document>` tags with an `index` (from 1) and
+    /// `<document_content>`, as recommended for long documents.
+    pub fn documents(mut self, documents: bool) -> Self {
+        self.documents = documents;
+        self
+    }
+
+    /// Add a `<source>` to each `<document>`. Only used with
+    /// [`Chunker::documents`].
+    pub fn source<S>(mut self, source: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Add a cache breakpoint to every `n`th [`Block`]. Set to 0 to disable
+    /// (the default). Note the API allows at most 4 breakpoints per prompt.
+    #[cfg(feature = "prompt-caching")]
+    pub fn cache_every(mut self, n: usize) -> Self {
+        self.cache_every = n;
+        self
+    }
+
+    /// Split `text` into chunks. Joining them gives back `text`. A single
+    /// [`char`] is never split, so it may exceed the limit on its own.
+    pub fn split<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let level = match self.split {
+            Split::Chars(_) => Level::Word,
+            Split::Sentences(_) | Split::Tokens { .. } => Level::Sentence,
+        };
+
+        let mut chunks = vec![];
+        self.pack(text, level, &mut chunks);
+        chunks
+    }
+
+    /// Split `text` into [`Block::Text`] chunks. Text is borrowed unless
+    /// [`Chunker::documents`] is set.
+    pub fn blocks<'t>(&self, text: &'t str) -> Vec<Block<'t>> {
+        #[allow(unused_mut)]
+        let mut blocks: Vec<Block<'t>> = self
+            .split(text)
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| match self.documents {
+                true => Block::text(self.document(i + 1, chunk)),
+                false => Block::text(chunk),
+            })
+            .collect();
+
+        #[cfg(feature = "prompt-caching")]
+        if self.cache_every > 0 {
+            blocks
+                .iter_mut()
+                .skip(self.cache_every - 1)
+                .step_by(self.cache_every)
+                .for_each(Block::cache);
+        }
+
+        blocks
+    }
+
+    /// Wrap a chunk in `<document>` tags.
+    fn document(&self, index: usize, chunk: &str) -> String {
+        let source = match &self.source {
+            Some(source) => format!("<source>{}</source>\n", source),
+            None => String::new(),
+        };
+        format!(
+            "<document index=\"{}\">\n{}<document_content>\n{}\n</document_content>\n</document>",
+            index,
+            source,
+            chunk.trim_end()
+        )
+    }
+
+    /// Returns true if `text` is within the limit.
+    fn fits(&self, text: &str) -> bool {
+        match self.split {
+            Split::Chars(max) | Split::Sentences(max) => {
+                text.chars().nth(max).is_none()
+            }
+            Split::Tokens { max, estimator } => {
+                estimator.estimate_tokens(text) <= max
+            }
+        }
+    }
+
+    /// Greedily pack segments of `text` at `level` into `chunks`, splitting
+    /// segments which don't fit at a finer level.
+    fn pack<'t>(&self, text: &'t str, level: Level, chunks: &mut Vec<&'t str>) {
+        // Start of the current chunk and the end of what fits so far.
+        let mut start = 0;
+        let mut end = 0;
+
+        for (seg_start, seg_end) in segments(text, level) {
+            if self.fits(&text[start..seg_end]) {
+                end = seg_end;
+                continue;
+            }
+            if end > start {
+                chunks.push(&text[start..end]);
+            }
+            start = seg_start;
+            end = seg_end;
+
+            let segment = &text[seg_start..seg_end];
+            if !self.fits(segment) && level != Level::Char {
+                self.pack(segment, level.finer(), chunks);
+                // Continue from the last, possibly partial, piece.
+                if let Some(last) = chunks.pop() {
+                    start = seg_end - last.len();
+                }
+            }
+        }
+
+        if end > start {
+            chunks.push(&text[start..end]);
+        }
+    }
+}
+
+/// Byte ranges of the segments of `text` at `level`. Segments include
+/// trailing whitespace, so they cover all of `text`.
+fn segments(text: &str, level: Level) -> Vec<(usize, usize)> {
+    if level == Level::Char {
+        return text
+            .char_indices()
+            .map(|(i, c)| (i, i + c.len_utf8()))
+            .collect();
+    }
+
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut prev = ' ';
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if !c.is_whitespace() {
+            prev = c;
+            continue;
+        }
+
+        // Consume the run of whitespace.
+        let mut newline = c == '\n';
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, c)) = chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            newline |= c == '\n';
+            end = j + c.len_utf8();
+            chars.next();
+        }
+
+        let boundary = match level {
+            Level::Sentence => newline || matches!(prev, '.' | '!' | '?'),
+            Level::Word | Level::Char => true,
+        };
+        if boundary {
+            ranges.push((start, end));
+            start = end;
+        }
+    }
+
+    if start < text.len() {
+        ranges.push((start, text.len()));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text<'a>(block: &'a Block) -> &'a str {
+        match block {
+            Block::Text { text, .. } => text,
+            _ => panic!("not text"),
+        }
+    }
+
+    const TEXT: &str = "One two three. Four five!\n\nSix seven eight nine ten eleven twelve. Thirteen?";
+
+    #[test]
+    fn test_split_sentences() {
+        let chunks = Chunker::sentences(30).split(TEXT);
+        assert_eq!(
+            chunks,
+            [
+                "One two three. Four five!\n\n",
+                "Six seven eight nine ten ",
+                "eleven twelve. Thirteen?",
+            ]
+        );
+        assert_eq!(chunks.concat(), TEXT);
+    }
+
+    #[test]
+    fn test_split_chars() {
+        let chunks = Chunker::chars(10).split(TEXT);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 10));
+        assert_eq!(chunks[0], "One two ");
+        assert_eq!(chunks.concat(), TEXT);
+
+        // Words longer than the limit are split.
+        assert_eq!(
+            Chunker::chars(3).split("abcdefg hi"),
+            ["abc", "def", "g ", "hi"]
+        );
+        assert!(Chunker::chars(3).split("").is_empty());
+    }
+
+    #[test]
+    fn test_split_tokens() {
+        let words = |text: &str| text.split_whitespace().count();
+        let chunks = Chunker::tokens(5, &words).split(TEXT);
+        assert!(chunks.iter().all(|c| words(c) <= 5));
+        assert_eq!(chunks[0], "One two three. Four five!\n\n");
+        assert_eq!(chunks.concat(), TEXT);
+
+        assert_eq!(estimate_tokens("12345678"), 2);
+        assert_eq!(estimate_tokens("123456789"), 3);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let blocks = Chunker::sentences(30).documents(true).blocks(TEXT);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(
+            text(&blocks[0]),
+            "<document index=\"1\">\n<document_content>\nOne two three. Four five!\n</document_content>\n</document>"
+        );
+
+        let blocks = Chunker::sentences(30).source("a.txt").blocks(TEXT);
+        assert_eq!(text(&blocks[2]), "eleven twelve. Thirteen?");
+    }
+
+    #[test]
+    fn test_document_source() {
+        let chunker = Chunker::chars(100).documents(true).source("a.txt");
+        assert_eq!(
+            chunker.document(2, "Hi.\n"),
+            "<document index=\"2\">\n<source>a.txt</source>\n<document_content>\nHi.\n</document_content>\n</document>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "prompt-caching")]
+    fn test_blocks_cache_every() {
+        let blocks = Chunker::chars(10).cache_every(2).blocks(TEXT);
+        let cached: Vec<bool> = blocks.iter().map(Block::is_cached).collect();
+        assert!(cached.len() > 4);
+        assert!(cached
+            .iter()
+            .enumerate()
+            .all(|(i, &cached)| cached == (i % 2 == 1)));
+    }
+}
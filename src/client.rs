@@ -1,11 +1,32 @@
 //! [`Client`] for the Anthropic Messages API and related types.
 
-use std::{env, num::NonZeroU16, sync::Arc};
+use std::{env, num::NonZeroU16, sync::Arc, time::Duration};
 
 use eventsource_stream::Eventsource;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 
-use crate::{key, response, Key};
+use crate::{
+    key,
+    prompt::message::Block,
+    redact::{self, Redactor},
+    response,
+    throttle::{self, RateLimiter},
+    Key,
+};
+
+#[cfg(feature = "log")]
+mod logging;
+#[cfg(feature = "log")]
+pub use logging::LogPolicy;
+mod overrides;
+pub use overrides::Overrides;
+pub mod pagination;
+pub use pagination::Paginator;
+pub mod sample;
+pub use sample::Selector;
+mod workspace;
+pub use workspace::Workspace;
 
 /// Result type for the client. See also [`Error`].
 pub type Result<T> = std::result::Result<T, Error>;
@@ -19,7 +40,7 @@ pub struct Client {
     /// Inner [`reqwest::Client`]. Be aware that setting this to a custom client
     /// without the appropriate headers (such as `anthropic-version`) will
     /// result in rejected requests. It is **not necessary** to set the API key
-    /// on a custom client.
+    /// on a custom client. Prefer [`Self::builder`] to add headers.
     ///
     /// ## Note:
     /// - The API [`Key`] is **set automatically on requests**. Set
@@ -30,22 +51,70 @@ pub struct Client {
     /// Encrypted API [`Key`] for convenience. It can be set to a new [`Key`] to
     /// change the key used for requests.
     pub key: Arc<Key>,
+    /// Metadata merged into every prompt sent by this client, for example
+    /// `user_id`. Keys already set in a prompt's metadata take precedence.
+    /// See [`Self::with_default_metadata`].
+    pub default_metadata: Arc<serde_json::Map<String, serde_json::Value>>,
+    /// Optional [`RateLimiter`] shared by all clones of this client. See
+    /// [`Self::with_rate_limiter`].
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Size [`Limits`] for requests and responses. Unlimited by default. See
+    /// [`Self::with_limits`].
+    pub limits: Limits,
+    /// Optional [`Redactor`] applied to every prompt before it is sent. See
+    /// [`Self::with_redactor`].
+    pub redactor: Option<Arc<dyn Redactor>>,
+    /// Whether the [`Self::redactor`] is also applied to message responses.
+    /// See [`Self::with_response_redaction`].
+    pub redact_responses: bool,
+    /// What is logged about request bodies. This requires the `log` feature.
+    /// See [`Self::with_log_policy`].
+    #[cfg(feature = "log")]
+    pub log_policy: LogPolicy,
+    /// [`Workspace`] this client is scoped to, if any. See
+    /// [`Self::for_workspace`].
+    pub workspace: Option<Arc<Workspace>>,
 }
 
 /// Claude client. Uses the Messages API and the prompt caching beta.
+/// Beta required for prompt caching, as a literal for use in `concat!`.
+#[cfg(feature = "prompt-caching")]
+macro_rules! prompt_caching_beta {
+    () => {
+        "prompt-caching-2024-07-31"
+    };
+}
+
 impl Client {
     /// Version of the API. This is appended to the header as
     /// "anthropic-version".
     pub const ANTHROPIC_VERSION: &'static str = "2023-06-01";
-    /// Beta we are using. This is appended to the header as "anthropic-beta".
-    #[cfg(feature = "prompt-caching")]
-    pub const BETA: &'static str = "prompt-caching-2024-07-31";
+    /// Betas we are using. This is appended to the header as
+    /// "anthropic-beta".
+    #[cfg(all(feature = "prompt-caching", not(feature = "code-execution")))]
+    pub const BETA: &'static str = prompt_caching_beta!();
+    /// Betas we are using. This is appended to the header as
+    /// "anthropic-beta".
+    #[cfg(all(feature = "code-execution", not(feature = "prompt-caching")))]
+    pub const BETA: &'static str = crate::tool::code_execution::BETA;
+    /// Betas we are using. This is appended to the header as
+    /// "anthropic-beta".
+    #[cfg(all(feature = "prompt-caching", feature = "code-execution"))]
+    pub const BETA: &'static str = concat!(
+        prompt_caching_beta!(),
+        ",",
+        crate::tool::code_execution::beta!()
+    );
     /// Our user agent.
     pub const USER_AGENT: &'static str =
         concat!(env!("CARGO_PKG_NAME"), "-", env!("CARGO_PKG_VERSION"));
     /// Default URL for the Messages API.
     pub const DEFAULT_URL: &'static str =
         "https://api.anthropic.com/v1/messages";
+    /// Header for the [`RequestOptions::idempotency_key`].
+    pub const IDEMPOTENCY_KEY: &'static str = "idempotency-key";
+    /// Header for the [`Workspace::id`]. See [`Self::for_workspace`].
+    pub const WORKSPACE_ID: &'static str = "x-workspace-id";
 
     /// Create a new client from any type that can be converted into a [`Key`].
     ///
@@ -62,46 +131,108 @@ impl Client {
 
     /// Create a new client with the given key.
     pub fn from_key(key: Key) -> Self {
-        #[cfg(feature = "log")]
-        {
-            log::info!(concat!(
-                "Creating ",
-                env!("CARGO_PKG_NAME", " client...")
-            ));
-            log::debug!(concat!("Crate version: ", env!("CARGO_PKG_VERSION")));
-            log::debug!("Anthropic version: {}", Self::ANTHROPIC_VERSION);
-            #[cfg(feature = "beta")]
-            log::debug!("Anthropic beta: {}", Self::BETA);
-        }
+        // This can only fail if the TLS backend cannot be initialized, in
+        // which case `reqwest::Client::new` would panic as well.
+        ClientBuilder::new(key).build().unwrap()
+    }
 
-        // Headers for all requests.
-        let mut headers = reqwest::header::HeaderMap::new();
+    /// Create a [`ClientBuilder`] from any type that can be converted into a
+    /// [`Key`], to add a [`ClientBuilder::user_agent_suffix`] or
+    /// [`ClientBuilder::default_header`]s.
+    ///
+    /// ```
+    /// use misanthropic::Client;
+    ///
+    /// # let key = "x".repeat(108);
+    /// let client = Client::builder(key)?
+    ///     .user_agent_suffix("my-app/1.0")
+    ///     .try_default_header("x-gateway-route", "claude")?
+    ///     .build()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn builder<K>(
+        key: K,
+    ) -> std::result::Result<ClientBuilder, key::InvalidKeyLength>
+    where
+        K: TryInto<Key, Error = key::InvalidKeyLength>,
+    {
+        Ok(ClientBuilder::new(key.try_into()?))
+    }
 
-        // Content type needs to be set to JSON.
-        headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            reqwest::header::HeaderValue::from_static("application/json"),
-        );
+    /// Set metadata to merge into every prompt sent by this client, such as
+    /// a `user_id` for abuse detection. Keys already set in a prompt's
+    /// metadata take precedence. Do not use PII here (email, phone).
+    pub fn with_default_metadata(
+        mut self,
+        metadata: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        self.default_metadata = Arc::new(metadata);
+        self
+    }
 
-        // Anthropic version needs to be set.
-        headers.insert(
-            "anthropic-version",
-            reqwest::header::HeaderValue::from_static(Self::ANTHROPIC_VERSION),
-        );
+    /// Queue requests with a [`RateLimiter`] instead of sending them
+    /// immediately and having them rejected once rate limited. The limiter is
+    /// updated from the rate limit headers of every response.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(Arc::new(limiter));
+        self
+    }
 
-        // Enable prompt caching beta.
-        #[cfg(feature = "prompt-caching")]
-        headers.insert(
-            "anthropic-beta",
-            reqwest::header::HeaderValue::from_static(Self::BETA),
-        );
+    /// Set size [`Limits`] so oversized requests fail before they are sent
+    /// and oversized responses fail while they are received, with
+    /// [`Error::TooLarge`] (or [`stream::Error::TooLarge`] when streaming).
+    ///
+    /// [`stream::Error::TooLarge`]: crate::stream::Error::TooLarge
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
 
-        Self {
-            inner: reqwest::Client::builder()
-                .default_headers(headers)
-                .build()
-                .unwrap(),
-            key: Arc::new(key),
+    /// Scrub prompts with a [`Redactor`] before they are sent, for example
+    /// to remove personal information. See the [`redact`] module.
+    pub fn with_redactor<R>(mut self, redactor: R) -> Self
+    where
+        R: Redactor + 'static,
+    {
+        self.redactor = Some(Arc::new(redactor));
+        self
+    }
+
+    /// Also apply the [`Self::redactor`] to [`response::Message`]s. Streams
+    /// and [`Self::message_body`] are not redacted since text may be split
+    /// across events. The default is `false`.
+    pub fn with_response_redaction(mut self, redact: bool) -> Self {
+        self.redact_responses = redact;
+        self
+    }
+
+    /// Set what is logged about request bodies at debug level. The default
+    /// is [`LogPolicy::Metadata`], which logs no content. This requires the
+    /// `log` feature.
+    #[cfg(feature = "log")]
+    pub fn with_log_policy(mut self, policy: LogPolicy) -> Self {
+        self.log_policy = policy;
+        self
+    }
+
+    /// Merge [`Self::default_metadata`] into the `metadata` of a prompt.
+    fn merge_metadata(&self, json: &mut serde_json::Value) {
+        if self.default_metadata.is_empty() {
+            return;
+        }
+        let Some(prompt) = json.as_object_mut() else {
+            return;
+        };
+
+        let metadata = prompt
+            .entry("metadata")
+            .or_insert_with(|| serde_json::Value::Object(Default::default()));
+        if let Some(metadata) = metadata.as_object_mut() {
+            for (key, value) in self.default_metadata.iter() {
+                if !metadata.contains_key(key) {
+                    metadata.insert(key.clone(), value.clone());
+                }
+            }
         }
     }
 
@@ -127,7 +258,13 @@ impl Client {
                 .unwrap();
         val.set_sensitive(true);
 
-        self.inner.request(method, url).header("x-api-key", val)
+        let req = self.inner.request(method, url).header("x-api-key", val);
+        match &self.workspace {
+            Some(workspace) => {
+                req.header(Self::WORKSPACE_ID, workspace.id.as_str())
+            }
+            None => req,
+        }
     }
 
     /// Send a GET request with the API key set as a sensitive header value.
@@ -148,12 +285,32 @@ impl Client {
         U: reqwest::IntoUrl,
         B: serde::Serialize,
     {
-        let req = self.request_raw(reqwest::Method::POST, url);
+        self.post_with(url, body, &RequestOptions::none()).await
+    }
+
+    /// Send a POST request like [`Self::post`] with [`RequestOptions`].
+    async fn post_with<U, B>(
+        &self,
+        url: U,
+        body: B,
+        options: &RequestOptions,
+    ) -> reqwest::Result<reqwest::Response>
+    where
+        U: reqwest::IntoUrl,
+        B: serde::Serialize,
+    {
+        let mut req = self.request_raw(reqwest::Method::POST, url);
+        if let Some(key) = &options.idempotency_key {
+            req = req.header(Self::IDEMPOTENCY_KEY, key.as_str());
+        }
 
         #[cfg(feature = "log")]
         {
-            if let Ok(json) = serde_json::to_string_pretty(&body) {
-                log::debug!("Sending body:\n{}", json);
+            if let Ok(json) = serde_json::to_value(&body) {
+                if let Some(body) = logging::format_body(self.log_policy, &json)
+                {
+                    log::debug!("Sending body:\n{}", body);
+                }
             } else {
                 log::warn!("Could not serialize body. Request will fail.");
             }
@@ -177,7 +334,10 @@ impl Client {
     /// [`Request`]: crate::prompt
     /// [`Message`]: crate::Message
     /// [`Stream`]: crate::Stream
-    pub async fn request<P>(&self, prompt: P) -> Result<crate::Response>
+    pub async fn request<P>(
+        &self,
+        prompt: P,
+    ) -> Result<crate::Response<'static>>
     where
         P: Serialize,
     {
@@ -192,7 +352,26 @@ impl Client {
         &self,
         prompt: P,
         url: U,
-    ) -> Result<crate::Response>
+    ) -> Result<crate::Response<'static>>
+    where
+        P: Serialize,
+        U: reqwest::IntoUrl,
+    {
+        self.request_with(prompt, url, &RequestOptions::new()).await
+    }
+
+    /// Post a [`request`] to `url` with [`RequestOptions`]. Other request
+    /// methods generate new options for every call, so to retry a request
+    /// with the same [`RequestOptions::idempotency_key`], use this with the
+    /// same options.
+    ///
+    /// [`request`]: Self::request
+    pub async fn request_with<P, U>(
+        &self,
+        prompt: P,
+        url: U,
+        options: &RequestOptions,
+    ) -> Result<crate::Response<'static>>
     where
         P: Serialize,
         U: reqwest::IntoUrl,
@@ -200,30 +379,41 @@ impl Client {
         let json = serde_json::to_value(prompt)?;
         let streaming = json["stream"].as_bool().unwrap_or(false);
 
-        let response: reqwest::Response = self.post(url, json).await?;
-
-        if response.status() != reqwest::StatusCode::OK {
-            let error: AnthropicErrorWrapper = response.json().await?;
-
-            // Error was sucessfully parsed from the API.
-            return Err(error.error.into());
-        }
+        let response = self.send(json, url, options).await?;
 
         if streaming {
-            // Get a stream and wrap it in our stream type.
+            // Get a stream and wrap it in our stream type. The headers are
+            // captured first since the body stream consumes the response.
+            let meta = crate::stream::Meta::from_response(&response);
             Ok(crate::Response::Stream {
-                stream: crate::Stream::new(
+                stream: crate::Stream::with_limits(
                     response.bytes_stream().eventsource(),
-                ),
+                    self.limits,
+                )
+                .with_meta(meta),
             })
         } else {
             // Get body as JSON.
-            let body = response.bytes().await?;
+            let body = self.read_body(response).await?;
+            let message: response::Message = match &self.redactor {
+                Some(redactor) if self.redact_responses => {
+                    let mut json = crate::parse::from_slice(&body)?;
+                    redact::redact_response(&**redactor, &mut json);
+                    serde_json::from_value(json)?
+                }
+                _ => crate::parse::from_slice(&body)?,
+            };
+
+            if let Some(max) = self.limits.content_block {
+                for (index, block) in message.message.content.iter().enumerate()
+                {
+                    let size = Limits::block_size(block);
+                    Limits::check(Limit::ContentBlock { index }, size, max)?;
+                }
+            }
 
             // Get a single response message.
-            Ok(crate::Response::Message {
-                message: serde_json::from_slice(&body)?,
-            })
+            Ok(crate::Response::Message { message })
         }
     }
 
@@ -231,7 +421,10 @@ impl Client {
     /// function will always return a single [`response::Message`].
     ///
     /// [`request`]: Self::request
-    pub async fn message<P>(&self, prompt: P) -> Result<response::Message>
+    pub async fn message<P>(
+        &self,
+        prompt: P,
+    ) -> Result<response::Message<'static>>
     where
         P: Serialize,
     {
@@ -252,11 +445,179 @@ impl Client {
         }
     }
 
+    /// Make a [`message`] request and, while the response [hit max tokens],
+    /// send it back as a prefill to continue it, up to `max_continuations`
+    /// times. The pieces are stitched together into one [`response::Message`]
+    /// with [`response::Message::append_continuation`].
+    ///
+    /// If the `prompt` ends with an [`Assistant`] message, it's used as the
+    /// prefill for the first request and is not part of the returned message,
+    /// just like with [`message`]. Continuation stops early if the response
+    /// ends in something other than text, such as a partial tool use, since
+    /// that can't be prefilled.
+    ///
+    /// [`message`]: Self::message
+    /// [hit max tokens]: response::Message::hit_max_tokens
+    /// [`Assistant`]: crate::prompt::message::Role::Assistant
+    pub async fn message_continued(
+        &self,
+        prompt: &crate::Prompt<'_>,
+        max_continuations: usize,
+    ) -> Result<response::Message<'static>> {
+        use crate::prompt::message::{Content, Message, Role};
+
+        let mut message = self.message(prompt).await?;
+
+        // The prompt for continuations, without any prefill, and the prefill.
+        let mut base = prompt.clone();
+        let prefill = match base.messages.last() {
+            Some(last) if last.role == Role::Assistant => {
+                base.messages.pop().map(|m| m.content).unwrap_or_default()
+            }
+            _ => Content::default(),
+        };
+
+        for _ in 0..max_continuations {
+            if !message.hit_max_tokens() {
+                break;
+            }
+
+            // The API rejects a prefill ending in whitespace.
+            match message.message.content.last_mut() {
+                Some(Block::Text { text, .. }) if !text.trim().is_empty() => {
+                    let len = text.trim_end().len();
+                    *text = text[..len].to_string().into();
+                }
+                _ => break,
+            }
+
+            let mut partial = prefill.clone();
+            partial.0.extend(message.message.content.iter().cloned());
+
+            let continuation = self
+                .message(base.clone().add_message(Message {
+                    role: Role::Assistant,
+                    content: partial,
+                }))
+                .await?;
+            message.append_continuation(continuation);
+        }
+
+        Ok(message)
+    }
+
+    /// Make a [`request`] to the Messages API forcing `stream=false` and
+    /// return the raw response body. Parse it with
+    /// [`response::Message::from_slice`] to borrow text from the body instead
+    /// of copying it, which matters for large responses.
+    ///
+    /// [`request`]: Self::request
+    pub async fn message_body<P>(&self, prompt: P) -> Result<bytes::Bytes>
+    where
+        P: Serialize,
+    {
+        let mut json = serde_json::to_value(prompt)?;
+        json["stream"] = serde_json::Value::Bool(false);
+
+        let response = self
+            .send(json, Self::DEFAULT_URL, &RequestOptions::new())
+            .await?;
+        self.read_body(response).await
+    }
+
+    /// Post `json` to `url` with [`Self::default_metadata`] merged in,
+    /// converting error responses into [`Error`]s. Applies the
+    /// [`Self::redactor`] and waits for the [`Self::rate_limiter`], if any.
+    /// Checks the [`Limits::request_body`].
+    async fn send<U>(
+        &self,
+        mut json: serde_json::Value,
+        url: U,
+        options: &RequestOptions,
+    ) -> Result<reqwest::Response>
+    where
+        U: reqwest::IntoUrl,
+    {
+        self.merge_metadata(&mut json);
+        if let Some(workspace) = &self.workspace {
+            workspace.apply(&mut json);
+        }
+
+        if let Some(redactor) = &self.redactor {
+            redact::redact_request(&**redactor, &mut json);
+        }
+
+        if let Some(max) = self.limits.request_body {
+            let size = serde_json::to_vec(&json)?.len();
+            Limits::check(Limit::RequestBody, size, max)?;
+        }
+
+        if let Some(limiter) = &self.rate_limiter {
+            limiter
+                .acquire(throttle::estimate_input_tokens(&json))
+                .await;
+        }
+
+        let response: reqwest::Response =
+            self.post_with(url, json, options).await?;
+
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.update(response.headers());
+        }
+
+        Self::check_status(response).await
+    }
+
+    /// Convert an error `response` into an [`Error`].
+    async fn check_status(
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status != reqwest::StatusCode::OK {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+            let body = response.bytes().await?;
+
+            return Err(Error::from_status(status, retry_after, &body));
+        }
+
+        Ok(response)
+    }
+
+    /// Read the body of a `response`, failing as soon as it exceeds the
+    /// [`Limits::response_body`].
+    async fn read_body(
+        &self,
+        response: reqwest::Response,
+    ) -> Result<bytes::Bytes> {
+        let Some(max) = self.limits.response_body else {
+            return Ok(response.bytes().await?);
+        };
+
+        if let Some(len) = response.content_length() {
+            let size = usize::try_from(len).unwrap_or(usize::MAX);
+            Limits::check(Limit::ResponseBody, size, max)?;
+        }
+
+        let mut body = vec![];
+        let mut chunks = response.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk?;
+            Limits::check(Limit::ResponseBody, body.len() + chunk.len(), max)?;
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(body.into())
+    }
+
     /// Make a [`request`] to the Messages API forcing `stream=true`. This
     /// function will always return a [`crate::Stream`].
     ///
     /// [`request`]: Self::request
-    pub async fn stream<P>(&self, prompt: P) -> Result<crate::Stream>
+    pub async fn stream<P>(&self, prompt: P) -> Result<crate::Stream<'static>>
     where
         P: Serialize,
     {
@@ -273,6 +634,299 @@ impl Client {
     }
 }
 
+/// Options for a single request. See [`Client::request_with`].
+#[derive(Clone, Debug)]
+pub struct RequestOptions {
+    /// Sent as the [`Client::IDEMPOTENCY_KEY`] header, so a gateway (or the
+    /// API, where supported) can recognize a retried request and not act on
+    /// it or charge for it twice. The [`Default`] is a new random UUID.
+    pub idempotency_key: Option<String>,
+}
+
+impl Default for RequestOptions {
+    fn default() -> Self {
+        Self {
+            idempotency_key: Some(uuid::Uuid::new_v4().to_string()),
+        }
+    }
+}
+
+impl RequestOptions {
+    /// Create [`RequestOptions`] with a new random idempotency key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options which add nothing to the request.
+    pub(crate) const fn none() -> Self {
+        Self {
+            idempotency_key: None,
+        }
+    }
+
+    /// Set the idempotency key, for example to the id of a job in your app
+    /// so it is the same across restarts.
+    pub fn idempotency_key<K>(mut self, key: K) -> Self
+    where
+        K: Into<String>,
+    {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Don't send an idempotency key.
+    pub fn without_idempotency_key(mut self) -> Self {
+        self.idempotency_key = None;
+        self
+    }
+}
+
+/// Builder for a [`Client`] with custom headers. See [`Client::builder`].
+///
+/// The required `anthropic-*` headers are always set, so this is preferred
+/// over replacing [`Client::inner`] with a custom [`reqwest::Client`].
+#[derive(Debug)]
+pub struct ClientBuilder {
+    key: Key,
+    headers: reqwest::header::HeaderMap,
+    user_agent_suffix: Option<String>,
+}
+
+impl ClientBuilder {
+    /// Create a new [`ClientBuilder`] with the given key.
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            headers: Default::default(),
+            user_agent_suffix: None,
+        }
+    }
+
+    /// Append `suffix` to the [`Client::USER_AGENT`] so your app can identify
+    /// itself, for example `my-app/1.0`.
+    pub fn user_agent_suffix<S>(mut self, suffix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.user_agent_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Add a header to every request, such as a gateway routing header. This
+    /// replaces any default header of the same name, for example
+    /// `anthropic-beta`. The API key header is always set by the [`Client`].
+    ///
+    /// See [`Self::try_default_header`] to parse the name and value.
+    pub fn default_header(
+        mut self,
+        name: reqwest::header::HeaderName,
+        value: reqwest::header::HeaderValue,
+    ) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Parse and add a header to every request. See [`Self::default_header`].
+    pub fn try_default_header<K, V>(
+        self,
+        name: K,
+        value: V,
+    ) -> std::result::Result<Self, InvalidHeader>
+    where
+        reqwest::header::HeaderName: TryFrom<K>,
+        <reqwest::header::HeaderName as TryFrom<K>>::Error:
+            Into<reqwest::header::InvalidHeaderName>,
+        reqwest::header::HeaderValue: TryFrom<V>,
+        <reqwest::header::HeaderValue as TryFrom<V>>::Error:
+            Into<reqwest::header::InvalidHeaderValue>,
+    {
+        let name = reqwest::header::HeaderName::try_from(name)
+            .map_err(|e| InvalidHeader::Name(e.into()))?;
+        let value = reqwest::header::HeaderValue::try_from(value)
+            .map_err(|e| InvalidHeader::Value(e.into()))?;
+
+        Ok(self.default_header(name, value))
+    }
+
+    /// The user agent, including the [`Self::user_agent_suffix`].
+    fn user_agent(&self) -> String {
+        match &self.user_agent_suffix {
+            Some(suffix) => format!("{} {}", Client::USER_AGENT, suffix),
+            None => Client::USER_AGENT.to_string(),
+        }
+    }
+
+    /// Headers for all requests, including the [`Self::default_header`]s.
+    fn headers(&self) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+
+        // Content type needs to be set to JSON.
+        headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+
+        // Anthropic version needs to be set.
+        headers.insert(
+            "anthropic-version",
+            reqwest::header::HeaderValue::from_static(
+                Client::ANTHROPIC_VERSION,
+            ),
+        );
+
+        // Enable betas.
+        #[cfg(any(feature = "prompt-caching", feature = "code-execution"))]
+        headers.insert(
+            "anthropic-beta",
+            reqwest::header::HeaderValue::from_static(Client::BETA),
+        );
+
+        for (name, value) in self.headers.iter() {
+            headers.insert(name, value.clone());
+        }
+
+        headers
+    }
+
+    /// Build the [`Client`]. This fails only if the inner
+    /// [`reqwest::Client`] can't be built.
+    pub fn build(self) -> reqwest::Result<Client> {
+        #[cfg(feature = "log")]
+        {
+            log::info!(concat!(
+                "Creating ",
+                env!("CARGO_PKG_NAME", " client...")
+            ));
+            log::debug!(concat!("Crate version: ", env!("CARGO_PKG_VERSION")));
+            log::debug!("Anthropic version: {}", Client::ANTHROPIC_VERSION);
+            #[cfg(any(feature = "prompt-caching", feature = "code-execution"))]
+            log::debug!("Anthropic beta: {}", Client::BETA);
+        }
+
+        Ok(Client {
+            inner: reqwest::Client::builder()
+                .default_headers(self.headers())
+                .user_agent(self.user_agent())
+                .build()?,
+            key: Arc::new(self.key),
+            default_metadata: Default::default(),
+            rate_limiter: None,
+            limits: Limits::default(),
+            redactor: None,
+            redact_responses: false,
+            #[cfg(feature = "log")]
+            log_policy: LogPolicy::default(),
+            workspace: None,
+        })
+    }
+}
+
+/// Error for an invalid [`ClientBuilder::try_default_header`].
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidHeader {
+    /// Invalid header name.
+    #[error("Invalid header name: {0}")]
+    Name(#[from] reqwest::header::InvalidHeaderName),
+    /// Invalid header value.
+    #[error("Invalid header value: {0}")]
+    Value(#[from] reqwest::header::InvalidHeaderValue),
+}
+
+/// Size limits for requests and responses. All are unlimited by default. See
+/// [`Client::with_limits`].
+///
+/// ```
+/// use misanthropic::{client::Limits, Client};
+///
+/// # let key = "x".repeat(108);
+/// let client = Client::new(key)?.with_limits(
+///     Limits::default()
+///         .request_body(1024 * 1024)
+///         .response_body(256 * 1024)
+///         .content_block(64 * 1024),
+/// );
+/// # Ok::<(), misanthropic::key::InvalidKeyLength>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum bytes of a serialized request body.
+    pub request_body: Option<usize>,
+    /// Maximum bytes of a response body, or of the event data of a stream.
+    pub response_body: Option<usize>,
+    /// Maximum bytes of a single content [`Block`]. This is the text of a
+    /// text [`Block`], or the input JSON of a tool use. See [`Block::len`]
+    /// for other types.
+    pub content_block: Option<usize>,
+}
+
+impl Limits {
+    /// Set the maximum bytes of a serialized request body.
+    pub fn request_body(mut self, max: usize) -> Self {
+        self.request_body = Some(max);
+        self
+    }
+
+    /// Set the maximum bytes of a response body, or of the event data of a
+    /// stream.
+    pub fn response_body(mut self, max: usize) -> Self {
+        self.response_body = Some(max);
+        self
+    }
+
+    /// Set the maximum bytes of a single content [`Block`].
+    pub fn content_block(mut self, max: usize) -> Self {
+        self.content_block = Some(max);
+        self
+    }
+
+    /// Returns [`TooLarge`] if `size` is over `max`.
+    pub(crate) fn check(
+        limit: Limit,
+        size: usize,
+        max: usize,
+    ) -> std::result::Result<(), TooLarge> {
+        match size > max {
+            true => Err(TooLarge { limit, size, max }),
+            false => Ok(()),
+        }
+    }
+
+    /// Size of a content [`Block`] as measured by [`Self::content_block`].
+    pub(crate) fn block_size(block: &Block) -> usize {
+        match block {
+            Block::ToolUse { call } => call.input.to_string().len(),
+            block => block.len(),
+        }
+    }
+}
+
+/// Which of the [`Limits`] was exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, derive_more::Display)]
+pub enum Limit {
+    /// [`Limits::request_body`].
+    #[display("request body")]
+    RequestBody,
+    /// [`Limits::response_body`].
+    #[display("response body")]
+    ResponseBody,
+    /// [`Limits::content_block`].
+    #[display("content block {index}")]
+    #[allow(missing_docs)]
+    ContentBlock { index: usize },
+}
+
+/// Error for when one of the [`Limits`] is exceeded.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("The {limit} is too large: {size} bytes (max {max}).")]
+pub struct TooLarge {
+    /// Which limit was exceeded.
+    pub limit: Limit,
+    /// Size in bytes, so far.
+    pub size: usize,
+    /// The limit in bytes.
+    pub max: usize,
+}
+
 /// [`Client`] error type.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -283,14 +937,178 @@ pub enum Error {
     #[error("Parse error: {0}")]
     Parse(#[from] serde_json::Error),
     /// Anthropic error.
-    #[error("Anthropic error: {0}")]
-    Anthropic(#[from] AnthropicError),
+    #[error("Anthropic error: {error}")]
+    Anthropic {
+        /// Error from the API.
+        error: AnthropicError,
+        /// How long to wait before retrying, from the `retry-after` header.
+        retry_after: Option<Duration>,
+    },
     /// Unexpected response from the API. These should never happen unless the
     /// server is misbehaving (for example, returning a stream when a message is
     /// expected).
     #[error("Unexpected response: {message}")]
     #[allow(missing_docs)]
     UnexpectedResponse { message: &'static str },
+    /// One of the [`Limits`] was exceeded.
+    #[error("Too large: {0}")]
+    TooLarge(#[from] TooLarge),
+    /// An error status with a body which is not an [`AnthropicError`], for
+    /// example an HTML page from a proxy or a truncated body. At most
+    /// [`Error::BODY_SNIPPET_LEN`] bytes of the body are kept.
+    #[error("HTTP status {status}: {body_snippet}")]
+    HttpStatus {
+        /// HTTP status code.
+        status: reqwest::StatusCode,
+        /// Start of the body, lossily decoded as UTF-8.
+        body_snippet: String,
+        /// How long to wait before retrying, from the `retry-after` header.
+        retry_after: Option<Duration>,
+    },
+}
+
+impl From<AnthropicError> for Error {
+    fn from(error: AnthropicError) -> Self {
+        Self::Anthropic {
+            error,
+            retry_after: None,
+        }
+    }
+}
+
+impl Error {
+    /// Maximum bytes of the body kept in [`Error::HttpStatus`].
+    pub const BODY_SNIPPET_LEN: usize = 512;
+
+    /// Error for a response with an error `status` and `body`. The body is
+    /// parsed as an [`AnthropicError`] if possible, otherwise this is an
+    /// [`Error::HttpStatus`].
+    fn from_status(
+        status: reqwest::StatusCode,
+        retry_after: Option<Duration>,
+        body: &[u8],
+    ) -> Self {
+        match serde_json::from_slice::<AnthropicErrorWrapper>(body) {
+            Ok(wrapper) => Self::Anthropic {
+                error: wrapper.error,
+                retry_after,
+            },
+            Err(_) => Self::HttpStatus {
+                status,
+                body_snippet: String::from_utf8_lossy(
+                    &body[..body.len().min(Self::BODY_SNIPPET_LEN)],
+                )
+                .into_owned(),
+                retry_after,
+            },
+        }
+    }
+
+    /// HTTP status code of the error response, if there was one.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Self::Anthropic { error, .. } => {
+                reqwest::StatusCode::from_u16(error.status().get()).ok()
+            }
+            Self::HttpStatus { status, .. } => Some(*status),
+            Self::HTTP(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request was rate limited (429).
+    pub fn is_rate_limit(&self) -> bool {
+        self.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS)
+    }
+
+    /// Whether the API was overloaded (529).
+    pub fn is_overloaded(&self) -> bool {
+        self.status().map(|s| s.as_u16()) == Some(529)
+    }
+
+    /// Whether the API key is invalid (401) or lacks permission (403).
+    pub fn is_auth(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(
+                reqwest::StatusCode::UNAUTHORIZED
+                    | reqwest::StatusCode::FORBIDDEN
+            )
+        )
+    }
+
+    /// Whether the request that caused this error is worth retrying, and
+    /// when. See [`RetryHint`].
+    pub fn retry_hint(&self) -> RetryHint {
+        match self {
+            Self::Anthropic { error, retry_after } => match error {
+                AnthropicError::RateLimit { .. }
+                | AnthropicError::API { .. }
+                | AnthropicError::Overloaded { .. } => RetryHint::Retryable {
+                    after: *retry_after,
+                },
+                AnthropicError::Unknown { code, .. }
+                    if code.get() == 408 || code.get() >= 500 =>
+                {
+                    RetryHint::Retryable {
+                        after: *retry_after,
+                    }
+                }
+                AnthropicError::RequestTooLarge { .. } => RetryHint::TooLarge,
+                _ => RetryHint::Fatal,
+            },
+            Self::HTTP(error) if error.is_timeout() => {
+                RetryHint::Retryable { after: None }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::HTTP(error) if error.is_connect() => {
+                RetryHint::Retryable { after: None }
+            }
+            Self::TooLarge(TooLarge {
+                limit: Limit::RequestBody,
+                ..
+            }) => RetryHint::TooLarge,
+            Self::HttpStatus {
+                status,
+                retry_after,
+                ..
+            } => match status.as_u16() {
+                408 | 429 | 500.. => RetryHint::Retryable {
+                    after: *retry_after,
+                },
+                413 => RetryHint::TooLarge,
+                _ => RetryHint::Fatal,
+            },
+            _ => RetryHint::Fatal,
+        }
+    }
+}
+
+/// Classification of an [`Error`] by whether retrying can help. See
+/// [`Error::retry_hint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryHint {
+    /// The same request may succeed later, for example when the API is
+    /// [`Overloaded`] or rate limited. Wait for `after` if it is known,
+    /// otherwise back off.
+    ///
+    /// [`Overloaded`]: AnthropicError::Overloaded
+    #[allow(missing_docs)]
+    Retryable { after: Option<Duration> },
+    /// The request is too large ([`RequestTooLarge`] or the
+    /// [`Limits::request_body`]). It can only succeed if it is made smaller,
+    /// for example by trimming the conversation.
+    ///
+    /// [`RequestTooLarge`]: AnthropicError::RequestTooLarge
+    TooLarge,
+    /// Retrying will not help, for example an invalid request or key.
+    Fatal,
+}
+
+impl From<&Error> for RetryHint {
+    fn from(error: &Error) -> Self {
+        error.retry_hint()
+    }
 }
 
 /// Anthropic error type.
@@ -495,13 +1313,259 @@ mod tests {
     #[test]
     fn test_client_new() {
         let client = Client::new(FAKE_API_KEY.to_string()).unwrap();
-        assert_eq!(client.key.to_string(), FAKE_API_KEY);
+        assert_eq!(client.key.expose_secret().as_str(), FAKE_API_KEY);
+        // The key is redacted when formatted, so it can't leak into logs.
+        assert_eq!(client.key.to_string(), "sk-ant-...sAAA");
+        assert!(!format!("{:?}", client.key).contains(FAKE_API_KEY));
 
         // Apparently there isn't a way to check if the headers have been set
         // on the client. Making a request returns a builder but the headers
         // are not exposed.
     }
 
+    #[test]
+    fn test_client_builder() {
+        let builder = Client::builder(FAKE_API_KEY.to_string())
+            .unwrap()
+            .user_agent_suffix("my-app/1.0")
+            .try_default_header("x-gateway-route", "claude")
+            .unwrap()
+            .try_default_header("anthropic-version", "2099-01-01")
+            .unwrap();
+
+        assert_eq!(
+            builder.user_agent(),
+            format!("{} my-app/1.0", Client::USER_AGENT)
+        );
+        let headers = builder.headers();
+        assert_eq!(headers["x-gateway-route"], "claude");
+        assert_eq!(headers["anthropic-version"], "2099-01-01");
+        assert_eq!(headers[reqwest::header::CONTENT_TYPE], "application/json");
+
+        assert!(matches!(
+            builder.try_default_header("bad header", "x"),
+            Err(InvalidHeader::Name(_))
+        ));
+
+        let builder = ClientBuilder::new(
+            Key::try_from(FAKE_API_KEY.to_string()).unwrap(),
+        );
+        assert_eq!(builder.user_agent(), Client::USER_AGENT);
+        assert!(matches!(
+            builder.try_default_header("x-ok", "bad\nvalue"),
+            Err(InvalidHeader::Value(_))
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(Limits::check(Limit::RequestBody, 10, 10).is_ok());
+        let error = Limits::check(Limit::ContentBlock { index: 2 }, 11, 10)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The content block 2 is too large: 11 bytes (max 10)."
+        );
+
+        assert_eq!(Limits::block_size(&Block::text("hello")), 5);
+        let call = Block::ToolUse {
+            call: crate::tool::Use {
+                id: "id".into(),
+                name: "name".into(),
+                input: serde_json::json!({"a": 1}),
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            },
+        };
+        assert_eq!(Limits::block_size(&call), r#"{"a":1}"#.len());
+    }
+
+    #[tokio::test]
+    async fn test_request_body_limit() {
+        // This fails before anything is sent, so no key is needed.
+        let client = Client::new(FAKE_API_KEY.to_string())
+            .unwrap()
+            .with_limits(Limits::default().request_body(100));
+        let prompt =
+            Prompt::default().add_message((Role::User, "x".repeat(100)));
+
+        match client.message(prompt).await {
+            Err(Error::TooLarge(TooLarge {
+                limit: Limit::RequestBody,
+                size,
+                max: 100,
+            })) => assert!(size > 100),
+            other => panic!("expected TooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_retry_hint() {
+        let error = Error::Anthropic {
+            error: AnthropicError::Overloaded {
+                message: "Overloaded".into(),
+            },
+            retry_after: Some(Duration::from_secs(3)),
+        };
+        assert_eq!(
+            error.retry_hint(),
+            RetryHint::Retryable {
+                after: Some(Duration::from_secs(3))
+            }
+        );
+
+        let error: Error = AnthropicError::RequestTooLarge {
+            message: "Too large".into(),
+        }
+        .into();
+        assert_eq!(error.retry_hint(), RetryHint::TooLarge);
+
+        let error: Error = AnthropicError::Unknown {
+            code: NonZeroU16::new(503).unwrap(),
+            message: "Unavailable".into(),
+        }
+        .into();
+        assert_eq!(error.retry_hint(), RetryHint::Retryable { after: None });
+
+        let error: Error = AnthropicError::Authentication {
+            message: "Bad key".into(),
+        }
+        .into();
+        assert_eq!(error.retry_hint(), RetryHint::Fatal);
+
+        let error: Error =
+            Limits::check(Limit::RequestBody, 2, 1).unwrap_err().into();
+        assert_eq!(error.retry_hint(), RetryHint::TooLarge);
+        let error: Error =
+            Limits::check(Limit::ResponseBody, 2, 1).unwrap_err().into();
+        assert_eq!(error.retry_hint(), RetryHint::Fatal);
+    }
+
+    #[test]
+    fn test_request_options() {
+        let a = RequestOptions::new();
+        let b = RequestOptions::new();
+        let key = a.idempotency_key.as_deref().unwrap();
+        assert_eq!(key.len(), 36);
+        assert_ne!(a.idempotency_key, b.idempotency_key);
+
+        let options = a.clone().idempotency_key("job-42");
+        assert_eq!(options.idempotency_key.as_deref(), Some("job-42"));
+        assert_eq!(options.without_idempotency_key().idempotency_key, None);
+    }
+
+    #[test]
+    fn test_error_status() {
+        use reqwest::StatusCode;
+
+        let error = Error::from(AnthropicError::RateLimit {
+            message: "Slow down.".into(),
+        });
+        assert_eq!(error.status(), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert!(error.is_rate_limit());
+        assert!(!error.is_overloaded() && !error.is_auth());
+        assert!(matches!(
+            RetryHint::from(&error),
+            RetryHint::Retryable { .. }
+        ));
+
+        let error = Error::from(AnthropicError::Overloaded {
+            message: "Overloaded.".into(),
+        });
+        assert!(error.is_overloaded());
+
+        let error = Error::from(AnthropicError::Permission {
+            message: "No.".into(),
+        });
+        assert!(error.is_auth());
+        assert_eq!(RetryHint::from(&error), RetryHint::Fatal);
+
+        let error =
+            Error::from_status(StatusCode::UNAUTHORIZED, None, b"<html>");
+        assert_eq!(error.status(), Some(StatusCode::UNAUTHORIZED));
+        assert!(error.is_auth());
+
+        let error: Error =
+            Limits::check(Limit::ResponseBody, 2, 1).unwrap_err().into();
+        assert_eq!(error.status(), None);
+        assert!(!error.is_rate_limit() && !error.is_auth());
+    }
+
+    #[test]
+    fn test_error_from_status() {
+        use reqwest::StatusCode;
+
+        let body = br#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let error =
+            Error::from_status(StatusCode::from_u16(529).unwrap(), None, body);
+        assert!(matches!(
+            error,
+            Error::Anthropic {
+                error: AnthropicError::Overloaded { .. },
+                ..
+            }
+        ));
+
+        // HTML from a proxy.
+        let body = b"<html><body>502 Bad Gateway</body></html>";
+        let error = Error::from_status(
+            StatusCode::BAD_GATEWAY,
+            Some(Duration::from_secs(1)),
+            body,
+        );
+        assert!(matches!(
+            &error,
+            Error::HttpStatus { status, body_snippet, .. }
+                if *status == StatusCode::BAD_GATEWAY
+                    && body_snippet == "<html><body>502 Bad Gateway</body></html>"
+        ));
+        assert_eq!(
+            error.retry_hint(),
+            RetryHint::Retryable {
+                after: Some(Duration::from_secs(1))
+            }
+        );
+        assert!(error.to_string().starts_with("HTTP status 502 Bad Gateway"));
+
+        // Truncated JSON, with a long body.
+        let body = format!(
+            r#"{{"type":"error","error":{{"message":"{}"#,
+            "x".repeat(1000)
+        );
+        let error =
+            Error::from_status(StatusCode::FORBIDDEN, None, body.as_bytes());
+        let Error::HttpStatus { body_snippet, .. } = &error else {
+            panic!("Expected HttpStatus, got {error:?}");
+        };
+        assert_eq!(body_snippet.len(), Error::BODY_SNIPPET_LEN);
+        assert_eq!(error.retry_hint(), RetryHint::Fatal);
+    }
+
+    #[test]
+    fn test_merge_metadata() {
+        let mut defaults = serde_json::Map::new();
+        defaults.insert("user_id".into(), "default".into());
+        defaults.insert("team".into(), "a".into());
+        let client = Client::new(FAKE_API_KEY.to_string())
+            .unwrap()
+            .with_default_metadata(defaults);
+
+        let mut json = serde_json::to_value(Prompt::default()).unwrap();
+        client.merge_metadata(&mut json);
+        assert_eq!(
+            json["metadata"],
+            serde_json::json!({"user_id": "default", "team": "a"})
+        );
+
+        let prompt = Prompt::default().user_id("abc123");
+        let mut json = serde_json::to_value(prompt).unwrap();
+        client.merge_metadata(&mut json);
+        assert_eq!(
+            json["metadata"],
+            serde_json::json!({"user_id": "abc123", "team": "a"})
+        );
+    }
+
     #[tokio::test]
     #[ignore = "This test requires a real API key."]
     async fn test_client_message() {
@@ -520,6 +1584,44 @@ mod tests {
         assert!(message.to_string().contains("🙏"));
     }
 
+    #[tokio::test]
+    #[ignore = "This test requires a real API key."]
+    async fn test_client_message_continued() {
+        let key = load_api_key().expect(NO_API_KEY);
+        let client = Client::new(key).unwrap();
+
+        let prompt = Prompt::default()
+            .max_tokens(NonZeroU16::new(16).unwrap())
+            .messages([(
+                Role::User,
+                "Count from 1 to 30 separated by spaces. Emit nothing else.",
+            )]);
+
+        let message = client.message_continued(&prompt, 8).await.unwrap();
+
+        assert!(!message.hit_max_tokens());
+        assert!(message.text().trim_end().ends_with("28 29 30"));
+    }
+
+    #[tokio::test]
+    #[ignore = "This test requires a real API key."]
+    async fn test_client_message_body() {
+        let key = load_api_key().expect(NO_API_KEY);
+        let client = Client::new(key).unwrap();
+
+        let body = client
+            .message_body(Prompt::default().messages([(
+                Role::User,
+                "Emit just the \"🙏\" emoji, please.",
+            )]))
+            .await
+            .unwrap();
+        let message = response::Message::from_slice(&body).unwrap();
+
+        assert_eq!(message.message.role, Role::Assistant);
+        assert!(message.to_string().contains("🙏"));
+    }
+
     #[tokio::test]
     #[ignore = "This test requires a real API key."]
     async fn test_client_stream() {
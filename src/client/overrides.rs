@@ -0,0 +1,227 @@
+//! Per-request [`Overrides`] for [`Client::message_with`].
+use std::num::NonZeroU16;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::{Client, Result};
+use crate::{prompt::Temperature, response, Model};
+
+/// Request parameters to patch into a serialized prompt, so a shared
+/// [`Prompt`] doesn't have to be cloned to change one of them. Fields which
+/// are [`None`] or empty are left as they are. See [`Client::message_with`].
+///
+/// [`Prompt`]: crate::Prompt
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overrides {
+    /// Replaces the [`Model`].
+    pub model: Option<Model>,
+    /// Replaces the `max_tokens`.
+    pub max_tokens: Option<NonZeroU16>,
+    /// Replaces the `temperature`.
+    pub temperature: Option<Temperature>,
+    /// Merged into the `metadata`, replacing existing keys.
+    pub metadata: Map<String, Value>,
+    /// Appended to the system prompt as a separate text block, so cache
+    /// breakpoints in the system prompt still apply.
+    pub system_suffix: Option<String>,
+}
+
+impl Overrides {
+    /// Create empty [`Overrides`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Self::model`].
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Set the [`Self::max_tokens`].
+    pub fn max_tokens(mut self, max_tokens: NonZeroU16) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set the [`Self::temperature`].
+    pub fn temperature(mut self, temperature: Temperature) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Add a key to the [`Self::metadata`].
+    pub fn metadata<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the [`Self::system_suffix`].
+    pub fn system_suffix<S>(mut self, suffix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.system_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Patch a serialized prompt.
+    pub(crate) fn apply(&self, json: &mut Value) -> serde_json::Result<()> {
+        let Some(body) = json.as_object_mut() else {
+            return Ok(());
+        };
+
+        if let Some(model) = self.model {
+            body.insert("model".into(), serde_json::to_value(model)?);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body.insert("max_tokens".into(), max_tokens.get().into());
+        }
+        if let Some(temperature) = self.temperature {
+            // Converting the `f32` to a `Value` widens it to an `f64`, so 0.7
+            // would be sent as 0.699999988079071. Its shortest text form
+            // parses back to the intended value.
+            body.insert(
+                "temperature".into(),
+                serde_json::from_str(&serde_json::to_string(&temperature)?)?,
+            );
+        }
+        if !self.metadata.is_empty() {
+            let metadata = body
+                .entry("metadata")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Some(metadata) = metadata.as_object_mut() {
+                metadata.extend(self.metadata.clone());
+            }
+        }
+        if let Some(suffix) = &self.system_suffix {
+            let block = serde_json::json!({"type": "text", "text": suffix});
+            let system = match body.remove("system") {
+                None | Some(Value::Null) => vec![block],
+                Some(Value::String(text)) => {
+                    vec![
+                        serde_json::json!({"type": "text", "text": text}),
+                        block,
+                    ]
+                }
+                Some(Value::Array(mut blocks)) => {
+                    blocks.push(block);
+                    blocks
+                }
+                Some(other) => vec![other, block],
+            };
+            body.insert("system".into(), Value::Array(system));
+        }
+
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Make a [`message`] request with [`Overrides`] patched into the
+    /// serialized `prompt`, which is not modified. [`Workspace`] overrides
+    /// still take precedence.
+    ///
+    /// ```no_run
+    /// use misanthropic::{
+    ///     client::Overrides, prompt::Temperature, Client, Model, Prompt,
+    /// };
+    ///
+    /// # async fn example(client: Client, prompt: Prompt<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let draft = client
+    ///     .message_with(&prompt, &Overrides::new().model(Model::Haiku35))
+    ///     .await?;
+    /// let creative = client
+    ///     .message_with(
+    ///         &prompt,
+    ///         &Overrides::new().temperature(Temperature::try_from(1.0)?),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`message`]: Self::message
+    /// [`Workspace`]: super::Workspace
+    pub async fn message_with<P>(
+        &self,
+        prompt: P,
+        overrides: &Overrides,
+    ) -> Result<response::Message<'static>>
+    where
+        P: Serialize,
+    {
+        let mut json = serde_json::to_value(prompt)?;
+        overrides.apply(&mut json)?;
+
+        self.message(json).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, prompt::Message, Prompt};
+
+    #[test]
+    fn test_overrides() {
+        let prompt = Prompt::default()
+            .model(Model::Sonnet35)
+            .system("Be brief.")
+            .add_message(Message::user("Hi!"));
+        let mut body = serde_json::to_value(&prompt).unwrap();
+        let original = body.clone();
+
+        // Nothing to change.
+        Overrides::new().apply(&mut body).unwrap();
+        assert_eq!(body, original);
+
+        Overrides::new()
+            .model(Model::Haiku35)
+            .max_tokens(NonZeroU16::new(100).unwrap())
+            .temperature(Temperature::try_from(0.5).unwrap())
+            .metadata("user_id", "u1")
+            .system_suffix("Answer in French.")
+            .apply(&mut body)
+            .unwrap();
+        assert_eq!(body["model"], "claude-3-5-haiku-latest");
+        assert_eq!(body["max_tokens"], 100);
+        assert_eq!(body["temperature"], 0.5);
+        assert_eq!(body["metadata"], json!({"user_id": "u1"}));
+        assert_eq!(
+            body["system"],
+            json!([
+                {"type": "text", "text": "Be brief."},
+                {"type": "text", "text": "Answer in French."},
+            ])
+        );
+        assert_eq!(body["messages"], original["messages"]);
+
+        // The temperature is sent as written, not widened to an `f64`.
+        let mut body = json!({});
+        Overrides::new()
+            .temperature(Temperature::try_from(0.7).unwrap())
+            .apply(&mut body)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&body).unwrap(),
+            r#"{"temperature":0.7}"#
+        );
+
+        // No system prompt.
+        let mut body = json!({"messages": []});
+        Overrides::new()
+            .system_suffix("Be brief.")
+            .apply(&mut body)
+            .unwrap();
+        assert_eq!(
+            body["system"],
+            json!([{"type": "text", "text": "Be brief."}])
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Pagination of the API's list endpoints, such as models, batches, and
+//! files. See [`Paginator`].
+use std::{marker::PhantomData, num::NonZeroU16};
+
+use futures::TryStreamExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Client, Result};
+use crate::MaybeSend;
+
+/// A page of items from a list endpoint.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Page<T> {
+    /// Items on this page.
+    pub data: Vec<T>,
+    /// Whether there are more items in the direction of the request.
+    pub has_more: bool,
+    /// Id of the first item on this page. Pass it as
+    /// [`Paginator::before_id`] for the previous page.
+    #[serde(default)]
+    pub first_id: Option<String>,
+    /// Id of the last item on this page. Pass it as [`Paginator::after_id`]
+    /// for the next page.
+    #[serde(default)]
+    pub last_id: Option<String>,
+}
+
+/// Query parameters of a list request.
+#[derive(Clone, Debug, Default, Serialize)]
+struct Query {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<NonZeroU16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace_id: Option<String>,
+}
+
+/// Pages through a list endpoint returning items of type `T`, following the
+/// cursors so you don't have to. Create one with [`Client::paginate`].
+///
+/// By default, pages are fetched from the start of the list. With a
+/// [`before_id`], they are fetched backwards from that item instead.
+///
+/// ```no_run
+/// use futures::TryStreamExt;
+/// use misanthropic::Client;
+///
+/// #[derive(serde::Deserialize)]
+/// struct ModelInfo {
+///     id: String,
+/// }
+///
+/// # async fn example(client: Client) -> misanthropic::client::Result<()> {
+/// let models: Vec<ModelInfo> = client
+///     .paginate("https://api.anthropic.com/v1/models")
+///     .limit(100.try_into().unwrap())
+///     .items()
+///     .try_collect()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`before_id`]: Self::before_id
+pub struct Paginator<T> {
+    client: Client,
+    url: String,
+    query: Query,
+    item: PhantomData<fn() -> T>,
+}
+
+impl Client {
+    /// Page through the list endpoint at `url`. See [`Paginator`]. If the
+    /// client is scoped to a [`Workspace`], the list is filtered by its id.
+    ///
+    /// [`Workspace`]: super::Workspace
+    pub fn paginate<T, U>(&self, url: U) -> Paginator<T>
+    where
+        U: Into<String>,
+    {
+        Paginator {
+            client: self.clone(),
+            url: url.into(),
+            query: Query {
+                workspace_id: self.workspace.as_ref().map(|w| w.id.clone()),
+                ..Default::default()
+            },
+            item: PhantomData,
+        }
+    }
+}
+
+impl<T> Paginator<T> {
+    /// Start after the item with this id. This clears the
+    /// [`Self::before_id`].
+    pub fn after_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.after_id = Some(id.into());
+        self.query.before_id = None;
+        self
+    }
+
+    /// Page backwards, starting before the item with this id. This clears
+    /// the [`Self::after_id`].
+    pub fn before_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.query.before_id = Some(id.into());
+        self.query.after_id = None;
+        self
+    }
+
+    /// Set the number of items per page. The API allows 1 to 1000 and
+    /// defaults to 20.
+    pub fn limit(mut self, limit: NonZeroU16) -> Self {
+        self.query.limit = Some(limit);
+        self
+    }
+
+    /// Move the cursor past `page`. Returns `false` if there are no more
+    /// pages.
+    fn advance(&mut self, page: &Page<T>) -> bool {
+        if !page.has_more {
+            return false;
+        }
+
+        let (cursor, id) = match self.query.before_id {
+            Some(_) => (&mut self.query.before_id, &page.first_id),
+            None => (&mut self.query.after_id, &page.last_id),
+        };
+        match id {
+            Some(id) => {
+                *cursor = Some(id.clone());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T> Paginator<T>
+where
+    T: DeserializeOwned,
+{
+    /// Fetch the page at the current cursor.
+    pub async fn page(&self) -> Result<Page<T>> {
+        let response = self
+            .client
+            .request_raw(reqwest::Method::GET, &self.url)
+            .query(&self.query)
+            .send()
+            .await?;
+        let response = Client::check_status(response).await?;
+        let body = self.client.read_body(response).await?;
+
+        Ok(crate::parse::from_slice(&body)?)
+    }
+}
+
+impl<T> Paginator<T>
+where
+    T: DeserializeOwned + MaybeSend + 'static,
+{
+    /// Fetch every page in turn, from the current cursor.
+    pub fn pages(
+        self,
+    ) -> impl futures::Stream<Item = Result<Page<T>>> + MaybeSend + 'static
+    {
+        futures::stream::try_unfold(Some(self), |state| async move {
+            let Some(mut paginator) = state else {
+                return Ok(None);
+            };
+
+            let page = paginator.page().await?;
+            let more = paginator.advance(&page);
+
+            Ok(Some((page, more.then_some(paginator))))
+        })
+    }
+
+    /// Fetch every item on every page, from the current cursor. Pages are
+    /// only fetched as the items are needed.
+    pub fn items(
+        self,
+    ) -> impl futures::Stream<Item = Result<T>> + MaybeSend + 'static {
+        self.pages()
+            .map_ok(|page| futures::stream::iter(page.data.into_iter().map(Ok)))
+            .try_flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Item {
+        id: String,
+    }
+
+    fn client() -> Client {
+        Client::new("x".repeat(108)).unwrap()
+    }
+
+    fn page(ids: &[&str], has_more: bool) -> Page<Item> {
+        serde_json::from_value(crate::json!({
+            "data": ids.iter().map(|id| crate::json!({"id": id})).collect::<Vec<_>>(),
+            "has_more": has_more,
+            "first_id": ids.first(),
+            "last_id": ids.last(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_page() {
+        let page: Page<Item> = serde_json::from_str(
+            r#"{"data": [{"id": "a"}, {"id": "b"}], "has_more": true, "first_id": "a", "last_id": "b"}"#,
+        )
+        .unwrap();
+        assert_eq!(page.data[1], Item { id: "b".into() });
+        assert_eq!(page.first_id.as_deref(), Some("a"));
+
+        // Empty pages may have no ids.
+        let page: Page<Item> =
+            serde_json::from_str(r#"{"data": [], "has_more": false}"#).unwrap();
+        assert_eq!(page.last_id, None);
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut paginator = client()
+            .paginate::<Item, _>("https://example.com/v1/things")
+            .limit(NonZeroU16::new(2).unwrap());
+        assert_eq!(
+            serde_json::to_value(&paginator.query).unwrap(),
+            crate::json!({"limit": 2})
+        );
+
+        // Forwards from the start.
+        assert!(paginator.advance(&page(&["a", "b"], true)));
+        assert_eq!(paginator.query.after_id.as_deref(), Some("b"));
+        assert!(!paginator.advance(&page(&["c"], false)));
+
+        // Backwards.
+        let mut paginator = paginator.before_id("z");
+        assert_eq!(paginator.query.after_id, None);
+        assert!(paginator.advance(&page(&["x", "y"], true)));
+        assert_eq!(paginator.query.before_id.as_deref(), Some("x"));
+
+        // No cursor to follow.
+        assert!(!paginator.advance(&page(&[], true)));
+
+        let paginator = client()
+            .for_workspace("wrkspc_01")
+            .paginate::<Item, _>("https://example.com/v1/things");
+        assert_eq!(
+            serde_json::to_value(&paginator.query).unwrap(),
+            crate::json!({"workspace_id": "wrkspc_01"})
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Best-of-n sampling with [`Client::sample_n`]: send the same prompt `n`
+//! times at once and pick a winner with a [`Selector`].
+//!
+//! ```no_run
+//! use misanthropic::{client::sample::Selector, Client, Prompt};
+//!
+//! # async fn example(client: Client) -> misanthropic::client::Result<()> {
+//! let prompt = Prompt::default().add_message((
+//!     misanthropic::prompt::message::Role::User,
+//!     "What is 17 * 23? Answer with just the number.",
+//! ));
+//! let samples = client.sample_n(&prompt, 5, &Selector::MajorityVote).await?;
+//! if let Some(best) = samples.best() {
+//!     println!("{best}");
+//! }
+//! println!("{} output tokens", samples.usage().output_tokens);
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde::Serialize;
+
+use super::{Error, Result};
+use crate::{
+    prompt::Message,
+    response::{self, Usage},
+    Client, Prompt,
+};
+
+/// Picks the index of the best candidate, if any. See [`Selector::Custom`].
+pub type SelectFn =
+    Arc<dyn Fn(&[response::Message<'static>]) -> Option<usize> + Send + Sync>;
+
+/// How [`Client::sample_n`] picks a winner from the candidates. Ties go to
+/// the first candidate.
+#[derive(Clone)]
+pub enum Selector {
+    /// The candidate with the longest text.
+    Longest,
+    /// The most common answer, comparing text case-insensitively and ignoring
+    /// whitespace and trailing punctuation. Best for short answers.
+    MajorityVote,
+    /// Ask a model to pick. The candidates are numbered and added to this
+    /// [`Prompt`] as a user message asking for the number of the best one,
+    /// so it should describe the task and what makes a response good.
+    Judge(Box<Prompt<'static>>),
+    /// A custom selector.
+    Custom(SelectFn),
+}
+
+impl Selector {
+    /// Create a [`Selector::Judge`] from a `prompt`.
+    pub fn judge(prompt: Prompt<'static>) -> Self {
+        Self::Judge(Box::new(prompt))
+    }
+
+    /// Create a [`Selector::Custom`] from a function.
+    pub fn custom<F>(select: F) -> Self
+    where
+        F: Fn(&[response::Message<'static>]) -> Option<usize>
+            + Send
+            + Sync
+            + 'static,
+    {
+        Self::Custom(Arc::new(select))
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Longest => f.write_str("Longest"),
+            Self::MajorityVote => f.write_str("MajorityVote"),
+            Self::Judge(_) => f.write_str("Judge(..)"),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+/// Candidates from [`Client::sample_n`] and the winner.
+#[derive(Debug, Default)]
+pub struct Samples {
+    /// Successful responses, in the order the requests were made.
+    pub candidates: Vec<response::Message<'static>>,
+    /// Index of the winning candidate, if any.
+    pub winner: Option<usize>,
+    /// Response of the [`Selector::Judge`], if any.
+    pub judgment: Option<response::Message<'static>>,
+    /// Errors of failed requests, including the [`Selector::Judge`]. If the
+    /// judge fails, there is no [`Self::winner`].
+    pub errors: Vec<Error>,
+}
+
+impl Samples {
+    /// The winning candidate, if any.
+    pub fn best(&self) -> Option<&response::Message<'static>> {
+        self.candidates.get(self.winner?)
+    }
+
+    /// Total [`Usage`] of the candidates and the judgment.
+    pub fn usage(&self) -> Usage {
+        self.candidates
+            .iter()
+            .chain(&self.judgment)
+            .map(|message| &message.usage)
+            .fold(Usage::default(), |mut total, usage| {
+                total.input_tokens += usage.input_tokens;
+                total.output_tokens += usage.output_tokens;
+                total
+            })
+    }
+}
+
+impl Client {
+    /// Send `prompt` `n` times at once and pick a winner with `selector`.
+    /// Requests wait for the [`Self::rate_limiter`], if any. Failed requests
+    /// are recorded in [`Samples::errors`] unless every request fails, in
+    /// which case the first error is returned.
+    ///
+    /// For varied candidates, the prompt should have a temperature above 0.
+    pub async fn sample_n<P>(
+        &self,
+        prompt: P,
+        n: usize,
+        selector: &Selector,
+    ) -> Result<Samples>
+    where
+        P: Serialize,
+    {
+        let json = serde_json::to_value(prompt)?;
+        let responses =
+            futures::future::join_all((0..n).map(|_| self.message(&json)))
+                .await;
+
+        let mut samples = Samples::default();
+        for response in responses {
+            match response {
+                Ok(message) => samples.candidates.push(message),
+                Err(error) => samples.errors.push(error),
+            }
+        }
+        if samples.candidates.is_empty() && !samples.errors.is_empty() {
+            return Err(samples.errors.remove(0));
+        }
+
+        samples.winner = match selector {
+            Selector::Longest => longest(&samples.candidates),
+            Selector::MajorityVote => majority(&samples.candidates),
+            Selector::Custom(select) => select(&samples.candidates)
+                .filter(|&i| i < samples.candidates.len()),
+            Selector::Judge(prompt) if samples.candidates.len() > 1 => {
+                let judge = prompt
+                    .as_ref()
+                    .clone()
+                    .add_message(judge_message(&samples.candidates));
+                match self.message(judge).await {
+                    Ok(judgment) => {
+                        let n = samples.candidates.len();
+                        let winner = parse_choice(&judgment.text(), n);
+                        if winner.is_none() {
+                            samples.errors.push(Error::UnexpectedResponse {
+                                message: "The judge did not pick a candidate.",
+                            });
+                        }
+                        samples.judgment = Some(judgment);
+                        winner
+                    }
+                    Err(error) => {
+                        samples.errors.push(error);
+                        None
+                    }
+                }
+            }
+            // There is nothing to judge.
+            Selector::Judge(_) => longest(&samples.candidates),
+        };
+
+        Ok(samples)
+    }
+}
+
+/// Index of the candidate with the longest text.
+fn longest(candidates: &[response::Message]) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        // `max_by_key` returns the last maximum, so compare in reverse.
+        .rev()
+        .max_by_key(|(_, message)| message.text().chars().count())
+        .map(|(i, _)| i)
+}
+
+/// Normalize text for [`Selector::MajorityVote`].
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase()
+}
+
+/// Index of the first candidate with the most common normalized text.
+fn majority(candidates: &[response::Message]) -> Option<usize> {
+    let answers: Vec<String> = candidates
+        .iter()
+        .map(|message| normalize(&message.text()))
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for answer in &answers {
+        *counts.entry(answer).or_default() += 1;
+    }
+
+    answers
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, answer)| counts.get(answer.as_str()))
+        .map(|(i, _)| i)
+}
+
+/// The user message asking the judge to pick one of the `candidates`.
+fn judge_message(candidates: &[response::Message]) -> Message<'static> {
+    let mut text = String::from(
+        "Which of these responses is best? Reply with only its number.\n",
+    );
+    for (i, candidate) in candidates.iter().enumerate() {
+        text.push_str(&format!(
+            "\n<response number=\"{}\">\n{}\n</response>\n",
+            i + 1,
+            candidate.text()
+        ));
+    }
+
+    Message::user(text)
+}
+
+/// Parse the first number in the judge's `text` as a 1-based choice of `n`
+/// candidates.
+fn parse_choice(text: &str, n: usize) -> Option<usize> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let digits = &text[start..];
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let choice: usize = digits[..end].parse().ok()?;
+
+    (1..=n).contains(&choice).then(|| choice - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(texts: &[&'static str]) -> Vec<response::Message<'static>> {
+        texts
+            .iter()
+            .map(|text| {
+                let mut message: response::Message<'static> =
+                    serde_json::from_str(
+                        crate::response::message::tests::RESPONSE_JSON,
+                    )
+                    .unwrap();
+                message.message = Message::assistant(*text);
+                message
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_longest() {
+        assert_eq!(longest(&[]), None);
+        assert_eq!(longest(&candidates(&["a", "abc", "ab"])), Some(1));
+        // Ties go to the first.
+        assert_eq!(longest(&candidates(&["ab", "cd"])), Some(0));
+    }
+
+    #[test]
+    fn test_majority() {
+        assert_eq!(majority(&[]), None);
+        let answers =
+            candidates(&["391", "The answer is 391.", " 391.", "Paris"]);
+        assert_eq!(majority(&answers), Some(0));
+        assert_eq!(normalize("  The   Answer!\n"), "the answer");
+
+        // Ties go to the first.
+        assert_eq!(majority(&candidates(&["a", "b"])), Some(0));
+    }
+
+    #[test]
+    fn test_judge() {
+        let message = judge_message(&candidates(&["one", "two"]));
+        let text = message.text();
+        assert!(text.contains("<response number=\"1\">\none\n</response>"));
+        assert!(text.contains("<response number=\"2\">\ntwo\n</response>"));
+
+        assert_eq!(parse_choice("2", 3), Some(1));
+        assert_eq!(parse_choice("Response 3 is best.", 3), Some(2));
+        assert_eq!(parse_choice("Response 4", 3), None);
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("None of them.", 3), None);
+    }
+
+    #[test]
+    fn test_samples() {
+        let samples = Samples {
+            candidates: candidates(&["a", "b"]),
+            winner: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(samples.best().unwrap().text(), "b");
+        let usage = &samples.candidates[0].usage;
+        assert_eq!(samples.usage().input_tokens, usage.input_tokens * 2);
+        assert_eq!(samples.usage().output_tokens, usage.output_tokens * 2);
+        assert!(Samples::default().best().is_none());
+
+        let selector = Selector::custom(|_| Some(0));
+        assert_eq!(format!("{selector:?}"), "Custom(..)");
+    }
+}
@@ -0,0 +1,145 @@
+//! Scope a [`Client`] to a [`Workspace`], for example to hold one client per
+//! tenant in a multi-tenant backend. See [`Client::for_workspace`].
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+use super::Client;
+use crate::Model;
+
+/// A workspace a [`Client`] is scoped to, with request parameters that
+/// override those of every Messages request.
+///
+/// ```
+/// use misanthropic::{client::Workspace, Client, Model};
+///
+/// # let client = Client::new("x".repeat(108)).unwrap();
+/// let tenant = client.for_workspace(
+///     Workspace::new("wrkspc_01")
+///         .model(Model::Haiku35)
+///         .param("max_tokens", 512),
+/// );
+/// # assert_eq!(tenant.workspace.unwrap().id, "wrkspc_01");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Workspace {
+    /// Workspace id, such as `wrkspc_01...`.
+    pub id: String,
+    /// Parameters set on every Messages request, replacing those of the
+    /// prompt.
+    pub overrides: Map<String, Value>,
+}
+
+impl Workspace {
+    /// Create a [`Workspace`] with no overrides.
+    pub fn new<S>(id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            overrides: Map::new(),
+        }
+    }
+
+    /// Use `model` for every Messages request.
+    pub fn model(self, model: Model) -> Self {
+        match serde_json::to_value(model) {
+            Ok(model) => self.param("model", model),
+            Err(_) => self,
+        }
+    }
+
+    /// Set the request parameter `key` to `value` on every Messages request,
+    /// for example `max_tokens` or `temperature`.
+    pub fn param<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.overrides.insert(key.into(), value.into());
+        self
+    }
+
+    /// Apply the [`Self::overrides`] to a request body.
+    pub(super) fn apply(&self, json: &mut Value) {
+        if let Some(body) = json.as_object_mut() {
+            for (key, value) in &self.overrides {
+                body.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+impl From<&str> for Workspace {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<String> for Workspace {
+    fn from(id: String) -> Self {
+        Self::new(id)
+    }
+}
+
+impl Client {
+    /// A clone of this client scoped to a [`Workspace`]. The clone shares the
+    /// connection pool and everything else with this client.
+    ///
+    /// - Every request has the [`Self::WORKSPACE_ID`] header, for gateways
+    ///   and logging. The API itself determines the workspace from the API
+    ///   [`Key`], so for it to apply, set a key from the workspace.
+    /// - List requests from [`Self::paginate`], such as admin and usage
+    ///   calls, have a `workspace_id` query parameter.
+    /// - Messages requests have the [`Workspace::overrides`].
+    ///
+    /// [`Key`]: crate::Key
+    pub fn for_workspace<W>(&self, workspace: W) -> Self
+    where
+        W: Into<Workspace>,
+    {
+        let mut client = self.clone();
+        client.workspace = Some(Arc::new(workspace.into()));
+        client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_workspace() {
+        let client = Client::new("x".repeat(108)).unwrap();
+        let tenant = client.for_workspace("wrkspc_01");
+        assert!(client.workspace.is_none());
+        assert_eq!(*tenant.workspace.unwrap(), Workspace::new("wrkspc_01"));
+
+        let workspace = Workspace::new("wrkspc_02")
+            .model(Model::Haiku35)
+            .param("max_tokens", 512);
+        let mut body = json!({
+            "model": "claude-3-5-sonnet-latest",
+            "max_tokens": 4096,
+            "messages": [],
+        });
+        workspace.apply(&mut body);
+        assert_eq!(
+            body,
+            json!({
+                "model": "claude-3-5-haiku-latest",
+                "max_tokens": 512,
+                "messages": [],
+            })
+        );
+
+        let request = client
+            .for_workspace(workspace)
+            .request_raw(reqwest::Method::GET, "https://example.com")
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[Client::WORKSPACE_ID], "wrkspc_02");
+    }
+}
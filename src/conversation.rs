@@ -0,0 +1,740 @@
+//! A [`Conversation`] history which can be archived in a stable, versioned
+//! JSONL session format.
+//!
+//! # Session format
+//!
+//! This is version `1` of the format. Each line is a JSON object with a
+//! `type`. The first line is the `session` header:
+//! - `version` - Format version. Readers reject versions newer than they
+//!   support.
+//! - `model` - Default [`Model`] for the conversation.
+//! - `system` - (optional) System prompt, as a string or an array of content
+//!   blocks.
+//! - `created_at` - (optional) Unix time in milliseconds.
+//!
+//! Every following line is a `message`:
+//! - `role` - `user` or `assistant`.
+//! - `content` - A string or an array of content blocks, as in the [Messages
+//!   API].
+//! - `id` - (optional) Id of the response message.
+//! - `model` - (optional) [`Model`] which generated the message.
+//! - `usage` - (optional) [`Usage`] for the response message.
+//! - `stop_reason` - (optional) [`StopReason`] of the response message.
+//! - `timestamp` - (optional) Unix time in milliseconds.
+//! - `latency` - (optional) Duration of the request which generated the
+//!   message, in milliseconds.
+//!
+//! Optional fields may be omitted or `null`. Unknown fields are ignored, so
+//! fields may be added without a version bump.
+//!
+//! ```json
+//! {"type":"session","version":1,"model":"claude-3-5-sonnet-latest","created_at":1727740800000}
+//! {"type":"message","role":"user","content":"Hi!","timestamp":1727740801000}
+//! {"type":"message","role":"assistant","content":[{"type":"text","text":"Hello!"}],"id":"msg_123","model":"claude-3-5-sonnet-20240620","usage":{"input_tokens":9,"output_tokens":5},"stop_reason":"end_turn","timestamp":1727740802000,"latency":1250}
+//! ```
+//!
+//! With the `markdown` feature, a [`Conversation`] can be rendered with the
+//! timestamps and latency of each message. See
+//! `markdown::Options::with_timestamps`.
+//!
+//! [Messages API]: <https://docs.anthropic.com/en/api/messages>
+//! [`StopReason`]: crate::response::StopReason
+use std::{
+    borrow::Cow,
+    io::{BufRead, Write},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    prompt::{self, message::Content},
+    response::{self, StopReason, Usage},
+    Model, Prompt,
+};
+
+pub mod branch;
+pub use branch::BranchTree;
+
+pub mod compact;
+pub use compact::CompactionPolicy;
+
+pub mod context;
+pub use context::ContextProvider;
+
+pub mod regenerate;
+pub use regenerate::{RegenerateOptions, Regenerated};
+
+pub mod resume;
+
+/// Current version of the session format.
+pub const VERSION: u32 = 1;
+
+/// Error reading or writing a [`Conversation`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// IO error.
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+    /// A line could not be parsed or serialized.
+    #[error("Parse error on line {line}: {error}")]
+    #[allow(missing_docs)]
+    Parse {
+        line: usize,
+        error: serde_json::Error,
+    },
+    /// The first line is not a `session` header.
+    #[error("Missing session header.")]
+    MissingHeader,
+    /// A second `session` header was found.
+    #[error("Unexpected session header on line {line}.")]
+    #[allow(missing_docs)]
+    UnexpectedHeader { line: usize },
+    /// The session was written by a newer version of the format.
+    #[error("Unsupported session version {version}. The latest supported is {VERSION}.")]
+    #[allow(missing_docs)]
+    Version { version: u32 },
+}
+
+/// A [`prompt::Message`] in a [`Conversation`] with metadata.
+#[derive(Clone, Debug)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Entry<'a> {
+    /// The message.
+    pub message: prompt::Message<'a>,
+    /// Id of the [`response::Message`], if any.
+    pub id: Option<Cow<'a, str>>,
+    /// [`Model`] which generated the message, if any.
+    pub model: Option<Model>,
+    /// [`Usage`] for the [`response::Message`], if any.
+    pub usage: Option<Usage>,
+    /// [`StopReason`] of the [`response::Message`], if any.
+    pub stop_reason: Option<StopReason>,
+    /// When the message was added, if known.
+    pub timestamp: Option<SystemTime>,
+    /// How long the request which generated the message took, if known.
+    pub latency: Option<Duration>,
+}
+
+impl<'a> Entry<'a> {
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Entry<'static> {
+        Entry {
+            message: self.message.into_static(),
+            id: self.id.map(|id| Cow::Owned(id.into_owned())),
+            model: self.model,
+            usage: self.usage,
+            stop_reason: self.stop_reason,
+            timestamp: self.timestamp,
+            latency: self.latency,
+        }
+    }
+
+    /// Set the [`Self::latency`], for example the time it took to get a
+    /// [`response::Message`].
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use misanthropic::{conversation::Entry, prompt::Message};
+    ///
+    /// let entry = Entry::from(Message::assistant("Hi!"))
+    ///     .latency(Duration::from_millis(1250));
+    /// assert_eq!(entry.latency, Some(Duration::from_millis(1250)));
+    /// ```
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+}
+
+impl<'a> From<prompt::Message<'a>> for Entry<'a> {
+    fn from(message: prompt::Message<'a>) -> Self {
+        Self {
+            message,
+            id: None,
+            model: None,
+            usage: None,
+            stop_reason: None,
+            timestamp: Some(now()),
+            latency: None,
+        }
+    }
+}
+
+impl<'a> From<response::Message<'a>> for Entry<'a> {
+    fn from(message: response::Message<'a>) -> Self {
+        Self {
+            message: message.message,
+            id: Some(message.id),
+            model: Some(message.model),
+            usage: Some(message.usage),
+            stop_reason: message.stop_reason,
+            timestamp: Some(now()),
+            latency: None,
+        }
+    }
+}
+
+/// A conversation history with metadata. Use [`Conversation::prompt`] to
+/// continue it and [`Conversation::to_jsonl`] to archive it. See the
+/// [module](self) documentation for the session format.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Conversation<'a> {
+    /// Default [`Model`] for the conversation.
+    pub model: Model,
+    /// System prompt, if any.
+    pub system: Option<Content<'a>>,
+    /// Messages with metadata. Entries are reference counted so they are
+    /// shared with [forks](Conversation::fork_at). Use [`Arc::make_mut`] to
+    /// edit one, which clones it only if it is shared.
+    pub entries: Vec<Arc<Entry<'a>>>,
+    /// When the conversation was created, if known.
+    pub created_at: Option<SystemTime>,
+    /// Queried for documents on every user turn, if set. See
+    /// [`Conversation::with_context_provider`]. This is not archived.
+    pub context_provider: Option<context::SharedContextProvider>,
+}
+
+impl<'a> Conversation<'a> {
+    /// Create a new, empty, [`Conversation`] with a [`Model`].
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            created_at: Some(now()),
+            ..Default::default()
+        }
+    }
+
+    /// Set the system prompt.
+    pub fn system(mut self, system: impl Into<Content<'a>>) -> Self {
+        self.system = Some(system.into());
+        self
+    }
+
+    /// Add a [`prompt::Message`] or [`response::Message`]. Usage and other
+    /// metadata from a [`response::Message`] is kept.
+    pub fn push(&mut self, message: impl Into<Entry<'a>>) {
+        self.entries.push(Arc::new(message.into()));
+    }
+
+    /// Returns an iterator over the [`prompt::Message`]s.
+    pub fn messages(&self) -> impl Iterator<Item = &prompt::Message<'a>> {
+        self.entries.iter().map(|entry| &entry.message)
+    }
+
+    /// Total [`Usage`] of all [`response::Message`]s.
+    pub fn usage(&self) -> Usage {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.usage.as_ref())
+            .fold(Usage::default(), |mut total, usage| {
+                total.input_tokens += usage.input_tokens;
+                total.output_tokens += usage.output_tokens;
+                total
+            })
+    }
+
+    /// Create a [`Prompt`] with the [`Model`], system prompt, and messages,
+    /// to continue the conversation. Documents from the
+    /// [`Self::context_provider`] are added to the latest user turn.
+    pub fn prompt(&self) -> Prompt<'a> {
+        let mut messages: Vec<_> = self.messages().cloned().collect();
+        self.inject_context(&mut messages);
+
+        Prompt {
+            model: self.model,
+            system: self.system.clone(),
+            messages,
+            ..Default::default()
+        }
+    }
+
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Conversation<'static> {
+        Conversation {
+            model: self.model,
+            system: self.system.map(Content::into_static),
+            entries: self
+                .entries
+                .into_iter()
+                .map(|entry| {
+                    Arc::new(Arc::unwrap_or_clone(entry).into_static())
+                })
+                .collect(),
+            created_at: self.created_at,
+            context_provider: self.context_provider,
+        }
+    }
+
+    /// Write the conversation in the JSONL session format. See the
+    /// [module](self) documentation.
+    pub fn to_jsonl<W>(&self, mut writer: W) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let header = SessionRef {
+            kind: "session",
+            version: VERSION,
+            model: self.model,
+            system: self.system.as_ref(),
+            created_at: self.created_at.map(to_millis),
+        };
+        write_line(&mut writer, 1, &header)?;
+
+        for (i, entry) in self.entries.iter().enumerate() {
+            let record = MessageRef {
+                kind: "message",
+                message: &entry.message,
+                id: entry.id.as_deref(),
+                model: entry.model,
+                usage: entry.usage.as_ref(),
+                stop_reason: entry.stop_reason.as_ref(),
+                timestamp: entry.timestamp.map(to_millis),
+                latency: entry
+                    .latency
+                    .map(|latency| latency.as_millis() as u64),
+            };
+            write_line(&mut writer, i + 2, &record)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a conversation in the JSONL session format. See the
+    /// [module](self) documentation. Blank lines are skipped.
+    pub fn from_jsonl<R>(reader: R) -> Result<Conversation<'static>, Error>
+    where
+        R: BufRead,
+    {
+        let mut conversation: Option<Conversation<'static>> = None;
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let record: Line = serde_json::from_str(&line)
+                .map_err(|error| Error::Parse { line: i + 1, error })?;
+
+            match (record, conversation.as_mut()) {
+                (
+                    Line::Session {
+                        version,
+                        model,
+                        system,
+                        created_at,
+                    },
+                    None,
+                ) => {
+                    if version > VERSION {
+                        return Err(Error::Version { version });
+                    }
+                    conversation = Some(Conversation {
+                        model,
+                        system,
+                        entries: vec![],
+                        created_at: created_at.map(from_millis),
+                        context_provider: None,
+                    });
+                }
+                (Line::Session { .. }, Some(_)) => {
+                    return Err(Error::UnexpectedHeader { line: i + 1 });
+                }
+                (Line::Message { .. }, None) => {
+                    return Err(Error::MissingHeader);
+                }
+                (
+                    Line::Message {
+                        message,
+                        id,
+                        model,
+                        usage,
+                        stop_reason,
+                        timestamp,
+                        latency,
+                    },
+                    Some(conversation),
+                ) => conversation.push(Entry {
+                    message: message.into_static(),
+                    id: id.map(Cow::Owned),
+                    model,
+                    usage,
+                    stop_reason,
+                    timestamp: timestamp.map(from_millis),
+                    latency: latency.map(Duration::from_millis),
+                }),
+            }
+        }
+
+        conversation.ok_or(Error::MissingHeader)
+    }
+}
+
+impl<'a> From<Prompt<'a>> for Conversation<'a> {
+    /// Create a [`Conversation`] from the [`Model`], system prompt, and
+    /// messages of a [`Prompt`]. Other parameters are discarded.
+    fn from(prompt: Prompt<'a>) -> Self {
+        Self {
+            model: prompt.model,
+            system: prompt.system,
+            entries: prompt
+                .messages
+                .into_iter()
+                .map(|message| {
+                    Arc::new(Entry {
+                        timestamp: None,
+                        ..message.into()
+                    })
+                })
+                .collect(),
+            created_at: None,
+            context_provider: None,
+        }
+    }
+}
+
+#[cfg(feature = "markdown")]
+impl crate::markdown::ToMarkdown for Conversation<'_> {
+    /// Format the [`Conversation`] as markdown like a [`Prompt`]. With
+    /// [`Options::timestamps`], each message heading is followed by when the
+    /// message was added and the latency of its request, if known.
+    ///
+    /// [`Options::timestamps`]: crate::markdown::Options::timestamps
+    fn markdown_events_custom<'a>(
+        &'a self,
+        options: crate::markdown::Options,
+    ) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
+        let system =
+            prompt::system_markdown_events(self.system.as_ref(), options);
+        let entries = self.entries.iter().flat_map(move |entry| {
+            let mut events = entry.message.markdown_events_custom(options);
+            let info = entry.info().filter(|_| options.timestamps);
+            // A rendered message starts with the three events of its heading.
+            let heading: Vec<_> = match info {
+                Some(_) => events.by_ref().take(3).collect(),
+                None => vec![],
+            };
+            let info = info.filter(|_| heading.len() == 3).map(|info| {
+                use pulldown_cmark::{Event, Tag, TagEnd};
+
+                [
+                    Event::Start(Tag::Paragraph),
+                    Event::Start(Tag::Emphasis),
+                    Event::Text(info.into()),
+                    Event::End(TagEnd::Emphasis),
+                    Event::End(TagEnd::Paragraph),
+                ]
+            });
+
+            heading
+                .into_iter()
+                .chain(info.into_iter().flatten())
+                .chain(events)
+        });
+
+        Box::new(system.chain(entries))
+    }
+}
+
+#[cfg(feature = "markdown")]
+impl Entry<'_> {
+    /// The timestamp and latency as text, such as
+    /// `2024-10-01 00:00:02 UTC, 1.25 s`, if either is known.
+    fn info(&self) -> Option<String> {
+        let timestamp = self.timestamp.map(format_utc);
+        let latency = self
+            .latency
+            .map(|latency| format!("{:.2} s", latency.as_secs_f64()));
+
+        match (timestamp, latency) {
+            (Some(timestamp), Some(latency)) => {
+                Some(format!("{timestamp}, {latency}"))
+            }
+            (timestamp, latency) => timestamp.or(latency),
+        }
+    }
+}
+
+/// A line of the session format, for reading.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line<'a> {
+    Session {
+        version: u32,
+        model: Model,
+        #[serde(default)]
+        system: Option<Content<'a>>,
+        #[serde(default)]
+        created_at: Option<u64>,
+    },
+    Message {
+        #[serde(flatten)]
+        message: prompt::Message<'a>,
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        model: Option<Model>,
+        #[serde(default)]
+        usage: Option<Usage>,
+        #[serde(default)]
+        stop_reason: Option<StopReason>,
+        #[serde(default)]
+        timestamp: Option<u64>,
+        #[serde(default)]
+        latency: Option<u64>,
+    },
+}
+
+/// The `session` header, for writing without copying.
+#[derive(Serialize)]
+struct SessionRef<'r, 'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    version: u32,
+    model: Model,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'r Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<u64>,
+}
+
+/// A `message` line, for writing without copying.
+#[derive(Serialize)]
+struct MessageRef<'r, 'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(flatten)]
+    message: &'r prompt::Message<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<&'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<Model>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<&'r Usage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_reason: Option<&'r StopReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency: Option<u64>,
+}
+
+fn write_line<W, T>(writer: &mut W, line: usize, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: Serialize,
+{
+    serde_json::to_writer(&mut *writer, value)
+        .map_err(|error| Error::Parse { line, error })?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// The current time. [`SystemTime::now`] panics in the browser, so there the
+/// time comes from JavaScript.
+pub(crate) fn now() -> SystemTime {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        SystemTime::now()
+    }
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        UNIX_EPOCH
+            + web_time::SystemTime::now()
+                .duration_since(web_time::UNIX_EPOCH)
+                .unwrap_or_default()
+    }
+}
+
+pub(crate) fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        // Times before 1970 are not supported.
+        .unwrap_or_default()
+}
+
+pub(crate) fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+/// Format `time` as `YYYY-MM-DD hh:mm:ss UTC`, to the second.
+#[cfg(feature = "markdown")]
+pub(crate) fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        // Times before 1970 are not supported.
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // From Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::message::Role;
+
+    const SESSION: &str = r#"{"type":"session","version":1,"model":"claude-3-5-sonnet-latest","created_at":1727740800000}
+{"type":"message","role":"user","content":"Hi!","timestamp":1727740801000}
+
+{"type":"message","role":"assistant","content":[{"type":"text","text":"Hello!"}],"id":"msg_123","model":"claude-3-5-sonnet-20240620","usage":{"input_tokens":9,"output_tokens":5},"timestamp":1727740802000,"future_field":true}
+"#;
+
+    #[test]
+    fn test_from_jsonl() {
+        let conversation =
+            Conversation::from_jsonl(SESSION.as_bytes()).unwrap();
+
+        assert_eq!(conversation.model, Model::Sonnet35);
+        assert!(conversation.system.is_none());
+        assert_eq!(conversation.created_at, Some(from_millis(1727740800000)));
+        assert_eq!(conversation.entries.len(), 2);
+
+        let entry = &conversation.entries[1];
+        assert_eq!(entry.message.role, Role::Assistant);
+        assert_eq!(entry.message.text(), "Hello!");
+        assert_eq!(entry.id.as_deref(), Some("msg_123"));
+        assert_eq!(entry.model, Some(Model::Sonnet35_20240620));
+        assert_eq!(entry.timestamp, Some(from_millis(1727740802000)));
+        assert_eq!(conversation.usage().output_tokens, 5);
+
+        let prompt = conversation.prompt();
+        assert_eq!(prompt.messages.len(), 2);
+        assert_eq!(prompt.model, Model::Sonnet35);
+    }
+
+    #[test]
+    fn test_jsonl_roundtrip() {
+        let mut conversation =
+            Conversation::new(Model::Sonnet35).system("Be nice.");
+        conversation.push(prompt::Message {
+            role: Role::User,
+            content: "Hi!".into(),
+        });
+        let response: response::Message = serde_json::from_str(
+            crate::response::message::tests::RESPONSE_JSON,
+        )
+        .unwrap();
+        conversation.push(response);
+
+        let mut jsonl = vec![];
+        conversation.to_jsonl(&mut jsonl).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert_eq!(jsonl.lines().count(), 3);
+        assert!(jsonl.starts_with(r#"{"type":"session","version":1,"#));
+        assert!(jsonl.contains(r#""stop_reason":"end_turn""#));
+
+        // Timestamps are stored with millisecond precision.
+        let truncate = |time: Option<SystemTime>| time.map(to_millis);
+        let read = Conversation::from_jsonl(jsonl.as_bytes()).unwrap();
+        assert_eq!(read.system, conversation.system);
+        assert_eq!(
+            truncate(read.created_at),
+            truncate(conversation.created_at)
+        );
+        for (read, entry) in read.entries.iter().zip(&conversation.entries) {
+            assert_eq!(read.message, entry.message);
+            assert_eq!(read.id, entry.id);
+            assert_eq!(read.usage, entry.usage);
+            assert_eq!(read.stop_reason, entry.stop_reason);
+            assert_eq!(truncate(read.timestamp), truncate(entry.timestamp));
+            assert_eq!(read.latency, entry.latency);
+        }
+    }
+
+    #[test]
+    fn test_latency() {
+        let mut conversation = Conversation::new(Model::Sonnet35);
+        conversation.push(
+            Entry::from(prompt::Message::assistant("Hello!"))
+                .latency(Duration::from_millis(1250)),
+        );
+
+        let mut jsonl = vec![];
+        conversation.to_jsonl(&mut jsonl).unwrap();
+        assert!(String::from_utf8_lossy(&jsonl).contains(r#""latency":1250"#));
+        let read = Conversation::from_jsonl(jsonl.as_slice()).unwrap();
+        assert_eq!(read.entries[0].latency, Some(Duration::from_millis(1250)));
+    }
+
+    #[test]
+    #[cfg(feature = "markdown")]
+    fn test_markdown_timestamps() {
+        use crate::markdown::{Options, ToMarkdown};
+
+        let mut conversation =
+            Conversation::from_jsonl(SESSION.as_bytes()).unwrap();
+        Arc::make_mut(&mut conversation.entries[1]).latency =
+            Some(Duration::from_millis(1250));
+
+        assert_eq!(
+            conversation.markdown().as_ref(),
+            conversation.prompt().markdown().as_ref()
+        );
+        assert_eq!(
+            conversation
+                .markdown_custom(Options::default().with_timestamps())
+                .as_ref(),
+            "### User\n\n*2024-10-01 00:00:01 UTC*\n\nHi!\n\n### Assistant\n\n*2024-10-01 00:00:02 UTC, 1.25 s*\n\nHello!"
+        );
+
+        assert_eq!(format_utc(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        assert_eq!(
+            format_utc(from_millis(951_782_400_000)),
+            "2000-02-29 00:00:00 UTC"
+        );
+    }
+
+    #[test]
+    fn test_from_jsonl_errors() {
+        let message = SESSION.lines().nth(1).unwrap();
+        assert!(matches!(
+            Conversation::from_jsonl(message.as_bytes()),
+            Err(Error::MissingHeader)
+        ));
+        assert!(matches!(
+            Conversation::from_jsonl("".as_bytes()),
+            Err(Error::MissingHeader)
+        ));
+
+        let header = SESSION.lines().next().unwrap();
+        let twice = format!("{header}\n{header}");
+        assert!(matches!(
+            Conversation::from_jsonl(twice.as_bytes()),
+            Err(Error::UnexpectedHeader { line: 2 })
+        ));
+
+        let future = header.replace(r#""version":1"#, r#""version":2"#);
+        assert!(matches!(
+            Conversation::from_jsonl(future.as_bytes()),
+            Err(Error::Version { version: 2 })
+        ));
+
+        assert!(matches!(
+            Conversation::from_jsonl(format!("{header}\nnope").as_bytes()),
+            Err(Error::Parse { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+//! Branching of [`Conversation`]s, for "edit and regenerate from here" UIs.
+//! See [`Conversation::fork_at`] and [`BranchTree`].
+use super::{now, Conversation};
+
+impl<'a> Conversation<'a> {
+    /// A new conversation with the first `index` entries of this one, the
+    /// same [`Model`] and system prompt, and the same context provider. An
+    /// `index` past the end copies every entry.
+    ///
+    /// The entries are reference counted, so the prefix is shared with this
+    /// conversation rather than copied. Only the list of pointers is
+    /// allocated. Editing a shared entry with [`Arc::make_mut`] clones it.
+    ///
+    /// [`Arc::make_mut`]: std::sync::Arc::make_mut
+    ///
+    /// ```
+    /// use misanthropic::{prompt::Message, Conversation, Model};
+    ///
+    /// let mut conversation = Conversation::new(Model::default());
+    /// conversation.push(Message::user("Write a haiku."));
+    /// conversation.push(Message::assistant("An old silent pond..."));
+    ///
+    /// // Edit the first message and regenerate from there.
+    /// let mut edited = conversation.fork_at(0);
+    /// edited.push(Message::user("Write a limerick."));
+    /// assert_eq!(edited.entries.len(), 1);
+    /// assert_eq!(conversation.entries.len(), 2);
+    /// ```
+    ///
+    /// [`Model`]: crate::Model
+    pub fn fork_at(&self, index: usize) -> Self {
+        let index = index.min(self.entries.len());
+
+        Self {
+            model: self.model,
+            system: self.system.clone(),
+            entries: self.entries[..index].to_vec(),
+            created_at: Some(now()),
+            context_provider: self.context_provider.clone(),
+        }
+    }
+}
+
+/// Id of a [`Branch`] in a [`BranchTree`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BranchId(usize);
+
+/// A named [`Conversation`] in a [`BranchTree`].
+#[derive(Clone, Debug)]
+pub struct Branch<'a> {
+    /// Name of the branch, such as "main" or "edit 2".
+    pub name: String,
+    /// Branch this was forked from, if any.
+    pub parent: Option<BranchId>,
+    /// Number of entries at the start of the [`Self::conversation`] which
+    /// were forked from the parent.
+    pub fork_index: usize,
+    /// The conversation.
+    pub conversation: Conversation<'a>,
+}
+
+/// A tree of named [`Conversation`] branches, which tracks which branch each
+/// was forked from and where.
+///
+/// ```
+/// use misanthropic::{
+///     conversation::BranchTree, prompt::Message, Conversation, Model,
+/// };
+///
+/// let mut conversation = Conversation::new(Model::default());
+/// conversation.push(Message::user("Hi!"));
+/// conversation.push(Message::assistant("Hello!"));
+///
+/// let mut tree = BranchTree::new("main", conversation);
+/// let main = tree.root();
+/// let retry = tree.fork(main, 1, "retry").unwrap();
+/// let branch = tree.get_mut(retry).unwrap();
+/// branch.conversation.push(Message::assistant("Hey there!"));
+///
+/// assert_eq!(tree.find("retry"), Some(retry));
+/// assert_eq!(tree.get(retry).unwrap().parent, Some(main));
+/// assert_eq!(tree.children(main).collect::<Vec<_>>(), [retry]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BranchTree<'a> {
+    branches: Vec<Branch<'a>>,
+}
+
+impl<'a> BranchTree<'a> {
+    /// Create a tree with a root branch.
+    pub fn new<S>(name: S, conversation: Conversation<'a>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            branches: vec![Branch {
+                name: name.into(),
+                parent: None,
+                fork_index: 0,
+                conversation,
+            }],
+        }
+    }
+
+    /// Id of the root branch.
+    pub const fn root(&self) -> BranchId {
+        BranchId(0)
+    }
+
+    /// Number of branches. There is always at least the root.
+    pub fn len(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Always `false`, since there is always a root branch.
+    pub fn is_empty(&self) -> bool {
+        self.branches.is_empty()
+    }
+
+    /// Fork a new branch named `name` from the first `index` entries of
+    /// `parent`. See [`Conversation::fork_at`]. Returns [`None`] if `parent`
+    /// is not in this tree.
+    pub fn fork<S>(
+        &mut self,
+        parent: BranchId,
+        index: usize,
+        name: S,
+    ) -> Option<BranchId>
+    where
+        S: Into<String>,
+    {
+        let conversation = self.get(parent)?.conversation.fork_at(index);
+        let id = BranchId(self.branches.len());
+        self.branches.push(Branch {
+            name: name.into(),
+            parent: Some(parent),
+            fork_index: conversation.entries.len(),
+            conversation,
+        });
+
+        Some(id)
+    }
+
+    /// The branch with id `id`, if any.
+    pub fn get(&self, id: BranchId) -> Option<&Branch<'a>> {
+        self.branches.get(id.0)
+    }
+
+    /// The branch with id `id`, if any, mutably.
+    pub fn get_mut(&mut self, id: BranchId) -> Option<&mut Branch<'a>> {
+        self.branches.get_mut(id.0)
+    }
+
+    /// Id of the first branch named `name`, if any.
+    pub fn find(&self, name: &str) -> Option<BranchId> {
+        self.branches
+            .iter()
+            .position(|branch| branch.name == name)
+            .map(BranchId)
+    }
+
+    /// Ids of the branches forked from `id`, in the order they were forked.
+    pub fn children(
+        &self,
+        id: BranchId,
+    ) -> impl Iterator<Item = BranchId> + '_ {
+        self.iter()
+            .filter(move |(_, branch)| branch.parent == Some(id))
+            .map(|(id, _)| id)
+    }
+
+    /// Ids of the ancestors of `id`, from its parent to the root.
+    pub fn ancestors(
+        &self,
+        id: BranchId,
+    ) -> impl Iterator<Item = BranchId> + '_ {
+        std::iter::successors(self.get(id).and_then(|b| b.parent), |id| {
+            self.get(*id).and_then(|b| b.parent)
+        })
+    }
+
+    /// Iterate over every branch and its id, in the order they were created.
+    pub fn iter(&self) -> impl Iterator<Item = (BranchId, &Branch<'a>)> {
+        self.branches
+            .iter()
+            .enumerate()
+            .map(|(i, branch)| (BranchId(i), branch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{prompt::Message, Model};
+
+    fn conversation() -> Conversation<'static> {
+        let mut conversation =
+            Conversation::new(Model::Sonnet35).system("Be brief.");
+        conversation.push(Message::user("Hi!"));
+        conversation.push(Message::assistant("Hello!"));
+        conversation.push(Message::user("Tell me a joke."));
+        conversation
+    }
+
+    #[test]
+    fn test_fork_at() {
+        let conversation = conversation();
+
+        let fork = conversation.fork_at(2);
+        assert_eq!(fork.entries[..], conversation.entries[..2]);
+        assert_eq!(fork.model, conversation.model);
+        assert_eq!(fork.system, conversation.system);
+
+        assert!(conversation.fork_at(0).entries.is_empty());
+        assert_eq!(conversation.fork_at(100).entries, conversation.entries);
+    }
+
+    #[test]
+    fn test_fork_at_shares_prefix() {
+        let conversation = conversation();
+
+        let mut fork = conversation.fork_at(2);
+        for (forked, entry) in fork.entries.iter().zip(&conversation.entries) {
+            assert!(Arc::ptr_eq(forked, entry));
+        }
+
+        // Editing the fork doesn't change the original.
+        Arc::make_mut(&mut fork.entries[1]).message = Message::assistant("Hi!");
+        assert!(!Arc::ptr_eq(&fork.entries[1], &conversation.entries[1]));
+        assert!(Arc::ptr_eq(&fork.entries[0], &conversation.entries[0]));
+        assert_eq!(conversation.entries[1].message.text(), "Hello!");
+    }
+
+    #[test]
+    fn test_branch_tree() {
+        let mut tree = BranchTree::new("main", conversation());
+        let main = tree.root();
+        assert_eq!(tree.len(), 1);
+        assert!(!tree.is_empty());
+
+        let a = tree.fork(main, 1, "a").unwrap();
+        let b = tree.fork(main, 2, "b").unwrap();
+        let a1 = tree.fork(a, 1, "a1").unwrap();
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree.get(b).unwrap().fork_index, 2);
+        assert_eq!(tree.get(a1).unwrap().conversation.entries.len(), 1);
+
+        assert_eq!(tree.children(main).collect::<Vec<_>>(), [a, b]);
+        assert_eq!(tree.ancestors(a1).collect::<Vec<_>>(), [a, main]);
+        assert_eq!(tree.ancestors(main).count(), 0);
+        assert_eq!(tree.find("a1"), Some(a1));
+        assert_eq!(tree.find("c"), None);
+
+        // Ids from another tree.
+        assert!(tree.fork(BranchId(100), 0, "c").is_none());
+        assert!(tree.get(BranchId(100)).is_none());
+
+        tree.get_mut(b).unwrap().name = "b2".into();
+        assert_eq!(tree.iter().map(|(_, b)| &*b.name).last(), Some("a1"));
+        assert_eq!(tree.find("b2"), Some(b));
+    }
+}
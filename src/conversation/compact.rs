@@ -0,0 +1,353 @@
+//! Summarize older turns of a [`Conversation`] to keep it within a token
+//! budget. See [`Conversation::compact`].
+use std::{num::NonZeroU16, sync::Arc};
+
+use super::{Conversation, Entry};
+use crate::{
+    client,
+    prompt::{
+        self,
+        message::{Block, Content, Role},
+    },
+    throttle, Client, Model, Prompt,
+};
+
+/// Default instructions for the summarizer.
+pub const DEFAULT_INSTRUCTIONS: &str = "\
+Summarize the conversation transcript you are given so it can replace the \
+transcript as context for continuing the conversation. If there is a previous \
+summary, include what is still relevant from it. Keep facts, decisions, names, \
+numbers, open questions, and the results of tool calls. Be concise. Respond \
+with only the summary.";
+
+/// Tag wrapping the summary in the system prompt.
+const SUMMARY_TAG: &str = "conversation_summary";
+
+/// When and how to compact a [`Conversation`]. See
+/// [`Conversation::compact`].
+#[derive(Clone, Debug)]
+pub struct CompactionPolicy {
+    max_tokens: u64,
+    keep_recent: usize,
+    model: Model,
+    instructions: String,
+    summary_max_tokens: NonZeroU16,
+}
+
+impl CompactionPolicy {
+    /// Compact when the estimated input tokens of the conversation exceed
+    /// `max_tokens`. Tokens are estimated with
+    /// [`throttle::estimate_input_tokens`].
+    pub fn new(max_tokens: u64) -> Self {
+        Self {
+            max_tokens,
+            keep_recent: 6,
+            model: Model::Haiku35,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            summary_max_tokens: NonZeroU16::new(1024).unwrap(),
+        }
+    }
+
+    /// Keep at least the last `keep_recent` messages as they are. More may be
+    /// kept so the kept messages start with a [`User`] turn and no tool
+    /// result is separated from its tool use. The default is 6.
+    ///
+    /// [`User`]: Role::User
+    pub fn keep_recent(mut self, keep_recent: usize) -> Self {
+        self.keep_recent = keep_recent;
+        self
+    }
+
+    /// Set the summarizer [`Model`]. The default is [`Model::Haiku35`].
+    pub fn model(mut self, model: Model) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Set the summarizer system prompt. See [`DEFAULT_INSTRUCTIONS`].
+    pub fn instructions<S>(mut self, instructions: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.instructions = instructions.into();
+        self
+    }
+
+    /// Set the `max_tokens` of the summary. The default is 1024.
+    pub fn summary_max_tokens(mut self, max_tokens: NonZeroU16) -> Self {
+        self.summary_max_tokens = max_tokens;
+        self
+    }
+
+    /// Index of the first entry to keep, if there is anything to summarize.
+    fn split(&self, entries: &[Arc<Entry>]) -> Option<usize> {
+        let mut split = entries.len().saturating_sub(self.keep_recent);
+        while split > 0 && entries.get(split).is_some_and(|e| !starts_turn(e)) {
+            split -= 1;
+        }
+
+        (split > 0).then_some(split)
+    }
+
+    /// The [`Prompt`] to summarize `entries` and any `previous` summary.
+    fn prompt(
+        &self,
+        previous: Option<&str>,
+        entries: &[Arc<Entry>],
+    ) -> Prompt<'static> {
+        let mut request = String::new();
+        if let Some(previous) = previous {
+            request.push_str("<previous_summary>\n");
+            request.push_str(previous);
+            request.push_str("\n</previous_summary>\n");
+        }
+        request.push_str("<transcript>\n");
+        request.push_str(&transcript(entries));
+        request.push_str("</transcript>");
+
+        Prompt::default()
+            .model(self.model)
+            .max_tokens(self.summary_max_tokens)
+            .system(self.instructions.clone())
+            .add_message((Role::User, request))
+    }
+}
+
+impl Conversation<'_> {
+    /// If the conversation exceeds the `policy` token budget, replace older
+    /// turns with a summary written by the summarizer [`Model`]. The summary
+    /// is kept in a block of the [`system`] prompt, replacing the summary of
+    /// any previous compaction. Recent turns are kept as they are. Returns
+    /// whether the conversation was compacted.
+    ///
+    /// Metadata of the summarized entries, including [`Usage`], is
+    /// discarded. Archive the conversation first with
+    /// [`Conversation::to_jsonl`] to keep it.
+    ///
+    /// [`system`]: Conversation::system
+    /// [`Usage`]: crate::response::Usage
+    pub async fn compact(
+        &mut self,
+        client: &Client,
+        policy: &CompactionPolicy,
+    ) -> client::Result<bool> {
+        let tokens = throttle::estimate_input_tokens(&serde_json::to_value(
+            self.prompt(),
+        )?);
+        if tokens <= policy.max_tokens {
+            return Ok(false);
+        }
+        let Some(split) = policy.split(&self.entries) else {
+            return Ok(false);
+        };
+
+        let prompt = policy.prompt(self.summary(), &self.entries[..split]);
+        let summary = client.message(prompt).await?.text().trim().to_string();
+
+        self.entries.drain(..split);
+        self.set_summary(summary);
+
+        Ok(true)
+    }
+
+    /// The summary from a previous [`Conversation::compact`], if any.
+    fn summary(&self) -> Option<&str> {
+        self.system.as_ref()?.iter().find_map(|block| match block {
+            Block::Text { text, .. } => summary_text(text),
+            _ => None,
+        })
+    }
+
+    /// Set the summary block of the [`system`](Conversation::system) prompt.
+    fn set_summary(&mut self, summary: String) {
+        let block = Block::text(format!(
+            "<{SUMMARY_TAG}>\n{summary}\n</{SUMMARY_TAG}>"
+        ));
+
+        let system = self.system.get_or_insert_with(Content::default);
+        let existing = system.iter_mut().find(|block| {
+            matches!(
+                block,
+                Block::Text { text, .. } if summary_text(text).is_some()
+            )
+        });
+        match existing {
+            Some(existing) => *existing = block,
+            None => system.push(block),
+        }
+    }
+}
+
+/// The summary inside a summary block's `text`, if it is one.
+fn summary_text(text: &str) -> Option<&str> {
+    text.strip_prefix(&format!("<{SUMMARY_TAG}>"))?
+        .strip_suffix(&format!("</{SUMMARY_TAG}>"))
+        .map(str::trim)
+}
+
+/// Whether the kept messages can start with an `entry`: a [`User`] message
+/// which is not a tool result.
+///
+/// [`User`]: Role::User
+fn starts_turn(entry: &Entry) -> bool {
+    let message = &entry.message;
+    message.role == Role::User
+        && !message
+            .content
+            .iter()
+            .any(|b| matches!(b, Block::ToolResult { .. }))
+}
+
+/// Plain text transcript of `entries` for the summarizer.
+fn transcript(entries: &[Arc<Entry>]) -> String {
+    let mut transcript = String::new();
+    for entry in entries {
+        transcript.push_str(match entry.message.role {
+            Role::User => "User:",
+            Role::Assistant => "Assistant:",
+        });
+        push_content(&mut transcript, &entry.message);
+        transcript.push('\n');
+    }
+
+    transcript
+}
+
+fn push_content(transcript: &mut String, message: &prompt::Message) {
+    for block in message.content.iter() {
+        transcript.push(' ');
+        match block {
+            Block::Text { text, .. } => transcript.push_str(text),
+            Block::ToolUse { call } | Block::ServerToolUse { call } => {
+                transcript.push_str(&format!(
+                    "[Tool call `{}`: {}]",
+                    call.name, call.input
+                ));
+            }
+            Block::ToolResult { result } => {
+                let text = prompt::Message {
+                    role: Role::User,
+                    content: result.content.clone(),
+                }
+                .text()
+                .into_owned();
+                let label = match result.is_error {
+                    true => "Tool error",
+                    false => "Tool result",
+                };
+                transcript.push_str(&format!("[{label}: {text}]"));
+            }
+            Block::Image { .. } => transcript.push_str("[Image]"),
+            _ => transcript.push_str("[Other content]"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool;
+
+    fn entries() -> Vec<Arc<Entry<'static>>> {
+        let tool_use = Block::ToolUse {
+            call: tool::Use {
+                id: "toolu_1".into(),
+                name: "weather".into(),
+                input: serde_json::json!({"city": "Paris"}),
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            },
+        };
+        let tool_result = Block::ToolResult {
+            result: tool::Result {
+                tool_use_id: "toolu_1".into(),
+                content: "Sunny".into(),
+                is_error: false,
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            },
+        };
+
+        [
+            (Role::User, Content::text("Hi, I'm Ada.")),
+            (Role::Assistant, Content::text("Hello Ada!")),
+            (Role::User, Content::text("Weather in Paris?")),
+            (Role::Assistant, Content::from(vec![tool_use])),
+            (Role::User, Content::from(vec![tool_result])),
+            (Role::Assistant, Content::text("It's sunny.")),
+        ]
+        .into_iter()
+        .map(|(role, content)| Entry::from(prompt::Message { role, content }))
+        .map(Arc::new)
+        .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        let entries = entries();
+        let policy = CompactionPolicy::new(0);
+
+        // The tool result and tool use are kept with the question.
+        assert_eq!(policy.clone().keep_recent(2).split(&entries), Some(2));
+        assert_eq!(policy.clone().keep_recent(4).split(&entries), Some(2));
+        assert_eq!(policy.clone().keep_recent(0).split(&entries), Some(6));
+        // Nothing to summarize.
+        assert_eq!(policy.clone().keep_recent(5).split(&entries), None);
+        assert_eq!(policy.keep_recent(6).split(&entries), None);
+    }
+
+    #[test]
+    fn test_prompt() {
+        let entries = entries();
+        let prompt = CompactionPolicy::new(0)
+            .model(Model::Sonnet35)
+            .prompt(Some("Ada said hi."), &entries[2..]);
+
+        assert_eq!(prompt.model, Model::Sonnet35);
+        assert_eq!(prompt.messages.len(), 1);
+        assert_eq!(
+            prompt.messages[0].text(),
+            "<previous_summary>\nAda said hi.\n</previous_summary>\n\
+             <transcript>\n\
+             User: Weather in Paris?\n\
+             Assistant: [Tool call `weather`: {\"city\":\"Paris\"}]\n\
+             User: [Tool result: Sunny]\n\
+             Assistant: It's sunny.\n\
+             </transcript>"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut conversation =
+            Conversation::new(Model::default()).system("Be nice.");
+        assert_eq!(conversation.summary(), None);
+
+        conversation.set_summary("First.".into());
+        assert_eq!(conversation.summary(), Some("First."));
+        conversation.set_summary("Second.".into());
+        assert_eq!(conversation.summary(), Some("Second."));
+
+        let Some(blocks) = conversation.system.as_ref().map(Content::as_slice)
+        else {
+            panic!("Expected a system prompt.");
+        };
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0], Block::text("Be nice."));
+    }
+
+    #[tokio::test]
+    async fn test_compact_under_budget() {
+        let client = Client::new("x".repeat(108)).unwrap();
+        let mut conversation = Conversation::new(Model::default());
+        conversation.entries = entries();
+
+        // No request is made when under budget.
+        let compacted = conversation
+            .compact(&client, &CompactionPolicy::new(10_000))
+            .await
+            .unwrap();
+        assert!(!compacted);
+        assert_eq!(conversation.entries.len(), 6);
+    }
+}
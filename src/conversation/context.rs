@@ -0,0 +1,245 @@
+//! Retrieval augmented generation for a [`Conversation`]. A [`ContextProvider`]
+//! set with [`Conversation::with_context_provider`] is queried with the text
+//! of the latest [`User`] turn, and its [`Document`]s are put before that turn
+//! in the [`Conversation::prompt`].
+//!
+//! The documents are not stored in the conversation, so on the next turn they
+//! are replaced with the documents for that turn. With the `prompt-caching`
+//! feature, cache breakpoints are set after the history before the turn,
+//! which is the same on the next turn, and after the documents, which are the
+//! same while tools are used during the turn.
+//!
+//! ```
+//! use misanthropic::{
+//!     conversation::Conversation,
+//!     prompt::{
+//!         message::{Block, Document},
+//!         Message,
+//!     },
+//!     Model,
+//! };
+//!
+//! let mut conversation = Conversation::new(Model::default())
+//!     .with_context_provider(|query: &str| {
+//!         vec![Document::text(format!("Notes about {query}"))
+//!             .with_title("Notes")]
+//!     });
+//! conversation.push(Message::user("bread"));
+//!
+//! let prompt = conversation.prompt();
+//! assert!(matches!(
+//!     prompt.messages[0].content.get(0),
+//!     Some(Block::Document { .. })
+//! ));
+//! ```
+//!
+//! [`User`]: Role::User
+use std::sync::Arc;
+
+use super::Conversation;
+use crate::prompt::{
+    self,
+    message::{Block, Content, Document, Role},
+};
+
+/// Retrieves [`Document`]s relevant to a user turn. See the [module](self)
+/// documentation.
+///
+/// This is implemented for closures taking the query text.
+pub trait ContextProvider: Send + Sync {
+    /// Retrieve documents relevant to `query`, the text of a [`User`] turn.
+    /// This is called every time a [`Conversation::prompt`] is made, so it
+    /// should be cheap or cached.
+    ///
+    /// [`User`]: Role::User
+    fn documents(&self, query: &str) -> Vec<Document<'static>>;
+}
+
+impl<F> ContextProvider for F
+where
+    F: Fn(&str) -> Vec<Document<'static>> + Send + Sync,
+{
+    fn documents(&self, query: &str) -> Vec<Document<'static>> {
+        self(query)
+    }
+}
+
+/// A [`ContextProvider`] shared by clones of a [`Conversation`]. See
+/// [`Conversation::with_context_provider`].
+#[derive(Clone)]
+pub struct SharedContextProvider(pub Arc<dyn ContextProvider>);
+
+impl std::fmt::Debug for SharedContextProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedContextProvider")
+    }
+}
+
+#[cfg(any(feature = "partial-eq", test))]
+impl PartialEq for SharedContextProvider {
+    /// Providers are equal if they are the same provider.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<'a> Conversation<'a> {
+    /// Query a [`ContextProvider`] on every user turn and put its
+    /// [`Document`]s before the turn in the [`Conversation::prompt`]. See the
+    /// [module](self) documentation.
+    pub fn with_context_provider<P>(mut self, provider: P) -> Self
+    where
+        P: ContextProvider + 'static,
+    {
+        self.context_provider = Some(SharedContextProvider(Arc::new(provider)));
+        self
+    }
+
+    /// Put the documents from the [`Self::context_provider`] before the
+    /// latest [`User`] turn, if any. Tool results are not turns.
+    ///
+    /// [`User`]: Role::User
+    pub(super) fn inject_context(&self, messages: &mut [prompt::Message<'a>]) {
+        let Some(SharedContextProvider(provider)) = &self.context_provider
+        else {
+            return;
+        };
+
+        let Some((index, query)) =
+            messages.iter().enumerate().rev().find_map(|(i, message)| {
+                let text = message.text();
+                (message.role == Role::User && !text.trim().is_empty())
+                    .then(|| (i, text.into_owned()))
+            })
+        else {
+            return;
+        };
+
+        let documents = provider.documents(&query);
+        if documents.is_empty() {
+            return;
+        }
+
+        let mut content: Content =
+            documents.into_iter().map(Block::from).collect();
+        #[cfg(feature = "prompt-caching")]
+        {
+            content.cache();
+            if let Some(previous) = index.checked_sub(1) {
+                messages[previous].content.cache();
+            }
+        }
+        let turn = std::mem::take(&mut messages[index].content);
+        content.extend(turn);
+        messages[index].content = content;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tool, Model};
+
+    fn provider(query: &str) -> Vec<Document<'static>> {
+        match query {
+            "nothing" => vec![],
+            query => vec![Document::text(format!("About {query}."))],
+        }
+    }
+
+    #[test]
+    fn test_inject_context() {
+        let mut conversation =
+            Conversation::new(Model::default()).with_context_provider(provider);
+        conversation.push(prompt::Message::user("bread"));
+
+        let prompt = conversation.prompt();
+        let content = &prompt.messages[0].content;
+        assert_eq!(content.iter().count(), 2);
+        assert!(matches!(
+            content.get(0),
+            Some(Block::Document { document, .. })
+                if document == &Document::text("About bread.")
+        ));
+        assert_eq!(content.get(1), Some(&Block::text("bread")));
+        #[cfg(feature = "prompt-caching")]
+        assert!(content.get(0).unwrap().is_cached());
+        // The documents are not stored.
+        assert_eq!(conversation.entries[0].message.content.iter().count(), 1);
+
+        // Tool results are not turns, so the documents stay during tool use.
+        conversation.push(prompt::Message::assistant("Let me check."));
+        conversation.push(prompt::Message {
+            role: Role::User,
+            content: tool::Result {
+                tool_use_id: "toolu_01".into(),
+                content: "Flour.".into(),
+                is_error: false,
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            }
+            .into(),
+        });
+        let prompt = conversation.prompt();
+        assert_eq!(prompt.messages[0].content.iter().count(), 2);
+        assert_eq!(prompt.messages[2].content.iter().count(), 1);
+
+        // The documents are replaced on the next turn.
+        conversation.push(prompt::Message::assistant("It's made of flour."));
+        conversation.push(prompt::Message::user("cake"));
+        let prompt = conversation.prompt();
+        assert_eq!(prompt.messages[0].content.iter().count(), 1);
+        let content = &prompt.messages[4].content;
+        assert_eq!(content.iter().count(), 2);
+        assert!(matches!(
+            content.get(0),
+            Some(Block::Document { document, .. })
+                if document == &Document::text("About cake.")
+        ));
+        // The history before the turn is cached.
+        #[cfg(feature = "prompt-caching")]
+        assert!(prompt.messages[3].content.last().unwrap().is_cached());
+
+        // Nothing is injected without documents.
+        conversation.push(prompt::Message::assistant("Yum."));
+        conversation.push(prompt::Message::user("nothing"));
+        let prompt = conversation.prompt();
+        assert_eq!(prompt.messages[6].content.iter().count(), 1);
+        #[cfg(feature = "prompt-caching")]
+        assert!(!prompt.messages[5].content.last().unwrap().is_cached());
+    }
+
+    #[test]
+    fn test_serialize_document() {
+        let block: Block = Document::text("Hello.")
+            .with_title("Greeting")
+            .with_context("From a test.")
+            .into();
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            json,
+            crate::json!({
+                "type": "document",
+                "source": {
+                    "type": "text",
+                    "media_type": "text/plain",
+                    "data": "Hello.",
+                },
+                "title": "Greeting",
+                "context": "From a test.",
+            })
+        );
+        let parsed: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, block);
+        assert_eq!(parsed.len(), 6);
+
+        let block: Block = Document::url("https://example.com/a.pdf").into();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            crate::json!({
+                "type": "document",
+                "source": {"type": "url", "url": "https://example.com/a.pdf"},
+            })
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Regenerate the last response of a [`Conversation`]. See
+//! [`Conversation::regenerate`].
+use std::sync::Arc;
+
+use super::{now, Conversation, Entry};
+use crate::{
+    client::{self, Overrides},
+    prompt::{message::TranscriptRole, Temperature},
+    response, Client,
+};
+
+/// Options for [`Conversation::regenerate`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RegenerateOptions {
+    /// [`Overrides`] for the request, such as a different [`Model`].
+    ///
+    /// [`Model`]: crate::Model
+    pub overrides: Overrides,
+    /// Added to the temperature of the request for more varied output. The
+    /// temperature is [`Overrides::temperature`] if set, otherwise the API
+    /// default of 1.0. The sum is kept between 0 and 1.
+    pub temperature_bump: Option<f32>,
+}
+
+impl RegenerateOptions {
+    /// Create default [`RegenerateOptions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Self::overrides`].
+    pub fn overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Set the [`Self::temperature_bump`].
+    pub fn temperature_bump(mut self, bump: f32) -> Self {
+        self.temperature_bump = Some(bump);
+        self
+    }
+
+    /// The [`Overrides`] with the temperature bumped.
+    fn request_overrides(&self) -> Overrides {
+        let mut overrides = self.overrides.clone();
+        if let Some(bump) = self.temperature_bump {
+            let temperature =
+                overrides.temperature.map_or(1.0, f32::from) + bump;
+            overrides.temperature =
+                Temperature::try_from(temperature.clamp(0.0, 1.0)).ok();
+        }
+
+        overrides
+    }
+}
+
+/// Both candidates from [`Conversation::regenerate`].
+#[derive(Clone, Debug)]
+pub struct Regenerated<'a> {
+    /// The entries which were removed, starting with the previous response.
+    /// This includes any tool use and results which followed it.
+    pub previous: Vec<Arc<Entry<'a>>>,
+    /// The new response, which was added to the conversation.
+    pub response: response::Message<'static>,
+}
+
+impl<'a> Conversation<'a> {
+    /// Remove the response to the last user turn: every entry after the last
+    /// [`User`] message which is not a tool result. Tool use and its results
+    /// are removed together, so the conversation stays valid for the API.
+    /// Returns the removed entries, or [`None`] if there is no user turn.
+    ///
+    /// ```
+    /// use misanthropic::{prompt::Message, Conversation, Model};
+    ///
+    /// let mut conversation = Conversation::new(Model::default());
+    /// conversation.push(Message::user("Hi!"));
+    /// conversation.push(Message::assistant("Hello!"));
+    ///
+    /// let removed = conversation.pop_response().unwrap();
+    /// assert_eq!(removed[0].message.text(), "Hello!");
+    /// assert_eq!(conversation.entries.len(), 1);
+    /// ```
+    ///
+    /// [`User`]: TranscriptRole::User
+    pub fn pop_response(&mut self) -> Option<Vec<Arc<Entry<'a>>>> {
+        let turn = self.entries.iter().rposition(|entry| {
+            TranscriptRole::of(&entry.message) == TranscriptRole::User
+        })?;
+
+        Some(self.entries.split_off(turn + 1))
+    }
+
+    /// Regenerate the response to the last user turn. The previous response
+    /// is removed with [`Conversation::pop_response`] and a new one is
+    /// requested with [`Client::message_with`] and added with its latency.
+    /// Returns both candidates, or [`None`] if there is no user turn.
+    ///
+    /// If the request fails, the previous response is restored.
+    ///
+    /// ```no_run
+    /// use misanthropic::{
+    ///     conversation::RegenerateOptions, Client, Conversation,
+    /// };
+    ///
+    /// # async fn example(client: Client, mut conversation: Conversation<'_>) -> misanthropic::client::Result<()> {
+    /// let options = RegenerateOptions::new().temperature_bump(0.2);
+    /// if let Some(regenerated) =
+    ///     conversation.regenerate(&client, &options).await?
+    /// {
+    ///     println!("Before: {}", regenerated.previous[0].message);
+    ///     println!("After: {}", regenerated.response);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn regenerate(
+        &mut self,
+        client: &Client,
+        options: &RegenerateOptions,
+    ) -> client::Result<Option<Regenerated<'a>>> {
+        let Some(previous) = self.pop_response() else {
+            return Ok(None);
+        };
+
+        let start = now();
+        let response = match client
+            .message_with(self.prompt(), &options.request_overrides())
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                self.entries.extend(previous);
+                return Err(error);
+            }
+        };
+
+        let mut entry = Entry::from(response.clone());
+        entry.latency = now().duration_since(start).ok();
+        self.entries.push(Arc::new(entry));
+
+        Ok(Some(Regenerated { previous, response }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prompt::Message, tool, Model};
+
+    #[test]
+    fn test_pop_response() {
+        let mut conversation = Conversation::new(Model::Sonnet35);
+        assert!(conversation.pop_response().is_none());
+
+        conversation.push(Message::user("Hi!"));
+        assert_eq!(conversation.pop_response().unwrap().len(), 0);
+
+        conversation.push(Message::assistant("Hello!"));
+        conversation.push(Message::user("What's the weather?"));
+        conversation.push(Message::assistant(
+            crate::prompt::message::Block::ToolUse {
+                call: tool::Use {
+                    id: "toolu_1".into(),
+                    name: "weather".into(),
+                    input: crate::json!({}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                },
+            },
+        ));
+        conversation.push(Message::tool_results([tool::Result {
+            tool_use_id: "toolu_1".into(),
+            content: "Sunny".into(),
+            is_error: false,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }]));
+        conversation.push(Message::assistant("It's sunny."));
+
+        // The tool use and its result go with the response.
+        let removed = conversation.pop_response().unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(removed[2].message.text(), "It's sunny.");
+        assert_eq!(conversation.entries.len(), 3);
+        assert_eq!(
+            conversation.entries.last().unwrap().message.text(),
+            "What's the weather?"
+        );
+    }
+
+    #[test]
+    fn test_request_overrides() {
+        assert_eq!(
+            RegenerateOptions::new().request_overrides(),
+            Overrides::new()
+        );
+
+        let options = RegenerateOptions::new()
+            .overrides(
+                Overrides::new()
+                    .temperature(Temperature::try_from(0.5).unwrap()),
+            )
+            .temperature_bump(0.25);
+        assert_eq!(
+            options.request_overrides().temperature,
+            Temperature::try_from(0.75).ok()
+        );
+
+        // Kept in range.
+        let options = RegenerateOptions::new().temperature_bump(0.25);
+        assert_eq!(
+            options.request_overrides().temperature,
+            Temperature::try_from(1.0).ok()
+        );
+        let options = options.temperature_bump(-2.0);
+        assert_eq!(
+            options.request_overrides().temperature,
+            Temperature::try_from(0.0).ok()
+        );
+    }
+}
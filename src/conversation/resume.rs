@@ -0,0 +1,129 @@
+//! Resume turns paused by server tools. See [`Conversation::resume_paused`].
+use std::{sync::Arc, time::Duration};
+
+use super::{now, Conversation};
+use crate::{
+    client,
+    prompt::message::Role,
+    response::{self, ServerToolUsage, StopReason},
+    Client,
+};
+
+/// Maximum number of times [`Conversation::resume_paused`] resumes a turn, so
+/// a server tool which keeps pausing can't loop forever.
+pub const MAX_RESUMES: usize = 8;
+
+impl<'a> Conversation<'a> {
+    /// Returns true if the last entry is a response which stopped with
+    /// [`StopReason::PauseTurn`], so it can be [resumed].
+    ///
+    /// [resumed]: Conversation::resume_paused
+    pub fn is_paused(&self) -> bool {
+        matches!(
+            self.entries.last(),
+            Some(entry) if entry.message.role == Role::Assistant
+                && matches!(entry.stop_reason, Some(StopReason::PauseTurn))
+        )
+    }
+
+    /// Resume a turn paused by a server tool, such as a long [`web_search`].
+    /// While the conversation [`is_paused`], it is sent back as is so the
+    /// model can continue, up to [`MAX_RESUMES`] times. Each response is
+    /// appended to the paused message, so the turn stays a single entry with
+    /// the summed [`Usage`] and latency. Returns the number of requests made.
+    ///
+    /// [`web_search`]: crate::tool::web_search
+    /// [`is_paused`]: Conversation::is_paused
+    /// [`Usage`]: crate::response::Usage
+    pub async fn resume_paused(
+        &mut self,
+        client: &Client,
+    ) -> client::Result<usize> {
+        let mut resumed = 0;
+        while self.is_paused() && resumed < MAX_RESUMES {
+            let start = now();
+            let response = client.message(self.prompt()).await?;
+            let latency = now().duration_since(start).ok();
+            self.append_resumed(response, latency);
+            resumed += 1;
+        }
+
+        Ok(resumed)
+    }
+
+    /// Append a `response` to the paused last entry.
+    fn append_resumed(
+        &mut self,
+        response: response::Message<'static>,
+        latency: Option<Duration>,
+    ) {
+        let Some(entry) = self.entries.last_mut() else {
+            return;
+        };
+        let entry = Arc::make_mut(entry);
+
+        entry.message.content.extend(response.message.content);
+        entry.id = Some(response.id);
+        entry.model = Some(response.model);
+        entry.stop_reason = response.stop_reason;
+        entry.latency = match (entry.latency, latency) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+
+        let usage = entry.usage.get_or_insert_with(Default::default);
+        usage.input_tokens += response.usage.input_tokens;
+        usage.output_tokens += response.usage.output_tokens;
+        if let Some(server) = response.usage.server_tool_use {
+            usage
+                .server_tool_use
+                .get_or_insert_with(ServerToolUsage::default)
+                .web_search_requests += server.web_search_requests;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{conversation::Entry, prompt::Message, Model};
+
+    fn fixture(json: &str) -> response::Message<'static> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_resume_paused() {
+        let paused =
+            fixture(include_str!("../../test/data/pause_turn/paused.json"));
+        let resumed =
+            fixture(include_str!("../../test/data/pause_turn/resumed.json"));
+
+        let mut conversation = Conversation::new(Model::Sonnet35);
+        conversation.push(Message::user("What is the latest Rust release?"));
+        assert!(!conversation.is_paused());
+        conversation
+            .push(Entry::from(paused).latency(Duration::from_millis(1500)));
+        assert!(conversation.is_paused());
+
+        // The paused message is sent back as is.
+        let prompt = conversation.prompt();
+        assert_eq!(prompt.messages.len(), 2);
+        assert_eq!(prompt.messages[1].content.iter().count(), 3);
+
+        conversation.append_resumed(resumed, Some(Duration::from_millis(500)));
+        assert!(!conversation.is_paused());
+        assert_eq!(conversation.entries.len(), 2);
+
+        let entry = &conversation.entries[1];
+        assert_eq!(entry.message.content.iter().count(), 4);
+        assert!(entry.message.text().ends_with("on the Rust Blog."));
+        assert_eq!(entry.id.as_deref(), Some("msg_01PauseTurn2"));
+        assert_eq!(entry.stop_reason, Some(StopReason::EndTurn));
+        assert_eq!(entry.latency, Some(Duration::from_millis(2000)));
+        let usage = entry.usage.as_ref().unwrap();
+        assert_eq!(usage.input_tokens, 420 + 1210);
+        assert_eq!(usage.output_tokens, 35 + 14);
+        assert_eq!(usage.web_search_requests(), 1);
+    }
+}
@@ -0,0 +1,363 @@
+//! Run [`Prompt`] variants against graders with [`Client::run_eval`] and
+//! collect the outputs, [`Usage`], latency, and scores as [`EvalResult`]s
+//! which can be saved as [JSON Lines].
+//!
+//! ```no_run
+//! use misanthropic::{eval::EvalSet, prompt::message::Role, Client, Prompt};
+//!
+//! # async fn example(client: Client) -> std::io::Result<()> {
+//! let question = (Role::User, "What is the capital of France?");
+//! let set = EvalSet::new()
+//!     .variant("plain", Prompt::default().add_message(question))
+//!     .variant(
+//!         "terse",
+//!         Prompt::default()
+//!             .system("Answer in one word.")
+//!             .add_message(question),
+//!     )
+//!     .grader("paris", |message| {
+//!         message.text().contains("Paris") as u8 as f64
+//!     })
+//!     .grader("short", |message| 1.0 / message.text().len().max(1) as f64)
+//!     .runs(5);
+//!
+//! let report = client.run_eval(&set).await;
+//! println!("{:?}", report.mean_score("terse", "paris"));
+//! report.to_jsonl(std::fs::File::create("eval.jsonl")?)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are made with the Messages API. Set a [`RateLimiter`] on the
+//! [`Client`] to stay under your rate limits.
+//!
+//! [JSON Lines]: <https://jsonlines.org/>
+//! [`RateLimiter`]: crate::throttle::RateLimiter
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::Instant;
+
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    response::{self, Usage},
+    Client, Prompt,
+};
+
+pub mod compare;
+
+/// Grades a [`response::Message`]. Higher is better, but the scale is up to
+/// the grader.
+pub type Grader = Arc<dyn Fn(&response::Message) -> f64 + Send + Sync>;
+
+/// Named [`Prompt`] variants and [`Grader`]s to evaluate them with. See the
+/// [module](self) documentation.
+#[derive(Clone)]
+pub struct EvalSet<'a> {
+    variants: Vec<(String, Prompt<'a>)>,
+    graders: Vec<(String, Grader)>,
+    runs: usize,
+    concurrency: usize,
+}
+
+impl Default for EvalSet<'_> {
+    fn default() -> Self {
+        Self {
+            variants: vec![],
+            graders: vec![],
+            runs: 1,
+            concurrency: 1,
+        }
+    }
+}
+
+impl<'a> EvalSet<'a> {
+    /// Create an empty [`EvalSet`] which runs each variant once.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a named [`Prompt`] variant.
+    pub fn variant<S>(mut self, name: S, prompt: Prompt<'a>) -> Self
+    where
+        S: Into<String>,
+    {
+        self.variants.push((name.into(), prompt));
+        self
+    }
+
+    /// Add a named grader. Every response is graded by every grader.
+    pub fn grader<S, F>(mut self, name: S, grader: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&response::Message) -> f64 + Send + Sync + 'static,
+    {
+        self.graders.push((name.into(), Arc::new(grader)));
+        self
+    }
+
+    /// Run each variant `runs` times. The default is 1.
+    pub fn runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// Send up to `concurrency` requests at once. The default is 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Grade a `message` with every grader.
+    fn grade(&self, message: &response::Message) -> BTreeMap<String, f64> {
+        self.graders
+            .iter()
+            .map(|(name, grader)| (name.clone(), grader(message)))
+            .collect()
+    }
+}
+
+/// The result of one run of one variant of an [`EvalSet`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct EvalResult {
+    /// Name of the variant.
+    pub variant: String,
+    /// Run number, from 0.
+    pub run: usize,
+    /// Text of the response, if the request succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// Error message, if the request failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Token usage, if the request succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Time from sending the request to receiving the whole response.
+    #[serde(rename = "latency_ms", with = "millis")]
+    pub latency: Duration,
+    /// Scores by grader name. Empty if the request failed.
+    #[serde(default)]
+    pub scores: BTreeMap<String, f64>,
+}
+
+/// [`EvalResult`]s from [`Client::run_eval`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct EvalReport {
+    /// Results in the order of variants, then runs.
+    pub results: Vec<EvalResult>,
+}
+
+impl EvalReport {
+    /// Results for a `variant`.
+    pub fn variant<'a>(
+        &'a self,
+        variant: &'a str,
+    ) -> impl Iterator<Item = &'a EvalResult> + 'a {
+        self.results.iter().filter(move |r| r.variant == variant)
+    }
+
+    /// Mean score of a `variant` by a `grader`, over the successful runs.
+    /// Returns [`None`] if there are none.
+    pub fn mean_score(&self, variant: &str, grader: &str) -> Option<f64> {
+        let scores: Vec<f64> = self
+            .variant(variant)
+            .filter_map(|r| r.scores.get(grader).copied())
+            .collect();
+
+        match scores.is_empty() {
+            true => None,
+            false => Some(scores.iter().sum::<f64>() / scores.len() as f64),
+        }
+    }
+
+    /// Mean latency of the successful runs of a `variant`.
+    pub fn mean_latency(&self, variant: &str) -> Option<Duration> {
+        let latencies: Vec<Duration> = self
+            .variant(variant)
+            .filter(|r| r.error.is_none())
+            .map(|r| r.latency)
+            .collect();
+
+        let count = u32::try_from(latencies.len()).ok().filter(|&n| n > 0)?;
+        Some(latencies.iter().sum::<Duration>() / count)
+    }
+
+    /// Write the results as [JSON Lines], one [`EvalResult`] per line.
+    ///
+    /// [JSON Lines]: <https://jsonlines.org/>
+    pub fn to_jsonl<W>(&self, mut writer: W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        for result in &self.results {
+            serde_json::to_writer(&mut writer, result)?;
+            writer.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// Read results written by [`EvalReport::to_jsonl`]. Blank lines are
+    /// skipped.
+    pub fn from_jsonl<R>(reader: R) -> std::io::Result<Self>
+    where
+        R: BufRead,
+    {
+        let mut results = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                results.push(serde_json::from_str(&line)?);
+            }
+        }
+
+        Ok(Self { results })
+    }
+}
+
+impl Client {
+    /// Run every variant of an [`EvalSet`] the configured number of times
+    /// and grade the responses. Failed requests are recorded as results with
+    /// an [`EvalResult::error`] rather than stopping the evaluation.
+    pub async fn run_eval(&self, set: &EvalSet<'_>) -> EvalReport {
+        let runs = set.variants.iter().flat_map(|(name, prompt)| {
+            (0..set.runs).map(move |run| (name, prompt, run))
+        });
+
+        let results = futures::stream::iter(runs)
+            .map(|(name, prompt, run)| async move {
+                let start = Instant::now();
+                let response = self.message(prompt).await;
+                let latency = start.elapsed();
+
+                match response {
+                    Ok(message) => EvalResult {
+                        variant: name.clone(),
+                        run,
+                        output: Some(message.text().into_owned()),
+                        error: None,
+                        scores: set.grade(&message),
+                        usage: Some(message.usage),
+                        latency,
+                    },
+                    Err(error) => EvalResult {
+                        variant: name.clone(),
+                        run,
+                        error: Some(error.to_string()),
+                        latency,
+                        ..Default::default()
+                    },
+                }
+            })
+            .buffered(set.concurrency)
+            .collect()
+            .await;
+
+        EvalReport { results }
+    }
+}
+
+/// (De)serialize a [`Duration`] as milliseconds.
+mod millis {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(
+        duration: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let millis = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(millis / 1000.0)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::message::Role;
+
+    fn result(variant: &str, run: usize, score: Option<f64>) -> EvalResult {
+        EvalResult {
+            variant: variant.into(),
+            run,
+            output: score.map(|_| "Paris".into()),
+            error: score.is_none().then(|| "HTTP error".into()),
+            usage: score.map(|_| Usage::default()),
+            latency: Duration::from_millis(100 * (run as u64 + 1)),
+            scores: score
+                .map(|score| [("paris".to_string(), score)].into())
+                .unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_eval_set() {
+        let set = EvalSet::new()
+            .variant("plain", Prompt::default().add_message((Role::User, "Hi")))
+            .grader("len", |message| message.text().len() as f64)
+            .runs(3)
+            .concurrency(0);
+        assert_eq!(set.variants.len(), 1);
+        assert_eq!(set.runs, 3);
+        assert_eq!(set.concurrency, 1);
+
+        let message: response::Message = serde_json::from_str(
+            crate::response::message::tests::RESPONSE_JSON,
+        )
+        .unwrap();
+        let scores = set.grade(&message);
+        assert_eq!(scores["len"], message.text().len() as f64);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = EvalReport {
+            results: vec![
+                result("a", 0, Some(1.0)),
+                result("a", 1, Some(0.0)),
+                result("a", 2, None),
+                result("b", 0, None),
+            ],
+        };
+
+        assert_eq!(report.mean_score("a", "paris"), Some(0.5));
+        assert_eq!(report.mean_score("a", "nope"), None);
+        assert_eq!(report.mean_score("b", "paris"), None);
+        assert_eq!(report.mean_latency("a"), Some(Duration::from_millis(150)));
+        assert_eq!(report.mean_latency("b"), None);
+
+        let mut jsonl = vec![];
+        report.to_jsonl(&mut jsonl).unwrap();
+        let text = String::from_utf8(jsonl).unwrap();
+        assert_eq!(text.lines().count(), 4);
+        assert!(text.starts_with(r#"{"variant":"a","run":0,"output":"Paris","#));
+        assert!(text.contains(r#""latency_ms":100.0"#));
+
+        let read = EvalReport::from_jsonl(text.as_bytes()).unwrap();
+        assert_eq!(read, report);
+    }
+}
@@ -0,0 +1,406 @@
+//! A/B comparison of two [`Prompt`]s, or of the same [`Prompt`] on two
+//! [`Model`]s, with [`Client::compare`]. The [`ComparisonReport`] has the
+//! output, [`Usage`], and latency of every run and can be rendered as
+//! markdown or HTML with the `markdown` and `html` features.
+//!
+//! ```no_run
+//! use misanthropic::{eval::compare::Comparison, prompt::message::Role};
+//! use misanthropic::{Client, Model, Prompt};
+//!
+//! # async fn example(client: Client) {
+//! let prompt = Prompt::default().add_message((Role::User, "Tell a joke."));
+//! let comparison = Comparison::models(
+//!     prompt,
+//!     Model::Haiku35_20241022,
+//!     Model::Sonnet35_20241022,
+//! )
+//! .runs(3);
+//!
+//! let report = client.compare(&comparison).await;
+//! # #[cfg(feature = "markdown")]
+//! # {
+//! use misanthropic::markdown::ToMarkdown;
+//! println!("{}", report.markdown());
+//! # }
+//! # }
+//! ```
+//!
+//! [`Usage`]: crate::response::Usage
+use super::{EvalReport, EvalResult, EvalSet};
+use crate::{response, Client, Model, Prompt};
+
+/// Two [`Prompt`]s to compare. See the [module](self) documentation.
+#[derive(Clone)]
+pub struct Comparison<'a> {
+    labels: [String; 2],
+    set: EvalSet<'a>,
+}
+
+impl<'a> Comparison<'a> {
+    /// Compare two [`Prompt`]s, labeled "A" and "B".
+    pub fn prompts(a: Prompt<'a>, b: Prompt<'a>) -> Self {
+        Self::new(["A".into(), "B".into()], a, b)
+    }
+
+    /// Compare the same [`Prompt`] on two [`Model`]s, labeled by their
+    /// [`Model::api_id`].
+    pub fn models(prompt: Prompt<'a>, a: Model, b: Model) -> Self {
+        let labels = [a.api_id().to_string(), b.api_id().to_string()];
+        let prompt_b = prompt.clone().model(b);
+        Self::new(labels, prompt.model(a), prompt_b)
+    }
+
+    fn new(labels: [String; 2], a: Prompt<'a>, b: Prompt<'a>) -> Self {
+        let set = EvalSet::new()
+            .variant(labels[0].clone(), a)
+            .variant(labels[1].clone(), b);
+        Self { labels, set }
+    }
+
+    /// Run each [`Prompt`] `runs` times. The default is 1.
+    pub fn runs(mut self, runs: usize) -> Self {
+        self.set = self.set.runs(runs);
+        self
+    }
+
+    /// Send up to `concurrency` requests at once. The default is 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.set = self.set.concurrency(concurrency);
+        self
+    }
+
+    /// Add a named grader. See [`EvalSet::grader`].
+    pub fn grader<S, F>(mut self, name: S, grader: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&response::Message) -> f64 + Send + Sync + 'static,
+    {
+        self.set = self.set.grader(name, grader);
+        self
+    }
+}
+
+/// Results of a [`Comparison`] from [`Client::compare`].
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct ComparisonReport {
+    /// Labels of the two sides.
+    pub labels: [String; 2],
+    /// Results of both sides, labeled by [`EvalResult::variant`].
+    pub report: EvalReport,
+}
+
+impl ComparisonReport {
+    /// Results of side `0` (A) or `1` (B).
+    pub fn side(&self, side: usize) -> impl Iterator<Item = &EvalResult> {
+        let label = self.labels.get(side).map(String::as_str);
+        self.report
+            .results
+            .iter()
+            .filter(move |r| Some(r.variant.as_str()) == label)
+    }
+
+    /// Mean input and output tokens of the successful runs of a side.
+    pub fn mean_tokens(&self, side: usize) -> Option<(f64, f64)> {
+        let usages: Vec<_> =
+            self.side(side).filter_map(|r| r.usage.as_ref()).collect();
+        if usages.is_empty() {
+            return None;
+        }
+
+        let count = usages.len() as f64;
+        let input: u64 = usages.iter().map(|u| u.input_tokens).sum();
+        let output: u64 = usages.iter().map(|u| u.output_tokens).sum();
+        Some((input as f64 / count, output as f64 / count))
+    }
+
+    /// Names of the graders, sorted.
+    #[cfg(any(feature = "markdown", test))]
+    fn graders(&self) -> Vec<&str> {
+        let mut graders: Vec<&str> = self
+            .report
+            .results
+            .iter()
+            .flat_map(|r| r.scores.keys().map(String::as_str))
+            .collect();
+        graders.sort_unstable();
+        graders.dedup();
+        graders
+    }
+}
+
+impl Client {
+    /// Run both sides of a [`Comparison`].
+    pub async fn compare(
+        &self,
+        comparison: &Comparison<'_>,
+    ) -> ComparisonReport {
+        ComparisonReport {
+            labels: comparison.labels.clone(),
+            report: self.run_eval(&comparison.set).await,
+        }
+    }
+}
+
+/// Format a [`Duration`] as milliseconds.
+///
+/// [`Duration`]: std::time::Duration
+#[cfg(feature = "markdown")]
+fn millis(duration: std::time::Duration) -> String {
+    format!("{:.0} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(feature = "markdown")]
+impl crate::markdown::ToMarkdown for ComparisonReport {
+    /// A summary table of runs, mean latency, tokens, and scores, followed
+    /// by the output of every run under a heading for each side.
+    fn markdown_events_custom<'a>(
+        &'a self,
+        options: crate::markdown::Options,
+    ) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
+        use pulldown_cmark::{
+            Alignment, CowStr, Event,
+            HeadingLevel::{H2, H6},
+            Tag, TagEnd,
+        };
+
+        let level = options.heading_level.unwrap_or(H2);
+        // Headings below `level`, saturating at H6.
+        let sub = |n: usize| (level as usize + n).try_into().unwrap_or(H6);
+        let heading = |level, text: CowStr<'a>| {
+            [
+                Event::Start(Tag::Heading {
+                    level,
+                    id: None,
+                    classes: vec![],
+                    attrs: vec![],
+                }),
+                Event::Text(text),
+                Event::End(TagEnd::Heading(level)),
+            ]
+        };
+        let row = |cells: Vec<CowStr<'a>>, head: bool| {
+            let mut events = vec![match head {
+                true => Event::Start(Tag::TableHead),
+                false => Event::Start(Tag::TableRow),
+            }];
+            for cell in cells {
+                events.push(Event::Start(Tag::TableCell));
+                events.push(Event::Text(cell));
+                events.push(Event::End(TagEnd::TableCell));
+            }
+            events.push(match head {
+                true => Event::End(TagEnd::TableHead),
+                false => Event::End(TagEnd::TableRow),
+            });
+            events
+        };
+
+        let mut events = vec![];
+        events.extend(heading(level, "Comparison".into()));
+
+        // Summary table.
+        let [a, b] = &self.labels;
+        events.push(Event::Start(Tag::Table(vec![
+            Alignment::Left,
+            Alignment::Right,
+            Alignment::Right,
+        ])));
+        events.extend(row(
+            vec!["".into(), a.as_str().into(), b.as_str().into()],
+            true,
+        ));
+        let summary = |f: &dyn Fn(usize) -> String| -> Vec<CowStr<'a>> {
+            vec![f(0).into(), f(1).into()]
+        };
+        let mut add_row = |name: CowStr<'a>, values: Vec<CowStr<'a>>| {
+            let mut cells = vec![name];
+            cells.extend(values);
+            events.extend(row(cells, false));
+        };
+        add_row(
+            "Successful runs".into(),
+            summary(&|side| {
+                let total = self.side(side).count();
+                let ok = self.side(side).filter(|r| r.error.is_none()).count();
+                format!("{ok}/{total}")
+            }),
+        );
+        add_row(
+            "Mean latency".into(),
+            summary(&|side| {
+                self.report
+                    .mean_latency(&self.labels[side])
+                    .map(millis)
+                    .unwrap_or_else(|| "-".into())
+            }),
+        );
+        add_row(
+            "Mean input tokens".into(),
+            summary(&|side| match self.mean_tokens(side) {
+                Some((input, _)) => format!("{input:.1}"),
+                None => "-".into(),
+            }),
+        );
+        add_row(
+            "Mean output tokens".into(),
+            summary(&|side| match self.mean_tokens(side) {
+                Some((_, output)) => format!("{output:.1}"),
+                None => "-".into(),
+            }),
+        );
+        for grader in self.graders() {
+            add_row(
+                format!("Score: {grader}").into(),
+                summary(&|side| {
+                    self.report
+                        .mean_score(&self.labels[side], grader)
+                        .map(|score| format!("{score:.3}"))
+                        .unwrap_or_else(|| "-".into())
+                }),
+            );
+        }
+        events.push(Event::End(TagEnd::Table));
+
+        // Every run.
+        for (side, label) in self.labels.iter().enumerate() {
+            events.extend(heading(sub(1), label.as_str().into()));
+            for result in self.side(side) {
+                events.extend(heading(
+                    sub(2),
+                    format!("Run {}", result.run + 1).into(),
+                ));
+
+                let mut stats = millis(result.latency);
+                if let Some(usage) = &result.usage {
+                    stats += &format!(
+                        ", {} input tokens, {} output tokens",
+                        usage.input_tokens, usage.output_tokens
+                    );
+                }
+                for (grader, score) in &result.scores {
+                    stats += &format!(", {grader}: {score:.3}");
+                }
+                events.push(Event::Start(Tag::Paragraph));
+                events.push(Event::Start(Tag::Emphasis));
+                events.push(Event::Text(stats.into()));
+                events.push(Event::End(TagEnd::Emphasis));
+                events.push(Event::End(TagEnd::Paragraph));
+
+                let text = match (&result.output, &result.error) {
+                    (_, Some(error)) => format!("Error: {error}").into(),
+                    (Some(output), None) => output.as_str().into(),
+                    (None, None) => "".into(),
+                };
+                events.push(Event::Start(Tag::Paragraph));
+                events.push(Event::Text(text));
+                events.push(Event::End(TagEnd::Paragraph));
+            }
+        }
+
+        Box::new(events.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{prompt::message::Role, response::Usage};
+
+    fn report() -> ComparisonReport {
+        let result = |variant: &str, run, latency, output: u64| EvalResult {
+            variant: variant.into(),
+            run,
+            output: Some(format!("{variant} says hi")),
+            error: None,
+            usage: Some(Usage {
+                input_tokens: 10,
+                output_tokens: output,
+                ..Default::default()
+            }),
+            latency: Duration::from_millis(latency),
+            scores: [("polite".to_string(), output as f64 / 10.0)].into(),
+        };
+
+        ComparisonReport {
+            labels: ["A".into(), "B".into()],
+            report: EvalReport {
+                results: vec![
+                    result("A", 0, 100, 5),
+                    result("A", 1, 300, 7),
+                    result("B", 0, 50, 2),
+                    EvalResult {
+                        variant: "B".into(),
+                        run: 1,
+                        error: Some("Overloaded".into()),
+                        ..Default::default()
+                    },
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn test_comparison() {
+        let prompt = Prompt::default().add_message((Role::User, "Hi"));
+        let comparison =
+            Comparison::models(prompt, Model::Haiku30, Model::Sonnet30).runs(2);
+        assert_eq!(
+            comparison.labels,
+            [Model::Haiku30.api_id(), Model::Sonnet30.api_id()]
+        );
+        assert_eq!(comparison.set.runs, 2);
+        assert_eq!(comparison.set.variants[0].1.model, Model::Haiku30);
+        assert_eq!(comparison.set.variants[1].1.model, Model::Sonnet30);
+        assert_eq!(comparison.set.variants[1].0, Model::Sonnet30.api_id());
+
+        let comparison =
+            Comparison::prompts(Prompt::default(), Prompt::default());
+        assert_eq!(comparison.labels, ["A", "B"]);
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report();
+        assert_eq!(report.side(0).count(), 2);
+        assert_eq!(report.side(1).count(), 2);
+        assert_eq!(report.side(2).count(), 0);
+        assert_eq!(report.mean_tokens(0), Some((10.0, 6.0)));
+        assert_eq!(report.mean_tokens(1), Some((10.0, 2.0)));
+        assert_eq!(report.graders(), ["polite"]);
+    }
+
+    #[test]
+    #[cfg(feature = "markdown")]
+    fn test_markdown() {
+        use crate::markdown::ToMarkdown;
+
+        let markdown = report().markdown().to_string();
+        assert!(markdown.starts_with("## Comparison"), "{markdown}");
+        assert!(markdown.contains("|Successful runs|2/2|1/2|"), "{markdown}");
+        assert!(
+            markdown.contains("|Mean latency|200 ms|50 ms|"),
+            "{markdown}"
+        );
+        assert!(
+            markdown.contains("|Score: polite|0.600|0.200|"),
+            "{markdown}"
+        );
+        assert!(markdown.contains("### B"));
+        assert!(markdown.contains("#### Run 2"));
+        assert!(markdown.contains("Error: Overloaded"));
+        assert!(markdown.contains("A says hi"));
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn test_html() {
+        use crate::html::ToHtml;
+
+        let html = report().html().to_string();
+        assert!(html.contains("<table>"), "{html}");
+        assert!(html.contains("<h3>A</h3>"), "{html}");
+    }
+}
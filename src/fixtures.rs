@@ -0,0 +1,312 @@
+//! Golden transcripts: conversations saved as JSON files which can be loaded
+//! in tests and rendered to stable strings for snapshot testing with tools
+//! like [`insta`].
+//!
+//! ```no_run
+//! use misanthropic::fixtures::Transcript;
+//!
+//! # #[cfg(feature = "markdown")]
+//! # fn example() -> Result<(), misanthropic::fixtures::Error> {
+//! for transcript in Transcript::load_dir("tests/transcripts")? {
+//!     let markdown = transcript.snapshot_markdown();
+//!     // With `insta`:
+//!     // insta::assert_snapshot!(transcript.name.unwrap(), markdown);
+//!     # drop(markdown);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Rendering requires the `markdown` or `html` feature.
+//!
+//! # Schema
+//!
+//! A transcript is a JSON object. It is a Messages API request body with some
+//! optional metadata, so any request body is also a valid transcript and a
+//! transcript can be shared between services in any language.
+//!
+//! | Field         | Type    | Required | Description                         |
+//! |---------------|---------|----------|-------------------------------------|
+//! | `version`     | integer | no       | Schema version. Currently `1`.      |
+//! | `name`        | string  | no       | Short name, such as a snapshot name.|
+//! | `description` | string  | no       | What the transcript is testing.     |
+//! | `model`       | string  | yes      | [Model] which generated it.         |
+//! | `messages`    | array   | yes      | [Messages] in the conversation.     |
+//! | `system`      | any     | no       | [System prompt].                    |
+//! | `tools`       | array   | no       | [Tools] available to the model.     |
+//!
+//! Any other [request parameters] such as `max_tokens` are allowed and
+//! preserved. Files are saved with [`Transcript::save`] as pretty-printed JSON
+//! with a trailing newline so they diff well under version control.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "name": "weather",
+//!   "model": "claude-3-5-sonnet-latest",
+//!   "max_tokens": 4096,
+//!   "messages": [
+//!     { "role": "user", "content": "What's the weather in Paris?" },
+//!     { "role": "assistant", "content": "It's sunny." }
+//!   ]
+//! }
+//! ```
+//!
+//! [`insta`]: <https://insta.rs/>
+//! [Model]: crate::Model
+//! [Messages]: crate::prompt::Message
+//! [System prompt]: crate::Prompt::system
+//! [Tools]: crate::Tool
+//! [request parameters]: <https://docs.anthropic.com/en/api/messages>
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Prompt;
+
+/// Current [`Transcript`] schema version.
+pub const VERSION: u32 = 1;
+
+/// Errors loading or saving a [`Transcript`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The file could not be read or written.
+    #[error("Could not access transcript `{path}`: {error}")]
+    #[allow(missing_docs)]
+    Io {
+        path: PathBuf,
+        #[source]
+        error: std::io::Error,
+    },
+    /// The file is not a valid transcript.
+    #[error("Could not parse transcript `{path}`: {error}")]
+    #[allow(missing_docs)]
+    Parse {
+        path: PathBuf,
+        #[source]
+        error: serde_json::Error,
+    },
+    /// The transcript is from a newer version of the schema.
+    #[error(
+        "Transcript `{path}` has version {version} but only {VERSION} is supported"
+    )]
+    #[allow(missing_docs)]
+    Version { path: PathBuf, version: u32 },
+}
+
+/// A golden conversation transcript. See the [module](self) documentation for
+/// the schema.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Transcript<'a> {
+    /// Schema version. See [`VERSION`].
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Short name, such as a snapshot name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// What the transcript is testing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The conversation.
+    #[serde(flatten)]
+    pub prompt: Prompt<'a>,
+}
+
+fn default_version() -> u32 {
+    VERSION
+}
+
+impl<'a> From<Prompt<'a>> for Transcript<'a> {
+    fn from(prompt: Prompt<'a>) -> Self {
+        Self {
+            version: VERSION,
+            name: None,
+            description: None,
+            prompt,
+        }
+    }
+}
+
+impl<'a> Transcript<'a> {
+    /// Create a [`Transcript`] from a [`Prompt`].
+    pub fn new(prompt: Prompt<'a>) -> Self {
+        prompt.into()
+    }
+
+    /// Set the [`name`](Transcript::name).
+    pub fn name<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the [`description`](Transcript::description).
+    pub fn description<S>(mut self, description: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Save as pretty-printed JSON with a trailing newline.
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut json = serde_json::to_string_pretty(self).map_err(|error| {
+            Error::Parse {
+                path: path.to_owned(),
+                error,
+            }
+        })?;
+        json.push('\n');
+
+        std::fs::write(path, json).map_err(|error| Error::Io {
+            path: path.to_owned(),
+            error,
+        })
+    }
+
+    /// Render as verbose markdown for a snapshot. This requires the
+    /// `markdown` feature.
+    #[cfg(feature = "markdown")]
+    pub fn snapshot_markdown(&self) -> String {
+        use crate::markdown::ToMarkdown;
+
+        self.prompt.markdown_verbose().into()
+    }
+
+    /// Render as verbose HTML for a snapshot. This requires the `html`
+    /// feature.
+    #[cfg(feature = "html")]
+    pub fn snapshot_html(&self) -> String {
+        use crate::html::ToHtml;
+
+        self.prompt.html_verbose().to_string()
+    }
+}
+
+impl Transcript<'static> {
+    /// Load a transcript from a JSON file.
+    pub fn load<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).map_err(|error| Error::Io {
+                path: path.to_owned(),
+                error,
+            })?;
+        let transcript: Self =
+            serde_json::from_str(&json).map_err(|error| Error::Parse {
+                path: path.to_owned(),
+                error,
+            })?;
+
+        if transcript.version > VERSION {
+            return Err(Error::Version {
+                path: path.to_owned(),
+                version: transcript.version,
+            });
+        }
+
+        Ok(transcript)
+    }
+
+    /// Load every `*.json` file in a directory, sorted by file name.
+    pub fn load_dir<P>(dir: P) -> Result<Vec<Self>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        let io_error = |error| Error::Io {
+            path: dir.to_owned(),
+            error,
+        };
+
+        let mut paths = vec![];
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        paths.into_iter().map(Self::load).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test/data/transcripts/weather.json"
+    );
+
+    #[test]
+    fn test_load() {
+        let transcript = Transcript::load(FIXTURE).unwrap();
+        assert_eq!(transcript.version, VERSION);
+        assert_eq!(transcript.name.as_deref(), Some("weather"));
+        assert_eq!(transcript.prompt.messages.len(), 2);
+        assert_eq!(transcript.prompt.extra.len(), 0);
+
+        let all =
+            Transcript::load_dir(Path::new(FIXTURE).parent().unwrap()).unwrap();
+        assert_eq!(all.len(), 1);
+        assert!(all[0] == transcript);
+    }
+
+    #[test]
+    fn test_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("saved.json");
+        let transcript =
+            Transcript::load(FIXTURE).unwrap().description("Round trip");
+        transcript.save(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.ends_with("}\n"));
+        assert!(Transcript::load(&path).unwrap() == transcript);
+
+        // Newer versions are rejected.
+        std::fs::write(
+            &path,
+            r#"{"version":2,"model":"claude-3-5-sonnet-latest","messages":[]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            Transcript::load(&path),
+            Err(Error::Version { version: 2, .. })
+        ));
+
+        assert!(matches!(
+            Transcript::load(dir.path().join("missing.json")),
+            Err(Error::Io { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "markdown")]
+    fn test_snapshot_markdown() {
+        let markdown = Transcript::load(FIXTURE).unwrap().snapshot_markdown();
+        assert!(markdown.contains("What's the weather in Paris?"));
+        assert!(markdown.contains("### Assistant"));
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn test_snapshot_html() {
+        let html = Transcript::load(FIXTURE).unwrap().snapshot_html();
+        assert!(html.contains("What's the weather in Paris?"));
+    }
+}
@@ -1,13 +1,21 @@
-use std::ops::Deref;
+use std::{borrow::Cow, fmt::Write, ops::Deref};
 
 use pulldown_cmark::html::push_html;
+use serde::{Deserialize, Serialize};
+use xml::escape::escape_str_pcdata;
 
-use crate::markdown::ToMarkdown;
+use crate::{
+    markdown::ToMarkdown,
+    prompt::{
+        message::{Block, Content, Image, TranscriptRole},
+        Message,
+    },
+};
 
 pub use crate::markdown::{Options, DEFAULT_OPTIONS, VERBOSE_OPTIONS};
 
 /// Immutable wrapper around a [`String`]. Guaranteed to be valid HTML.
-#[derive(derive_more::Display)]
+#[derive(Clone, Default, derive_more::Display)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 #[display("{inner}")]
 pub struct Html {
@@ -82,6 +90,157 @@ impl Html {
     }
 }
 
+#[cfg(feature = "highlight")]
+impl Html {
+    /// Create a new `Html` from a stream of markdown events, highlighting
+    /// fenced code blocks with the [`Highlighter`]. Code blocks in languages
+    /// the [`Highlighter`] does not know are rendered as usual.
+    pub fn from_events_highlighted<'a>(
+        events: impl Iterator<Item = pulldown_cmark::Event<'a>>,
+        highlighter: &Highlighter,
+    ) -> Self {
+        use pulldown_cmark::{CodeBlockKind, Event, Tag, TagEnd};
+
+        let mut html = Html::default();
+        let mut pending = vec![];
+        let mut events = events.peekable();
+
+        while let Some(event) = events.next() {
+            let lang = match &event {
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                    info.split_whitespace().next().unwrap_or_default()
+                }
+                _ => {
+                    pending.push(event);
+                    continue;
+                }
+            };
+
+            let mut code = String::new();
+            while let Some(Event::Text(text)) = events.peek() {
+                code.push_str(text);
+                events.next();
+            }
+
+            match highlighter.highlight(&code, lang) {
+                Some(highlighted) => {
+                    // Skip the end tag, which we have written ourselves.
+                    if let Some(Event::End(TagEnd::CodeBlock)) = events.peek() {
+                        events.next();
+                    }
+                    html.extend(pending.drain(..));
+                    html.inner.push_str(&highlighted);
+                }
+                None => {
+                    pending.push(event);
+                    pending.push(Event::Text(code.into()));
+                }
+            }
+        }
+        html.extend(pending);
+
+        html
+    }
+}
+
+/// How a [`Highlighter`] styles code.
+#[cfg(feature = "highlight")]
+#[derive(Clone, Debug)]
+pub enum HighlightStyle {
+    /// Spans get CSS classes, such as `<span class="keyword">`. Use
+    /// [`Highlighter::css`] to generate a stylesheet.
+    Classes,
+    /// Spans get inline `style` attributes from a theme.
+    Inline(Box<syntect::highlighting::Theme>),
+}
+
+/// Syntax highlighter for fenced code blocks, used by
+/// [`ToHtml::html_highlighted`]. Creating one loads the default syntaxes,
+/// which takes a few milliseconds, so it is best created once and reused.
+#[cfg(feature = "highlight")]
+pub struct Highlighter {
+    syntaxes: syntect::parsing::SyntaxSet,
+    style: HighlightStyle,
+}
+
+#[cfg(feature = "highlight")]
+impl Highlighter {
+    /// Create a [`Highlighter`] which adds CSS classes. See
+    /// [`HighlightStyle::Classes`].
+    pub fn classes() -> Self {
+        Self {
+            syntaxes: syntect::parsing::SyntaxSet::load_defaults_newlines(),
+            style: HighlightStyle::Classes,
+        }
+    }
+
+    /// Create a [`Highlighter`] which adds inline styles from one of the
+    /// default themes, such as `base16-ocean.dark` or `InspiredGitHub`.
+    /// Returns [`None`] if there is no such theme.
+    pub fn inline(theme: &str) -> Option<Self> {
+        let theme = Self::theme(theme)?;
+        Some(Self {
+            syntaxes: syntect::parsing::SyntaxSet::load_defaults_newlines(),
+            style: HighlightStyle::Inline(Box::new(theme)),
+        })
+    }
+
+    /// Generate a stylesheet for [`HighlightStyle::Classes`] from one of the
+    /// default themes. Returns [`None`] if there is no such theme.
+    pub fn css(theme: &str) -> Option<String> {
+        syntect::html::css_for_theme_with_class_style(
+            &Self::theme(theme)?,
+            syntect::html::ClassStyle::Spaced,
+        )
+        .ok()
+    }
+
+    fn theme(name: &str) -> Option<syntect::highlighting::Theme> {
+        syntect::highlighting::ThemeSet::load_defaults()
+            .themes
+            .remove(name)
+    }
+
+    /// Highlight `code` in `lang` as a `<pre>` block. Returns [`None`] if the
+    /// language is unknown or highlighting fails.
+    fn highlight(&self, code: &str, lang: &str) -> Option<String> {
+        use syntect::{html::ClassStyle, util::LinesWithEndings};
+        use xml::escape::escape_str_attribute;
+
+        let syntax = self.syntaxes.find_syntax_by_token(lang)?;
+
+        match &self.style {
+            HighlightStyle::Classes => {
+                let mut generator =
+                    syntect::html::ClassedHTMLGenerator::new_with_class_style(
+                        syntax,
+                        &self.syntaxes,
+                        ClassStyle::Spaced,
+                    );
+                for line in LinesWithEndings::from(code) {
+                    generator
+                        .parse_html_for_line_which_includes_newline(line)
+                        .ok()?;
+                }
+                Some(format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                    escape_str_attribute(lang),
+                    generator.finalize()
+                ))
+            }
+            HighlightStyle::Inline(theme) => {
+                syntect::html::highlighted_html_for_string(
+                    code,
+                    &self.syntaxes,
+                    syntax,
+                    theme,
+                )
+                .ok()
+            }
+        }
+    }
+}
+
 impl From<Html> for String {
     fn from(html: Html) -> Self {
         html.inner
@@ -132,8 +291,6 @@ impl<'a> FromIterator<pulldown_cmark::Event<'a>> for Html {
 pub trait ToHtml: ToMarkdown {
     /// Render the type to an HTML string.
     fn html(&self) -> Html {
-        let mut opts = DEFAULT_OPTIONS;
-        opts.attrs = true;
         self.html_custom(DEFAULT_OPTIONS)
     }
 
@@ -146,10 +303,332 @@ pub trait ToHtml: ToMarkdown {
     fn html_custom(&self, options: Options) -> Html {
         self.markdown_events_custom(options).collect()
     }
+
+    /// Render the type to an HTML string with custom [`Options`], highlighting
+    /// fenced code blocks with a [`Highlighter`].
+    #[cfg(feature = "highlight")]
+    fn html_highlighted(
+        &self,
+        options: Options,
+        highlighter: &Highlighter,
+    ) -> Html {
+        Html::from_events_highlighted(
+            self.markdown_events_custom(options),
+            highlighter,
+        )
+    }
 }
 
 impl<T> ToHtml for T where T: ToMarkdown {}
 
+/// How [`Image`]s are rendered by [`RenderHtml`].
+///
+/// [`Image`]: crate::prompt::message::Image
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum ImagePolicy {
+    /// An `<img>` tag with the base64 data embedded in a `data:` URL.
+    #[default]
+    Inline,
+    /// A `<span class="image">` placeholder, keeping the data out of the
+    /// document.
+    Placeholder,
+}
+
+/// Tag and class structure of the HTML rendered by [`RenderHtml`].
+///
+/// With the [`Default`] theme, each message is rendered as:
+/// ```html
+/// <div class="msg assistant">
+/// <h3>Assistant</h3>
+/// <p>Hello!</p>
+/// </div>
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(default)]
+pub struct Theme {
+    /// Tag wrapping each message. This is not escaped.
+    pub message_tag: Cow<'static, str>,
+    /// Class of the message wrapper. The role (`system`, `user`,
+    /// `assistant`, `tool`, or `error`) is added as a second class.
+    pub message_class: Cow<'static, str>,
+    /// Whether to include a heading with the role in each message.
+    pub headings: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            message_tag: "div".into(),
+            message_class: "msg".into(),
+            headings: true,
+        }
+    }
+}
+
+impl Theme {
+    /// Set the tag wrapping each message.
+    pub fn message_tag(mut self, tag: impl Into<Cow<'static, str>>) -> Self {
+        self.message_tag = tag.into();
+        self
+    }
+
+    /// Set the class of the message wrapper.
+    pub fn message_class(
+        mut self,
+        class: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.message_class = class.into();
+        self
+    }
+
+    /// Set whether to include a heading with the role in each message.
+    pub fn headings(mut self, headings: bool) -> Self {
+        self.headings = headings;
+        self
+    }
+}
+
+/// Options for [`RenderHtml`].
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Markdown [`Options`], for example whether to include tool use.
+    /// [`Options::attrs`] is ignored since the role is a class of the
+    /// message wrapper.
+    pub markdown: Options,
+    /// Tag and class structure.
+    pub theme: Theme,
+    /// How images are rendered.
+    pub images: ImagePolicy,
+    /// Whether to collapse tool use and results into `<details>` elements.
+    pub collapse_tools: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            markdown: DEFAULT_OPTIONS,
+            theme: Theme::default(),
+            images: ImagePolicy::default(),
+            collapse_tools: false,
+        }
+    }
+}
+
+impl From<Options> for RenderOptions {
+    fn from(markdown: Options) -> Self {
+        Self {
+            markdown,
+            ..Default::default()
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Set the markdown [`Options`].
+    pub fn markdown(mut self, options: Options) -> Self {
+        self.markdown = options;
+        self
+    }
+
+    /// Set the [`Theme`].
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Set the [`ImagePolicy`].
+    pub fn images(mut self, policy: ImagePolicy) -> Self {
+        self.images = policy;
+        self
+    }
+
+    /// Set whether to collapse tool use and results into `<details>`.
+    pub fn collapse_tools(mut self, collapse: bool) -> Self {
+        self.collapse_tools = collapse;
+        self
+    }
+}
+
+/// A trait for types that can be rendered to HTML with [`RenderOptions`],
+/// for styling into a chat UI. Unlike [`ToHtml`], each message is wrapped in
+/// an element according to the [`Theme`].
+pub trait RenderHtml {
+    /// Render the type, appending to `html`.
+    fn render_html(&self, options: &RenderOptions, html: &mut Html);
+
+    /// Render the type to [`Html`] with [`RenderOptions`].
+    fn html_with(&self, options: &RenderOptions) -> Html {
+        let mut html = Html::default();
+        self.render_html(options, &mut html);
+        html
+    }
+}
+
+impl RenderHtml for Block<'_> {
+    fn render_html(&self, options: &RenderOptions, html: &mut Html) {
+        let summary = match self {
+            Block::Text { text, .. } => {
+                html.extend(pulldown_cmark::Parser::new_ext(
+                    text,
+                    options.markdown.inner,
+                ));
+                return;
+            }
+            Block::Image {
+                image: Image::Base64 { media_type, data },
+                ..
+            } => {
+                match options.images {
+                    ImagePolicy::Inline => writeln!(
+                        html.inner,
+                        "<img src=\"data:{};base64,{}\" alt=\"Image\" />",
+                        media_type,
+                        base64::display::Base64Display::new(
+                            data,
+                            &base64::engine::general_purpose::STANDARD
+                        ),
+                    ),
+                    ImagePolicy::Placeholder => writeln!(
+                        html.inner,
+                        "<span class=\"image\">[{} image]</span>",
+                        media_type,
+                    ),
+                }
+                // Writing to a `String` can't fail.
+                .ok();
+                return;
+            }
+            Block::Image {
+                image: Image::Url { url },
+                ..
+            } => {
+                match options.images {
+                    ImagePolicy::Inline => writeln!(
+                        html.inner,
+                        "<img src=\"{}\" alt=\"Image\" />",
+                        xml::escape::escape_str_attribute(url),
+                    ),
+                    ImagePolicy::Placeholder => {
+                        writeln!(
+                            html.inner,
+                            "<span class=\"image\">[image]</span>"
+                        )
+                    }
+                }
+                // Writing to a `String` can't fail.
+                .ok();
+                return;
+            }
+            Block::ToolUse { call } | Block::ServerToolUse { call } => {
+                format!("Tool use: {}", call.name)
+            }
+            Block::ToolResult { .. }
+            | Block::WebSearchToolResult { .. }
+            | Block::CodeExecutionToolResult { .. } => "Tool result".into(),
+            Block::Document { .. } | Block::Unknown(_) => return,
+        };
+
+        let events = self.markdown_events_custom(options.markdown);
+        if !options.collapse_tools {
+            html.extend(events);
+            return;
+        }
+
+        let mut events = events.peekable();
+        if events.peek().is_none() {
+            return;
+        }
+        writeln!(
+            html.inner,
+            "<details class=\"tool\">\n<summary>{}</summary>",
+            escape_str_pcdata(&summary)
+        )
+        .ok();
+        html.extend(events);
+        html.inner.push_str("</details>\n");
+    }
+}
+
+impl RenderHtml for Content<'_> {
+    fn render_html(&self, options: &RenderOptions, html: &mut Html) {
+        for block in self.iter() {
+            block.render_html(options, html);
+        }
+    }
+}
+
+impl RenderHtml for Message<'_> {
+    fn render_html(&self, options: &RenderOptions, html: &mut Html) {
+        if self.is_rendered(&options.markdown) {
+            render_message(self.heading(), &self.content, options, html);
+        }
+    }
+}
+
+impl RenderHtml for crate::response::Message<'_> {
+    fn render_html(&self, options: &RenderOptions, html: &mut Html) {
+        self.message.render_html(options, html)
+    }
+}
+
+impl RenderHtml for crate::Prompt<'_> {
+    fn render_html(&self, options: &RenderOptions, html: &mut Html) {
+        if let Some(system) =
+            self.system.as_ref().filter(|_| options.markdown.system)
+        {
+            let role = TranscriptRole::System;
+            render_message(
+                (role.as_str(), role.as_attr()),
+                system,
+                options,
+                html,
+            );
+        }
+
+        for message in &self.messages {
+            message.render_html(options, html);
+        }
+    }
+}
+
+/// Render `content` wrapped according to the [`Theme`], with a `role` class
+/// and heading.
+fn render_message(
+    (role, class_name): (&str, &str),
+    content: &Content,
+    options: &RenderOptions,
+    html: &mut Html,
+) {
+    use pulldown_cmark::{Event, HeadingLevel::H3, Tag};
+
+    let theme = &options.theme;
+    let tag = &theme.message_tag;
+    let class = xml::escape::escape_str_attribute(&theme.message_class);
+
+    writeln!(html.inner, "<{} class=\"{} {}\">", tag, class, class_name).ok();
+    if theme.headings {
+        let heading = Tag::Heading {
+            level: options.markdown.heading_level.unwrap_or(H3),
+            id: None,
+            classes: vec![],
+            attrs: vec![],
+        };
+        let end = heading.to_end();
+        html.extend([
+            Event::Start(heading),
+            Event::Text(role.into()),
+            Event::End(end),
+        ]);
+    }
+    content.render_html(options, html);
+    writeln!(html.inner, "</{}>", tag).ok();
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Borrow;
@@ -191,6 +670,7 @@ mod tests {
         let prompt = crate::prompt::Prompt {
             system: Some("Do stuff the user says.".into()),
             tools: Some(vec![Tool {
+                kind: None,
                 name: "python".into(),
                 description: "Run a Python script.".into(),
                 input_schema: json!({
@@ -203,6 +683,7 @@ mod tests {
                     },
                     "required": ["script"],
                 }),
+                extra: Default::default(),
                 #[cfg(feature = "prompt-caching")]
                 cache_control: None,
             }]),
@@ -258,7 +739,7 @@ mod tests {
 
         assert_eq!(
             prompt.html_verbose().as_ref(),
-            "<h3 role=\"system\">System</h3>\n<p>Do stuff the user says.</p>\n<h3 role=\"user\">User</h3>\n<p>Run a hello world python program.</p>\n<h3 role=\"assistant\">Assistant</h3>\n<pre><code class=\"language-json\">{\"type\":\"tool_use\",\"id\":\"id\",\"name\":\"python\",\"input\":{\"script\":\"print('Hello, world!')\"}}</code></pre>\n<h3 role=\"tool\">Tool</h3>\n<pre><code class=\"language-json\">{\"type\":\"tool_result\",\"tool_use_id\":\"id\",\"content\":[{\"type\":\"text\",\"text\":\"{\\\"stdout\\\":\\\"Hello, world!\\\\n\\\"}\"}],\"is_error\":false}</code></pre>\n<h3 role=\"assistant\">Assistant</h3>\n<p>It is done!</p>\n",
+            "<h3 role=\"system\">System</h3>\n<p>Do stuff the user says.</p>\n<h3 role=\"user\">User</h3>\n<p>Run a hello world python program.</p>\n<h3 role=\"assistant\">Assistant</h3>\n<pre><code class=\"language-json\">{\"type\":\"tool_use\",\"id\":\"id\",\"name\":\"python\",\"input\":{\"script\":\"print('Hello, world!')\"}}</code></pre>\n<h3 role=\"tool\">Tool</h3>\n<pre><code class=\"language-json\">{\"type\":\"tool_result\",\"tool_use_id\":\"id\",\"content\":\"{\\\"stdout\\\":\\\"Hello, world!\\\\n\\\"}\",\"is_error\":false}</code></pre>\n<h3 role=\"assistant\">Assistant</h3>\n<p>It is done!</p>\n",
         )
     }
 
@@ -286,7 +767,7 @@ mod tests {
             pulldown_cmark::Event::End(pulldown_cmark::TagEnd::Paragraph),
         ];
 
-        html.extend(events.into_iter());
+        html.extend(events);
         assert_eq!(html.as_ref(), "<p>Hello, world!</p>\n");
     }
 
@@ -331,6 +812,98 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "highlight")]
+    fn test_html_highlighted() {
+        let message = Message {
+            role: Role::Assistant,
+            content:
+                "Run:\n\n```rust\nfn main() {}\n```\n\n```nope\nplain\n```"
+                    .into(),
+        };
+
+        let highlighter = Highlighter::classes();
+        let html = message.html_highlighted(DEFAULT_OPTIONS, &highlighter);
+        assert!(html.starts_with(
+            "<h3>Assistant</h3>\n<p>Run:</p>\n<pre><code class=\"language-rust\"><span class=\"source rust\">"
+        ));
+        assert!(html
+            .contains("<span class=\"storage type function rust\">fn</span>"));
+        // Unknown languages are rendered as usual.
+        assert!(html.ends_with(
+            "<pre><code class=\"language-nope\">plain\n</code></pre>\n"
+        ));
+
+        let highlighter = Highlighter::inline("InspiredGitHub").unwrap();
+        let html = message.html_highlighted(DEFAULT_OPTIONS, &highlighter);
+        assert!(html.contains("<pre style=\"background-color:#ffffff;\">"));
+        assert!(html.contains("style=\"color:"));
+
+        assert!(Highlighter::inline("nope").is_none());
+        assert!(Highlighter::css("InspiredGitHub")
+            .unwrap()
+            .contains(".comment"));
+    }
+
+    #[test]
+    fn test_render_html() {
+        use crate::prompt::message::MediaType;
+
+        let prompt = crate::Prompt {
+            system: Some("Be *nice*.".into()),
+            messages: vec![
+                Message {
+                    role: Role::User,
+                    content: Content::from(vec![
+                        Block::text("Look:"),
+                        Image::from_parts(MediaType::Png, "AAAA")
+                            .unwrap()
+                            .into(),
+                    ]),
+                },
+                tool::Use {
+                    id: "id".into(),
+                    name: "look".into(),
+                    input: json!({}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                }
+                .into(),
+                Message {
+                    role: Role::Assistant,
+                    content: "A <b>cat</b>.".into(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            prompt.html_with(&RenderOptions::default()).as_ref(),
+            "<div class=\"msg user\">\n<h3>User</h3>\n<p>Look:</p>\n<img src=\"data:image/png;base64,AAAA\" alt=\"Image\" />\n</div>\n\
+             <div class=\"msg assistant\">\n<h3>Assistant</h3>\n<p>A &lt;b&gt;cat&lt;/b&gt;.</p>\n</div>\n",
+        );
+
+        let options = RenderOptions::from(VERBOSE_OPTIONS)
+            .theme(Theme::default().message_tag("section").headings(false))
+            .images(ImagePolicy::Placeholder)
+            .collapse_tools(true);
+        assert_eq!(
+            prompt.html_with(&options).as_ref(),
+            "<section class=\"msg system\">\n<p>Be <em>nice</em>.</p>\n</section>\n\
+             <section class=\"msg user\">\n<p>Look:</p>\n<span class=\"image\">[image/png image]</span>\n</section>\n\
+             <section class=\"msg assistant\">\n<details class=\"tool\">\n<summary>Tool use: look</summary>\n\
+             <pre><code class=\"language-json\">{\"type\":\"tool_use\",\"id\":\"id\",\"name\":\"look\",\"input\":{}}</code></pre>\n\
+             </details>\n</section>\n\
+             <section class=\"msg assistant\">\n<p>A &lt;b&gt;cat&lt;/b&gt;.</p>\n</section>\n",
+        );
+
+        let url = Block::image_url("https://example.com/?a=1&b=\"2\"");
+        assert_eq!(
+            url.html_with(&RenderOptions::default()).as_ref(),
+            "<img src=\"https://example.com/?a=1&amp;b=&quot;2&quot;\" alt=\"Image\" />\n",
+        );
+    }
+
     #[test]
     fn test_borrow() {
         let message = Message {
@@ -0,0 +1,3 @@
+//! Conversion from conversation formats of other applications.
+
+pub mod claude_export;
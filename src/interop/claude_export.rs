@@ -0,0 +1,376 @@
+//! Import conversations from a [Claude.ai data export]. The export is a zip
+//! file containing `conversations.json`, which can be parsed with
+//! [`from_str`] or [`from_reader`].
+//!
+//! Only text is imported. Attachments are imported as text if Claude.ai
+//! extracted their content. Tool use, thinking, and other content is skipped
+//! since it can't be sent back to the API as is.
+//!
+//! ```
+//! use misanthropic::interop::claude_export;
+//!
+//! let json = r#"[{
+//!     "uuid": "a1b2",
+//!     "name": "Greetings",
+//!     "created_at": "2024-10-01T00:00:00.000000Z",
+//!     "chat_messages": [
+//!         {"sender": "human", "text": "Hi!", "created_at": "2024-10-01T00:00:01Z"},
+//!         {"sender": "assistant", "text": "Hello!", "created_at": "2024-10-01T00:00:02Z"}
+//!     ]
+//! }]"#;
+//!
+//! let exported = claude_export::from_str(json).unwrap();
+//! assert_eq!(exported[0].name, "Greetings");
+//!
+//! let conversation = exported[0].conversation();
+//! assert_eq!(conversation.entries.len(), 2);
+//! assert_eq!(conversation.prompt().messages[1].text(), "Hello!");
+//! ```
+//!
+//! [Claude.ai data export]: <https://support.anthropic.com/en/articles/9450526-how-can-i-export-my-claude-ai-data>
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    conversation::Entry,
+    prompt::{
+        self,
+        message::{Block, Content, Role},
+    },
+    Conversation,
+};
+
+/// Parse the `conversations.json` of a Claude.ai data export.
+pub fn from_str(
+    json: &str,
+) -> Result<Vec<ExportedConversation>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Read the `conversations.json` of a Claude.ai data export.
+pub fn from_reader<R>(
+    reader: R,
+) -> Result<Vec<ExportedConversation>, serde_json::Error>
+where
+    R: std::io::Read,
+{
+    serde_json::from_reader(reader)
+}
+
+/// A conversation from a Claude.ai data export.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(default)]
+pub struct ExportedConversation {
+    /// Unique id of the conversation.
+    pub uuid: String,
+    /// Title of the conversation.
+    pub name: String,
+    /// When the conversation was created, as an RFC 3339 timestamp.
+    pub created_at: Option<String>,
+    /// When the conversation was last updated, as an RFC 3339 timestamp.
+    pub updated_at: Option<String>,
+    /// Messages, in order.
+    pub chat_messages: Vec<ChatMessage>,
+}
+
+impl ExportedConversation {
+    /// Convert the [`ChatMessage`]s to [`prompt::Message`]s. Messages with no
+    /// text are skipped and consecutive messages from the same sender are
+    /// merged, since the API requires roles to alternate.
+    pub fn messages(&self) -> Vec<prompt::Message<'static>> {
+        self.entries()
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect()
+    }
+
+    /// Convert to a [`Conversation`] with the default [`Model`], since the
+    /// export does not include it. Timestamps are kept. See [`messages`].
+    ///
+    /// [`Model`]: crate::Model
+    /// [`messages`]: ExportedConversation::messages
+    pub fn conversation(&self) -> Conversation<'static> {
+        Conversation {
+            entries: self.entries().into_iter().map(Arc::new).collect(),
+            created_at: self.created_at.as_deref().and_then(parse_rfc3339),
+            ..Default::default()
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry<'static>> {
+        let mut entries: Vec<Entry<'static>> = vec![];
+
+        for message in &self.chat_messages {
+            let role = match message.sender {
+                Sender::Human => Role::User,
+                Sender::Assistant => Role::Assistant,
+            };
+            let blocks = message.blocks();
+            if blocks.is_empty() {
+                continue;
+            }
+
+            match entries.last_mut() {
+                Some(last) if last.message.role == role => {
+                    for block in blocks {
+                        last.message.content.push(block);
+                    }
+                }
+                _ => entries.push(Entry {
+                    message: prompt::Message {
+                        role,
+                        content: Content::from(blocks),
+                    },
+                    id: None,
+                    model: None,
+                    usage: None,
+                    stop_reason: None,
+                    timestamp: message
+                        .created_at
+                        .as_deref()
+                        .and_then(parse_rfc3339),
+                    latency: None,
+                }),
+            }
+        }
+
+        entries
+    }
+}
+
+/// Author of a [`ChatMessage`].
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum Sender {
+    /// The user.
+    Human,
+    /// Claude.
+    Assistant,
+}
+
+/// A message from a Claude.ai data export.
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct ChatMessage {
+    /// Unique id of the message.
+    #[serde(default)]
+    pub uuid: String,
+    /// Author of the message.
+    pub sender: Sender,
+    /// Text of the message. Newer exports also have [`content`].
+    ///
+    /// [`content`]: ChatMessage::content
+    #[serde(default)]
+    pub text: String,
+    /// Content blocks of the message, in newer exports.
+    #[serde(default)]
+    pub content: Vec<ExportedBlock>,
+    /// When the message was created, as an RFC 3339 timestamp.
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// Files attached to the message.
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+impl ChatMessage {
+    /// Text [`Block`]s of the message, including attachments with extracted
+    /// content.
+    fn blocks(&self) -> Vec<Block<'static>> {
+        let mut blocks: Vec<Block<'static>> = self
+            .content
+            .iter()
+            .filter_map(|block| match block {
+                ExportedBlock::Text { text } if !text.is_empty() => {
+                    Some(text.clone().into())
+                }
+                _ => None,
+            })
+            .collect();
+
+        if blocks.is_empty() && !self.text.is_empty() {
+            blocks.push(self.text.clone().into());
+        }
+
+        for attachment in &self.attachments {
+            if let Some(content) = attachment
+                .extracted_content
+                .as_deref()
+                .filter(|c| !c.is_empty())
+            {
+                blocks.push(
+                    format!(
+                        "Attachment `{}`:\n\n{}",
+                        attachment.file_name, content
+                    )
+                    .into(),
+                );
+            }
+        }
+
+        blocks
+    }
+}
+
+/// A content block from a Claude.ai data export. Only text is supported.
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ExportedBlock {
+    /// Text content.
+    Text {
+        /// The text.
+        #[serde(default)]
+        text: String,
+    },
+    /// Tool use, tool results, thinking, and anything else.
+    #[serde(other)]
+    Other,
+}
+
+/// A file attached to a [`ChatMessage`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(default)]
+pub struct Attachment {
+    /// Name of the file.
+    pub file_name: String,
+    /// Text extracted from the file by Claude.ai, if any.
+    pub extracted_content: Option<String>,
+}
+
+/// Parse an RFC 3339 UTC timestamp such as `2024-10-01T12:34:56.789Z`, which
+/// is what the export uses. Returns [`None`] for other offsets or invalid
+/// timestamps.
+fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let (date, time) = s.split_once(['T', ' '])?;
+    let time = time
+        .strip_suffix(['Z', 'z'])
+        .or(time.strip_suffix("+00:00"))?;
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) =
+        (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hour, minute, second) =
+        (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // Days since 1970-01-01, from Howard Hinnant's `days_from_civil`.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = u64::try_from(era * 146097 + doe - 719468).ok()?;
+
+    let nanos = fraction
+        .bytes()
+        .chain(std::iter::repeat(b'0'))
+        .take(9)
+        .fold(0, |n, b| n * 10 + u32::from(b - b'0'));
+
+    Some(
+        UNIX_EPOCH
+            + Duration::new(
+                days * 86400 + hour * 3600 + minute * 60 + second,
+                nanos,
+            ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = r#"[{
+        "uuid": "c1",
+        "name": "Files",
+        "created_at": "2024-10-01T00:00:00.123456Z",
+        "updated_at": "2024-10-01T00:01:00Z",
+        "account": {"uuid": "a1"},
+        "chat_messages": [
+            {
+                "uuid": "m1",
+                "text": "Summarize this.",
+                "content": [{"type": "text", "text": "Summarize this.", "citations": []}],
+                "sender": "human",
+                "created_at": "2024-10-01T00:00:01Z",
+                "attachments": [{"file_name": "notes.txt", "file_size": 5, "file_type": "txt", "extracted_content": "Notes"}],
+                "files": []
+            },
+            {"uuid": "m2", "text": "Also this.", "sender": "human", "created_at": "2024-10-01T00:00:02Z"},
+            {"uuid": "m3", "text": "", "sender": "assistant", "content": [{"type": "tool_use", "name": "x", "input": {}}]},
+            {"uuid": "m4", "text": "Done.", "sender": "assistant", "created_at": "2024-10-01T00:00:03Z"}
+        ]
+    }]"#;
+
+    #[test]
+    fn test_from_str() {
+        let exported = from_str(EXPORT).unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].chat_messages.len(), 4);
+        assert_eq!(
+            exported[0].chat_messages[2].content[0],
+            ExportedBlock::Other
+        );
+
+        let messages = exported[0].messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, Role::User);
+        assert_eq!(
+            messages[0].text(),
+            "Summarize this.Attachment `notes.txt`:\n\nNotesAlso this."
+        );
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(messages[1].text(), "Done.");
+
+        let conversation = exported[0].conversation();
+        assert_eq!(
+            conversation.created_at,
+            Some(UNIX_EPOCH + Duration::new(1727740800, 123456000))
+        );
+        assert_eq!(
+            conversation.entries[1].timestamp,
+            Some(UNIX_EPOCH + Duration::from_secs(1727740803))
+        );
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let parse =
+            |s| parse_rfc3339(s).map(|t| t.duration_since(UNIX_EPOCH).unwrap());
+
+        assert_eq!(parse("1970-01-01T00:00:00Z"), Some(Duration::ZERO));
+        assert_eq!(
+            parse("2000-02-29T12:30:15.5Z"),
+            Some(Duration::new(951827415, 500_000_000))
+        );
+        assert_eq!(
+            parse("2024-10-01T00:00:00+00:00"),
+            Some(Duration::from_secs(1727740800))
+        );
+        assert_eq!(parse("2024-10-01T00:00:00-07:00"), None);
+        assert_eq!(parse("2024-13-01T00:00:00Z"), None);
+        assert_eq!(parse("1969-12-31T23:59:59Z"), None);
+        assert_eq!(parse("nope"), None);
+    }
+}
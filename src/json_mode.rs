@@ -0,0 +1,226 @@
+//! Structured output with [`Client::json`]. The model is forced to call a
+//! single [`TOOL_NAME`] tool whose input schema is derived from `T` with
+//! [`schemars`], and the tool input is parsed as `T`.
+//!
+//! ```no_run
+//! use misanthropic::{prompt::message::Role, Client, Prompt};
+//! use schemars::JsonSchema;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct Recipe {
+//!     /// Name of the dish.
+//!     name: String,
+//!     ingredients: Vec<String>,
+//! }
+//!
+//! # async fn example(client: Client) -> Result<(), misanthropic::json_mode::Error> {
+//! let recipe: Recipe = client
+//!     .json(Prompt::default().add_message((Role::User, "A bread recipe.")))
+//!     .await?;
+//! println!("{}: {:?}", recipe.name, recipe.ingredients);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Doc comments on `T` and its fields become descriptions in the schema, which
+//! the model uses as documentation.
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+
+use crate::{client, response, tool, Client, Prompt, Tool};
+
+/// Name of the tool the model is forced to call.
+pub const TOOL_NAME: &str = "emit_json";
+
+/// Description of the tool, used if `T` has no doc comment.
+pub const DEFAULT_DESCRIPTION: &str =
+    "Emit the response as JSON matching the input schema.";
+
+/// Error from [`Client::json`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request failed.
+    #[error("Client error: {0}")]
+    Client(#[from] client::Error),
+    /// The schema of `T` can't be used as a tool input schema, for example
+    /// because `T` is not a struct.
+    #[error("Invalid schema: {0}")]
+    Schema(#[from] tool::ToolBuildError),
+    /// The response did not call the [`TOOL_NAME`] tool.
+    #[error("The response did not call `{TOOL_NAME}`.")]
+    NoToolUse,
+    /// The tool input was not a valid `T`.
+    #[error("Could not parse `{input}`: {error}")]
+    #[allow(missing_docs)]
+    Parse {
+        input: serde_json::Value,
+        error: serde_json::Error,
+    },
+}
+
+/// The [`TOOL_NAME`] [`Tool`] with the schema of `T` as its input schema.
+pub fn tool<T>() -> Result<Tool<'static>, Error>
+where
+    T: JsonSchema,
+{
+    let mut schema = schemars::schema_for!(T).to_value();
+    let mut description = DEFAULT_DESCRIPTION.to_string();
+    if let Some(obj) = schema.as_object_mut() {
+        obj.remove("$schema");
+        if let Some(serde_json::Value::String(doc)) = obj.get("description") {
+            description = doc.clone();
+        }
+        // Structs with only optional fields have no `required` array, which
+        // the `ToolBuilder` expects.
+        obj.entry("required")
+            .or_insert_with(|| serde_json::Value::Array(vec![]));
+    }
+
+    Ok(Tool::builder(TOOL_NAME)
+        .description(description)
+        .schema(schema)
+        .build()?)
+}
+
+/// Add the [`tool()`] for `T` to `prompt` and force the model to use it. Any
+/// other tools are kept, but won't be called. The prompt must not end with
+/// an [`Assistant`] prefill, which the API rejects with a forced tool.
+///
+/// [`Assistant`]: crate::prompt::message::Role::Assistant
+pub fn prompt<'a, T>(prompt: Prompt<'a>) -> Result<Prompt<'a>, Error>
+where
+    T: JsonSchema,
+{
+    Ok(prompt
+        .add_tool(tool::<T>()?)
+        .tool_choice(tool::Choice::tool(TOOL_NAME).disable_parallel_tool_use()))
+}
+
+/// Parse `T` from the input of the [`TOOL_NAME`] call in `message`.
+pub fn parse<T>(message: &response::Message) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let call = message
+        .tool_uses()
+        .find(|call| call.name == TOOL_NAME)
+        .ok_or(Error::NoToolUse)?;
+
+    serde_json::from_value(call.input.clone()).map_err(|error| Error::Parse {
+        input: call.input.clone(),
+        error,
+    })
+}
+
+impl Client {
+    /// Get a `T` as structured output for a `prompt`. See the
+    /// [`json_mode`](crate::json_mode) module. This requires the `schemars`
+    /// feature.
+    pub async fn json<T>(&self, prompt: Prompt<'_>) -> Result<T, Error>
+    where
+        T: DeserializeOwned + JsonSchema,
+    {
+        let message = self.message(self::prompt::<T>(prompt)?).await?;
+
+        parse(&message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::message::Role;
+    use serde::Deserialize;
+
+    /// A person.
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Person {
+        /// Full name.
+        name: String,
+        age: Option<u8>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Options {
+        verbose: Option<bool>,
+    }
+
+    #[test]
+    fn test_tool() {
+        let person = tool::<Person>().unwrap();
+        assert_eq!(person.name, TOOL_NAME);
+        assert_eq!(person.description, "A person.");
+        assert!(person.input_schema.get("$schema").is_none());
+        assert_eq!(person.input_schema["required"], crate::json!(["name"]));
+        assert_eq!(
+            person.input_schema["properties"]["name"]["description"],
+            "Full name."
+        );
+
+        // Only optional fields, and no doc comment.
+        let options = tool::<Options>().unwrap();
+        assert_eq!(options.description, DEFAULT_DESCRIPTION);
+        assert_eq!(options.input_schema["required"], crate::json!([]));
+
+        // Not an object.
+        assert!(matches!(tool::<Vec<String>>(), Err(Error::Schema(_))));
+    }
+
+    #[test]
+    fn test_prompt() {
+        let prompt = prompt::<Person>(
+            Prompt::default().add_message((Role::User, "Who wrote Dune?")),
+        )
+        .unwrap();
+
+        assert_eq!(prompt.tools.as_ref().unwrap()[0].name, TOOL_NAME);
+        assert_eq!(
+            prompt.tool_choice,
+            Some(tool::Choice::tool(TOOL_NAME).disable_parallel_tool_use())
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut message: response::Message = serde_json::from_str(
+            crate::response::message::tests::RESPONSE_JSON,
+        )
+        .unwrap();
+        assert!(matches!(parse::<Person>(&message), Err(Error::NoToolUse)));
+
+        message.message.content.push(tool::Use {
+            id: "toolu_01".into(),
+            name: TOOL_NAME.into(),
+            input: crate::json!({"name": "Frank Herbert"}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        });
+        assert_eq!(
+            parse::<Person>(&message).unwrap(),
+            Person {
+                name: "Frank Herbert".into(),
+                age: None
+            }
+        );
+        assert!(matches!(
+            parse::<Options>(&message),
+            Ok(Options { verbose: None })
+        ));
+
+        message.message.content.push(tool::Use {
+            id: "toolu_02".into(),
+            name: TOOL_NAME.into(),
+            input: crate::json!({"age": 65}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        });
+        // The first call is used.
+        assert!(parse::<Person>(&message).is_ok());
+        message.message.content.remove(1);
+        assert!(matches!(
+            parse::<Person>(&message),
+            Err(Error::Parse { .. })
+        ));
+    }
+}
@@ -1,5 +1,8 @@
 //! [`Key`] is a wrapper around an Anthropic API key.
 
+#[cfg(all(feature = "memsecurity", target_arch = "wasm32"))]
+compile_error!("The `memsecurity` feature does not build on wasm32.");
+
 #[cfg(feature = "memsecurity")]
 mod encrypted;
 #[cfg(feature = "memsecurity")]
@@ -8,3 +11,41 @@ pub use encrypted::{InvalidKeyLength, Key};
 mod unencrypted;
 #[cfg(not(feature = "memsecurity"))]
 pub use unencrypted::{InvalidKeyLength, Key};
+
+/// Redacted form of a [`Key`] showing only the last 4 characters, such as
+/// `sk-ant-...gAAA`. This is what the [`Display`] and [`Debug`]
+/// implementations of [`Key`] write, so keys can't leak through format
+/// strings or logs. Returned by [`Key::redacted`].
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Redacted {
+    tail: [u8; 4],
+}
+
+impl Redacted {
+    /// Keep only the last 4 bytes of `key`.
+    pub(crate) fn new(key: &[u8]) -> Self {
+        let mut tail = [b'*'; 4];
+        let start = key.len().saturating_sub(4);
+        tail[..key.len() - start].copy_from_slice(&key[start..]);
+        Self { tail }
+    }
+}
+
+impl std::fmt::Display for Redacted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sk-ant-...{}", String::from_utf8_lossy(&self.tail))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted() {
+        assert_eq!(Redacted::new(b"sk-ant-1234").to_string(), "sk-ant-...1234");
+        assert_eq!(Redacted::new(b"ab").to_string(), "sk-ant-...ab**");
+    }
+}
@@ -2,9 +2,11 @@
 
 // TODO: Remove this dependency for wasm32 and find an alternative. It's not a
 // super idea to use this in a web app but wasm32 also has server use cases.
-// This is the only thing that prevents this from building on wasm32.
+// Without this feature the crate builds on wasm32.
 use memsecurity::zeroize::Zeroizing;
 
+use super::Redacted;
+
 /// The length of an Anthropic API key in bytes.
 pub const LEN: usize = 108;
 
@@ -17,13 +19,11 @@ pub struct InvalidKeyLength {
 }
 
 /// Stores an Anthropic API key securely. The API key is encrypted in memory.
-/// The object features a [`Display`] implementation that can be used to write
-/// out the key. **Be sure to zeroize whatever you write it to**. Prefer
-/// [`Key::read`] if you want a return value that will automatically zeroize
-/// the key on drop.
+///
+/// The [`Display`] and [`Debug`] implementations are [`Redacted`]. Use
+/// [`Key::read`] or [`Key::expose_secret`] to opt in to reading the whole key.
 ///
 /// [`Display`]: std::fmt::Display
-#[derive(Debug)]
 pub struct Key {
     // FIXME: `memsecurity` does not build on wasm32. Find a solution for web.
     // The `keyring` crate may work, but I'm likewise not sure if it builds on
@@ -36,6 +36,22 @@ impl Key {
     pub fn read(&self) -> memsecurity::ZeroizeBytes {
         self.mem.decrypt().unwrap()
     }
+
+    /// Copy out the whole key. The copy is zeroized on drop. **Be sure not to
+    /// copy it anywhere else**.
+    pub fn expose_secret(&self) -> zeroize::Zeroizing<String> {
+        // Zeroized on drop
+        let key = self.read();
+        // A Key can only be created from a String, so this is valid UTF-8.
+        zeroize::Zeroizing::new(
+            String::from_utf8_lossy(key.as_ref()).into_owned(),
+        )
+    }
+
+    /// Redacted form of the key, showing only the last 4 characters.
+    pub fn redacted(&self) -> Redacted {
+        Redacted::new(self.read().as_ref())
+    }
 }
 
 impl TryFrom<String> for Key {
@@ -63,18 +79,16 @@ impl TryFrom<String> for Key {
 }
 
 impl std::fmt::Display for Key {
-    /// Write out the key. Make sure to zeroize whatever you write it to if at
-    /// all possible.
-    ///
-    /// Prefer [`Self::read`] if you want a return value that will automatically
-    /// zeroize the key on drop.
+    /// Write out the [`Redacted`] key. Use [`Key::expose_secret`] for the
+    /// whole key.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // Zeroized on drop
-        let key = self.read();
-        // Unwrap can never panic because a Key can only be created from a
-        // String which is guaranteed to be valid UTF-8.
-        let key_str = std::str::from_utf8(key.as_ref()).unwrap();
-        write!(f, "{}", key_str)
+        self.redacted().fmt(f)
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Key({})", self.redacted())
     }
 }
 
@@ -90,8 +104,9 @@ mod tests {
     #[test]
     fn test_key() {
         let key = Key::try_from(API_KEY.to_string()).unwrap();
-        let key_str = key.to_string();
-        assert_eq!(key_str, API_KEY);
+        assert_eq!(key.expose_secret().as_str(), API_KEY);
+        assert_eq!(key.to_string(), "sk-ant-...sAAA");
+        assert_eq!(format!("{key:?}"), "Key(sk-ant-...sAAA)");
     }
 
     #[test]
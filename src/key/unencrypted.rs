@@ -1,6 +1,8 @@
 //! Unencrypted [`Key`] management for Anthropic API keys.
 use zeroize::{ZeroizeOnDrop, Zeroizing};
 
+use super::Redacted;
+
 /// The length of an Anthropic API key in bytes.
 pub const LEN: usize = 108;
 
@@ -15,12 +17,13 @@ pub struct InvalidKeyLength {
     pub actual: usize,
 }
 
-/// Stores an Anthropic API key securely. The object features a [`Display`]
-/// implementation that can be used to write out the key. **Be sure to zeroize
-/// whatever you write it to**. The key is zeroized on drop.
+/// Stores an Anthropic API key securely. The key is zeroized on drop.
+///
+/// The [`Display`] and [`Debug`] implementations are [`Redacted`]. Use
+/// [`Key::expose_secret`] to opt in to writing out the whole key.
 ///
 /// [`Display`]: std::fmt::Display
-#[derive(Debug, ZeroizeOnDrop)]
+#[derive(ZeroizeOnDrop)]
 pub struct Key {
     mem: Arr,
 }
@@ -34,6 +37,18 @@ impl Key {
     pub fn read(&self) -> &[u8] {
         &self.mem
     }
+
+    /// Copy out the whole key. The copy is zeroized on drop. **Be sure not to
+    /// copy it anywhere else**.
+    pub fn expose_secret(&self) -> Zeroizing<String> {
+        // A Key can only be created from a String, so this is valid UTF-8.
+        Zeroizing::new(String::from_utf8_lossy(self.read()).into_owned())
+    }
+
+    /// Redacted form of the key, showing only the last 4 characters.
+    pub fn redacted(&self) -> Redacted {
+        Redacted::new(self.read())
+    }
 }
 
 impl TryFrom<String> for Key {
@@ -55,16 +70,16 @@ impl TryFrom<String> for Key {
 }
 
 impl std::fmt::Display for Key {
-    /// Write out the key. Make sure to zeroize whatever you write it to if at
-    /// all possible.
-    ///
-    /// Prefer [`Self::read`] if you want a return value that will automatically
-    /// zeroize the key on drop.
+    /// Write out the [`Redacted`] key. Use [`Key::expose_secret`] for the
+    /// whole key.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.redacted().fmt(f)
+    }
+}
+
+impl std::fmt::Debug for Key {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // Unwrap can never panic because a Key can only be created from String
-        // whic is guaranteed to be valid UTF-8.
-        let key_str = std::str::from_utf8(self.read()).unwrap();
-        write!(f, "{}", key_str)
+        write!(f, "Key({})", self.redacted())
     }
 }
 
@@ -80,8 +95,10 @@ mod tests {
     #[test]
     fn test_key() {
         let key = Key::try_from(API_KEY.to_string()).unwrap();
-        let key_str = key.to_string();
-        assert_eq!(key_str, API_KEY);
+        assert_eq!(key.expose_secret().as_str(), API_KEY);
+        assert_eq!(key.to_string(), "sk-ant-...sAAA");
+        assert_eq!(format!("{key:?}"), "Key(sk-ant-...sAAA)");
+        assert_eq!(key.redacted().to_string(), key.to_string());
     }
 
     #[test]
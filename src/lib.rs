@@ -33,6 +33,50 @@ pub use tool::Tool;
 pub mod response;
 pub use response::Response;
 
+pub mod conversation;
+pub use conversation::Conversation;
+
+pub mod owned;
+pub use owned::ToOwnedStatic;
+
+pub mod interop;
+
+pub mod chunking;
+
+pub mod throttle;
+
+#[cfg(feature = "prompt-caching")]
+pub mod cache;
+
+pub mod eval;
+
+pub mod moderation;
+
+#[cfg(feature = "classify")]
+pub mod classify;
+
+#[cfg(feature = "schemars")]
+pub mod json_mode;
+
+#[cfg(feature = "judge")]
+pub mod judge;
+
+#[cfg(feature = "tasks")]
+pub mod tasks;
+
+pub mod partial_json;
+
+mod parse;
+
+pub mod fixtures;
+
+pub mod store;
+
+pub mod redact;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_utils;
+
 #[cfg(feature = "markdown")]
 /// Markdown utilities for parsing and rendering.
 pub mod markdown;
@@ -41,6 +85,33 @@ pub mod markdown;
 /// Converts prompts and messages to HTML.
 pub mod html;
 
+#[cfg(all(feature = "mcp", target_arch = "wasm32"))]
+compile_error!("The `mcp` feature does not build on wasm32.");
+#[cfg(all(feature = "openapi", target_arch = "wasm32"))]
+compile_error!("The `openapi` feature does not build on wasm32.");
+#[cfg(all(feature = "sqlite", target_arch = "wasm32"))]
+compile_error!("The `sqlite` feature does not build on wasm32.");
+#[cfg(feature = "mcp")]
+pub mod mcp;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
+/// [`Send`] with the `send` feature (the default) and nothing without. Used
+/// for bounds on [`Stream`]s so they can be [`Send`] on native targets while
+/// wrapping the non-[`Send`] futures of the browser on `wasm32`.
+#[cfg(feature = "send")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "send")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+/// [`Send`] with the `send` feature (the default) and nothing without. Used
+/// for bounds on [`Stream`]s so they can be [`Send`] on native targets while
+/// wrapping the non-[`Send`] futures of the browser on `wasm32`.
+#[cfg(not(feature = "send"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "send"))]
+impl<T: ?Sized> MaybeSend for T {}
+
 #[cfg(not(feature = "langsan"))]
 pub(crate) type CowStr<'a> = std::borrow::Cow<'a, str>;
 #[cfg(feature = "langsan")]
@@ -49,7 +120,10 @@ pub(crate) type CowStr<'a> = langsan::CowStr<'a>;
 /// Re-exports of commonly used crates to avoid version conflicts and reduce
 /// dependency bloat.
 pub mod exports {
+    #[cfg(feature = "axum")]
+    pub use axum;
     pub use base64;
+    pub use bytes;
     pub use eventsource_stream;
     pub use futures;
     #[cfg(feature = "image")]
@@ -65,8 +139,14 @@ pub mod exports {
     #[cfg(feature = "markdown")]
     pub use pulldown_cmark_to_cmark;
     pub use reqwest;
+    #[cfg(feature = "schemars")]
+    pub use schemars;
     pub use serde;
     pub use serde_json;
+    #[cfg(feature = "classify")]
+    pub use strum;
+    #[cfg(any(feature = "mcp", feature = "tokio"))]
+    pub use tokio;
 }
 
 /// Re-export of `serde_json::json!` for convenience because this is used
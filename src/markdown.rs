@@ -3,6 +3,12 @@ use std::ops::Deref;
 use pulldown_cmark::HeadingLevel;
 use serde::{Deserialize, Serialize};
 
+mod cached;
+pub use cached::Cached;
+
+mod stream;
+pub use stream::StreamRenderer;
+
 /// Default [`Options`]
 pub const DEFAULT_OPTIONS: Options = Options {
     inner: pulldown_cmark::Options::empty(),
@@ -10,6 +16,7 @@ pub const DEFAULT_OPTIONS: Options = Options {
     tool_results: false,
     system: false,
     attrs: false,
+    timestamps: false,
     heading_level: None,
 };
 
@@ -20,6 +27,7 @@ pub const VERBOSE_OPTIONS: Options = Options {
     tool_results: true,
     system: true,
     attrs: true,
+    timestamps: true,
     heading_level: None,
 };
 
@@ -75,6 +83,11 @@ pub struct Options {
     /// [`Prompt`]: crate::prompt::Prompt
     /// [`Message`]: crate::prompt::Message
     pub attrs: bool,
+    /// Whether to include the timestamp and latency of each message, if
+    /// known. Only [`Conversation`]s have them.
+    ///
+    /// [`Conversation`]: crate::Conversation
+    pub timestamps: bool,
     /// Heading level to begin at (optional)
     pub heading_level: Option<HeadingLevel>,
 }
@@ -108,6 +121,14 @@ impl Options {
         self.system = true;
         self
     }
+
+    /// Set [`timestamps`] to true
+    ///
+    /// [`timestamps`]: Options::timestamps
+    pub fn with_timestamps(mut self) -> Self {
+        self.timestamps = true;
+        self
+    }
 }
 
 #[cfg(feature = "markdown")]
@@ -286,7 +307,7 @@ mod tests {
     #[test]
     fn test_markdown() {
         let expected = "Hello, **world**!";
-        let events = pulldown_cmark::Parser::new(&expected);
+        let events = pulldown_cmark::Parser::new(expected);
         let markdown: Markdown = events.into();
         let actual: &str = markdown.borrow();
         assert_eq!(actual, expected);
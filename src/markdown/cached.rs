@@ -0,0 +1,143 @@
+//! [`Cached`] markdown events for rendering the same content many times.
+use pulldown_cmark::Event;
+
+use super::{Markdown, Options, ToMarkdown};
+
+/// [`pulldown_cmark::Event`]s generated once so they can be rendered many
+/// times, for example to redraw a long transcript in a UI.
+///
+/// Generating events is most of the cost of rendering: text is parsed as
+/// markdown and tool blocks are serialized as JSON. The cached events borrow
+/// from the source where possible and are rendered by reference, so
+/// rendering again doesn't allocate anything but the output.
+///
+/// Events are generated with the [`Options`] at the time they are pushed.
+/// Since a transcript usually only grows, the events of new messages can be
+/// [`push`]ed as they arrive. A message still being streamed is best rendered
+/// with a [`StreamRenderer`] until it is complete.
+///
+/// ```
+/// use misanthropic::{
+///     markdown::{Cached, Options},
+///     prompt::Message,
+///     Prompt,
+/// };
+///
+/// let prompt = Prompt::default()
+///     .add_message(Message::user("Hello, **world**!"))
+///     .add_message(Message::assistant("Hi!"));
+/// let mut cached = Cached::new(&prompt, Options::default());
+/// let mut text = String::new();
+/// for _frame in 0..3 {
+///     text.clear();
+///     cached.write_markdown(&mut text)?;
+/// }
+/// assert_eq!(
+///     text,
+///     "### User\n\nHello, **world**!\n\n### Assistant\n\nHi!"
+/// );
+/// # Ok::<(), std::fmt::Error>(())
+/// ```
+///
+/// [`push`]: Cached::push
+/// [`StreamRenderer`]: super::StreamRenderer
+#[derive(Clone, Debug, Default)]
+pub struct Cached<'a> {
+    events: Vec<Event<'a>>,
+}
+
+impl<'a> Cached<'a> {
+    /// Generate and cache the events of `item` with `options`.
+    pub fn new<T>(item: &'a T, options: Options) -> Self
+    where
+        T: ToMarkdown + ?Sized,
+    {
+        Self {
+            events: item.markdown_events_custom(options).collect(),
+        }
+    }
+
+    /// Generate and cache the events of `item` with `options`, after those
+    /// already cached.
+    pub fn push<T>(&mut self, item: &'a T, options: Options)
+    where
+        T: ToMarkdown + ?Sized,
+    {
+        self.events.extend(item.markdown_events_custom(options));
+    }
+
+    /// The cached events.
+    pub fn events(&self) -> &[Event<'a>] {
+        &self.events
+    }
+
+    /// Render the cached events to a [`Markdown`] string.
+    pub fn markdown(&self) -> Markdown {
+        let mut text = String::new();
+        // The formatter for `String` never returns an error.
+        let _ = self.write_markdown(&mut text);
+
+        Markdown { text }
+    }
+
+    /// Render the cached events to a type implementing [`std::fmt::Write`].
+    pub fn write_markdown(
+        &self,
+        writer: &mut dyn std::fmt::Write,
+    ) -> std::fmt::Result {
+        use pulldown_cmark_to_cmark::cmark;
+
+        let events = self.events.iter();
+        let _ = cmark(events, writer)?;
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Cached<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_markdown(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prompt::{message::Block, Message},
+        Prompt,
+    };
+
+    #[test]
+    fn test_cached() {
+        let prompt = Prompt::default()
+            .system("Be brief.")
+            .add_message(Message::user("Hello, **world**!"))
+            .add_message(Message::assistant(Block::ToolUse {
+                call: crate::tool::Use {
+                    id: "toolu_1".into(),
+                    name: "echo".into(),
+                    input: crate::json!({"text": "hi"}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                },
+            }));
+
+        for options in [Options::default(), Options::verbose()] {
+            let cached = Cached::new(&prompt, options);
+            let expected = prompt.markdown_custom(options);
+            assert_eq!(cached.markdown().as_ref(), expected.as_ref());
+            assert_eq!(cached.to_string(), expected.to_string());
+        }
+
+        // Pushing is the same as rendering everything at once.
+        let messages = Prompt::default()
+            .add_message(Message::user("Hello, **world**!"))
+            .add_message(Message::assistant("Hi!"));
+        let mut cached = Cached::default();
+        for message in &messages.messages {
+            cached.push(message, Options::default());
+        }
+        assert_eq!(cached.markdown().as_ref(), messages.markdown().as_ref());
+        assert!(!cached.events().is_empty());
+    }
+}
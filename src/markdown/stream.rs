@@ -0,0 +1,424 @@
+//! [`StreamRenderer`] for rendering markdown as it is streamed.
+use std::borrow::Cow;
+
+use pulldown_cmark::Parser;
+
+use super::Markdown;
+
+/// Renders markdown text incrementally as [`Delta`]s arrive, for progressive
+/// UI updates.
+///
+/// Complete blocks (paragraphs, code blocks, lists, etc.) are rendered once
+/// and cached. Only the final, incomplete block is parsed and rendered again
+/// on each update, so the parsing cost does not grow with the length of the
+/// reply. [`markdown`] and [`html`] still copy the cached output. For updates
+/// which don't, append the new part of [`stable_markdown`] (which only ever
+/// grows) and replace the [`tail_markdown`]. The incomplete block is patched
+/// so it renders as it will once complete:
+/// - An unterminated code fence is closed.
+/// - Unterminated `*`, `**`, and `` ` `` spans are closed.
+///
+/// Because blocks are rendered independently, constructs that refer across
+/// blocks, such as link reference definitions, may render differently than
+/// the same text rendered all at once.
+///
+/// ```
+/// use misanthropic::markdown::StreamRenderer;
+///
+/// let mut renderer = StreamRenderer::new();
+/// renderer.push("Hello, **wor");
+/// assert_eq!(renderer.markdown().as_ref(), "Hello, **wor**");
+/// renderer.push("ld**!");
+/// assert_eq!(renderer.markdown().as_ref(), "Hello, **world**!");
+/// ```
+///
+/// [`Delta`]: crate::stream::Delta
+/// [`markdown`]: StreamRenderer::markdown
+/// [`html`]: StreamRenderer::html
+/// [`stable_markdown`]: StreamRenderer::stable_markdown
+/// [`tail_markdown`]: StreamRenderer::tail_markdown
+#[derive(Clone)]
+pub struct StreamRenderer {
+    options: pulldown_cmark::Options,
+    text: String,
+    /// Bytes of `text` which are complete blocks and already rendered.
+    stable: usize,
+    /// Bytes of `text` which have been scanned for block boundaries. This is
+    /// always at the start of a line.
+    scanned: usize,
+    /// Code fence open at `scanned`, as the fence character and length.
+    fence: Option<(char, usize)>,
+    /// Whether the last scanned line was blank.
+    blank: bool,
+    /// Rendered markdown for `text[..stable]`.
+    markdown: String,
+    /// Rendered HTML for `text[..stable]`.
+    #[cfg(feature = "html")]
+    html: crate::html::Html,
+}
+
+impl Default for StreamRenderer {
+    fn default() -> Self {
+        Self {
+            options: pulldown_cmark::Options::empty(),
+            text: String::new(),
+            stable: 0,
+            scanned: 0,
+            fence: None,
+            blank: false,
+            markdown: String::new(),
+            #[cfg(feature = "html")]
+            html: Default::default(),
+        }
+    }
+}
+
+impl StreamRenderer {
+    /// Create a new [`StreamRenderer`] with default parsing options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`pulldown_cmark::Options`] used for parsing.
+    pub fn with_options(mut self, options: pulldown_cmark::Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The raw text received so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Append a piece of text, for example a [`Delta::Text`].
+    ///
+    /// [`Delta::Text`]: crate::stream::Delta::Text
+    pub fn push(&mut self, delta: &str) {
+        self.text.push_str(delta);
+
+        while let Some(len) = self.text[self.scanned..].find('\n') {
+            let start = self.scanned;
+            self.scanned += len + 1;
+            self.scan_line(start);
+        }
+    }
+
+    /// Update block state for the complete line starting at `start` and
+    /// ending at `scanned`.
+    fn scan_line(&mut self, start: usize) {
+        let line = self.text[start..self.scanned].trim_end();
+
+        if let Some((c, n)) = self.fence {
+            if is_fence_close(line, c, n) {
+                self.fence = None;
+            }
+            self.blank = false;
+            return;
+        }
+
+        let blank = line.is_empty();
+        let starts_block = !blank && self.blank && starts_block(line);
+        let fence = fence_open(line);
+
+        if starts_block {
+            self.commit(start);
+        }
+        if !blank {
+            self.fence = fence;
+        }
+        self.blank = blank;
+    }
+
+    /// Render `text[stable..end]` into the cache.
+    fn commit(&mut self, end: usize) {
+        let block = &self.text[self.stable..end];
+
+        let markdown = Markdown::from(Parser::new_ext(block, self.options));
+        join(&mut self.markdown, &markdown);
+
+        #[cfg(feature = "html")]
+        self.html.extend(Parser::new_ext(block, self.options));
+
+        self.stable = end;
+    }
+
+    /// The incomplete final block, patched to render as if complete.
+    fn tail(&self) -> Cow<'_, str> {
+        let tail = &self.text[self.stable..];
+        let partial = &self.text[self.scanned..];
+
+        let fence = match self.fence {
+            Some((c, n)) if is_fence_close(partial, c, n) => None,
+            Some(fence) => Some(fence),
+            None if !partial.is_empty() => fence_open(partial),
+            None => None,
+        };
+
+        if let Some((c, n)) = fence {
+            let mut tail = tail.to_string();
+            if !tail.ends_with('\n') {
+                tail.push('\n');
+            }
+            tail.push_str(&c.to_string().repeat(n));
+            return Cow::Owned(tail);
+        }
+
+        // Only the last paragraph can have open inline spans.
+        let trimmed = tail.trim_end();
+        let paragraph = match trimmed.rfind("\n\n") {
+            Some(i) => &trimmed[i..],
+            None => trimmed,
+        };
+        let closers = inline_closers(paragraph);
+        if closers.is_empty() {
+            Cow::Borrowed(tail)
+        } else {
+            Cow::Owned(format!("{}{}", trimmed, closers))
+        }
+    }
+
+    /// Render everything so far as [`Markdown`]. This copies the
+    /// [`stable_markdown`]. It is followed by a blank line and the
+    /// [`tail_markdown`], if both are non-empty.
+    ///
+    /// [`stable_markdown`]: StreamRenderer::stable_markdown
+    /// [`tail_markdown`]: StreamRenderer::tail_markdown
+    pub fn markdown(&self) -> Markdown {
+        let mut text = self.markdown.clone();
+        join(&mut text, &self.tail_markdown());
+
+        Markdown { text }
+    }
+
+    /// Rendered markdown of the complete blocks so far. Text is only ever
+    /// appended to this, so a UI can append what is new since the last
+    /// update.
+    pub fn stable_markdown(&self) -> &str {
+        &self.markdown
+    }
+
+    /// Render the incomplete final block as [`Markdown`]. This is the only
+    /// part which changes other than by appending.
+    pub fn tail_markdown(&self) -> Markdown {
+        Markdown::from(Parser::new_ext(&self.tail(), self.options))
+    }
+
+    /// Render everything so far as [`Html`]. This copies the
+    /// [`stable_html`], followed by the [`tail_html`].
+    ///
+    /// [`Html`]: crate::html::Html
+    /// [`stable_html`]: StreamRenderer::stable_html
+    /// [`tail_html`]: StreamRenderer::tail_html
+    #[cfg(feature = "html")]
+    pub fn html(&self) -> crate::html::Html {
+        let mut html = self.html.clone();
+        html.extend(Parser::new_ext(&self.tail(), self.options));
+        html
+    }
+
+    /// Rendered HTML of the complete blocks so far. Like the
+    /// [`stable_markdown`], this is only ever appended to.
+    ///
+    /// [`stable_markdown`]: StreamRenderer::stable_markdown
+    #[cfg(feature = "html")]
+    pub fn stable_html(&self) -> &str {
+        &self.html
+    }
+
+    /// Render the incomplete final block as [`Html`].
+    ///
+    /// [`Html`]: crate::html::Html
+    #[cfg(feature = "html")]
+    pub fn tail_html(&self) -> crate::html::Html {
+        Parser::new_ext(&self.tail(), self.options).collect()
+    }
+}
+
+/// Append a separately rendered `block` to `markdown`.
+fn join(markdown: &mut String, block: &str) {
+    // Code blocks are rendered with a leading newline.
+    let block = block.trim_start_matches('\n');
+    if block.is_empty() {
+        return;
+    }
+    if !markdown.is_empty() {
+        markdown.push_str("\n\n");
+    }
+    markdown.push_str(block);
+}
+
+/// Returns true if `line` can only start a new block, rather than continue a
+/// list item or indented code after a blank line.
+fn starts_block(line: &str) -> bool {
+    if line.starts_with(char::is_whitespace) {
+        return false;
+    }
+
+    let digits = line.len()
+        - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match digits {
+        0 => line.strip_prefix(['-', '*', '+']),
+        _ => line[digits..].strip_prefix(['.', ')']),
+    };
+
+    !matches!(marker, Some(rest) if rest.is_empty() || rest.starts_with(' '))
+}
+
+/// If `line` opens a code fence, returns the fence character and length.
+fn fence_open(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    let c = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let n = trimmed.len() - trimmed.trim_start_matches(c).len();
+    // Backtick fences cannot have backticks in the info string.
+    let valid = n >= 3 && (c == '~' || !trimmed[n..].contains('`'));
+
+    valid.then_some((c, n))
+}
+
+/// Returns true if `line` closes a code fence of `n` `c` characters.
+fn is_fence_close(line: &str, c: char, n: usize) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+
+    let rest = trimmed.trim_start_matches(c);
+    trimmed.len() - rest.len() >= n && rest.trim().is_empty()
+}
+
+/// Returns the markers needed to close the inline code, `*`, and `**` spans
+/// left open in `paragraph`, innermost first.
+fn inline_closers(paragraph: &str) -> String {
+    let mut open: Vec<&str> = vec![];
+    let mut code = false;
+    let mut prev = ' ';
+    let mut chars = paragraph.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '`' => code = !code,
+            '*' if !code => {
+                let marker = if paragraph[i..].starts_with("**") {
+                    chars.next();
+                    "**"
+                } else {
+                    "*"
+                };
+                let next = chars.peek().map(|&(_, c)| c).unwrap_or(' ');
+
+                if open.last() == Some(&marker) && !prev.is_whitespace() {
+                    open.pop();
+                } else if !next.is_whitespace() {
+                    open.push(marker);
+                }
+            }
+            _ => {}
+        }
+        prev = c;
+    }
+
+    if code {
+        // Emphasis cannot be closed inside code.
+        return "`".to_string();
+    }
+
+    open.into_iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(pieces: &[&str]) -> StreamRenderer {
+        let mut renderer = StreamRenderer::new();
+        for piece in pieces {
+            renderer.push(piece);
+        }
+        renderer
+    }
+
+    #[test]
+    fn test_stream_renderer() {
+        const TEXT: &str = "# Title\n\nSome *text*.\n\n- a\n\n- b\n\n```rust\nfn main() {}\n\n```\n\nDone.\n";
+
+        // Split into single characters, the worst case.
+        let pieces: Vec<String> = TEXT.chars().map(String::from).collect();
+        let pieces: Vec<&str> = pieces.iter().map(String::as_str).collect();
+        let renderer = render(&pieces);
+        assert_eq!(renderer.text(), TEXT);
+
+        let expected: Markdown = Parser::new(TEXT).into();
+        assert_eq!(renderer.markdown().as_ref(), expected.as_ref());
+        // The blank line in the code block and loose list didn't split them.
+        assert_eq!(renderer.stable, TEXT.find("Done.").unwrap());
+    }
+
+    #[test]
+    fn test_stream_renderer_parts() {
+        let mut renderer = StreamRenderer::new();
+        let mut shown = String::new();
+        for piece in ["# Title\n\nSome *te", "xt*.\n\n- a\n", "\nDone.\n\nMo"] {
+            renderer.push(piece);
+
+            // The stable part is only appended to.
+            let stable = renderer.stable_markdown();
+            assert!(stable.starts_with(&shown));
+            shown = stable.to_string();
+
+            let mut markdown = shown.clone();
+            join(&mut markdown, &renderer.tail_markdown());
+            assert_eq!(markdown, renderer.markdown().as_ref());
+        }
+        assert_eq!(renderer.tail_markdown().as_ref(), "Done.\n\nMo");
+
+        #[cfg(feature = "html")]
+        assert_eq!(
+            format!("{}{}", renderer.stable_html(), renderer.tail_html()),
+            renderer.html().as_ref()
+        );
+    }
+
+    #[test]
+    fn test_stream_renderer_fence() {
+        let renderer = render(&["Code:\n\n```py\nprint('hi')"]);
+        assert_eq!(
+            renderer.markdown().as_ref(),
+            "Code:\n\n````py\nprint('hi')\n````"
+        );
+
+        // A fence that was just opened is closed too.
+        let renderer = render(&["~~~"]);
+        assert_eq!(renderer.tail(), "~~~\n~~~");
+    }
+
+    #[test]
+    fn test_inline_closers() {
+        assert_eq!(inline_closers("plain"), "");
+        assert_eq!(inline_closers("**bold *both"), "***");
+        assert_eq!(inline_closers("*it **both"), "***");
+        assert_eq!(inline_closers("*it **both** it"), "*");
+        assert_eq!(inline_closers("`code **"), "`");
+        assert_eq!(inline_closers("2 * 3"), "");
+        assert_eq!(inline_closers("\\*escaped"), "");
+
+        let renderer = render(&["Some **bold "]);
+        assert_eq!(renderer.tail(), "Some **bold**");
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn test_stream_renderer_html() {
+        let renderer = render(&["Hello, **wor", "ld**!\n\n<b>Bye</b> *n"]);
+        assert_eq!(
+            renderer.html().as_ref(),
+            "<p>Hello, <strong>world</strong>!</p>\n<p>&lt;b&gt;Bye&lt;/b&gt; <em>n</em></p>\n"
+        );
+    }
+}
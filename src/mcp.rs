@@ -0,0 +1,698 @@
+//! Client for the [Model Context Protocol] (MCP). Connects to a local MCP
+//! server over stdio or SSE and registers its tools in a [`ToolBox`], so a
+//! model can use any MCP server's tools.
+//!
+//! Only the parts of the protocol needed for tools are implemented
+//! (`initialize`, `tools/list`, `tools/call`, and `ping`).
+//!
+//! [Model Context Protocol]: <https://modelcontextprotocol.io/>
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use eventsource_stream::{EventStreamError, Eventsource};
+use futures::{lock::Mutex, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader,
+};
+
+use crate::{
+    prompt::message::{Block, Content, Image, MediaType},
+    tool::ToolBox,
+    Tool,
+};
+
+/// MCP protocol version this client speaks.
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Errors from an MCP [`Client`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// I/O error talking to a stdio server.
+    #[error("I/O error: {error}")]
+    Io {
+        #[from]
+        /// Error from [`std::io`].
+        error: std::io::Error,
+    },
+    /// HTTP error talking to an SSE server.
+    #[error("HTTP error: {error}")]
+    HTTP {
+        #[from]
+        /// Error from [`reqwest`].
+        error: reqwest::Error,
+    },
+    /// Error in the SSE stream from an SSE server.
+    #[error("SSE error: {error}")]
+    Stream {
+        #[from]
+        /// Error from the `eventsource_stream` crate.
+        error: EventStreamError<reqwest::Error>,
+    },
+    /// JSON parsing error.
+    #[error("JSON error: {error}")]
+    Parse {
+        #[from]
+        /// Error from [`serde_json`].
+        error: serde_json::Error,
+    },
+    /// JSON-RPC error from the server.
+    #[error("MCP error {code}: {message}")]
+    Rpc {
+        /// JSON-RPC error code.
+        code: i64,
+        /// Error message.
+        message: String,
+        /// Additional error data, if any.
+        data: Option<Value>,
+    },
+    /// The server violated the protocol.
+    #[error("MCP protocol error: {message}")]
+    Protocol {
+        /// What went wrong.
+        message: String,
+    },
+    /// The server closed the connection.
+    #[error("MCP server closed the connection.")]
+    Closed,
+}
+
+/// Boxed SSE stream from an SSE server.
+type EventStream = Pin<
+    Box<
+        dyn futures::Stream<
+                Item = Result<
+                    eventsource_stream::Event,
+                    EventStreamError<reqwest::Error>,
+                >,
+            > + Send,
+    >,
+>;
+
+/// How we talk to the server.
+enum Transport {
+    /// Newline delimited JSON over a pair of byte streams, usually the stdin
+    /// and stdout of a child process.
+    Stdio {
+        reader: BufReader<Box<dyn AsyncRead + Send + Unpin>>,
+        writer: Box<dyn AsyncWrite + Send + Unpin>,
+        /// Child process, if we spawned one. Kept so it is killed on drop.
+        _child: Option<tokio::process::Child>,
+    },
+    /// Messages are `POST`ed to the `endpoint` and received as SSE events.
+    Sse {
+        http: reqwest::Client,
+        endpoint: reqwest::Url,
+        events: EventStream,
+    },
+}
+
+impl Transport {
+    async fn send(&mut self, message: &Value) -> Result<(), Error> {
+        match self {
+            Self::Stdio { writer, .. } => {
+                let mut line = serde_json::to_vec(message)?;
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+                writer.flush().await?;
+            }
+            Self::Sse { http, endpoint, .. } => {
+                http.post(endpoint.clone())
+                    .json(message)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Value, Error> {
+        match self {
+            Self::Stdio { reader, .. } => {
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).await? == 0 {
+                        return Err(Error::Closed);
+                    }
+                    if !line.trim().is_empty() {
+                        return Ok(serde_json::from_str(&line)?);
+                    }
+                }
+            }
+            Self::Sse { events, .. } => loop {
+                let event = events.next().await.ok_or(Error::Closed)??;
+                // The event name defaults to `message` if unset.
+                if event.event.is_empty() || event.event == "message" {
+                    return Ok(serde_json::from_str(&event.data)?);
+                }
+            },
+        }
+    }
+}
+
+/// Tool as described by an MCP server.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpTool {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    input_schema: Value,
+}
+
+/// Result of an MCP `tools/call`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CallResult {
+    #[serde(default)]
+    content: Vec<Value>,
+    #[serde(default)]
+    is_error: bool,
+}
+
+/// Client connection to a single MCP server.
+///
+/// Requests are sent one at a time, so concurrent calls (for example from
+/// [`ToolBox::handle`]) wait their turn.
+pub struct Client {
+    transport: Mutex<Transport>,
+    next_id: AtomicU64,
+    server_info: Value,
+}
+
+static_assertions::assert_impl_all!(Client: Send, Sync);
+
+impl Client {
+    /// Spawn an MCP server process and connect to it over stdio. The process
+    /// is killed when the [`Client`] is dropped.
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), misanthropic::mcp::Error> {
+    /// use misanthropic::{exports::tokio::process::Command, mcp, tool::ToolBox};
+    /// use std::sync::Arc;
+    ///
+    /// let mut command = Command::new("npx");
+    /// command.args(["-y", "@modelcontextprotocol/server-everything"]);
+    ///
+    /// let client = Arc::new(mcp::Client::stdio(command).await?);
+    /// let mut toolbox = ToolBox::new();
+    /// client.register(&mut toolbox).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stdio(
+        mut command: tokio::process::Command,
+    ) -> Result<Self, Error> {
+        use std::process::Stdio;
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        // Unwraps can never panic because we just set these to piped.
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        Self::connect(Transport::Stdio {
+            reader: BufReader::new(Box::new(stdout)),
+            writer: Box::new(stdin),
+            _child: Some(child),
+        })
+        .await
+    }
+
+    /// Connect to an MCP server over any pair of byte streams speaking
+    /// newline delimited JSON, such as a socket.
+    pub async fn from_io<R, W>(reader: R, writer: W) -> Result<Self, Error>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        Self::connect(Transport::Stdio {
+            reader: BufReader::new(Box::new(reader)),
+            writer: Box::new(writer),
+            _child: None,
+        })
+        .await
+    }
+
+    /// Connect to an MCP server using the HTTP with SSE transport. The `url`
+    /// is the SSE endpoint, for example `http://localhost:3001/sse`.
+    pub async fn sse<U>(url: U) -> Result<Self, Error>
+    where
+        U: reqwest::IntoUrl,
+    {
+        let url = url.into_url()?;
+        let http = reqwest::Client::new();
+        let mut events: EventStream = Box::pin(
+            http.get(url.clone())
+                .header(reqwest::header::ACCEPT, "text/event-stream")
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes_stream()
+                .eventsource(),
+        );
+
+        // The first event tells us where to POST our messages.
+        let endpoint = loop {
+            let event = events.next().await.ok_or(Error::Closed)??;
+            if event.event == "endpoint" {
+                break url.join(event.data.trim()).map_err(|e| {
+                    Error::Protocol {
+                        message: format!("Invalid endpoint: {}", e),
+                    }
+                })?;
+            }
+        };
+
+        Self::connect(Transport::Sse {
+            http,
+            endpoint,
+            events,
+        })
+        .await
+    }
+
+    /// Initialize the session.
+    async fn connect(transport: Transport) -> Result<Self, Error> {
+        let mut client = Self {
+            transport: Mutex::new(transport),
+            next_id: AtomicU64::new(0),
+            server_info: Value::Null,
+        };
+
+        client.server_info = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await?;
+
+        client
+            .transport
+            .lock()
+            .await
+            .send(&json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/initialized",
+            }))
+            .await?;
+
+        Ok(client)
+    }
+
+    /// Result of the `initialize` request, including the server's
+    /// `serverInfo` and `capabilities`.
+    pub fn server_info(&self) -> &Value {
+        &self.server_info
+    }
+
+    /// Send a JSON-RPC request and wait for the response. Server requests
+    /// and notifications received in the meantime are answered or ignored.
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut transport = self.transport.lock().await;
+
+        transport
+            .send(&json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+            }))
+            .await?;
+
+        loop {
+            let mut message = transport.recv().await?;
+
+            if let Some(method) = message.get("method").and_then(Value::as_str)
+            {
+                // A request from the server has an id. Without one it's a
+                // notification, which we don't need.
+                if let Some(request_id) = message.get("id") {
+                    let response = match method {
+                        "ping" => json!({
+                            "jsonrpc": "2.0",
+                            "id": request_id,
+                            "result": {},
+                        }),
+                        _ => json!({
+                            "jsonrpc": "2.0",
+                            "id": request_id,
+                            "error": {
+                                "code": -32601,
+                                "message": "Method not found",
+                            },
+                        }),
+                    };
+                    transport.send(&response).await?;
+                } else {
+                    #[cfg(feature = "log")]
+                    log::trace!("Ignoring MCP notification: {}", method);
+                }
+                continue;
+            }
+
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                #[cfg(feature = "log")]
+                log::warn!("Ignoring unexpected MCP message: {}", message);
+                continue;
+            }
+
+            if let Some(error) = message.get_mut("error") {
+                return Err(Error::Rpc {
+                    code: error
+                        .get("code")
+                        .and_then(Value::as_i64)
+                        .unwrap_or_default(),
+                    message: error
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    data: error.get_mut("data").map(Value::take),
+                });
+            }
+
+            return message.get_mut("result").map(Value::take).ok_or_else(
+                || Error::Protocol {
+                    message: format!(
+                        "Response to `{}` has no result or error.",
+                        method
+                    ),
+                },
+            );
+        }
+    }
+
+    /// List the server's tools as [`Tool`]s.
+    pub async fn tools(&self) -> Result<Vec<Tool<'static>>, Error> {
+        let mut tools = vec![];
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let mut result = self.request("tools/list", params).await?;
+
+            let page: Vec<McpTool> = serde_json::from_value(
+                result.get_mut("tools").map(Value::take).unwrap_or_default(),
+            )?;
+            // The server is the authority on its own tools, so we don't
+            // validate them. MCP schemas, for example, may omit `required`.
+            tools.extend(page.into_iter().map(|tool| {
+                Tool::builder(tool.name)
+                    .description(tool.description.unwrap_or_default())
+                    .schema(tool.input_schema)
+                    .build_unchecked()
+            }));
+
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(ToString::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Call a tool by `name`. The outer [`Result`] is for protocol errors. The
+    /// inner one is the tool's output, where [`Err`] means the tool reported an
+    /// error. Text and image content is translated to [`Block`]s. Anything else
+    /// (such as embedded resources) is passed through as JSON text.
+    pub async fn call(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<Result<Content<'static>, Content<'static>>, Error> {
+        let result: CallResult = serde_json::from_value(
+            self.request(
+                "tools/call",
+                json!({ "name": name, "arguments": arguments }),
+            )
+            .await?,
+        )?;
+
+        let content = if result.content.is_empty() {
+            Content::text("")
+        } else {
+            result
+                .content
+                .into_iter()
+                .map(mcp_to_block)
+                .collect::<Vec<_>>()
+                .into()
+        };
+
+        Ok(if result.is_error {
+            Err(content)
+        } else {
+            Ok(content)
+        })
+    }
+
+    /// List the server's tools and insert them in a [`ToolBox`], replacing any
+    /// with the same name. Protocol errors during calls are reported to the
+    /// model as tool errors.
+    pub async fn register(
+        self: &Arc<Self>,
+        toolbox: &mut ToolBox,
+    ) -> Result<(), Error> {
+        for tool in self.tools().await? {
+            let client = self.clone();
+            let name = tool.name.to_string();
+            toolbox.insert(tool, move |input| {
+                let client = client.clone();
+                let name = name.clone();
+                async move {
+                    client.call(&name, input).await.unwrap_or_else(|e| {
+                        #[cfg(feature = "log")]
+                        log::error!("MCP tool `{}` failed: {}", name, e);
+                        Err(Content::text(e.to_string()))
+                    })
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Translate MCP content into a [`Block`].
+fn mcp_to_block(content: Value) -> Block<'static> {
+    match content.get("type").and_then(Value::as_str) {
+        Some("text") => {
+            if let Some(text) = content.get("text").and_then(Value::as_str) {
+                return Block::text(text.to_string());
+            }
+        }
+        Some("image") => {
+            let media_type = content
+                .get("mimeType")
+                .cloned()
+                .and_then(|v| serde_json::from_value::<MediaType>(v).ok());
+            let data = content.get("data").and_then(Value::as_str);
+            if let Some(image) =
+                media_type.zip(data).and_then(|(media_type, data)| {
+                    Image::from_parts(media_type, data).ok()
+                })
+            {
+                return image.into();
+            }
+        }
+        _ => {}
+    }
+
+    Block::text(content.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal MCP server with an `echo` tool. Handles requests until the
+    /// client hangs up.
+    async fn mock_server(
+        reader: impl AsyncRead + Unpin,
+        mut writer: impl AsyncWrite + Unpin,
+    ) {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = request.get("id").cloned() else {
+                // Notification.
+                continue;
+            };
+
+            let result = match request["method"].as_str().unwrap() {
+                "initialize" => json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "mock", "version": "0" },
+                }),
+                "tools/list" => json!({
+                    "tools": [{
+                        "name": "echo",
+                        "description": "Echo the input.",
+                        "inputSchema": {
+                            "type": "object",
+                            "properties": { "text": { "type": "string" } },
+                        },
+                    }],
+                }),
+                "tools/call" => {
+                    let text = &request["params"]["arguments"]["text"];
+                    // Send a ping and a notification first, like a real
+                    // server might.
+                    let ping =
+                        json!({"jsonrpc": "2.0", "id": "p", "method": "ping"});
+                    let note = json!({"jsonrpc": "2.0", "method": "notifications/progress"});
+                    for message in [ping, note] {
+                        writer
+                            .write_all(format!("{}\n", message).as_bytes())
+                            .await
+                            .unwrap();
+                    }
+                    // Skip the client's ping response.
+                    lines.next_line().await.unwrap();
+
+                    match text.as_str() {
+                        Some(text) => json!({
+                            "content": [
+                                { "type": "text", "text": text },
+                                { "type": "resource", "resource": { "uri": "x" } },
+                            ],
+                        }),
+                        None => json!({
+                            "content": [{ "type": "text", "text": "no text" }],
+                            "isError": true,
+                        }),
+                    }
+                }
+                _ => {
+                    let error = json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": "nope" },
+                    });
+                    writer
+                        .write_all(format!("{}\n", error).as_bytes())
+                        .await
+                        .unwrap();
+                    continue;
+                }
+            };
+
+            let response =
+                json!({ "jsonrpc": "2.0", "id": id, "result": result });
+            writer
+                .write_all(format!("{}\n", response).as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn mock_client() -> Arc<Client> {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+        let (server_read, server_write) = tokio::io::split(server_io);
+        tokio::spawn(mock_server(server_read, server_write));
+        let (client_read, client_write) = tokio::io::split(client_io);
+
+        Arc::new(Client::from_io(client_read, client_write).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_mcp_client() {
+        let client = mock_client().await;
+        assert_eq!(client.server_info()["serverInfo"]["name"], "mock");
+
+        let tools = client.tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo");
+        assert_eq!(tools[0].description, "Echo the input.");
+
+        let content = client
+            .call("echo", json!({ "text": "hello" }))
+            .await
+            .unwrap()
+            .unwrap();
+        let blocks = content.as_slice();
+        assert_eq!(blocks[0], Block::text("hello"));
+        assert_eq!(
+            blocks[1],
+            Block::text(r#"{"resource":{"uri":"x"},"type":"resource"}"#)
+        );
+
+        // Tool errors are `Err` content.
+        assert!(client.call("echo", json!({})).await.unwrap().is_err());
+
+        // Protocol errors are errors.
+        assert!(matches!(
+            client.request("nope", json!({})).await,
+            Err(Error::Rpc { code: -32601, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_register() {
+        let client = mock_client().await;
+        let mut toolbox = ToolBox::new();
+        client.register(&mut toolbox).await.unwrap();
+        assert!(toolbox.get("echo").is_some());
+
+        let call = crate::tool::Use {
+            id: "1".into(),
+            name: "echo".into(),
+            input: json!({ "text": "hello" }),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+        let result = toolbox.call(&call).await;
+        assert!(!result.is_error);
+        assert_eq!(result.tool_use_id, "1");
+    }
+
+    #[test]
+    fn test_mcp_to_block() {
+        let block = mcp_to_block(json!({
+            "type": "image",
+            "mimeType": "image/png",
+            "data": "AAAA",
+        }));
+        assert!(matches!(block, Block::Image { .. }));
+
+        // Unsupported image types are passed through as text.
+        let block = mcp_to_block(json!({
+            "type": "image",
+            "mimeType": "image/bmp",
+            "data": "AAAA",
+        }));
+        assert!(matches!(block, Block::Text { .. }));
+    }
+}
@@ -84,6 +84,130 @@ impl Model {
         Model::Haiku35_20241022,
         Model::Haiku30,
     ];
+
+    /// Model ids on the Anthropic API, [Amazon Bedrock], and [Vertex AI]. The
+    /// `-latest` aliases are only available on the Anthropic API.
+    ///
+    /// [Amazon Bedrock]: <https://docs.anthropic.com/en/api/claude-on-amazon-bedrock>
+    /// [Vertex AI]: <https://docs.anthropic.com/en/api/claude-on-vertex-ai>
+    const IDS: &'static [(Model, &'static str, &'static str, &'static str)] = &[
+        (
+            Model::Sonnet35_20240620,
+            "claude-3-5-sonnet-20240620",
+            "anthropic.claude-3-5-sonnet-20240620-v1:0",
+            "claude-3-5-sonnet@20240620",
+        ),
+        (
+            Model::Sonnet35_20241022,
+            "claude-3-5-sonnet-20241022",
+            "anthropic.claude-3-5-sonnet-20241022-v2:0",
+            "claude-3-5-sonnet-v2@20241022",
+        ),
+        (
+            Model::Opus30_20240229,
+            "claude-3-opus-20240229",
+            "anthropic.claude-3-opus-20240229-v1:0",
+            "claude-3-opus@20240229",
+        ),
+        (
+            Model::Sonnet30,
+            "claude-3-sonnet-20240229",
+            "anthropic.claude-3-sonnet-20240229-v1:0",
+            "claude-3-sonnet@20240229",
+        ),
+        (
+            Model::Haiku35_20241022,
+            "claude-3-5-haiku-20241022",
+            "anthropic.claude-3-5-haiku-20241022-v1:0",
+            "claude-3-5-haiku@20241022",
+        ),
+        (
+            Model::Haiku30,
+            "claude-3-haiku-20240307",
+            "anthropic.claude-3-haiku-20240307-v1:0",
+            "claude-3-haiku@20240307",
+        ),
+    ];
+
+    /// Id of the model on the Anthropic API.
+    pub const fn api_id(self) -> &'static str {
+        match self {
+            Model::Sonnet35 => "claude-3-5-sonnet-latest",
+            Model::Sonnet35_20240620 => "claude-3-5-sonnet-20240620",
+            Model::Sonnet35_20241022 => "claude-3-5-sonnet-20241022",
+            Model::Opus30 => "claude-3-opus-latest",
+            Model::Opus30_20240229 => "claude-3-opus-20240229",
+            Model::Sonnet30 => "claude-3-sonnet-20240229",
+            Model::Haiku35 => "claude-3-5-haiku-latest",
+            Model::Haiku35_20241022 => "claude-3-5-haiku-20241022",
+            Model::Haiku30 => "claude-3-haiku-20240307",
+        }
+    }
+
+    /// Id of the model on Amazon Bedrock. Returns [`None`] for `-latest`
+    /// aliases, which Bedrock does not have.
+    pub fn bedrock_id(self) -> Option<&'static str> {
+        Self::IDS
+            .iter()
+            .find(|(model, ..)| *model == self)
+            .map(|&(_, _, bedrock, _)| bedrock)
+    }
+
+    /// Id of the model on Vertex AI. Returns [`None`] for `-latest` aliases,
+    /// which Vertex AI does not have.
+    pub fn vertex_id(self) -> Option<&'static str> {
+        Self::IDS
+            .iter()
+            .find(|(model, ..)| *model == self)
+            .map(|&(_, _, _, vertex)| vertex)
+    }
+
+    /// Model from an Amazon Bedrock id such as
+    /// `anthropic.claude-3-5-sonnet-20241022-v2:0`. Cross-region inference
+    /// profile ids such as `us.anthropic.claude-3-5-sonnet-20241022-v2:0` are
+    /// accepted too.
+    pub fn bedrock(id: &str) -> Option<Self> {
+        let id = match id.split_once('.') {
+            Some((region, rest)) if !region.contains('-') => {
+                match rest.starts_with("anthropic.") {
+                    true => rest,
+                    false => id,
+                }
+            }
+            _ => id,
+        };
+        Self::IDS
+            .iter()
+            .find(|&&(_, _, bedrock, _)| bedrock == id)
+            .map(|&(model, ..)| model)
+    }
+
+    /// Model from a Vertex AI id such as `claude-3-5-sonnet-v2@20241022`.
+    pub fn vertex(id: &str) -> Option<Self> {
+        Self::IDS
+            .iter()
+            .find(|&&(_, _, _, vertex)| vertex == id)
+            .map(|&(model, ..)| model)
+    }
+
+    /// Model from an id in any naming scheme: Anthropic API, Amazon Bedrock,
+    /// or Vertex AI.
+    ///
+    /// ```
+    /// use misanthropic::Model;
+    ///
+    /// let api = Model::from_id("claude-3-5-sonnet-20241022");
+    /// assert_eq!(api, Some(Model::Sonnet35_20241022));
+    /// assert_eq!(Model::from_id("anthropic.claude-3-5-sonnet-20241022-v2:0"), api);
+    /// assert_eq!(Model::from_id("claude-3-5-sonnet-v2@20241022"), api);
+    /// ```
+    pub fn from_id(id: &str) -> Option<Self> {
+        // This includes deprecated aliases. See `Model::Haiku30`.
+        serde_json::from_value(serde_json::Value::String(id.into()))
+            .ok()
+            .or_else(|| Self::bedrock(id))
+            .or_else(|| Self::vertex(id))
+    }
 }
 
 #[cfg(test)]
@@ -105,6 +229,43 @@ mod tests {
         Some(key.trim().to_string())
     }
 
+    #[test]
+    fn test_api_id() {
+        for &model in Model::ALL {
+            assert_eq!(
+                serde_json::to_value(model).unwrap(),
+                model.api_id(),
+                "{model:?}"
+            );
+            assert_eq!(Model::from_id(model.api_id()), Some(model));
+        }
+    }
+
+    #[test]
+    fn test_cloud_ids() {
+        for &(model, _, bedrock, vertex) in Model::IDS {
+            assert_eq!(model.bedrock_id(), Some(bedrock));
+            assert_eq!(model.vertex_id(), Some(vertex));
+            assert_eq!(Model::bedrock(bedrock), Some(model));
+            assert_eq!(Model::vertex(vertex), Some(model));
+            assert_eq!(Model::from_id(bedrock), Some(model));
+            assert_eq!(Model::from_id(vertex), Some(model));
+        }
+
+        assert_eq!(Model::Sonnet35.bedrock_id(), None);
+        assert_eq!(Model::Sonnet35.vertex_id(), None);
+        assert_eq!(
+            Model::bedrock("us.anthropic.claude-3-haiku-20240307-v1:0"),
+            Some(Model::Haiku30)
+        );
+        assert_eq!(Model::bedrock("claude-3-haiku-20240307"), None);
+        assert_eq!(
+            Model::from_id("claude-3-haiku-latest"),
+            Some(Model::Haiku30)
+        );
+        assert_eq!(Model::from_id("gpt-4"), None);
+    }
+
     #[tokio::test]
     #[ignore = "This test requires a real API key."]
     async fn test_models_are_valid() {
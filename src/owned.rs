@@ -0,0 +1,175 @@
+//! `'static` aliases of the types with lifetimes, and the [`ToOwnedStatic`]
+//! trait to convert to them. This is useful to store prompts, messages, and
+//! events in long-lived state without naming lifetimes.
+//!
+//! ```
+//! use misanthropic::{owned::OwnedMessage, prompt::Message, ToOwnedStatic};
+//!
+//! struct State {
+//!     history: Vec<OwnedMessage>,
+//! }
+//!
+//! fn remember(state: &mut State, messages: Vec<Message<'_>>) {
+//!     state.history.extend(messages.into_static());
+//! }
+//! ```
+use crate::{
+    conversation::Entry,
+    prompt::message::{Block, Content, Message as PromptMessage},
+    response::{Message as ResponseMessage, Response},
+    stream::{Delta, Event, MessageDelta},
+    tool::{Result as ToolResult, Tool, Use},
+    Conversation, Prompt, Stream,
+};
+
+/// [`Prompt`] with a `'static` lifetime.
+pub type OwnedPrompt = Prompt<'static>;
+/// [`prompt::Message`](PromptMessage) with a `'static` lifetime.
+pub type OwnedMessage = PromptMessage<'static>;
+/// [`Content`] with a `'static` lifetime.
+pub type OwnedContent = Content<'static>;
+/// [`Block`] with a `'static` lifetime.
+pub type OwnedBlock = Block<'static>;
+/// [`Tool`] with a `'static` lifetime.
+pub type OwnedTool = Tool<'static>;
+/// [`tool::Use`](Use) with a `'static` lifetime.
+pub type OwnedToolUse = Use<'static>;
+/// [`tool::Result`](ToolResult) with a `'static` lifetime.
+pub type OwnedToolResult = ToolResult<'static>;
+/// [`Response`] with a `'static` lifetime.
+pub type OwnedResponse = Response<'static>;
+/// [`response::Message`](ResponseMessage) with a `'static` lifetime.
+pub type OwnedResponseMessage = ResponseMessage<'static>;
+/// [`Stream`] with a `'static` lifetime.
+pub type OwnedStream = Stream<'static>;
+/// [`stream::Event`](Event) with a `'static` lifetime.
+pub type OwnedEvent = Event<'static>;
+/// [`stream::Delta`](Delta) with a `'static` lifetime.
+pub type OwnedDelta = Delta<'static>;
+/// [`Conversation`] with a `'static` lifetime.
+pub type OwnedConversation = Conversation<'static>;
+/// [`conversation::Entry`](Entry) with a `'static` lifetime.
+pub type OwnedEntry = Entry<'static>;
+
+/// Conversion to a `'static` lifetime by taking ownership of any borrowed
+/// data. This is implemented for every type with a lifetime that can be
+/// stored, as well as [`Vec`]s and [`Option`]s of them.
+pub trait ToOwnedStatic {
+    /// The `'static` version of `Self`.
+    type Static: 'static;
+
+    /// Convert to [`Self::Static`].
+    fn into_static(self) -> Self::Static;
+}
+
+/// Implement [`ToOwnedStatic`] with the inherent `into_static` method.
+macro_rules! impl_to_owned_static {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl ToOwnedStatic for $name<'_> {
+                type Static = $name<'static>;
+
+                fn into_static(self) -> Self::Static {
+                    $name::into_static(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_to_owned_static!(
+    Prompt,
+    PromptMessage,
+    Content,
+    Block,
+    Tool,
+    Use,
+    ToolResult,
+    Response,
+    ResponseMessage,
+    Event,
+    Delta,
+    Conversation,
+    Entry,
+);
+
+impl ToOwnedStatic for Stream<'_> {
+    type Static = Stream<'static>;
+
+    fn into_static(self) -> Self::Static {
+        self.into_owned()
+    }
+}
+
+/// [`MessageDelta`] is already `'static`.
+impl ToOwnedStatic for MessageDelta {
+    type Static = MessageDelta;
+
+    fn into_static(self) -> Self::Static {
+        self
+    }
+}
+
+impl<T: ToOwnedStatic> ToOwnedStatic for Vec<T> {
+    type Static = Vec<T::Static>;
+
+    fn into_static(self) -> Self::Static {
+        self.into_iter().map(T::into_static).collect()
+    }
+}
+
+impl<T: ToOwnedStatic> ToOwnedStatic for Option<T> {
+    type Static = Option<T::Static>;
+
+    fn into_static(self) -> Self::Static {
+        self.map(T::into_static)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::prompt::message::Role;
+
+    #[test]
+    fn test_prompt_into_static() {
+        let text = String::from("Hello");
+        let name = String::from("weather");
+        let mut prompt = Prompt::default()
+            .add_message(PromptMessage {
+                role: Role::User,
+                content: Content::text(text.as_str()),
+            })
+            .add_tool(Tool {
+                kind: None,
+                name: Cow::Borrowed(&name),
+                description: Cow::Borrowed("Get the weather."),
+                input_schema: serde_json::json!({"type": "object"}),
+                extra: Default::default(),
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            });
+        prompt.stop_sequences = Some(vec![Cow::Borrowed(text.as_str())]);
+
+        let owned: OwnedPrompt = ToOwnedStatic::into_static(prompt);
+        drop((text, name));
+
+        assert_eq!(owned.messages[0].text(), "Hello");
+        assert_eq!(owned.stop_sequences.unwrap(), ["Hello"]);
+        assert_eq!(owned.tools.unwrap()[0].name, "weather");
+    }
+
+    #[test]
+    fn test_collections_into_static() {
+        let text = String::from("Hi");
+        let blocks = vec![Block::text(text.as_str())];
+        let owned: Vec<OwnedBlock> = blocks.into_static();
+        let none: Option<OwnedDelta> = None::<Delta>.into_static();
+        drop(text);
+
+        assert_eq!(owned, [Block::text("Hi")]);
+        assert!(none.is_none());
+    }
+}
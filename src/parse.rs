@@ -0,0 +1,83 @@
+//! JSON parsing of responses and stream events. With the `simd-json` feature
+//! this uses [`simd_json`], which is faster for large responses and
+//! high-throughput streams. Otherwise, and for errors, [`serde_json`] is used,
+//! so the types and errors are the same either way.
+use serde::de::{Deserialize, DeserializeOwned, Deserializer, Error};
+
+/// Parse `T` from a JSON slice.
+pub(crate) fn from_slice<T>(json: &[u8]) -> serde_json::Result<T>
+where
+    T: DeserializeOwned,
+{
+    #[cfg(feature = "simd-json")]
+    {
+        // `simd_json` parses in place, so it needs a mutable copy.
+        let mut buf = json.to_vec();
+        if let Ok(value) = simd_json::serde::from_slice(&mut buf) {
+            return Ok(value);
+        }
+        // Parse again for a `serde_json::Error`. Errors are rare, so this
+        // doesn't cost much and the errors are consistent.
+    }
+
+    serde_json::from_slice(json)
+}
+
+/// Parse `T` from a JSON string.
+pub(crate) fn from_str<T>(json: &str) -> serde_json::Result<T>
+where
+    T: DeserializeOwned,
+{
+    from_slice(json.as_bytes())
+}
+
+/// Deserialize the raw JSON of the `Unknown` variant of an internally tagged
+/// enum. Serde also falls back to an untagged variant when a known variant
+/// fails to parse, so a `type` in `known` is an error rather than an unknown
+/// type. Otherwise a malformed event or block would silently become unknown.
+pub(crate) fn unknown_variant<'de, D>(
+    deserializer: D,
+    known: &[&str],
+) -> Result<serde_json::Value, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    match value.get("type").and_then(serde_json::Value::as_str) {
+        Some(tag) if known.contains(&tag) => {
+            Err(D::Error::custom(format_args!("invalid `{tag}`")))
+        }
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{response, stream::Event};
+
+    #[test]
+    fn test_from_slice() {
+        let message: response::Message = from_slice(
+            crate::response::message::tests::RESPONSE_JSON.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(message.id, "msg_013Zva2CMHLNnXjNJJKqJ2EF");
+
+        let event: Event = from_str(
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hi"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(event, Event::ContentBlockDelta { index: 0, .. }));
+
+        // Errors are from `serde_json`, with the same message.
+        let json = r#"{"type": "message", "id": 42}"#;
+        assert_eq!(
+            from_str::<response::Message>(json).unwrap_err().to_string(),
+            serde_json::from_str::<response::Message>(json)
+                .unwrap_err()
+                .to_string()
+        );
+        assert!(from_str::<Event>("{").unwrap_err().is_eof());
+    }
+}
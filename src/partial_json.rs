@@ -0,0 +1,466 @@
+//! Best-effort parsing of incomplete JSON, such as the [`Delta::Json`]s of a
+//! tool call received so far, so the arguments can be previewed before the
+//! block is complete.
+//!
+//! ```
+//! use misanthropic::partial_json::{parse, Partial};
+//!
+//! let partial = parse(r#"{"city": "San Fra"#).unwrap();
+//! assert!(!partial.is_complete());
+//! assert_eq!(
+//!     partial.get("city"),
+//!     Some(&Partial::String {
+//!         value: "San Fra".into(),
+//!         complete: false
+//!     })
+//! );
+//! assert_eq!(partial.to_value(), serde_json::json!({"city": "San Fra"}));
+//! ```
+//!
+//! [`Delta::Json`]: crate::stream::Delta::Json
+
+/// Maximum nesting of arrays and objects, the same as [`serde_json`]'s.
+const MAX_DEPTH: usize = 128;
+
+/// A possibly incomplete JSON value. Strings, arrays, and objects are marked
+/// `complete` once they are closed. Object fields are only included once
+/// their key is complete and their value has started. Literals and numbers
+/// are only included once they can be parsed, so a trailing `tr` or `-` is
+/// left out.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Partial {
+    /// `null`.
+    Null,
+    /// `true` or `false`.
+    Bool(bool),
+    /// A number. At the end of the input this may be the beginning of a
+    /// longer number.
+    Number(serde_json::Number),
+    /// A string.
+    #[allow(missing_docs)]
+    String { value: String, complete: bool },
+    /// An array.
+    #[allow(missing_docs)]
+    Array { items: Vec<Partial>, complete: bool },
+    /// An object, with fields in the order received.
+    #[allow(missing_docs)]
+    Object {
+        fields: Vec<(String, Partial)>,
+        complete: bool,
+    },
+}
+
+impl Partial {
+    /// Returns true if the value is closed. A closed array or object only
+    /// contains complete values.
+    pub fn is_complete(&self) -> bool {
+        match self {
+            Self::Null | Self::Bool(_) | Self::Number(_) => true,
+            Self::String { complete, .. }
+            | Self::Array { complete, .. }
+            | Self::Object { complete, .. } => *complete,
+        }
+    }
+
+    /// Get the value of an object field, if this is an object with that
+    /// field.
+    pub fn get(&self, key: &str) -> Option<&Partial> {
+        match self {
+            Self::Object { fields, .. } => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Convert to a [`serde_json::Value`], dropping the `complete` markers.
+    pub fn to_value(&self) -> serde_json::Value {
+        match self {
+            Self::Null => serde_json::Value::Null,
+            Self::Bool(value) => serde_json::Value::Bool(*value),
+            Self::Number(value) => serde_json::Value::Number(value.clone()),
+            Self::String { value, .. } => {
+                serde_json::Value::String(value.clone())
+            }
+            Self::Array { items, .. } => {
+                items.iter().map(Self::to_value).collect()
+            }
+            Self::Object { fields, .. } => serde_json::Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parse possibly incomplete JSON. Returns [`None`] if the JSON is invalid
+/// or nothing could be parsed yet.
+pub fn parse(json: &str) -> Option<Partial> {
+    let mut parser = Parser { json, pos: 0 };
+    let value = parser.value(0).ok()??;
+    parser.whitespace();
+    match parser.pos == json.len() {
+        true => Some(value),
+        false => None,
+    }
+}
+
+/// The JSON is invalid. It can't become valid by receiving more.
+struct Invalid;
+
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Parse a value. Returns `Ok(None)` if the input ended before a value
+    /// could be parsed.
+    fn value(&mut self, depth: usize) -> Result<Option<Partial>, Invalid> {
+        if depth > MAX_DEPTH {
+            return Err(Invalid);
+        }
+
+        self.whitespace();
+        let Some(byte) = self.peek() else {
+            return Ok(None);
+        };
+
+        match byte {
+            b'{' => self.object(depth + 1).map(Some),
+            b'[' => self.array(depth + 1).map(Some),
+            b'"' => {
+                let (value, complete) = self.string()?;
+                Ok(Some(Partial::String { value, complete }))
+            }
+            b't' => self.literal("true", Partial::Bool(true)),
+            b'f' => self.literal("false", Partial::Bool(false)),
+            b'n' => self.literal("null", Partial::Null),
+            b'-' | b'0'..=b'9' => self.number(),
+            _ => Err(Invalid),
+        }
+    }
+
+    fn literal(
+        &mut self,
+        literal: &str,
+        value: Partial,
+    ) -> Result<Option<Partial>, Invalid> {
+        let rest = &self.json[self.pos..];
+        if rest.starts_with(literal) {
+            self.pos += literal.len();
+            Ok(Some(value))
+        } else if literal.starts_with(rest) {
+            self.pos = self.json.len();
+            Ok(None)
+        } else {
+            Err(Invalid)
+        }
+    }
+
+    fn number(&mut self) -> Result<Option<Partial>, Invalid> {
+        let start = self.pos;
+        while matches!(
+            self.peek(),
+            Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+        ) {
+            self.pos += 1;
+        }
+        let number = &self.json[start..self.pos];
+
+        if let Ok(number) = number.parse() {
+            return Ok(Some(Partial::Number(number)));
+        }
+        if self.pos < self.json.len() {
+            return Err(Invalid);
+        }
+
+        // The number may be cut off, as in `1.` or `1e-`.
+        Ok(number
+            .trim_end_matches(['.', 'e', 'E', '+', '-'])
+            .parse()
+            .ok()
+            .map(Partial::Number))
+    }
+
+    /// Parse a string, returning it and whether it is complete. An escape
+    /// sequence cut off at the end of the input is left out.
+    fn string(&mut self) -> Result<(String, bool), Invalid> {
+        // Skip the opening quote.
+        self.pos += 1;
+        let mut value = String::new();
+
+        while let Some(c) = self.json[self.pos..].chars().next() {
+            self.pos += c.len_utf8();
+            match c {
+                '"' => return Ok((value, true)),
+                '\\' => match self.escape()? {
+                    Some(c) => value.push(c),
+                    None => return Ok((value, false)),
+                },
+                c if c < ' ' => return Err(Invalid),
+                c => value.push(c),
+            }
+        }
+
+        Ok((value, false))
+    }
+
+    /// Parse an escape sequence after the backslash. Returns `Ok(None)` if it
+    /// is cut off.
+    fn escape(&mut self) -> Result<Option<char>, Invalid> {
+        let Some(byte) = self.peek() else {
+            return Ok(None);
+        };
+        self.pos += 1;
+
+        let c = match byte {
+            b'"' => '"',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let Some(high) = self.hex()? else {
+                    return Ok(None);
+                };
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high).map(Some).ok_or(Invalid);
+                }
+
+                // A surrogate pair, so another `\u` escape must follow.
+                match self.json.as_bytes().get(self.pos..self.pos + 2) {
+                    Some(b"\\u") => self.pos += 2,
+                    Some(_) => return Err(Invalid),
+                    None if b"\\u"
+                        .starts_with(&self.json.as_bytes()[self.pos..]) =>
+                    {
+                        self.pos = self.json.len();
+                        return Ok(None);
+                    }
+                    None => return Err(Invalid),
+                }
+                let Some(low) = self.hex()? else {
+                    return Ok(None);
+                };
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err(Invalid);
+                }
+
+                let c = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(c).map(Some).ok_or(Invalid);
+            }
+            _ => return Err(Invalid),
+        };
+
+        Ok(Some(c))
+    }
+
+    /// Parse the four hex digits of a `\u` escape. Returns `Ok(None)` if they
+    /// are cut off.
+    fn hex(&mut self) -> Result<Option<u32>, Invalid> {
+        let rest = &self.json.as_bytes()[self.pos..];
+        let digits = &rest[..rest.len().min(4)];
+        if !digits.iter().all(u8::is_ascii_hexdigit) {
+            return Err(Invalid);
+        }
+        if digits.len() < 4 {
+            self.pos = self.json.len();
+            return Ok(None);
+        }
+
+        self.pos += 4;
+        // The digits are ASCII, so this is a valid `str`.
+        let digits = std::str::from_utf8(digits).map_err(|_| Invalid)?;
+        u32::from_str_radix(digits, 16)
+            .map(Some)
+            .map_err(|_| Invalid)
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Partial, Invalid> {
+        // Skip the opening bracket.
+        self.pos += 1;
+        let mut items = vec![];
+
+        loop {
+            self.whitespace();
+            if items.is_empty() && self.peek() == Some(b']') {
+                self.pos += 1;
+                return Ok(Partial::Array {
+                    items,
+                    complete: true,
+                });
+            }
+
+            match self.value(depth)? {
+                Some(item) => items.push(item),
+                None => break,
+            }
+
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Partial::Array {
+                        items,
+                        complete: true,
+                    });
+                }
+                Some(_) => return Err(Invalid),
+                None => break,
+            }
+        }
+
+        Ok(Partial::Array {
+            items,
+            complete: false,
+        })
+    }
+
+    fn object(&mut self, depth: usize) -> Result<Partial, Invalid> {
+        // Skip the opening brace.
+        self.pos += 1;
+        let mut fields = vec![];
+
+        loop {
+            self.whitespace();
+            match self.peek() {
+                Some(b'}') if fields.is_empty() => {
+                    self.pos += 1;
+                    return Ok(Partial::Object {
+                        fields,
+                        complete: true,
+                    });
+                }
+                Some(b'"') => {}
+                Some(_) => return Err(Invalid),
+                None => break,
+            }
+
+            let (key, complete) = self.string()?;
+            if !complete {
+                break;
+            }
+
+            self.whitespace();
+            match self.peek() {
+                Some(b':') => self.pos += 1,
+                Some(_) => return Err(Invalid),
+                None => break,
+            }
+
+            match self.value(depth)? {
+                Some(value) => fields.push((key, value)),
+                None => break,
+            }
+
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Partial::Object {
+                        fields,
+                        complete: true,
+                    });
+                }
+                Some(_) => return Err(Invalid),
+                None => break,
+            }
+        }
+
+        Ok(Partial::Object {
+            fields,
+            complete: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn value(json: &str) -> Option<serde_json::Value> {
+        parse(json).map(|partial| partial.to_value())
+    }
+
+    #[test]
+    fn test_complete() {
+        let json = r#"{"a": [1, -2.5e3, true, false, null], "b": {"c": "d"}}"#;
+        let partial = parse(json).unwrap();
+        assert!(partial.is_complete());
+        assert_eq!(
+            partial.to_value(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_incomplete() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("  "), None);
+        assert_eq!(value("{"), Some(json!({})));
+        assert_eq!(value(r#"{"ci"#), Some(json!({})));
+        assert_eq!(value(r#"{"city""#), Some(json!({})));
+        assert_eq!(value(r#"{"city": "#), Some(json!({})));
+        assert_eq!(value(r#"{"city": "Par"#), Some(json!({"city": "Par"})));
+        assert_eq!(value(r#"{"a": 1, "b": tr"#), Some(json!({"a": 1})));
+        assert_eq!(value(r#"{"a": [1, 2"#), Some(json!({"a": [1, 2]})));
+        assert_eq!(value(r#"{"a": [1, 2."#), Some(json!({"a": [1, 2]})));
+        assert_eq!(value(r#"[1, -"#), Some(json!([1])));
+        assert_eq!(value(r#"[{"a": {"b": ["#), Some(json!([{"a": {"b": []}}])));
+
+        let partial = parse(r#"{"a": [1], "b": "x"#).unwrap();
+        assert!(!partial.is_complete());
+        assert!(partial.get("a").unwrap().is_complete());
+        assert!(!partial.get("b").unwrap().is_complete());
+        assert_eq!(partial.get("c"), None);
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert_eq!(
+            value(r#""a\"b\\c\/\n\té😀""#),
+            Some(json!("a\"b\\c/\n\té😀"))
+        );
+        // Cut off escapes are left out until complete.
+        assert_eq!(value(r#""a\"#), Some(json!("a")));
+        assert_eq!(value(r#""a\u00"#), Some(json!("a")));
+        assert_eq!(value(r#""a\ud83d"#), Some(json!("a")));
+        assert_eq!(value(r#""a\ud83d\"#), Some(json!("a")));
+        assert_eq!(value(r#""a\ud83d\ude"#), Some(json!("a")));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(parse("x"), None);
+        assert_eq!(parse("[1,]"), None);
+        assert_eq!(parse(r#"{"a" 1}"#), None);
+        assert_eq!(parse(r#"{"a": 1} x"#), None);
+        assert_eq!(parse("truth"), None);
+        assert_eq!(parse("1x"), None);
+        assert_eq!(parse(r#""\x""#), None);
+        assert_eq!(parse(r#""\ud83dA""#), None);
+        assert_eq!(parse(&"[".repeat(MAX_DEPTH + 1)), None);
+        assert!(parse(&"[".repeat(MAX_DEPTH)).is_some());
+    }
+}
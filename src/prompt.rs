@@ -3,19 +3,23 @@
 //!
 //! [Anthropic Messages API]: <https://docs.anthropic.com/en/api/messages>
 
-use std::{borrow::Cow, num::NonZeroU16, vec};
+use std::{borrow::Cow, num::NonZeroU16};
 
 use crate::{tool, Model, Tool};
 use message::Content;
 use serde::{Deserialize, Serialize};
 
+pub mod display;
+pub use display::DisplayOptions;
 pub mod message;
 pub use message::Message;
+pub mod shared;
+pub use shared::SharedPrompt;
 
 /// Request for the [Anthropic Messages API].
 ///
 /// [Anthropic Messages API]: <https://docs.anthropic.com/en/api/messages>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 #[serde(default)]
 pub struct Prompt<'a> {
@@ -53,15 +57,14 @@ pub struct Prompt<'a> {
     /// [`response::Message`]: crate::response::Message
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
-    /// System prompt as [`SinglePart`] or [`MultiPart`] [`Content`].
+    /// System prompt as [`Content`].
     ///
-    /// [`SinglePart`]: message::Content::SinglePart
-    /// [`MultiPart`]: message::Content::MultiPart
     /// [`Content`]: message::Content
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system: Option<message::Content<'a>>,
     /// Temperature for sampling. Must be between 0 and 1. Higher values mean
-    /// more randomness. Note that 0.0 is not fully deterministic.
+    /// more randomness. Note that 0.0 is not fully deterministic. Set it with
+    /// [`Prompt::temperature`] to check the range.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     /// [`tool::Choice`] for the model.
@@ -78,9 +81,30 @@ pub struct Prompt<'a> {
     /// `top_p`. A token is then sampled from this reduced distribution.
     ///
     /// This is a float between 0 and 1 where higher values mean more
-    /// randomness.
+    /// randomness. Set it with [`Prompt::top_p`] to check the range.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Id of a [`Container`] to reuse for the [code execution] tool. See
+    /// [`response::Message::container`].
+    ///
+    /// [`Container`]: crate::tool::code_execution::Container
+    /// [code execution]: crate::tool::code_execution
+    /// [`response::Message::container`]: crate::response::Message::container
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<Cow<'a, str>>,
+    /// Whether to use [priority tier] capacity, if available. The tier used
+    /// is returned in [`Usage::service_tier`].
+    ///
+    /// [priority tier]: <https://docs.anthropic.com/en/api/service-tiers>
+    /// [`Usage::service_tier`]: crate::response::Usage::service_tier
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+    /// Other request parameters, serialized alongside the rest. This allows
+    /// using new or beta API parameters before this crate supports them. Keys
+    /// should not be the same as those of other fields. Set them with
+    /// [`Prompt::extra`].
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Default for Prompt<'_> {
@@ -98,11 +122,22 @@ impl Default for Prompt<'_> {
             tools: Default::default(),
             top_k: Default::default(),
             top_p: Default::default(),
+            container: Default::default(),
+            service_tier: Default::default(),
+            extra: Default::default(),
         }
     }
 }
 
 impl<'a> Prompt<'a> {
+    /// Maximum number of [`stop_sequences`] accepted by the API.
+    ///
+    /// [`stop_sequences`]: Prompt::stop_sequences
+    pub const MAX_STOP_SEQUENCES: usize = 8191;
+
+    /// Maximum length of a stop sequence in [`char`]s.
+    pub const MAX_STOP_SEQUENCE_LEN: usize = 8191;
+
     /// Turn streaming on.
     ///
     /// **Note**: [`Client::stream`] and [`Client::message`] are more ergonomic
@@ -158,6 +193,40 @@ impl<'a> Prompt<'a> {
         self
     }
 
+    /// Answer multiple [`tool::Use`]s with a single [`User`] [`Message`]
+    /// containing all the [`tool::Result`]s. Each [`tool_use_id`] should match
+    /// one of the [`Message::tool_uses`] of the previous message.
+    ///
+    /// [`User`]: crate::prompt::message::Role::User
+    /// [`tool_use_id`]: tool::Result::tool_use_id
+    pub fn add_tool_results<Rs>(mut self, results: Rs) -> Self
+    where
+        Rs: IntoIterator<Item = tool::Result<'a>>,
+    {
+        self.messages.push(Message::tool_results(results));
+        self
+    }
+
+    /// Prefill the assistant's response with `text` by appending an
+    /// [`Assistant`] [`Message`]. The model continues from there, which is a
+    /// good way to constrain the output, for example prefilling `{` for JSON.
+    /// The API does not return the prefill. Use [`Accumulator::prefill`] to add
+    /// it back.
+    ///
+    /// The API rejects a prefill ending in whitespace.
+    ///
+    /// [`Assistant`]: crate::prompt::message::Role::Assistant
+    /// [`Accumulator::prefill`]: crate::stream::Accumulator::prefill
+    pub fn prefill<T>(self, text: T) -> Self
+    where
+        T: Into<crate::CowStr<'a>>,
+    {
+        self.add_message(Message {
+            role: message::Role::Assistant,
+            content: Content::text(text),
+        })
+    }
+
     /// Extend the [`messages`] from an iterable.
     ///
     /// [`messages`]: Prompt::messages
@@ -182,6 +251,26 @@ impl<'a> Prompt<'a> {
         self
     }
 
+    /// Set an [`extra`] request parameter, such as a beta parameter this crate
+    /// doesn't support yet. Setting the same `key` again replaces the value.
+    ///
+    /// ```
+    /// use misanthropic::{json, Prompt};
+    ///
+    /// let prompt = Prompt::default().extra("new_param", json!({"on": true}));
+    /// assert_eq!(json!(prompt)["new_param"]["on"], true);
+    /// ```
+    ///
+    /// [`extra`]: Prompt::extra
+    pub fn extra<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<serde_json::Value>,
+    {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
     /// Set the [`metadata`] from an iterable of key-value pairs.
     /// The values must be serializable to JSON.
     ///
@@ -245,6 +334,18 @@ impl<'a> Prompt<'a> {
         Ok(self)
     }
 
+    /// Set `metadata.user_id`, which helps Anthropic detect abuse. This
+    /// should be an opaque identifier such as a UUID or hash, not PII (name,
+    /// email, phone).
+    pub fn user_id<S>(mut self, id: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.metadata
+            .insert("user_id".into(), serde_json::Value::String(id.into()));
+        self
+    }
+
     /// Set the [`stop_sequences`]. If one is generated, the completion will
     /// stop with [`StopReason::StopSequence`] in the
     /// [`response::Message::stop_reason`].
@@ -297,6 +398,14 @@ impl<'a> Prompt<'a> {
         self
     }
 
+    /// Remove all [`stop_sequences`].
+    ///
+    /// [`stop_sequences`]: Prompt::stop_sequences
+    pub fn clear_stop_sequences(mut self) -> Self {
+        self.stop_sequences = None;
+        self
+    }
+
     /// Set the [`system`] prompt [`Content`]. This is content that the model
     /// will give special attention to. Instructions should be placed here.
     ///
@@ -332,24 +441,23 @@ impl<'a> Prompt<'a> {
     where
         B: Into<message::Block<'a>>,
     {
-        match self.system {
-            Some(mut content) => {
-                content.push(block);
-                self.system = Some(content);
-            }
-            None => {
-                // MultiPart doesn't actually need to have multiple parts.
-                self.system = Some(Content::MultiPart(vec![block.into()]));
-            }
-        }
+        self.system.get_or_insert_with(Content::default).push(block);
         self
     }
 
     /// Set the [`temperature`] to `Some(value)` or [`None`] to use the default.
     ///
+    /// ```
+    /// # use misanthropic::{prompt::Temperature, Prompt};
+    /// let prompt = Prompt::default().temperature(Some(0.7.try_into()?));
+    /// assert_eq!(prompt.temperature, Some(0.7));
+    /// assert!(Temperature::try_from(1.7).is_err());
+    /// # Ok::<(), misanthropic::prompt::OutOfRange>(())
+    /// ```
+    ///
     /// [`temperature`]: Prompt::temperature
-    pub fn temperature(mut self, temperature: Option<f32>) -> Self {
-        self.temperature = temperature;
+    pub fn temperature(mut self, temperature: Option<Temperature>) -> Self {
+        self.temperature = temperature.map(f32::from);
         self
     }
 
@@ -361,6 +469,17 @@ impl<'a> Prompt<'a> {
         self
     }
 
+    /// Force the model to use the [`Tool`] named `name`. This is shorthand for
+    /// [`tool_choice`] with [`tool::Choice::tool`].
+    ///
+    /// [`tool_choice`]: Prompt::tool_choice
+    pub fn force_tool<S>(self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tool_choice(tool::Choice::tool(name))
+    }
+
     /// Set the available [`tools`]. When the [`Model`] uses a [`Tool`], the
     /// [`StopReason`] will be [`ToolUse`] in the
     /// [`response::Message::stop_reason`] and the final [`Content`] [`Block`]
@@ -453,6 +572,27 @@ impl<'a> Prompt<'a> {
     // No extend for tools because it's not very common or useful. If somebody
     // really wants this they can submit a PR.
 
+    /// Reuse a [code execution] container by `id`, for example from
+    /// [`response::Message::container`]. Files created in the container are
+    /// available to the code in this request.
+    ///
+    /// [code execution]: crate::tool::code_execution
+    /// [`response::Message::container`]: crate::response::Message::container
+    pub fn container<S>(mut self, id: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        self.container = Some(id.into());
+        self
+    }
+
+    /// Set the [`ServiceTier`]. Priority tier capacity is configured in the
+    /// Anthropic Console.
+    pub fn service_tier(mut self, tier: ServiceTier) -> Self {
+        self.service_tier = Some(tier);
+        self
+    }
+
     /// Set the top K tokens to consider for each token. Set to `None` to use
     /// the default value.
     pub fn top_k(mut self, top_k: Option<NonZeroU16>) -> Self {
@@ -462,11 +602,35 @@ impl<'a> Prompt<'a> {
 
     /// Set the top P for nucleus sampling. Set to [`None`] to use the default
     /// value.
-    pub fn top_p(mut self, top_p: Option<f32>) -> Self {
-        self.top_p = top_p;
+    pub fn top_p(mut self, top_p: Option<TopP>) -> Self {
+        self.top_p = top_p.map(f32::from);
         self
     }
 
+    /// Apply a [`SamplingConfig`], replacing the [`temperature`], [`top_p`],
+    /// and [`top_k`]. The [`SamplingConfig::seed`] is not used since the API
+    /// does not support it.
+    ///
+    /// [`temperature`]: Prompt::temperature
+    /// [`top_p`]: Prompt::top_p
+    /// [`top_k`]: Prompt::top_k
+    pub fn sampling(self, config: SamplingConfig) -> Self {
+        self.temperature(config.temperature)
+            .top_p(config.top_p)
+            .top_k(config.top_k)
+    }
+
+    /// Get the current sampling settings as a [`SamplingConfig`]. Values out
+    /// of range (see [`Prompt::validate`]) are left out.
+    pub fn sampling_config(&self) -> SamplingConfig {
+        SamplingConfig {
+            temperature: self.temperature.and_then(|t| t.try_into().ok()),
+            top_p: self.top_p.and_then(|p| p.try_into().ok()),
+            top_k: self.top_k,
+            seed: None,
+        }
+    }
+
     /// Add a cache breakpoint to the end of the prompt, setting `cache_control`
     /// to `Ephemeral`.
     ///
@@ -513,6 +677,302 @@ impl<'a> Prompt<'a> {
 
         self
     }
+
+    /// Check the prompt against API limits which would otherwise be reported
+    /// only as an [`InvalidRequest`] error from the API. This includes the
+    /// range of [`temperature`] and [`top_p`] if they were set directly.
+    ///
+    /// [`temperature`]: Prompt::temperature
+    /// [`top_p`]: Prompt::top_p
+    ///
+    /// [`InvalidRequest`]: crate::client::AnthropicError::InvalidRequest
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let stop_sequences = self.stop_sequences.as_deref().unwrap_or_default();
+        if stop_sequences.len() > Self::MAX_STOP_SEQUENCES {
+            return Err(ValidationError::TooManyStopSequences {
+                count: stop_sequences.len(),
+                max: Self::MAX_STOP_SEQUENCES,
+            });
+        }
+        if let Some(value) = self.temperature {
+            Temperature::try_from(value)
+                .map_err(|_| ValidationError::Temperature { value })?;
+        }
+        if let Some(value) = self.top_p {
+            TopP::try_from(value)
+                .map_err(|_| ValidationError::TopP { value })?;
+        }
+
+        for (index, sequence) in stop_sequences.iter().enumerate() {
+            if sequence.trim().is_empty() {
+                return Err(ValidationError::BlankStopSequence { index });
+            }
+            let len = sequence.chars().count();
+            if len > Self::MAX_STOP_SEQUENCE_LEN {
+                return Err(ValidationError::StopSequenceTooLong {
+                    index,
+                    len,
+                    max: Self::MAX_STOP_SEQUENCE_LEN,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize to canonical JSON, for hashing, diffing, or cache keys. The
+    /// output is byte-for-byte reproducible across runs and platforms:
+    /// - There is no whitespace and object keys are sorted by their bytes.
+    /// - Floats are written in their shortest round-trip form, without a
+    ///   fraction if integral. Floats exactly representable as [`f32`], such as
+    ///   [`temperature`], are written as [`f32`], so `0.7` rather than
+    ///   `0.699999988079071`.
+    ///
+    /// [`temperature`]: Prompt::temperature
+    pub fn to_canonical_json(&self) -> Result<String, serde_json::Error> {
+        let value = serde_json::to_value(self)?;
+        let mut json = String::new();
+        write_canonical(&value, &mut json)?;
+
+        Ok(json)
+    }
+
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Prompt<'static> {
+        Prompt {
+            model: self.model,
+            messages: self
+                .messages
+                .into_iter()
+                .map(Message::into_static)
+                .collect(),
+            max_tokens: self.max_tokens,
+            metadata: self.metadata,
+            stop_sequences: self.stop_sequences.map(|sequences| {
+                sequences
+                    .into_iter()
+                    .map(|s| Cow::Owned(s.into_owned()))
+                    .collect()
+            }),
+            stream: self.stream,
+            system: self.system.map(Content::into_static),
+            temperature: self.temperature,
+            tool_choice: self.tool_choice,
+            tools: self.tools.map(|tools| {
+                tools.into_iter().map(Tool::into_static).collect()
+            }),
+            top_k: self.top_k,
+            top_p: self.top_p,
+            container: self.container.map(|c| Cow::Owned(c.into_owned())),
+            service_tier: self.service_tier,
+            extra: self.extra,
+        }
+    }
+}
+
+/// Write `value` as canonical JSON. See [`Prompt::to_canonical_json`].
+fn write_canonical(
+    value: &serde_json::Value,
+    json: &mut String,
+) -> Result<(), serde_json::Error> {
+    use serde_json::Value;
+
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let f = n.as_f64().unwrap_or_default();
+            if f64::from(f as f32) == f {
+                json.push_str(&(f as f32).to_string());
+            } else {
+                json.push_str(&f.to_string());
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {
+            json.push_str(&value.to_string())
+        }
+        Value::String(s) => json.push_str(&serde_json::to_string(s)?),
+        Value::Array(values) => {
+            json.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_canonical(value, json)?;
+            }
+            json.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+
+            json.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                json.push_str(&serde_json::to_string(key)?);
+                json.push(':');
+                write_canonical(value, json)?;
+            }
+            json.push('}');
+        }
+    }
+
+    Ok(())
+}
+
+/// Errors returned by [`Prompt::validate`].
+#[derive(Debug, thiserror::Error)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[allow(missing_docs)]
+pub enum ValidationError {
+    /// There are more than [`Prompt::MAX_STOP_SEQUENCES`] stop sequences.
+    #[error("{count} stop sequences is more than the maximum of {max}.")]
+    TooManyStopSequences { count: usize, max: usize },
+    /// A stop sequence is empty or only whitespace.
+    #[error("Stop sequence {index} is empty or only whitespace.")]
+    BlankStopSequence { index: usize },
+    /// [`Prompt::temperature`] is out of range. See [`Temperature`].
+    #[error("Temperature {value} is not between 0 and 1.")]
+    Temperature { value: f32 },
+    /// [`Prompt::top_p`] is out of range. See [`TopP`].
+    #[error("Top P {value} is not between 0 and 1.")]
+    TopP { value: f32 },
+    /// A stop sequence is longer than [`Prompt::MAX_STOP_SEQUENCE_LEN`].
+    #[error("Stop sequence {index} is {len} characters, more than the maximum of {max}.")]
+    StopSequenceTooLong {
+        index: usize,
+        len: usize,
+        max: usize,
+    },
+}
+
+/// Error for when a [`Temperature`] or [`TopP`] is not between 0 and 1.
+#[derive(Debug, thiserror::Error)]
+#[error("{value} is not between 0 and 1.")]
+pub struct OutOfRange {
+    /// The value that was out of range.
+    pub value: f32,
+}
+
+/// Sampling temperature between 0 and 1. See [`Prompt::temperature`].
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
+pub struct Temperature(f32);
+
+impl TryFrom<f32> for Temperature {
+    type Error = OutOfRange;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        match (0.0..=1.0).contains(&value) {
+            true => Ok(Self(value)),
+            false => Err(OutOfRange { value }),
+        }
+    }
+}
+
+impl From<Temperature> for f32 {
+    fn from(temperature: Temperature) -> Self {
+        temperature.0
+    }
+}
+
+/// Top P for nucleus sampling between 0 and 1. See [`Prompt::top_p`].
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
+pub struct TopP(f32);
+
+impl TryFrom<f32> for TopP {
+    type Error = OutOfRange;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        match (0.0..=1.0).contains(&value) {
+            true => Ok(Self(value)),
+            false => Err(OutOfRange { value }),
+        }
+    }
+}
+
+impl From<TopP> for f32 {
+    fn from(top_p: TopP) -> Self {
+        top_p.0
+    }
+}
+
+/// Sampling settings as one value which can be saved, reused, and applied
+/// with [`Prompt::sampling`]. Fields which are [`None`] use the API default.
+///
+/// ```
+/// # use misanthropic::{prompt::SamplingConfig, Prompt};
+/// let config: SamplingConfig =
+///     serde_json::from_str(r#"{"temperature":0.2,"top_p":0.9}"#)?;
+/// let prompt = Prompt::default().sampling(config);
+/// assert_eq!(prompt.temperature, Some(0.2));
+/// assert_eq!(prompt.sampling_config(), config);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingConfig {
+    /// See [`Prompt::temperature`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<Temperature>,
+    /// See [`Prompt::top_p`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<TopP>,
+    /// See [`Prompt::top_k`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<NonZeroU16>,
+    /// Sampling seed. The API does not support seeding yet so this is not
+    /// sent, but it is kept so experiments can record it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl SamplingConfig {
+    /// Set the [`temperature`].
+    ///
+    /// [`temperature`]: SamplingConfig::temperature
+    pub fn temperature(mut self, temperature: Temperature) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the [`top_p`].
+    ///
+    /// [`top_p`]: SamplingConfig::top_p
+    pub fn top_p(mut self, top_p: TopP) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Set the [`top_k`].
+    ///
+    /// [`top_k`]: SamplingConfig::top_k
+    pub fn top_k(mut self, top_k: NonZeroU16) -> Self {
+        self.top_k = Some(top_k);
+        self
+    }
+
+    /// Set the [`seed`].
+    ///
+    /// [`seed`]: SamplingConfig::seed
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+/// Requested service tier for a [`Prompt`]. See [`Prompt::service_tier`].
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    /// Use priority tier capacity if available, otherwise standard.
+    #[default]
+    Auto,
+    /// Only use standard capacity.
+    StandardOnly,
 }
 
 #[cfg(feature = "markdown")]
@@ -528,43 +988,12 @@ impl crate::markdown::ToMarkdown for Prompt<'_> {
         &'a self,
         options: crate::markdown::Options,
     ) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
-        use pulldown_cmark::{Event, HeadingLevel::H3, Tag, TagEnd};
-
         // TODO: Add the title if there is metadata for it. Also add a metadata
         // option to Options to include arbitrary metadata. In my use case I am
         // feeding the markdown to another model that will make use of this data
         // so it does need to be included.
 
-        let system: Box<dyn Iterator<Item = Event<'_>>> = if let Some(system) =
-            self.system
-                .as_ref()
-                .map(|s| s.markdown_events_custom(options))
-        {
-            if options.system {
-                let heading_level = options.heading_level.unwrap_or(H3);
-
-                let header = [
-                    Event::Start(Tag::Heading {
-                        level: heading_level,
-                        id: None,
-                        classes: vec![],
-                        attrs: if options.attrs {
-                            vec![("role".into(), Some("system".into()))]
-                        } else {
-                            vec![]
-                        },
-                    }),
-                    Event::Text("System".into()),
-                    Event::End(TagEnd::Heading(heading_level)),
-                ];
-
-                Box::new(header.into_iter().chain(system))
-            } else {
-                Box::new(std::iter::empty())
-            }
-        } else {
-            Box::new(std::iter::empty())
-        };
+        let system = system_markdown_events(self.system.as_ref(), options);
 
         let messages = self
             .messages
@@ -575,6 +1004,49 @@ impl crate::markdown::ToMarkdown for Prompt<'_> {
     }
 }
 
+/// Markdown events for a `system` prompt with a heading, if any and if the
+/// [`Options`] say so.
+///
+/// [`Options`]: crate::markdown::Options
+#[cfg(feature = "markdown")]
+pub(crate) fn system_markdown_events<'a>(
+    system: Option<&'a message::Content<'a>>,
+    options: crate::markdown::Options,
+) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
+    use crate::markdown::ToMarkdown;
+    use message::TranscriptRole;
+    use pulldown_cmark::{Event, HeadingLevel::H3, Tag, TagEnd};
+
+    let Some(system) = system.filter(|_| options.system) else {
+        return Box::new(std::iter::empty());
+    };
+
+    let heading_level = options.heading_level.unwrap_or(H3);
+    let header = [
+        Event::Start(Tag::Heading {
+            level: heading_level,
+            id: None,
+            classes: vec![],
+            attrs: if options.attrs {
+                vec![(
+                    "role".into(),
+                    Some(TranscriptRole::System.as_attr().into()),
+                )]
+            } else {
+                vec![]
+            },
+        }),
+        Event::Text(TranscriptRole::System.as_str().into()),
+        Event::End(TagEnd::Heading(heading_level)),
+    ];
+
+    Box::new(
+        header
+            .into_iter()
+            .chain(system.markdown_events_custom(options)),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -583,7 +1055,7 @@ mod tests {
 
     use crate::prompt::message::Role;
 
-    const STOP_SEQUENCES: [&'static str; 2] = ["stop1", "stop2"];
+    const STOP_SEQUENCES: [&str; 2] = ["stop1", "stop2"];
 
     // Credit to GitHub Copilot for the following tests.
 
@@ -604,6 +1076,28 @@ mod tests {
         assert!(request.top_p.is_none());
     }
 
+    #[test]
+    fn test_to_canonical_json() {
+        let prompt = Prompt::default()
+            .temperature(Some(0.7.try_into().unwrap()))
+            .top_p(Some(1.0.try_into().unwrap()))
+            .insert_metadata("z", json!({"b": 0.1, "a": [3, -2.5]}))
+            .unwrap()
+            .insert_metadata("a", "\u{1F600}\n")
+            .unwrap()
+            .add_message((Role::User, "Hi!"));
+
+        let json = prompt.to_canonical_json().unwrap();
+        assert_eq!(
+            json,
+            r#"{"max_tokens":4096,"messages":[{"content":"Hi!","role":"user"}],"metadata":{"a":"😀\n","z":{"a":[3,-2.5],"b":0.1}},"model":"claude-3-haiku-20240307","temperature":0.7,"top_p":1}"#
+        );
+
+        // It's still valid and equivalent JSON.
+        let parsed: Prompt = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_canonical_json().unwrap(), json);
+    }
+
     #[test]
     fn test_stream_on() {
         let request = Prompt::default().stream();
@@ -653,6 +1147,36 @@ mod tests {
         assert_eq!(prompt.messages[1], (Role::Assistant, "Hi").into());
     }
 
+    #[test]
+    fn test_add_tool_results() {
+        let result = |id: &'static str| tool::Result {
+            tool_use_id: id.into(),
+            content: "Done.".into(),
+            is_error: false,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+
+        let prompt =
+            Prompt::default().add_tool_results([result("a"), result("b")]);
+        assert_eq!(prompt.messages.len(), 1);
+        assert_eq!(
+            prompt.messages[0],
+            Message::tool_results([result("a"), result("b")])
+        );
+        assert_eq!(prompt.messages[0].role, Role::User);
+    }
+
+    #[test]
+    fn test_prefill() {
+        let prompt = Prompt::default()
+            .add_message((Role::User, "Write some JSON."))
+            .prefill("{");
+        assert_eq!(prompt.messages.len(), 2);
+        assert_eq!(prompt.messages[1].role, Role::Assistant);
+        assert_eq!(prompt.messages[1].text(), "{");
+    }
+
     #[test]
     fn test_extend_messages() {
         let mut request = Prompt::default();
@@ -683,6 +1207,40 @@ mod tests {
         assert_eq!(request.metadata.get("key2").unwrap(), "value2");
     }
 
+    #[test]
+    fn test_service_tier() {
+        let json = serde_json::to_value(
+            Prompt::default().service_tier(ServiceTier::StandardOnly),
+        )
+        .unwrap();
+        assert_eq!(json["service_tier"], "standard_only");
+        assert!(serde_json::to_value(Prompt::default())
+            .unwrap()
+            .get("service_tier")
+            .is_none());
+    }
+
+    #[test]
+    fn test_extra() {
+        let prompt = Prompt::default()
+            .extra("beta_param", 1)
+            .extra("beta_param", json!({"enabled": true}))
+            .add_message((Role::User, "Hi!"));
+        let json = serde_json::to_value(&prompt).unwrap();
+        assert_eq!(json["beta_param"], json!({"enabled": true}));
+
+        // Unknown parameters survive a round trip.
+        let parsed: Prompt = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.extra.len(), 1);
+        assert!(parsed == prompt);
+    }
+
+    #[test]
+    fn test_user_id() {
+        let request = Prompt::default().user_id("abc123");
+        assert_eq!(request.metadata.get("user_id").unwrap(), "abc123");
+    }
+
     #[test]
     fn test_insert_metadata() {
         let request =
@@ -704,6 +1262,51 @@ mod tests {
         assert_eq!(request.stop_sequences.unwrap()[0], STOP_SEQUENCES[0]);
     }
 
+    #[test]
+    fn test_clear_stop_sequences() {
+        let request = Prompt::default()
+            .stop_sequences(STOP_SEQUENCES)
+            .clear_stop_sequences();
+        assert!(request.stop_sequences.is_none());
+    }
+
+    #[test]
+    fn test_validate_stop_sequences() {
+        assert!(Prompt::default().validate().is_ok());
+        assert!(Prompt::default()
+            .stop_sequences(STOP_SEQUENCES)
+            .validate()
+            .is_ok());
+
+        assert_eq!(
+            Prompt::default().stop_sequences(["ok", " \n\t"]).validate(),
+            Err(ValidationError::BlankStopSequence { index: 1 })
+        );
+        assert_eq!(
+            Prompt::default().stop_sequence("").validate(),
+            Err(ValidationError::BlankStopSequence { index: 0 })
+        );
+
+        let long = "x".repeat(Prompt::MAX_STOP_SEQUENCE_LEN + 1);
+        assert_eq!(
+            Prompt::default().stop_sequence(long).validate(),
+            Err(ValidationError::StopSequenceTooLong {
+                index: 0,
+                len: Prompt::MAX_STOP_SEQUENCE_LEN + 1,
+                max: Prompt::MAX_STOP_SEQUENCE_LEN,
+            })
+        );
+
+        let many = vec!["stop"; Prompt::MAX_STOP_SEQUENCES + 1];
+        assert_eq!(
+            Prompt::default().stop_sequences(many).validate(),
+            Err(ValidationError::TooManyStopSequences {
+                count: Prompt::MAX_STOP_SEQUENCES + 1,
+                max: Prompt::MAX_STOP_SEQUENCES,
+            })
+        );
+    }
+
     #[test]
     fn test_extend_stop_sequences() {
         let mut request = Prompt::default();
@@ -743,9 +1346,11 @@ mod tests {
         // Test with no system prompt or messages that the call to cache affects
         // the tools.
         let request = Prompt::default().add_tool(Tool {
+            kind: None,
             name: "ping".into(),
             description: "Ping a server.".into(),
             input_schema: json!({}),
+            extra: Default::default(),
             #[cfg(feature = "prompt-caching")]
             cache_control: None,
         });
@@ -789,11 +1394,17 @@ mod tests {
             })
             .cache();
 
-        // The first message should still be a single part string.
-        assert!(request.messages.first().unwrap().content.last().is_none());
+        // The first message should not be cached.
+        assert!(!request
+            .messages
+            .first()
+            .unwrap()
+            .content
+            .last()
+            .unwrap()
+            .is_cached());
 
-        // By now the final part should be a multi part string, since only
-        // Block has `cache_control`
+        // The final block of the last message should be cached.
         assert!(request
             .messages
             .last()
@@ -840,12 +1451,14 @@ mod tests {
         let schema = json_tool["input_schema"].clone();
 
         // A tool can be created from a Tool itself. This is infallible, however
-        // the API might reject the request if the tool is invalid. There is
-        // currently no schema validation in this crate.
+        // the API might reject the request if the tool is invalid. The schema
+        // itself is not validated by this crate.
         let tool = Tool {
+            kind: None,
             name: "ping".into(),
             description: "Ping a server.".into(),
             input_schema: schema.clone(),
+            extra: Default::default(),
             #[cfg(feature = "prompt-caching")]
             cache_control: None,
         };
@@ -901,18 +1514,38 @@ mod tests {
 
     #[test]
     fn test_temperature() {
-        let request = Prompt::default().temperature(Some(0.5));
+        let temperature = Temperature::try_from(0.5).unwrap();
+        let request = Prompt::default().temperature(Some(temperature));
         assert_eq!(request.temperature, Some(0.5));
+
+        assert!(Temperature::try_from(1.7).is_err());
+        assert!(Temperature::try_from(-0.1).is_err());
+        assert!(Temperature::try_from(f32::NAN).is_err());
+
+        // Raw values still deserialize, but don't validate.
+        let request: Prompt =
+            serde_json::from_str(r#"{"temperature":1.7}"#).unwrap();
+        assert_eq!(request.temperature, Some(1.7));
+        assert!(matches!(
+            request.validate(),
+            Err(ValidationError::Temperature { value }) if value == 1.7
+        ));
     }
 
     #[test]
     #[allow(unused_variables)] // because the compiler is silly sometimes
     fn test_tool_choice() {
-        let choice = tool::Choice::Any;
+        let choice = tool::Choice::any();
         let request = Prompt::default().tool_choice(choice);
         assert!(matches!(request.tool_choice, Some(choice)));
     }
 
+    #[test]
+    fn test_force_tool() {
+        let request = Prompt::default().force_tool("ping");
+        assert_eq!(request.tool_choice, Some(tool::Choice::tool("ping")));
+    }
+
     #[test]
     fn test_top_k() {
         let request =
@@ -920,10 +1553,64 @@ mod tests {
         assert_eq!(request.top_k, Some(NonZeroU16::new(5).unwrap()));
     }
 
+    #[test]
+    fn test_container() {
+        let request = Prompt::default().container("container_123");
+        assert_eq!(request.container.as_deref(), Some("container_123"));
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["container"],
+            "container_123"
+        );
+    }
+
+    #[test]
+    fn test_sampling() {
+        let config = SamplingConfig::default()
+            .temperature(0.3.try_into().unwrap())
+            .top_k(NonZeroU16::new(40).unwrap())
+            .seed(42);
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"temperature":0.3,"top_k":40,"seed":42}"#);
+        assert_eq!(
+            serde_json::from_str::<SamplingConfig>(&json).unwrap(),
+            config
+        );
+        assert!(
+            serde_json::from_str::<SamplingConfig>(r#"{"top_p":1.5}"#).is_err()
+        );
+
+        let prompt = Prompt::default()
+            .top_p(Some(0.5.try_into().unwrap()))
+            .sampling(config);
+        assert_eq!(prompt.temperature, Some(0.3));
+        assert_eq!(prompt.top_p, None);
+        assert_eq!(prompt.top_k, NonZeroU16::new(40));
+        assert!(!serde_json::to_string(&prompt).unwrap().contains("seed"));
+        assert_eq!(
+            prompt.sampling_config(),
+            SamplingConfig {
+                seed: None,
+                ..config
+            }
+        );
+    }
+
     #[test]
     fn test_top_p() {
-        let request = Prompt::default().top_p(Some(0.5));
+        let request = Prompt::default().top_p(Some(0.5.try_into().unwrap()));
         assert_eq!(request.top_p, Some(0.5));
+
+        assert!(TopP::try_from(1.0).is_ok());
+        assert!(TopP::try_from(1.01).is_err());
+
+        let request = Prompt {
+            top_p: Some(2.0),
+            ..Default::default()
+        };
+        assert!(matches!(
+            request.validate(),
+            Err(ValidationError::TopP { value }) if value == 2.0
+        ));
     }
 
     #[test]
@@ -933,6 +1620,7 @@ mod tests {
 
         let request = Prompt::default()
             .tools([Tool {
+                kind: None,
                 name: "ping".into(),
                 description: "Ping a server.".into(),
                 input_schema: json!({
@@ -945,6 +1633,7 @@ mod tests {
                     },
                     "required": ["host"]
                 }),
+                extra: Default::default(),
                 #[cfg(feature = "prompt-caching")]
                 cache_control: None,
             }])
@@ -992,7 +1681,7 @@ mod tests {
         // we generate markdown like this because it's easier to read. The user
         // does not submit a tool result, so it's confusing if the header is
         // "User".
-        let expected = "### System { role=system }\n\nYou are a very succinct assistant.\n\n### User { role=user }\n\nHello\n\n### Assistant { role=assistant }\n\nHi\n\n### User { role=user }\n\nCall a tool.\n\n### Assistant { role=assistant }\n\n````json\n{\"type\":\"tool_use\",\"id\":\"abc123\",\"name\":\"ping\",\"input\":{\"host\":\"example.com\"}}\n````\n\n### Tool { role=tool }\n\n````json\n{\"type\":\"tool_result\",\"tool_use_id\":\"abc123\",\"content\":[{\"type\":\"text\",\"text\":\"Pinging example.com.\"}],\"is_error\":false}\n````\n\n### Assistant { role=assistant }\n\nDone.";
+        let expected = "### System { role=system }\n\nYou are a very succinct assistant.\n\n### User { role=user }\n\nHello\n\n### Assistant { role=assistant }\n\nHi\n\n### User { role=user }\n\nCall a tool.\n\n### Assistant { role=assistant }\n\n````json\n{\"type\":\"tool_use\",\"id\":\"abc123\",\"name\":\"ping\",\"input\":{\"host\":\"example.com\"}}\n````\n\n### Tool { role=tool }\n\n````json\n{\"type\":\"tool_result\",\"tool_use_id\":\"abc123\",\"content\":\"Pinging example.com.\",\"is_error\":false}\n````\n\n### Assistant { role=assistant }\n\nDone.";
 
         assert_eq!(markdown.as_ref(), expected);
     }
@@ -0,0 +1,284 @@
+//! Plain text display of [`Message`]s with [`DisplayOptions`], to hide
+//! images, truncate their base64 data, or show tool use as JSON. This works
+//! with or without the `markdown` feature.
+use std::fmt::{self, Formatter};
+
+use base64::{engine::general_purpose, Engine};
+
+use super::message::{Block, Content, Image, Message};
+
+/// Options for displaying [`Message`]s as plain text with
+/// [`DisplayWith::display_with`]. The [`Default`] shows images in full and
+/// hides tool use, like the plain [`Display`] impls without the `markdown`
+/// feature.
+///
+/// ```
+/// use misanthropic::prompt::{
+///     display::{DisplayOptions, DisplayWith},
+///     message::{Block, Image, MediaType},
+///     Message,
+/// };
+///
+/// let message = Message::user([
+///     Block::from("Look at this:"),
+///     Block::from(Image::from_compressed(MediaType::Png, vec![0u8; 1024])),
+/// ]);
+/// let options = DisplayOptions::default().truncate_base64(8);
+/// assert_eq!(
+///     message.display_with(options).to_string(),
+///     "### User\n\nLook at this:\n\n![Image](data:image/png;base64,AAAAAAAA…)"
+/// );
+/// let options = DisplayOptions::default().hide_images();
+/// assert_eq!(
+///     message.display_with(options).to_string(),
+///     "### User\n\nLook at this:"
+/// );
+/// ```
+///
+/// [`Display`]: std::fmt::Display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Whether to show images.
+    pub images: bool,
+    /// Maximum number of base64 characters of image data to show, after
+    /// which it is truncated with an ellipsis.
+    pub base64_limit: Option<usize>,
+    /// Whether to show tool use and results as JSON code blocks.
+    pub tool_json: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            images: true,
+            base64_limit: None,
+            tool_json: false,
+        }
+    }
+}
+
+impl DisplayOptions {
+    /// Set [`images`] to false.
+    ///
+    /// [`images`]: DisplayOptions::images
+    pub fn hide_images(mut self) -> Self {
+        self.images = false;
+        self
+    }
+
+    /// Set the [`base64_limit`].
+    ///
+    /// [`base64_limit`]: DisplayOptions::base64_limit
+    pub fn truncate_base64(mut self, limit: usize) -> Self {
+        self.base64_limit = Some(limit);
+        self
+    }
+
+    /// Set [`tool_json`] to true.
+    ///
+    /// [`tool_json`]: DisplayOptions::tool_json
+    pub fn with_tool_json(mut self) -> Self {
+        self.tool_json = true;
+        self
+    }
+}
+
+/// Types which can be displayed as plain text with [`DisplayOptions`].
+pub trait DisplayWith {
+    /// Format `self` with `options`.
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result;
+
+    /// A [`Display`]able wrapper which formats `self` with `options`.
+    ///
+    /// [`Display`]: std::fmt::Display
+    fn display_with(&self, options: DisplayOptions) -> Displayed<'_, Self> {
+        Displayed {
+            item: self,
+            options,
+        }
+    }
+}
+
+/// [`Display`]s the inner item with [`DisplayOptions`]. See
+/// [`DisplayWith::display_with`].
+///
+/// [`Display`]: std::fmt::Display
+pub struct Displayed<'a, T: ?Sized> {
+    item: &'a T,
+    options: DisplayOptions,
+}
+
+impl<T> fmt::Display for Displayed<'_, T>
+where
+    T: DisplayWith + ?Sized,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.item.fmt_with(f, &self.options)
+    }
+}
+
+/// Whether `block` shows anything with `options`.
+fn is_shown(block: &Block, options: &DisplayOptions) -> bool {
+    match block {
+        Block::Text { .. } => true,
+        Block::Image { .. } => options.images,
+        Block::ToolUse { .. }
+        | Block::ToolResult { .. }
+        | Block::ServerToolUse { .. }
+        | Block::WebSearchToolResult { .. }
+        | Block::CodeExecutionToolResult { .. } => options.tool_json,
+        Block::Document { .. } | Block::Unknown(_) => false,
+    }
+}
+
+impl DisplayWith for Image {
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result {
+        match (self, options.base64_limit) {
+            (Image::Base64 { media_type, data }, Some(limit)) => {
+                // Base64 encodes 3 bytes as 4 characters, so only the bytes
+                // which are shown have to be encoded.
+                let shown = data.len().min(limit.div_ceil(4) * 3);
+                let mut encoded =
+                    general_purpose::STANDARD.encode(&data[..shown]);
+                let truncated = encoded.len() > limit || shown < data.len();
+                encoded.truncate(limit);
+                let ellipsis = if truncated { "…" } else { "" };
+
+                write!(
+                    f,
+                    "![Image](data:{media_type};base64,{encoded}{ellipsis})"
+                )
+            }
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+impl DisplayWith for Block<'_> {
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result {
+        if !is_shown(self, options) {
+            return Ok(());
+        }
+
+        match self {
+            Block::Text { text, .. } => f.write_str(text),
+            Block::Image { image, .. } => image.fmt_with(f, options),
+            _ => {
+                let json = serde_json::to_string_pretty(self)
+                    .map_err(|_| fmt::Error)?;
+                write!(f, "````json\n{json}\n````")
+            }
+        }
+    }
+}
+
+impl DisplayWith for Content<'_> {
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result {
+        let mut blocks = self.iter().filter(|block| is_shown(block, options));
+        if let Some(block) = blocks.next() {
+            block.fmt_with(f, options)?;
+            for block in blocks {
+                f.write_str("\n\n")?;
+                block.fmt_with(f, options)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl DisplayWith for Message<'_> {
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result {
+        write!(f, "### {}\n\n", self.role)?;
+        self.content.fmt_with(f, options)
+    }
+}
+
+impl DisplayWith for crate::response::Message<'_> {
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        options: &DisplayOptions,
+    ) -> fmt::Result {
+        self.message.fmt_with(f, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prompt::message::{MediaType, Role},
+        tool,
+    };
+
+    #[test]
+    fn test_display_options() {
+        let image = Image::from_compressed(MediaType::Png, vec![0u8; 6]);
+        let message = Message {
+            role: Role::Assistant,
+            content: Content::from([
+                Block::from("Here you go."),
+                Block::from(image.clone()),
+                Block::ToolUse {
+                    call: tool::Use {
+                        id: "toolu_1".into(),
+                        name: "echo".into(),
+                        input: crate::json!({"text": "hi"}),
+                        #[cfg(feature = "prompt-caching")]
+                        cache_control: None,
+                    },
+                },
+            ]),
+        };
+
+        // The default shows the whole image and hides tool use.
+        assert_eq!(
+            message.display_with(DisplayOptions::default()).to_string(),
+            "### Assistant\n\nHere you go.\n\n![Image](data:image/png;base64,AAAAAAAA)"
+        );
+        #[cfg(not(feature = "markdown"))]
+        assert_eq!(
+            image.display_with(DisplayOptions::default()).to_string(),
+            image.to_string()
+        );
+
+        // Truncated only if longer than the limit.
+        let options = DisplayOptions::default().truncate_base64(8);
+        assert_eq!(
+            image.display_with(options).to_string(),
+            "![Image](data:image/png;base64,AAAAAAAA)"
+        );
+        let options = DisplayOptions::default().truncate_base64(5);
+        assert_eq!(
+            image.display_with(options).to_string(),
+            "![Image](data:image/png;base64,AAAAA…)"
+        );
+
+        let options = DisplayOptions::default().hide_images().with_tool_json();
+        assert_eq!(
+            message.display_with(options).to_string(),
+            "### Assistant\n\nHere you go.\n\n````json\n{\n  \"type\": \"tool_use\",\n  \"id\": \"toolu_1\",\n  \"name\": \"echo\",\n  \"input\": {\n    \"text\": \"hi\"\n  }\n}\n````"
+        );
+    }
+}
@@ -4,6 +4,8 @@
 //! [`response::Message`]: crate::response::Message
 //! [`prompt::Message`]: crate::prompt::Message
 
+use std::borrow::Cow;
+
 use base64::engine::{general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +16,8 @@ use crate::{
 };
 
 /// Role of the [`Message`] author.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 pub enum Role {
     /// From the user.
     User,
@@ -40,6 +41,92 @@ impl std::fmt::Display for Role {
     }
 }
 
+/// Role of a [`Message`] in a transcript. The API only has the [`User`] and
+/// [`Assistant`] [`Role`]s, but transcripts, and formats such as OpenAI's,
+/// show the system prompt and tool results as their own roles. Rendering and
+/// interop use this so that information isn't lost.
+///
+/// ```
+/// use misanthropic::prompt::message::{Message, Role, TranscriptRole};
+///
+/// let message = Message::user("Hi!");
+/// assert_eq!(TranscriptRole::of(&message), TranscriptRole::User);
+/// assert_eq!(TranscriptRole::Tool.role(), Some(Role::User));
+/// assert_eq!(TranscriptRole::System.role(), None);
+/// ```
+///
+/// [`User`]: Role::User
+/// [`Assistant`]: Role::Assistant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptRole {
+    /// The system prompt.
+    System,
+    /// From the user.
+    User,
+    /// From the AI.
+    Assistant,
+    /// Results of tool use, which are sent as [`Role::User`].
+    Tool,
+}
+
+impl TranscriptRole {
+    /// Get the string representation of the role.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::System => "System",
+            Self::User => "User",
+            Self::Assistant => "Assistant",
+            Self::Tool => "Tool",
+        }
+    }
+
+    /// Lowercase string representation, for `role` attributes and classes.
+    #[cfg(feature = "markdown")]
+    pub(crate) const fn as_attr(&self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+        }
+    }
+
+    /// Role of `message`. It is [`TranscriptRole::Tool`] if the last
+    /// [`Block`] is a [`Block::ToolResult`] and its [`Role`] otherwise.
+    pub fn of(message: &Message) -> Self {
+        match message.content.last() {
+            Some(Block::ToolResult { .. }) => Self::Tool,
+            _ => message.role.into(),
+        }
+    }
+
+    /// The [`Role`] this is sent to the API as, if any. The system prompt is
+    /// not a [`Message`], so [`TranscriptRole::System`] has none.
+    pub const fn role(&self) -> Option<Role> {
+        match self {
+            Self::System => None,
+            Self::User | Self::Tool => Some(Role::User),
+            Self::Assistant => Some(Role::Assistant),
+        }
+    }
+}
+
+impl From<Role> for TranscriptRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::User => Self::User,
+            Role::Assistant => Self::Assistant,
+        }
+    }
+}
+
+impl std::fmt::Display for TranscriptRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// A message in a [`Request`]. See [`response::Message`] for the version with
 /// additional metadata.
 ///
@@ -50,7 +137,7 @@ impl std::fmt::Display for Role {
 /// [`Display`]: std::fmt::Display
 /// [`Request`]: crate::prompt
 /// [`response::Message`]: crate::response::Message
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(
     not(feature = "markdown"),
@@ -61,14 +148,11 @@ impl std::fmt::Display for Role {
 pub struct Message<'a> {
     /// Who is the message from.
     pub role: Role,
-    /// The [`Content`] of the message as [one] or [more] [`Block`]s.
-    ///
-    /// [one]: Content::SinglePart
-    /// [more]: Content::MultiPart
+    /// The [`Content`] of the message as one or more [`Block`]s.
     pub content: Content<'a>,
 }
 
-impl Message<'_> {
+impl<'a> Message<'a> {
     /// Heading for the message when rendered as markdown using [`Display`].
     ///
     /// [`Display`]: std::fmt::Display
@@ -87,10 +171,124 @@ impl Message<'_> {
 
     /// Returns Some([`tool::Use`]) if the final [`Content`] [`Block`] is a
     /// [`Block::ToolUse`].
-    pub fn tool_use(&self) -> Option<&crate::tool::Use> {
+    pub fn tool_use(&self) -> Option<&crate::tool::Use<'_>> {
         self.content.last()?.tool_use()
     }
 
+    /// Returns an iterator over every [`tool::Use`] in the message. The model
+    /// may call several tools at once. Answer them all in a single
+    /// [`Message::tool_results`].
+    pub fn tool_uses(&self) -> impl Iterator<Item = &tool::Use<'a>> {
+        self.content.tool_uses()
+    }
+
+    /// Returns only the text of the message, concatenating all
+    /// [`Block::Text`]s and skipping everything else. Unlike [`Display`],
+    /// there are no headings, separators, or tool blocks, so this is suitable
+    /// for passing raw model output onward. Borrows when possible.
+    ///
+    /// [`Display`]: std::fmt::Display
+    pub fn text(&self) -> std::borrow::Cow<'_, str> {
+        use std::borrow::Cow;
+
+        let mut texts = self.content.iter().filter_map(|block| match block {
+            Block::Text { text, .. } => Some(&**text),
+            _ => None,
+        });
+
+        let Some(first) = texts.next() else {
+            return Cow::Borrowed("");
+        };
+
+        match texts.next() {
+            None => Cow::Borrowed(first),
+            Some(second) => {
+                let mut text = String::from(first);
+                text.push_str(second);
+                texts.for_each(|t| text.push_str(t));
+                Cow::Owned(text)
+            }
+        }
+    }
+
+    /// Returns a copy of the message with `<thinking>` spans removed. See
+    /// [`Content::strip_thoughts`].
+    pub fn without_thoughts(&self) -> Self {
+        Message {
+            role: self.role,
+            content: self.content.strip_thoughts(),
+        }
+    }
+
+    /// Create a [`User`] message. Add more blocks with [`push_block`].
+    ///
+    /// [`User`]: Role::User
+    /// [`push_block`]: Message::push_block
+    pub fn user<C>(content: C) -> Self
+    where
+        C: Into<Content<'a>>,
+    {
+        Message {
+            role: Role::User,
+            content: content.into(),
+        }
+    }
+
+    /// Create an [`Assistant`] message. Add more blocks, such as a
+    /// [`tool::Use`], with [`push_block`].
+    ///
+    /// [`Assistant`]: Role::Assistant
+    /// [`push_block`]: Message::push_block
+    pub fn assistant<C>(content: C) -> Self
+    where
+        C: Into<Content<'a>>,
+    {
+        Message {
+            role: Role::Assistant,
+            content: content.into(),
+        }
+    }
+
+    /// Add a [`Block`] to the [`Content`]. See [`Content::push`].
+    pub fn push_block<B>(mut self, block: B) -> Self
+    where
+        B: Into<Block<'a>>,
+    {
+        self.content.push(block);
+        self
+    }
+
+    /// Create a [`User`] message with an image followed by a caption or
+    /// question about it. See [`Content::push_image_with_caption`].
+    ///
+    /// [`User`]: Role::User
+    pub fn user_image<I, T>(image: I, caption: T) -> Self
+    where
+        I: Into<Block<'a>>,
+        T: Into<crate::CowStr<'a>>,
+    {
+        let mut content = Content::default();
+        content.push_image_with_caption(image, caption);
+        Message {
+            role: Role::User,
+            content,
+        }
+    }
+
+    /// Create a [`User`] message answering multiple [`tool::Use`]s at once
+    /// with their [`tool::Result`]s. See [`Message::tool_uses`].
+    ///
+    /// [`User`]: Role::User
+    pub fn tool_results<Rs>(results: Rs) -> Self
+    where
+        Rs: IntoIterator<Item = tool::Result<'a>>,
+    {
+        Message {
+            role: Role::User,
+            content: Content(results.into_iter().map(Block::from).collect()),
+        }
+    }
+
     /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
     /// fields.
     ///
@@ -139,6 +337,38 @@ impl<'a> From<tool::Result<'a>> for Message<'a> {
     }
 }
 
+#[cfg(feature = "markdown")]
+impl Message<'_> {
+    /// Whether the message is rendered with `options`. Tool use and results
+    /// are only rendered if the [`Options`] say so.
+    ///
+    /// [`Options`]: crate::markdown::Options
+    pub(crate) fn is_rendered(
+        &self,
+        options: &crate::markdown::Options,
+    ) -> bool {
+        match self.content.last() {
+            Some(Block::ToolResult { .. }) => options.tool_results,
+            Some(Block::ToolUse { .. }) => options.tool_use,
+            _ => true,
+        }
+    }
+
+    /// Heading and lowercase `role` attribute of the message when rendered.
+    /// Failed tool results are shown as errors.
+    pub(crate) fn heading(&self) -> (&'static str, &'static str) {
+        match self.content.last() {
+            Some(Block::ToolResult { result }) if result.is_error => {
+                ("Error", "error")
+            }
+            _ => {
+                let role = TranscriptRole::of(self);
+                (role.as_str(), role.as_attr())
+            }
+        }
+    }
+}
+
 #[cfg(feature = "markdown")]
 impl crate::markdown::ToMarkdown for Message<'_> {
     /// Returns an iterator over the text as [`pulldown_cmark::Event`]s using
@@ -152,36 +382,19 @@ impl crate::markdown::ToMarkdown for Message<'_> {
     ) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
         use pulldown_cmark::{Event, HeadingLevel::H3, Tag};
 
+        if !self.is_rendered(&options) {
+            return Box::new(std::iter::empty());
+        }
         let content = self.content.markdown_events_custom(options);
-        let role = match self.content.last() {
-            Some(Block::ToolResult {
-                result: tool::Result { is_error, .. },
-            }) => {
-                if !options.tool_results {
-                    return Box::new(std::iter::empty());
-                }
-
-                if *is_error {
-                    "Error"
-                } else {
-                    "Tool"
-                }
-            }
-            Some(Block::ToolUse { .. }) => {
-                if !options.tool_use {
-                    return Box::new(std::iter::empty());
-                }
-
-                self.role.as_str()
-            }
-            _ => self.role.as_str(),
-        };
+        // The heading and the `role` attribute are static, so no events are
+        // allocated for them.
+        let (role, attr) = self.heading();
         let heading_tag = Tag::Heading {
             level: options.heading_level.unwrap_or(H3),
             id: None,
             classes: vec![],
             attrs: if options.attrs {
-                vec![("role".into(), Some(role.to_lowercase().into()))]
+                vec![("role".into(), Some(attr.into()))]
             } else {
                 vec![]
             },
@@ -206,24 +419,26 @@ impl std::fmt::Display for Message<'_> {
     }
 }
 
-/// Content of a [`Message`].
-#[derive(Clone, Debug, Serialize, Deserialize, derive_more::IsVariant)]
-#[serde(rename_all = "snake_case")]
-#[serde(untagged)]
+/// Content of a [`Message`] as a list of [`Block`]s.
+///
+/// Content with a single [`Block::Text`] and no cache breakpoint is serialized
+/// as a plain string, as the API sends it. Anything else is serialized as a
+/// list of [`Block`]s. Either form can be deserialized.
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
-pub enum Content<'a> {
-    /// Single part text-only content.
-    SinglePart(crate::CowStr<'a>),
-    /// Multiple content [`Block`]s.
-    MultiPart(Vec<Block<'a>>),
-}
+pub struct Content<'a>(pub(crate) Vec<Block<'a>>);
 
 impl<'a> Content<'a> {
-    /// Const constructor for static text content. Not available with the
-    /// `langsan` feature.
+    /// Constructor for static text content. Not available with the `langsan`
+    /// feature. This is no longer a `const fn`.
     #[cfg(not(feature = "langsan"))]
-    pub const fn const_text(text: &'static str) -> Self {
-        Self::SinglePart(std::borrow::Cow::Borrowed(text))
+    #[deprecated(
+        since = "0.6.0",
+        note = "This is no longer a `const fn`, since `Content` is a list of \
+                `Block`s. Use `Content::text` instead."
+    )]
+    pub fn const_text(text: &'static str) -> Self {
+        Self::text(text)
     }
 
     /// Text content.
@@ -231,16 +446,13 @@ impl<'a> Content<'a> {
     where
         T: Into<crate::CowStr<'a>>,
     {
-        Self::SinglePart(text.into())
+        Self(vec![Block::text(text)])
     }
 
     /// Returns the number of bytes in self. Does not include tool use or other
     /// metadata. Does include the base64 encoded image data length.
     pub fn len(&self) -> usize {
-        match self {
-            Self::SinglePart(s) => s.as_bytes().len(),
-            Self::MultiPart(parts) => parts.iter().map(Block::len).sum(),
-        }
+        self.0.iter().map(Block::len).sum()
     }
 
     /// Returns true if `self` is empty.
@@ -248,80 +460,184 @@ impl<'a> Content<'a> {
         self.len() == 0
     }
 
-    /// Unwrap [`Content::SinglePart`] as a [`Block::Text`]. This will panic if
-    /// `self` is [`MultiPart`].
-    ///
-    /// [`SinglePart`]: Content::SinglePart
-    /// [`MultiPart`]: Content::MultiPart
+    /// Returns true if the content is a single [`Block::Text`] without a cache
+    /// breakpoint, which is serialized as a plain string.
+    #[deprecated(
+        since = "0.6.0",
+        note = "`Content` is always a list of `Block`s. Use `Content::iter` \
+                or `Content::as_slice` instead."
+    )]
+    pub fn is_single_part(&self) -> bool {
+        self.plain_text().is_some()
+    }
+
+    /// Returns true if the content is serialized as a list of [`Block`]s. See
+    /// [`Content::is_single_part`].
+    #[deprecated(
+        since = "0.6.0",
+        note = "`Content` is always a list of `Block`s. Use `Content::iter` \
+                or `Content::as_slice` instead."
+    )]
+    pub fn is_multi_part(&self) -> bool {
+        self.plain_text().is_none()
+    }
+
+    /// Unwrap content with a single [`Block::Text`] without a cache breakpoint.
+    /// This will panic for any other content.
     ///
     /// # Panics
-    /// - If the content is [`MultiPart`].
+    /// - If the content is not a single [`Block::Text`] without a cache
+    ///   breakpoint.
+    #[deprecated(
+        since = "0.6.0",
+        note = "`Content` is always a list of `Block`s. Use `Content::get` or \
+                `Content::into_iter` instead."
+    )]
     pub fn unwrap_single_part(self) -> Block<'a> {
-        match self {
-            #[cfg(feature = "prompt-caching")]
-            Self::SinglePart(text) => Block::Text {
+        if self.plain_text().is_none() {
+            panic!("Content is not a single text block.");
+        }
+
+        let mut blocks = self.0;
+        blocks.remove(0)
+    }
+
+    /// Returns the text if the content is serialized as a plain string: a
+    /// single [`Block::Text`] without a cache breakpoint.
+    fn plain_text(&self) -> Option<&str> {
+        match self.0.as_slice() {
+            [Block::Text {
                 text,
-                cache_control: None,
-            },
-            #[cfg(not(feature = "prompt-caching"))]
-            Self::SinglePart(text) => Block::Text { text },
-            Self::MultiPart(_) => {
-                panic!("Content is MultiPart, not SinglePart");
-            }
+                #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+            }] => Some(&**text),
+            _ => None,
         }
     }
 
-    /// Add a [`Block`] to the [`Content`]. If the [`Content`] is a
-    /// [`SinglePart`], it will be converted to a [`MultiPart`].
-    ///
-    /// [`SinglePart`]: Content::SinglePart
-    /// [`MultiPart`]: Content::MultiPart
+    /// Add a [`Block`] to the [`Content`].
     pub fn push<P>(&mut self, part: P)
     where
         P: Into<Block<'a>>,
     {
-        // If there is a SinglePart message, convert it to a MultiPart message.
-        if self.is_single_part() {
-            // the old switcheroo
-            let mut old = Content::MultiPart(vec![]);
-            std::mem::swap(self, &mut old);
-            // This can never loop because we ensure self is a MultiPart which
-            // will skip this block.
-            self.push(old.unwrap_single_part());
-        }
+        self.0.push(part.into());
+    }
+
+    /// Add an image followed by a caption or question about it. Images work
+    /// best before the text that refers to them. See [Anthropic's vision
+    /// guide].
+    ///
+    /// [Anthropic's vision guide]: <https://docs.anthropic.com/en/docs/build-with-claude/vision>
+    pub fn push_image_with_caption<I, T>(&mut self, image: I, caption: T)
+    where
+        I: Into<Block<'a>>,
+        T: Into<crate::CowStr<'a>>,
+    {
+        self.push(image);
+        self.push(Block::text(caption));
+    }
+
+    /// Get the [`Block`] at `index`. Returns [`None`] if it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&Block<'a>> {
+        self.0.get(index)
+    }
+
+    /// Get the [`Block`] at `index` mutably. Returns [`None`] if it is out of
+    /// bounds.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Block<'a>> {
+        self.0.get_mut(index)
+    }
+
+    /// Returns an iterator over the [`Block`]s.
+    pub fn iter(&self) -> std::slice::Iter<'_, Block<'a>> {
+        self.0.iter()
+    }
+
+    /// Returns an iterator over the [`Block`]s, mutably.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Block<'a>> {
+        self.0.iter_mut()
+    }
+
+    /// The [`Block`]s as a slice.
+    pub fn as_slice(&self) -> &[Block<'a>] {
+        &self.0
+    }
+
+    /// The [`Block`]s as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [Block<'a>] {
+        &mut self.0
+    }
 
-        if let Content::MultiPart(parts) = self {
-            parts.push(part.into());
+    /// Remove and return the [`Block`] at `index`, shifting the following
+    /// [`Block`]s down. Returns [`None`] if it is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Option<Block<'a>> {
+        if index < self.0.len() {
+            Some(self.0.remove(index))
+        } else {
+            None
         }
     }
 
-    /// Add a cache breakpoint to the final [`Block`]. If the [`Content`] is
-    /// [`SinglePart`], it will be converted to [`MultiPart`] first.
-    ///
-    /// [`SinglePart`]: Content::SinglePart
-    /// [`MultiPart`]: Content::MultiPart
+    /// Add a cache breakpoint to the final [`Block`].
     #[cfg(feature = "prompt-caching")]
     pub fn cache(&mut self) {
-        if self.is_single_part() {
-            let mut old = Content::MultiPart(vec![]);
-            std::mem::swap(self, &mut old);
-            self.push(old.unwrap_single_part());
+        if let Some(block) = self.0.last_mut() {
+            block.cache();
         }
+    }
 
-        if let Content::MultiPart(parts) = self {
-            if let Some(block) = parts.last_mut() {
-                block.cache();
-            }
-        }
+    /// Remove every cache breakpoint. See [`Block::uncache`].
+    #[cfg(feature = "prompt-caching")]
+    pub fn uncache(&mut self) {
+        self.0.iter_mut().for_each(Block::uncache);
+    }
+
+    /// Returns an iterator over every [`tool::Use`] in the [`Content`].
+    pub fn tool_uses(&self) -> impl Iterator<Item = &tool::Use<'a>> {
+        self.0.iter().filter_map(|block| match block {
+            Block::ToolUse { call } => Some(call),
+            _ => None,
+        })
     }
 
     /// Get the last [`Block`] in the [`Content`]. Returns [`None`] if the
     /// [`Content`] is empty.
-    pub fn last(&self) -> Option<&Block> {
-        match self {
-            Self::SinglePart(_) => None,
-            Self::MultiPart(parts) => parts.last(),
-        }
+    pub fn last(&self) -> Option<&Block<'_>> {
+        self.0.last()
+    }
+
+    /// Get the last [`Block`] in the [`Content`] mutably. Returns [`None`] if
+    /// the [`Content`] is empty.
+    pub fn last_mut(&mut self) -> Option<&mut Block<'a>> {
+        self.0.last_mut()
+    }
+
+    /// Returns a copy of the content with `<thinking>...</thinking>` spans
+    /// removed from the text, for storing user-facing transcripts. An
+    /// unterminated span is removed to the end of the text. [`Block::Text`]s
+    /// left empty are dropped. Other [`Block`]s are unchanged.
+    pub fn strip_thoughts(&self) -> Self {
+        Self(
+            self.0
+                .iter()
+                .filter_map(|block| match block {
+                    Block::Text {
+                        text,
+                        #[cfg(feature = "prompt-caching")]
+                        cache_control,
+                    } => match strip_thinking(text) {
+                        Some(stripped) if stripped.is_empty() => None,
+                        Some(stripped) => Some(Block::Text {
+                            text: stripped.into(),
+                            #[cfg(feature = "prompt-caching")]
+                            cache_control: cache_control.clone(),
+                        }),
+                        None => Some(block.clone()),
+                    },
+                    block => Some(block.clone()),
+                })
+                .collect(),
+        )
     }
 
     /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
@@ -329,45 +645,84 @@ impl<'a> Content<'a> {
     ///
     /// [`Cow`]: std::borrow::Cow
     pub fn into_static(self) -> Content<'static> {
-        match self {
-            Self::SinglePart(text) => {
-                #[cfg(not(feature = "langsan"))]
-                {
-                    Content::SinglePart(std::borrow::Cow::Owned(
-                        text.into_owned(),
-                    ))
-                }
-                #[cfg(feature = "langsan")]
-                {
-                    Content::SinglePart(text.into_static())
-                }
-            }
-            Self::MultiPart(parts) => Content::MultiPart(
-                parts.into_iter().map(Block::into_static).collect(),
-            ),
+        Content(self.0.into_iter().map(Block::into_static).collect())
+    }
+
+    /// Push a [`Delta`] into the last [`Block`] of the [`Content`]. The types
+    /// must be compatible or this will return a [`ContentMismatch`] error. If
+    /// the [`Content`] is empty, this will return an [`OutOfBounds`] error.
+    ///
+    /// This ignores the index of the [`Delta`]'s event. To apply a stream,
+    /// use an [`Accumulator`] instead.
+    ///
+    /// [`OutOfBounds`]: crate::stream::OutOfBounds
+    /// [`Accumulator`]: crate::stream::Accumulator
+    pub fn push_delta(
+        &mut self,
+        delta: Delta<'a>,
+    ) -> Result<(), DeltaError<'a>> {
+        match self.0.last_mut() {
+            Some(block) => block.merge_deltas(std::iter::once(delta)),
+            None => Err(crate::stream::OutOfBounds { index: 0, max: 0 }.into()),
         }
     }
+}
 
-    /// Push a [`Delta`] into the [`Content`]. The types must be compatible or
-    /// this will return a [`ContentMismatch`] error.
-    pub fn push_delta(&mut self, delta: Delta<'a>) -> Result<(), DeltaError> {
-        match self {
-            Self::SinglePart(_) => {
-                let mut old = Content::MultiPart(vec![]);
-                std::mem::swap(self, &mut old);
-                self.push(old.unwrap_single_part());
-                self.push_delta(delta)?;
-            }
-            Self::MultiPart(parts) => {
-                parts
-                    .last_mut()
-                    .unwrap()
-                    .merge_deltas(std::iter::once(delta))?;
-            }
+impl Serialize for Content<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.plain_text() {
+            Some(text) => serializer.serialize_str(text),
+            None => self.0.serialize(serializer),
         }
+    }
+}
 
-        Ok(())
+impl<'de, 'a> Deserialize<'de> for Content<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        /// The API accepts content as a string or a list of blocks.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr<'a> {
+            Text(crate::CowStr<'a>),
+            Blocks(Vec<Block<'a>>),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Text(text) => Self::text(text),
+            Repr::Blocks(blocks) => Self(blocks),
+        })
+    }
+}
+
+/// Remove `<thinking>` spans, and any whitespace following them, from `text`.
+/// Returns [`None`] if there are none.
+fn strip_thinking(text: &str) -> Option<String> {
+    const OPEN: &str = "<thinking>";
+    const CLOSE: &str = "</thinking>";
+
+    let mut start = text.find(OPEN)?;
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start..].find(CLOSE) {
+            Some(end) => rest[start + end + CLOSE.len()..].trim_start(),
+            None => "",
+        };
+        match rest.find(OPEN) {
+            Some(i) => start = i,
+            None => break,
+        }
     }
+    stripped.push_str(rest);
+
+    Some(stripped)
 }
 
 #[cfg(feature = "markdown")]
@@ -381,41 +736,27 @@ impl crate::markdown::ToMarkdown for Content<'_> {
         &'a self,
         options: crate::markdown::Options,
     ) -> Box<dyn Iterator<Item = pulldown_cmark::Event<'a>> + 'a> {
-        use pulldown_cmark::Event;
-
-        let it: Box<dyn Iterator<Item = Event<'a>> + 'a> = match self {
-            Self::SinglePart(string) => {
-                Box::new(pulldown_cmark::Parser::new(string))
-            }
-            Self::MultiPart(parts) => Box::new(
-                parts
-                    .iter()
-                    .flat_map(move |part| part.markdown_events_custom(options)),
-            ),
-        };
-
-        it
+        Box::new(
+            self.0
+                .iter()
+                .flat_map(move |part| part.markdown_events_custom(options)),
+        )
     }
 }
 
 #[cfg(not(feature = "markdown"))]
 impl std::fmt::Display for Content<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::SinglePart(string) => write!(f, "{}", string),
-            // This could be derived but the `Join` trait is not stable. Neither
-            // is `Iterator::intersperse`. This also has fewer allocations.
-            Self::MultiPart(parts) => {
-                let mut iter = parts.iter();
-                if let Some(part) = iter.next() {
-                    write!(f, "{}", part)?;
-                    for part in iter {
-                        write!(f, "{}{}", Self::SEP, part)?;
-                    }
-                }
-                Ok(())
+        // This could be derived but the `Join` trait is not stable. Neither
+        // is `Iterator::intersperse`. This also has fewer allocations.
+        let mut iter = self.0.iter();
+        if let Some(part) = iter.next() {
+            write!(f, "{}", part)?;
+            for part in iter {
+                write!(f, "{}{}", Self::SEP, part)?;
             }
         }
+        Ok(())
     }
 }
 
@@ -439,7 +780,7 @@ where
     T: Into<Block<'a>>,
 {
     fn from(block: T) -> Self {
-        Self::MultiPart(vec![block.into()])
+        Self(vec![block.into()])
     }
 }
 
@@ -455,13 +796,49 @@ where
     T: Into<Block<'a>>,
 {
     fn from(blocks: [T; N]) -> Self {
-        Self::MultiPart(blocks.into_iter().map(|t| t.into()).collect())
+        Self(blocks.into_iter().map(|t| t.into()).collect())
+    }
+}
+
+impl<'a> IntoIterator for Content<'a> {
+    type Item = Block<'a>;
+    type IntoIter = std::vec::IntoIter<Block<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'c, 'a> IntoIterator for &'c Content<'a> {
+    type Item = &'c Block<'a>;
+    type IntoIter = std::slice::Iter<'c, Block<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> Extend<Block<'a>> for Content<'a> {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Block<'a>>,
+    {
+        self.0.extend(iter);
+    }
+}
+
+impl<'a> FromIterator<Block<'a>> for Content<'a> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Block<'a>>,
+    {
+        Self(iter.into_iter().collect())
     }
 }
 
 impl<'a> From<&'a [&'a str]> for Content<'a> {
     fn from(text: &'a [&'a str]) -> Self {
-        Self::MultiPart(text.iter().map(|t| (*t).into()).collect())
+        Self(text.iter().map(|t| (*t).into()).collect())
     }
 }
 
@@ -470,7 +847,7 @@ where
     T: Into<Block<'a>>,
 {
     fn from(blocks: Vec<T>) -> Self {
-        Self::MultiPart(blocks.into_iter().map(Into::into).collect())
+        Self(blocks.into_iter().map(Into::into).collect())
     }
 }
 
@@ -479,6 +856,7 @@ where
 #[cfg_attr(not(feature = "markdown"), derive(derive_more::Display))]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
+#[serde(expecting = "a content block with valid fields for its `type`")]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 pub enum Block<'a> {
     /// Text content.
@@ -496,8 +874,23 @@ pub enum Block<'a> {
     #[cfg_attr(not(feature = "markdown"), display("{}", image))]
     Image {
         #[serde(rename = "source")]
-        /// An base64 encoded image.
-        image: Image<'a>,
+        /// An base64 encoded image or an image URL.
+        image: Image,
+        /// Use prompt caching. See [`Block::cache`] for more information.
+        #[cfg(feature = "prompt-caching")]
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    /// A [`Document`] for the model to use as context, such as a search
+    /// result. This should only be used with the [`User`] role. Documents are
+    /// not rendered, since they are context rather than conversation.
+    ///
+    /// [`User`]: Role::User
+    #[cfg_attr(not(feature = "markdown"), display(""))]
+    Document {
+        /// The document.
+        #[serde(flatten)]
+        document: Document<'a>,
         /// Use prompt caching. See [`Block::cache`] for more information.
         #[cfg(feature = "prompt-caching")]
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -525,6 +918,75 @@ pub enum Block<'a> {
         #[serde(flatten)]
         result: tool::Result<'a>,
     },
+    /// Server [`Tool`] call, such as a [web search]. This is generated by the
+    /// API, not by you, and is not returned by [`Block::tool_use`] since there
+    /// is nothing for you to run.
+    ///
+    /// [web search]: crate::tool::web_search
+    /// [`Tool`]: crate::Tool
+    #[cfg_attr(not(feature = "markdown"), display(""))]
+    ServerToolUse {
+        /// Server tool use input.
+        #[serde(flatten)]
+        call: tool::Use<'a>,
+    },
+    /// Result of the [web search] server [`Tool`]. This is generated by the
+    /// API, not by you.
+    ///
+    /// [web search]: crate::tool::web_search
+    /// [`Tool`]: crate::Tool
+    #[cfg_attr(not(feature = "markdown"), display(""))]
+    WebSearchToolResult {
+        /// Id of the server tool use this is the result of.
+        tool_use_id: std::borrow::Cow<'a, str>,
+        /// The search results or an error.
+        content: tool::web_search::Outcome,
+    },
+    /// Result of the [code execution] server [`Tool`]. This is generated by
+    /// the API, not by you.
+    ///
+    /// [code execution]: crate::tool::code_execution
+    /// [`Tool`]: crate::Tool
+    #[cfg_attr(not(feature = "markdown"), display(""))]
+    CodeExecutionToolResult {
+        /// Id of the server tool use this is the result of.
+        tool_use_id: std::borrow::Cow<'a, str>,
+        /// What happened when the code ran.
+        content: tool::code_execution::Outcome,
+    },
+    /// A block type this crate does not know about, for example one added to
+    /// the API after this version was released. The raw JSON is preserved and
+    /// serialized as-is, so it can be sent back to the API unchanged.
+    ///
+    /// Unknown blocks have no length, cannot have [`Delta`]s applied, and
+    /// cannot be cached with `Block::cache`.
+    ///
+    /// Only the `type` is unknown. A block of a known `type` which fails to
+    /// parse is an error.
+    #[cfg_attr(not(feature = "markdown"), display(""))]
+    #[serde(untagged, deserialize_with = "unknown_block")]
+    Unknown(serde_json::Value),
+}
+
+/// `type`s of the known [`Block`]s.
+const BLOCK_TYPES: &[&str] = &[
+    "text",
+    "text_delta",
+    "image",
+    "document",
+    "tool_use",
+    "tool_result",
+    "server_tool_use",
+    "web_search_tool_result",
+    "code_execution_tool_result",
+];
+
+/// Deserialize [`Block::Unknown`] only if the `type` is unknown.
+fn unknown_block<'de, D>(deserializer: D) -> Result<serde_json::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    crate::parse::unknown_variant(deserializer, BLOCK_TYPES)
 }
 
 #[cfg(feature = "markdown")]
@@ -562,16 +1024,31 @@ impl<'a> Block<'a> {
         }
     }
 
+    /// Image content from a URL. The API fetches the image, so it does not
+    /// need to be downloaded and encoded locally.
+    pub fn image_url<U>(url: U) -> Self
+    where
+        U: Into<String>,
+    {
+        Image::from_url(url).into()
+    }
+
     /// Merge [`Delta`]s into a [`Block`]. The types must be compatible or this
     /// will return a [`ContentMismatch`] error.
-    pub fn merge_deltas<Ds>(&mut self, deltas: Ds) -> Result<(), DeltaError>
+    ///
+    /// JSON [`Delta`]s are appended to the JSON text so far, which replaces the
+    /// `input` of the [`tool::Use`] until it is parsed by [`finish`]. This way
+    /// deltas can be merged across calls.
+    ///
+    /// [`finish`]: Block::finish
+    pub fn merge_deltas<Ds>(&mut self, deltas: Ds) -> Result<(), DeltaError<'a>>
     where
         Ds: IntoIterator<Item = Delta<'a>>,
     {
         let mut it = deltas.into_iter();
 
         // Get the first delta so we can try to fold the rest into it.
-        let acc: Delta = match it.next() {
+        let acc: Delta<'a> = match it.next() {
             Some(delta) => delta,
             // Empty iterator, nothing to merge.
             None => return Ok(()),
@@ -596,23 +1073,22 @@ impl<'a> Block<'a> {
             (
                 Block::ToolUse {
                     call: tool::Use { input, .. },
+                }
+                | Block::ServerToolUse {
+                    call: tool::Use { input, .. },
                 },
                 Delta::Json { partial_json },
             ) => {
-                use serde_json::Value::Object;
-                // Parse the partial json as an object and merge it into the
-                // input.
-                let partial_json: serde_json::Value =
-                    serde_json::from_str(&partial_json).map_err(|e| {
-                        DeltaError::Parse {
-                            error: format!(
-                        "Could not merge partial json `{}` into `{}` because {}",
-                        partial_json, input, e
-                    ),
-                        }
-                    })?;
-                if let (Object(new), Object(old)) = (partial_json, input) {
-                    old.extend(new);
+                // Append to the JSON so far. It is parsed by `finish`, since
+                // partial JSON can't be.
+                match input {
+                    serde_json::Value::String(json) => {
+                        json.push_str(&partial_json)
+                    }
+                    input => {
+                        *input =
+                            serde_json::Value::String(partial_json.into_owned())
+                    }
                 }
             }
             (this, acc) => {
@@ -621,6 +1097,17 @@ impl<'a> Block<'a> {
                     Block::ToolUse { .. } => stringify!(Block::ToolUse),
                     Block::ToolResult { .. } => stringify!(Block::ToolResult),
                     Block::Image { .. } => stringify!(Block::Image),
+                    Block::Document { .. } => stringify!(Block::Document),
+                    Block::ServerToolUse { .. } => {
+                        stringify!(Block::ServerToolUse)
+                    }
+                    Block::WebSearchToolResult { .. } => {
+                        stringify!(Block::WebSearchToolResult)
+                    }
+                    Block::CodeExecutionToolResult { .. } => {
+                        stringify!(Block::CodeExecutionToolResult)
+                    }
+                    Block::Unknown(_) => stringify!(Block::Unknown),
                 };
 
                 return Err(ContentMismatch {
@@ -634,25 +1121,81 @@ impl<'a> Block<'a> {
         Ok(())
     }
 
+    /// Parse the JSON text from [`merge_deltas`] into the `input` of a
+    /// [`Block::ToolUse`] or [`Block::ServerToolUse`]. Call this when the
+    /// [`Block`] stops. Empty JSON is an empty object. Other [`Block`]s are
+    /// unchanged.
+    ///
+    /// [`merge_deltas`]: Block::merge_deltas
+    pub fn finish(&mut self) -> Result<(), DeltaError<'a>> {
+        let (Block::ToolUse { call } | Block::ServerToolUse { call }) = self
+        else {
+            return Ok(());
+        };
+        let serde_json::Value::String(json) = &call.input else {
+            return Ok(());
+        };
+
+        call.input = if json.trim().is_empty() {
+            serde_json::Value::Object(Default::default())
+        } else {
+            serde_json::from_str(json).map_err(|e| DeltaError::Parse {
+                error: format!(
+                    "Could not parse partial json `{}` because {}",
+                    json, e
+                ),
+            })?
+        };
+
+        Ok(())
+    }
+
     /// Create a cache breakpoint at this block. See [`Prompt::cache`] for more
     /// information.
     ///
     /// [`Prompt::cache`]: crate::Prompt::cache
     #[cfg(feature = "prompt-caching")]
     pub fn cache(&mut self) {
+        match self.cache_control_mut() {
+            Some(cache_control) => {
+                *cache_control = Some(CacheControl::Ephemeral);
+            }
+            None => {
+                #[cfg(feature = "log")]
+                log::warn!("Server generated blocks cannot be cached.");
+            }
+        }
+    }
+
+    /// Remove the cache breakpoint at this block, if any.
+    #[cfg(feature = "prompt-caching")]
+    pub fn uncache(&mut self) {
+        if let Some(cache_control) = self.cache_control_mut() {
+            *cache_control = None;
+        }
+    }
+
+    /// The `cache_control` of the block, or [`None`] if it can't be cached.
+    #[cfg(feature = "prompt-caching")]
+    fn cache_control_mut(&mut self) -> Option<&mut Option<CacheControl>> {
         use crate::tool;
 
         match self {
             Self::Text { cache_control, .. }
             | Self::Image { cache_control, .. }
+            | Self::Document { cache_control, .. }
             | Self::ToolUse {
                 call: tool::Use { cache_control, .. },
             }
+            | Self::ServerToolUse {
+                call: tool::Use { cache_control, .. },
+            }
             | Self::ToolResult {
                 result: tool::Result { cache_control, .. },
-            } => {
-                *cache_control = Some(CacheControl::Ephemeral);
-            }
+            } => Some(cache_control),
+            Self::WebSearchToolResult { .. }
+            | Self::CodeExecutionToolResult { .. }
+            | Self::Unknown(_) => None,
         }
     }
 
@@ -664,18 +1207,25 @@ impl<'a> Block<'a> {
         match self {
             Self::Text { cache_control, .. }
             | Self::Image { cache_control, .. }
+            | Self::Document { cache_control, .. }
             | Self::ToolUse {
                 call: tool::Use { cache_control, .. },
             }
+            | Self::ServerToolUse {
+                call: tool::Use { cache_control, .. },
+            }
             | Self::ToolResult {
                 result: tool::Result { cache_control, .. },
             } => cache_control.is_some(),
+            Self::WebSearchToolResult { .. }
+            | Self::CodeExecutionToolResult { .. }
+            | Self::Unknown(_) => false,
         }
     }
 
     /// Returns the [`tool::Use`] if this is a [`Block::ToolUse`]. See also
     /// [`response::Message::tool_use`].
-    pub fn tool_use(&self) -> Option<&crate::tool::Use> {
+    pub fn tool_use(&self) -> Option<&crate::tool::Use<'_>> {
         match self {
             Self::ToolUse { call, .. } => Some(call),
             _ => None,
@@ -705,7 +1255,16 @@ impl<'a> Block<'a> {
                 #[cfg(feature = "prompt-caching")]
                 cache_control,
             } => Block::Image {
-                image: image.into_static(),
+                image,
+                #[cfg(feature = "prompt-caching")]
+                cache_control,
+            },
+            Self::Document {
+                document,
+                #[cfg(feature = "prompt-caching")]
+                cache_control,
+            } => Block::Document {
+                document: document.into_static(),
                 #[cfg(feature = "prompt-caching")]
                 cache_control,
             },
@@ -715,6 +1274,24 @@ impl<'a> Block<'a> {
             Self::ToolResult { result } => Block::ToolResult {
                 result: result.into_static(),
             },
+            Self::ServerToolUse { call } => Block::ServerToolUse {
+                call: call.into_static(),
+            },
+            Self::WebSearchToolResult {
+                tool_use_id,
+                content,
+            } => Block::WebSearchToolResult {
+                tool_use_id: std::borrow::Cow::Owned(tool_use_id.into_owned()),
+                content,
+            },
+            Self::CodeExecutionToolResult {
+                tool_use_id,
+                content,
+            } => Block::CodeExecutionToolResult {
+                tool_use_id: std::borrow::Cow::Owned(tool_use_id.into_owned()),
+                content,
+            },
+            Self::Unknown(value) => Block::Unknown(value),
         }
     }
 
@@ -723,10 +1300,15 @@ impl<'a> Block<'a> {
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         match self {
-            Self::Text { text, .. } => text.as_bytes().len(),
+            Self::Text { text, .. } => text.len(),
             Self::Image { image, .. } => image.len(),
+            Self::Document { document, .. } => document.len(),
             Self::ToolUse { .. } => 0,
             Self::ToolResult { .. } => 0,
+            Self::ServerToolUse { .. } => 0,
+            Self::WebSearchToolResult { .. } => 0,
+            Self::CodeExecutionToolResult { .. } => 0,
+            Self::Unknown(_) => 0,
         }
     }
 }
@@ -757,7 +1339,7 @@ impl crate::markdown::ToMarkdown for Block<'_> {
                     Some(Event::Text(image.to_string().into())).into_iter(),
                 )
             }
-            Block::ToolUse { .. } => {
+            Block::ToolUse { .. } | Block::ServerToolUse { .. } => {
                 if options.tool_use {
                     Box::new(
                         [
@@ -775,7 +1357,9 @@ impl crate::markdown::ToMarkdown for Block<'_> {
                     Box::new(std::iter::empty())
                 }
             }
-            Block::ToolResult { .. } => {
+            Block::ToolResult { .. }
+            | Block::WebSearchToolResult { .. }
+            | Block::CodeExecutionToolResult { .. } => {
                 if options.tool_results {
                     Box::new(
                         [
@@ -793,6 +1377,11 @@ impl crate::markdown::ToMarkdown for Block<'_> {
                     Box::new(std::iter::empty())
                 }
             }
+            // Documents are context, not conversation, and we don't know how
+            // to render unknown blocks, so we don't.
+            Block::Document { .. } | Block::Unknown(_) => {
+                Box::new(std::iter::empty())
+            }
         };
 
         it
@@ -825,8 +1414,8 @@ impl<'a> From<crate::CowStr<'a>> for Block<'a> {
     }
 }
 
-impl<'a> From<Image<'a>> for Block<'a> {
-    fn from(image: Image<'a>) -> Self {
+impl From<Image> for Block<'_> {
+    fn from(image: Image) -> Self {
         Self::Image {
             image,
             #[cfg(feature = "prompt-caching")]
@@ -835,6 +1424,16 @@ impl<'a> From<Image<'a>> for Block<'a> {
     }
 }
 
+impl<'a> From<Document<'a>> for Block<'a> {
+    fn from(document: Document<'a>) -> Self {
+        Self::Document {
+            document,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }
+    }
+}
+
 impl<'a> From<tool::Use<'a>> for Block<'a> {
     fn from(call: tool::Use<'a>) -> Self {
         Self::ToolUse { call }
@@ -857,7 +1456,7 @@ impl From<image::RgbaImage> for Block<'_> {
             .unwrap_or_else(|e| {
                 #[cfg(feature = "log")]
                 log::error!("Error encoding image: {}", e);
-                Image::from_parts(MediaType::Png, String::new())
+                Image::from_compressed(MediaType::Png, bytes::Bytes::new())
             })
             .into()
     }
@@ -882,50 +1481,197 @@ pub enum CacheControl {
     Ephemeral,
 }
 
-/// Image content for [`MultiPart`] [`Message`]s.
-///
-/// [`MultiPart`]: Content::MultiPart
+/// A document for the model to use as context, such as a search result. See
+/// [`Block::Document`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Document<'a> {
+    /// Content of the document.
+    pub source: DocumentSource<'a>,
+    /// Title of the document, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<Cow<'a, str>>,
+    /// Context about the document which is not part of it, such as where it
+    /// came from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Cow<'a, str>>,
+}
+
+impl<'a> Document<'a> {
+    /// A plain text document.
+    pub fn text<T>(text: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        Self {
+            source: DocumentSource::Text {
+                media_type: Cow::Borrowed("text/plain"),
+                data: text.into(),
+            },
+            title: None,
+            context: None,
+        }
+    }
+
+    /// A PDF document hosted at a URL, which the API fetches.
+    pub fn url<U>(url: U) -> Self
+    where
+        U: Into<Cow<'a, str>>,
+    {
+        Self {
+            source: DocumentSource::Url { url: url.into() },
+            title: None,
+            context: None,
+        }
+    }
+
+    /// Set the [`title`].
+    ///
+    /// [`title`]: Document::title
+    pub fn with_title<T>(mut self, title: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Set the [`context`].
+    ///
+    /// [`context`]: Document::context
+    pub fn with_context<T>(mut self, context: T) -> Self
+    where
+        T: Into<Cow<'a, str>>,
+    {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Returns the number of bytes of text in the document. Documents hosted
+    /// at a URL have no length.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        match &self.source {
+            DocumentSource::Text { data, .. } => data.len(),
+            DocumentSource::Url { .. } => 0,
+        }
+    }
+
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Document<'static> {
+        Document {
+            source: match self.source {
+                DocumentSource::Text { media_type, data } => {
+                    DocumentSource::Text {
+                        media_type: Cow::Owned(media_type.into_owned()),
+                        data: Cow::Owned(data.into_owned()),
+                    }
+                }
+                DocumentSource::Url { url } => DocumentSource::Url {
+                    url: Cow::Owned(url.into_owned()),
+                },
+            },
+            title: self.title.map(|t| Cow::Owned(t.into_owned())),
+            context: self.context.map(|c| Cow::Owned(c.into_owned())),
+        }
+    }
+}
+
+/// Source of a [`Document`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type")]
+pub enum DocumentSource<'a> {
+    /// Text data, such as `text/plain`.
+    Text {
+        /// Media type of the text.
+        media_type: Cow<'a, str>,
+        /// The text.
+        data: Cow<'a, str>,
+    },
+    /// A PDF hosted at a URL, which the API fetches.
+    Url {
+        /// URL of the PDF.
+        url: Cow<'a, str>,
+    },
+}
+
+/// Image content for [`Message`]s.
 #[derive(Clone, Debug, Serialize, Deserialize, derive_more::Display)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
-pub enum Image<'a> {
-    /// Base64 encoded image data. When displayed, it will be rendered as a
-    /// markdown image with embedded data.
-    #[display("![Image](data:{media_type};base64,{data})")]
+pub enum Image {
+    /// Compressed image data, which is base64 encoded for the API. When
+    /// displayed, it will be rendered as a markdown image with embedded data.
+    #[display(
+        "![Image](data:{media_type};base64,{})",
+        base64::display::Base64Display::new(data, &general_purpose::STANDARD)
+    )]
     Base64 {
         /// Image encoding format.
         media_type: MediaType,
-        /// Base64 encoded compressed image data.
-        data: crate::CowStr<'a>,
+        /// Compressed image data (not base64 encoded). It is encoded only
+        /// when serialized or displayed, and [`Clone`]s share it.
+        #[serde(with = "base64_bytes")]
+        data: bytes::Bytes,
+    },
+    /// An image hosted at a URL, which the API fetches. When displayed, it
+    /// will be rendered as a markdown image with the URL.
+    #[display("![Image]({url})")]
+    Url {
+        /// URL of the image.
+        url: String,
     },
 }
 
-impl Image<'_> {
+impl Image {
+    /// Recommended maximum length of the long edge of an [`Image`]. Larger
+    /// images are downscaled by the API, which adds latency.
+    pub const MAX_DIMENSION: u32 = 1568;
+
+    /// Maximum size of compressed [`Image`] data accepted by the API. This is
+    /// 5 MiB once base64 encoded.
+    pub const MAX_BYTES: usize = 5 * 1024 * 1024 / 4 * 3;
+
     /// From raw parts. The data is expected to be base64 encoded compressed
-    /// image data or the API will reject it.
-    pub fn from_parts(media_type: MediaType, data: String) -> Self {
-        Self::Base64 {
+    /// image data. It is decoded, so this fails if it is not valid base64.
+    pub fn from_parts<D>(
+        media_type: MediaType,
+        data: D,
+    ) -> Result<Self, base64::DecodeError>
+    where
+        D: AsRef<[u8]>,
+    {
+        Ok(Self::Base64 {
             media_type,
-            data: data.into(),
-        }
+            data: general_purpose::STANDARD.decode(data)?.into(),
+        })
     }
 
-    /// Encode from compressed image data (not base64 encoded). This cannot fail
-    /// but if the data is invalid, the API will reject it.
+    /// From compressed image data (not base64 encoded). This cannot fail but
+    /// if the data is invalid, the API will reject it. [`Vec<u8>`] and
+    /// [`bytes::Bytes`] are not copied.
     pub fn from_compressed<D>(format: MediaType, data: D) -> Self
     where
-        D: AsRef<[u8]>,
+        D: Into<bytes::Bytes>,
     {
-        let data: &[u8] = data.as_ref();
-        let encoder = general_purpose::STANDARD;
-
         Self::Base64 {
             media_type: format,
-            data: encoder.encode(data).into(),
+            data: data.into(),
         }
     }
 
+    /// From a URL. The API fetches the image, so it is not validated here.
+    pub fn from_url<U>(url: U) -> Self
+    where
+        U: Into<String>,
+    {
+        Self::Url { url: url.into() }
+    }
+
     /// Encode an [`Image`] from any type that can be converted into an
     /// [`image::RgbaImage`].
     #[cfg(feature = "image")]
@@ -942,7 +1688,73 @@ impl Image<'_> {
         Ok(Self::from_compressed(format, cursor.into_inner()))
     }
 
-    /// Decode the image data into an [`image::RgbaImage`].
+    /// Encode an [`Image`], downscaling it so the long edge is at most
+    /// `max_dimension` pixels and the compressed data is at most `max_bytes`.
+    /// Use [`Image::MAX_DIMENSION`] and [`Image::MAX_BYTES`] for the API's
+    /// recommendations and limits. A warning is logged if the image must be
+    /// downscaled further to fit `max_bytes`.
+    ///
+    /// JPEG images are encoded without alpha since JPEG does not support it.
+    #[cfg(feature = "image")]
+    pub fn encode_fit<I>(
+        format: MediaType,
+        image: I,
+        max_dimension: u32,
+        max_bytes: usize,
+    ) -> Result<Self, ImageEncodeError>
+    where
+        I: Into<image::RgbaImage>,
+    {
+        use image::{imageops::FilterType, DynamicImage};
+
+        let mut image = DynamicImage::ImageRgba8(image.into());
+        let max_dimension = max_dimension.max(1);
+        if image.width().max(image.height()) > max_dimension {
+            image = image.resize(
+                max_dimension,
+                max_dimension,
+                FilterType::Lanczos3,
+            );
+        }
+
+        // Each attempt downscales by the square root of the excess, since
+        // the compressed size is roughly proportional to the area, with a
+        // margin since it's only roughly.
+        for _ in 0..8 {
+            let mut cursor = std::io::Cursor::new(Vec::new());
+            match format {
+                MediaType::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+                    .write_to(&mut cursor, format.into())?,
+                _ => image.write_to(&mut cursor, format.into())?,
+            }
+            let data = cursor.into_inner();
+            if data.len() <= max_bytes {
+                return Ok(Self::from_compressed(format, data));
+            }
+
+            let (width, height) = (image.width(), image.height());
+            if width <= 1 && height <= 1 {
+                break;
+            }
+            let scale = (max_bytes as f64 / data.len() as f64).sqrt() * 0.9;
+            let width = ((width as f64 * scale) as u32).clamp(1, width - 1);
+            let height = ((height as f64 * scale) as u32).max(1);
+
+            #[cfg(feature = "log")]
+            log::warn!(
+                "{format} image is {} bytes, more than {max_bytes}. Downscaling from {}x{} to {width}x{height}.",
+                data.len(),
+                image.width(),
+                image.height(),
+            );
+            image = image.resize_exact(width, height, FilterType::Lanczos3);
+        }
+
+        Err(ImageEncodeError::TooLarge { max_bytes })
+    }
+
+    /// Decode the image data into an [`image::RgbaImage`]. [`Image::Url`]s
+    /// are not fetched, so they can't be decoded.
     ///
     /// # Note:
     /// - There is also a [`TryInto`] implementation for this.
@@ -950,56 +1762,122 @@ impl Image<'_> {
     pub fn decode(&self) -> Result<image::RgbaImage, ImageDecodeError> {
         match self {
             Self::Base64 { data, .. } => {
-                let data = general_purpose::STANDARD.decode(data.as_bytes())?;
-                Ok(image::load_from_memory(&data)?.to_rgba8())
+                Ok(image::load_from_memory(data)?.to_rgba8())
             }
+            Self::Url { .. } => Err(ImageDecodeError::Url),
         }
     }
 
-    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
-    /// fields.
-    ///
-    /// [`Cow`]: std::borrow::Cow
-    pub fn into_static(self) -> Image<'static> {
+    /// Returns the compressed image data, or [`None`] for an [`Image::Url`].
+    pub fn data(&self) -> Option<&bytes::Bytes> {
         match self {
-            Self::Base64 { media_type, data } => Image::Base64 {
-                media_type,
-                #[cfg(not(feature = "langsan"))]
-                data: std::borrow::Cow::Owned(data.into_owned()),
-                #[cfg(feature = "langsan")]
-                data: data.into_static(),
-            },
+            Self::Base64 { data, .. } => Some(data),
+            Self::Url { .. } => None,
+        }
+    }
+
+    /// Returns the URL of an [`Image::Url`].
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Self::Base64 { .. } => None,
+            Self::Url { url } => Some(url),
         }
     }
 
-    /// Returns the number of bytes in the image data (base64 encoded). Call
-    /// [`decode`] to get the actual image size.
+    /// Returns the number of bytes in the image data once base64 encoded, or
+    /// the length of the URL for an [`Image::Url`]. Call `decode` (with the
+    /// `image` feature) to get the actual image size.
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         match self {
-            Self::Base64 { data, .. } => data.as_bytes().len(),
+            Self::Base64 { data, .. } => {
+                base64::encoded_len(data.len(), true).unwrap_or(usize::MAX)
+            }
+            Self::Url { url } => url.len(),
+        }
+    }
+}
+
+/// Serialize [`bytes::Bytes`] as a base64 string without an intermediate
+/// copy, and deserialize a base64 string into [`bytes::Bytes`].
+mod base64_bytes {
+    use base64::{
+        display::Base64Display,
+        engine::{general_purpose::STANDARD, Engine as _},
+    };
+
+    pub fn serialize<S>(
+        data: &bytes::Bytes,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&Base64Display::new(data, &STANDARD))
+    }
+
+    pub fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> Result<bytes::Bytes, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = bytes::Bytes;
+
+            fn expecting(
+                &self,
+                f: &mut std::fmt::Formatter,
+            ) -> std::fmt::Result {
+                f.write_str("base64 encoded data")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                STANDARD.decode(s).map(Into::into).map_err(E::custom)
+            }
         }
+
+        deserializer.deserialize_str(Visitor)
     }
 }
 
+/// Errors that can occur when encoding an [`Image`] with
+/// [`Image::encode_fit`].
+#[cfg(feature = "image")]
+#[derive(Debug, thiserror::Error)]
+pub enum ImageEncodeError {
+    /// The image could not be encoded.
+    #[error("Image encode error: {0}")]
+    Image(#[from] image::ImageError),
+    /// The image could not be made small enough.
+    #[error("Image could not be downscaled to fit in {max_bytes} bytes.")]
+    #[allow(missing_docs)]
+    TooLarge { max_bytes: usize },
+}
+
 /// Errors that can occur when decoding an [`Image`].
 #[cfg(feature = "image")]
 #[derive(Debug, thiserror::Error)]
 pub enum ImageDecodeError {
-    /// Invalid base64 encoding.
-    #[error("Base64 decode error: {0}")]
-    Base64(#[from] base64::DecodeError),
     /// Invalid image data.
     #[error("Image decode error: {0}")]
     Image(#[from] image::ImageError),
+    /// The image is a URL, which must be fetched first.
+    #[error("Image is a URL, which must be fetched before decoding.")]
+    Url,
 }
 
 #[cfg(feature = "image")]
-impl TryInto<image::RgbaImage> for Image<'_> {
+impl TryInto<image::RgbaImage> for Image {
     type Error = ImageDecodeError;
 
     /// An [`Image`] can be decoded into an [`image::RgbaImage`] if it is valid
-    /// base64 encoded compressed image data and the image format is supported.
+    /// compressed image data and the image format is supported.
     fn try_into(self) -> Result<image::RgbaImage, Self::Error> {
         self.decode()
     }
@@ -1092,6 +1970,34 @@ mod tests {
         assert_eq!(Role::Assistant.to_string(), "Assistant");
     }
 
+    #[test]
+    fn test_transcript_role() {
+        let result = Message::tool_results([tool::Result {
+            tool_use_id: "toolu_1".into(),
+            content: "42".into(),
+            is_error: false,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }]);
+        assert_eq!(TranscriptRole::of(&result), TranscriptRole::Tool);
+        assert_eq!(
+            TranscriptRole::of(&Message::assistant("Hi!")),
+            TranscriptRole::Assistant
+        );
+
+        // Every role converts back to the role it is sent as.
+        for role in [Role::User, Role::Assistant] {
+            assert_eq!(TranscriptRole::from(role).role(), Some(role));
+        }
+        assert_eq!(TranscriptRole::of(&result).role(), Some(result.role));
+        assert_eq!(TranscriptRole::System.role(), None);
+        assert_eq!(TranscriptRole::Tool.to_string(), "Tool");
+        assert_eq!(
+            serde_json::to_value(TranscriptRole::System).unwrap(),
+            "system"
+        );
+    }
+
     #[test]
     fn deserialize_content() {
         let content: Content = serde_json::from_str(CONTENT_SINGLE).unwrap();
@@ -1100,6 +2006,39 @@ mod tests {
         assert_eq!(content.to_string(), "Hello, world!\n\nHow are you?");
     }
 
+    #[test]
+    fn serialize_content() {
+        // A single text block is sent as a plain string.
+        let content: Content = serde_json::from_str(CONTENT_SINGLE).unwrap();
+        assert_eq!(content, Content::text("Hello, world!"));
+        assert_eq!(serde_json::to_string(&content).unwrap(), CONTENT_SINGLE);
+        let content = Content::from(Block::text("Hello, world!"));
+        assert_eq!(serde_json::to_string(&content).unwrap(), CONTENT_SINGLE);
+
+        let content: Content = serde_json::from_str(CONTENT_MULTI).unwrap();
+        assert_eq!(
+            serde_json::to_value(&content).unwrap(),
+            serde_json::from_str::<serde_json::Value>(CONTENT_MULTI).unwrap()
+        );
+
+        assert_eq!(serde_json::to_string(&Content::default()).unwrap(), "[]");
+
+        // A cache breakpoint needs a block.
+        #[cfg(feature = "prompt-caching")]
+        {
+            let mut content = Content::text("Hello, world!");
+            content.cache();
+            assert_eq!(
+                serde_json::to_value(&content).unwrap(),
+                serde_json::json!([{
+                    "type": "text",
+                    "text": "Hello, world!",
+                    "cache_control": {"type": "ephemeral"}
+                }])
+            );
+        }
+    }
+
     pub const MESSAGE_JSON_SINGLE: &str =
         r#"{"role": "user", "content": "Hello, world"}"#;
 
@@ -1136,7 +2075,7 @@ mod tests {
         assert!(!message.is_empty());
         let message: Message = Message {
             role: Role::User,
-            content: Content::MultiPart(vec![]),
+            content: Content::default(),
         };
         assert!(message.is_empty());
     }
@@ -1163,16 +2102,11 @@ mod tests {
         let content: Content<'static> = content.into_static();
         assert_eq!(content.to_string(), "Hello, world!");
 
-        let content = Content::SinglePart("Hello, world!".into());
-        let content: Content<'static> = content.into_static();
-        assert_eq!(content.to_string(), "Hello, world!");
-
         let block: Block = "Hello, world!".into();
         let block: Block<'static> = block.into_static();
         assert_eq!(block.to_string(), "Hello, world!");
 
-        let image: Image = Image::from_parts(MediaType::Png, String::new());
-        let image: Image<'static> = image.into_static();
+        let image: Image = Image::from_parts(MediaType::Png, "").unwrap();
         assert_eq!(image.to_string(), "![Image](data:image/png;base64,)");
 
         let tool_use: Block = tool::Use {
@@ -1195,7 +2129,7 @@ mod tests {
 
     #[test]
     fn test_push_delta() {
-        let mut content = Content::SinglePart("Hello, world!".into());
+        let mut content = Content::text("Hello, world!");
         content
             .push_delta(Delta::Text {
                 text: " How are you?".into(),
@@ -1203,13 +2137,18 @@ mod tests {
             .unwrap();
 
         assert_eq!(content.to_string(), "Hello, world! How are you?");
-        assert!(content.is_multi_part());
 
         // an incompatible delta
         let err = content.push_delta(Delta::Json {
             partial_json: "blabla".into(),
         });
         assert!(err.is_err());
+
+        // nothing to apply it to
+        let err = Content::default()
+            .push_delta(Delta::Text { text: "Hi".into() })
+            .unwrap_err();
+        assert!(matches!(err, DeltaError::OutOfBounds { .. }));
     }
 
     #[test]
@@ -1252,6 +2191,7 @@ mod tests {
         }];
 
         block.merge_deltas(deltas).unwrap();
+        block.finish().unwrap();
 
         // by default tool use is hidden
         let opts = crate::markdown::Options::default().with_tool_use();
@@ -1263,14 +2203,38 @@ mod tests {
             "\n````json\n{\"type\":\"tool_use\",\"id\":\"tool_123\",\"name\":\"tool\",\"input\":{\"key\":\"value\"}}\n````"
         );
 
+        // deltas can be merged across calls
+        for partial_json in [r#"{"key": "#, r#""value2"}"#] {
+            block
+                .merge_deltas([Delta::Json {
+                    partial_json: partial_json.into(),
+                }])
+                .unwrap();
+        }
+        block.finish().unwrap();
+        assert_eq!(
+            block.tool_use().unwrap().input,
+            serde_json::json!({"key": "value2"})
+        );
+
+        // no input at all
+        block
+            .merge_deltas([Delta::Json {
+                partial_json: "".into(),
+            }])
+            .unwrap();
+        block.finish().unwrap();
+        assert_eq!(block.tool_use().unwrap().input, serde_json::json!({}));
+
         // test junk json
         let deltas = [Delta::Json {
             partial_json: "blabla".into(),
         }];
-        let err = block.merge_deltas(deltas).unwrap_err();
+        block.merge_deltas(deltas).unwrap();
+        let err = block.finish().unwrap_err();
         assert_eq!(
             err.to_string(),
-            "Cannot apply delta because deserialization failed because: Could not merge partial json `blabla` into `{\"key\":\"value\"}` because expected value at line 1 column 1"
+            "Cannot apply delta because deserialization failed because: Could not parse partial json `blabla` because expected value at line 1 column 1"
         );
 
         // content mismatch
@@ -1294,7 +2258,7 @@ mod tests {
     fn test_message_len() {
         let mut message = Message {
             role: Role::User,
-            content: Content::SinglePart("Hello, world!".into()),
+            content: Content::text("Hello, world!"),
         };
 
         assert_eq!(message.len(), 13);
@@ -1316,6 +2280,7 @@ mod tests {
             stop_reason: None,
             stop_sequence: None,
             usage: Default::default(),
+            container: None,
         };
 
         let message: Message = response.into();
@@ -1340,26 +2305,13 @@ mod tests {
 
     #[test]
     fn test_content_is_empty() {
-        let mut content = Content::SinglePart("Hello, world!".into());
+        let mut content = Content::text("Hello, world!");
         assert!(!content.is_empty());
 
-        content = Content::MultiPart(vec![]);
+        content = Content::default();
         assert!(content.is_empty());
     }
 
-    #[test]
-    fn tests_content_unwrap_single_part() {
-        let content = Content::SinglePart("Hello, world!".into());
-        assert_eq!(content.unwrap_single_part().to_string(), "Hello, world!");
-    }
-
-    #[test]
-    #[should_panic]
-    fn test_content_unwrap_single_part_panics() {
-        let content = Content::MultiPart(vec![]);
-        content.unwrap_single_part();
-    }
-
     #[test]
     fn test_content_from_string() {
         let content: Content = "Hello, world!".to_string().into();
@@ -1378,6 +2330,38 @@ mod tests {
         assert_eq!(content.to_string(), "Hello, world!");
     }
 
+    #[test]
+    fn test_content_indexing() {
+        let mut content = Content::text("Hello");
+        assert_eq!(content.get(0), Some(&Block::text("Hello")));
+        assert_eq!(content.last(), Some(&Block::text("Hello")));
+
+        if let Some(Block::Text { text, .. }) = content.get_mut(0) {
+            *text = "Hi".into();
+        }
+        assert_eq!(content.get(0), Some(&Block::text("Hi")));
+        assert!(content.get_mut(1).is_none());
+
+        content.push(Block::text(", world"));
+        content.push(Block::text("!"));
+        for block in content.iter_mut() {
+            if let Block::Text { text, .. } = block {
+                *text = text.to_uppercase().into();
+            }
+        }
+        let texts: Vec<String> =
+            content.iter().map(|block| block.to_string()).collect();
+        assert_eq!(texts, ["HI", ", WORLD", "!"]);
+
+        assert_eq!(content.remove(1), Some(Block::text(", WORLD")));
+        assert_eq!(content.remove(2), None);
+        assert_eq!(content.iter().count(), 2);
+
+        let mut content = Content::text("Hello");
+        assert_eq!(content.remove(0), Some(Block::text("Hello")));
+        assert!(content.is_empty());
+    }
+
     #[test]
     #[cfg(feature = "markdown")]
     fn test_merge_deltas_error() {
@@ -1404,6 +2388,7 @@ mod tests {
         }];
 
         json_block.merge_deltas(json_deltas).unwrap();
+        json_block.finish().unwrap();
         assert_eq!(
             json_block.markdown_verbose().as_ref(),
             "\n````json\n{\"type\":\"tool_use\",\"id\":\"tool_123\",\"name\":\"tool\",\"input\":{\"k\":\"v\"}}\n````"
@@ -1420,7 +2405,7 @@ mod tests {
         // test user heading, single part
         let message = Message {
             role: Role::User,
-            content: Content::SinglePart("Hello, world!".into()),
+            content: Content::text("Hello, world!"),
         };
 
         let opts = crate::markdown::Options::default()
@@ -1435,7 +2420,7 @@ mod tests {
         // test assistant heading, multi part
         let message = Message {
             role: Role::Assistant,
-            content: Content::MultiPart(vec![
+            content: Content(vec![
                 "Hello, world!".into(),
                 "How are you?".into(),
             ]),
@@ -1449,7 +2434,7 @@ mod tests {
         // Test tool result (success)
         let message: Message = tool::Result {
             tool_use_id: "tool_123".into(),
-            content: Content::SinglePart("Hello, world!".into()),
+            content: Content::text("Hello, world!"),
             is_error: false,
             #[cfg(feature = "prompt-caching")]
             cache_control: None,
@@ -1464,7 +2449,7 @@ mod tests {
         // Test tool result (error)
         let message: Message = tool::Result {
             tool_use_id: "tool_123".into(),
-            content: Content::SinglePart("Hello, world!".into()),
+            content: Content::text("Hello, world!"),
             is_error: true,
             #[cfg(feature = "prompt-caching")]
             cache_control: None,
@@ -1477,6 +2462,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_message_text() {
+        use std::borrow::Cow;
+
+        let message = Message {
+            role: Role::Assistant,
+            content: "Hello, **world**!".into(),
+        };
+        assert!(matches!(message.text(), Cow::Borrowed("Hello, **world**!")));
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content(vec![
+                tool::Use {
+                    id: "tool_123".into(),
+                    name: "tool".into(),
+                    input: serde_json::json!({}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                }
+                .into(),
+                "Hello, ".into(),
+            ]),
+        };
+        assert!(matches!(message.text(), Cow::Borrowed("Hello, ")));
+
+        let mut message = message;
+        message.content.push(Block::Unknown(serde_json::json!({})));
+        message.content.push("world");
+        message.content.push("!");
+        assert_eq!(message.text(), "Hello, world!");
+
+        let message = Message {
+            role: Role::User,
+            content: Content::default(),
+        };
+        assert_eq!(message.text(), "");
+    }
+
+    #[test]
+    fn test_strip_thoughts() {
+        assert_eq!(strip_thinking("No thoughts."), None);
+        assert_eq!(
+            strip_thinking("<thinking>Hmm.</thinking>\n\nHello!").unwrap(),
+            "Hello!"
+        );
+        assert_eq!(
+            strip_thinking("A <thinking>1</thinking> B <thinking>2").unwrap(),
+            "A B "
+        );
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content(vec![
+                "<thinking>Only thoughts.</thinking>".into(),
+                "<thinking>Let me see.</thinking> The answer is 42.".into(),
+                tool::Use {
+                    id: "tool_123".into(),
+                    name: "tool".into(),
+                    input: serde_json::json!({}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                }
+                .into(),
+            ]),
+        };
+        let stripped = message.without_thoughts();
+        assert_eq!(stripped.text(), "The answer is 42.");
+        let blocks = &stripped.content.0;
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[1].tool_use().is_some());
+
+        let content = Content::text("<thinking>Hmm.</thinking>Hi");
+        assert_eq!(content.strip_thoughts(), Content::text("Hi"));
+    }
+
+    #[test]
+    fn test_message_tool_uses() {
+        let call = |id: &'static str| tool::Use {
+            id: id.into(),
+            name: "tool".into(),
+            input: serde_json::json!({}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+
+        let message = Message {
+            role: Role::Assistant,
+            content: "No tools here.".into(),
+        };
+        assert_eq!(message.tool_uses().count(), 0);
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content(vec![
+                "Calling tools.".into(),
+                call("a").into(),
+                call("b").into(),
+            ]),
+        };
+        let ids: Vec<_> =
+            message.tool_uses().map(|call| call.id.as_ref()).collect();
+        assert_eq!(ids, ["a", "b"]);
+
+        let answer = Message::tool_results(message.tool_uses().map(|call| {
+            tool::Result {
+                tool_use_id: call.id.clone(),
+                content: "Done.".into(),
+                is_error: false,
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            }
+        }));
+        assert_eq!(answer.role, Role::User);
+        let blocks = &answer.content.0;
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().zip(["a", "b"]).all(|(block, id)| matches!(
+            block,
+            Block::ToolResult { result } if result.tool_use_id == id
+        )));
+    }
+
+    #[test]
+    fn test_message_builder() {
+        let message = Message::user("Hi.");
+        assert_eq!(message.role, Role::User);
+        assert_eq!(message.text(), "Hi.");
+
+        let call = tool::Use {
+            id: "a".into(),
+            name: "tool".into(),
+            input: serde_json::json!({}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+        let message = Message::assistant(Content::text("Calling a tool."))
+            .push_block(call.clone());
+        assert_eq!(message.role, Role::Assistant);
+        assert_eq!(
+            message.content,
+            Content(vec!["Calling a tool.".into(), call.into()])
+        );
+        assert_eq!(message.tool_use().unwrap().id, "a");
+    }
+
     #[test]
     fn test_block_tool_use() {
         let expected = tool::Use {
@@ -1494,6 +2624,157 @@ mod tests {
         assert_eq!(block.tool_use(), Some(&expected));
     }
 
+    #[test]
+    fn test_block_unknown() {
+        const RESPONSE: &str = r#"{"role":"assistant","content":[{"type":"text","text":"Hi"},{"type":"future_block","id":"future_1","input":{}}]}"#;
+
+        let message: Message = serde_json::from_str(RESPONSE).unwrap();
+        let blocks = &message.content.0;
+        let block = &blocks[1];
+        assert!(matches!(block, Block::Unknown(_)));
+        assert_eq!(block.len(), 0);
+        assert_eq!(block.to_string(), "");
+        assert_eq!(block.clone().into_static(), *block);
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::from_str::<serde_json::Value>(RESPONSE).unwrap()
+        );
+
+        #[cfg(feature = "prompt-caching")]
+        {
+            let mut block = block.clone();
+            block.cache();
+            assert!(!block.is_cached());
+        }
+
+        let mut block = block.clone();
+        assert!(matches!(
+            block.merge_deltas([Delta::Text { text: "".into() }]),
+            Err(DeltaError::ContentMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_block_malformed() {
+        // Known types with missing fields are errors, not unknown blocks.
+        for json in [
+            r#"{"type":"text"}"#,
+            r#"{"type":"tool_use","id":"toolu_1"}"#,
+            r#"{"type":"server_tool_use","id":"srvtoolu_1"}"#,
+        ] {
+            assert!(serde_json::from_str::<Block>(json).is_err(), "{json}");
+        }
+
+        const RESPONSE: &str = r#"{"role":"assistant","content":[{"type":"text","text":"Hi"},{"type":"image"}]}"#;
+        assert!(serde_json::from_str::<Message>(RESPONSE).is_err());
+    }
+
+    #[test]
+    fn test_block_code_execution_tool_result() {
+        use crate::tool::code_execution::{File, Outcome};
+
+        const JSON: &str = r#"{"type":"code_execution_tool_result","tool_use_id":"srvtoolu_1","content":{"type":"code_execution_result","stdout":"4\n","stderr":"","return_code":0,"content":[{"type":"code_execution_output","file_id":"file_1"}]}}"#;
+
+        let block: Block = serde_json::from_str(JSON).unwrap();
+        let Block::CodeExecutionToolResult {
+            tool_use_id,
+            content,
+        } = &block
+        else {
+            panic!("Expected code execution result.");
+        };
+        assert_eq!(tool_use_id, "srvtoolu_1");
+        assert!(content.is_success());
+        assert_eq!(
+            content,
+            &Outcome::Result {
+                stdout: "4\n".into(),
+                stderr: "".into(),
+                return_code: 0,
+                files: vec![File {
+                    file_id: "file_1".into()
+                }],
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::from_str::<serde_json::Value>(JSON).unwrap()
+        );
+
+        let block: Block = serde_json::from_str(
+            r#"{"type":"code_execution_tool_result","tool_use_id":"srvtoolu_2","content":{"type":"code_execution_tool_result_error","error_code":"unavailable"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            block,
+            Block::CodeExecutionToolResult {
+                content: Outcome::Error { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_block_web_search() {
+        use crate::tool::web_search::{Outcome, SearchError, SearchResult};
+
+        const RESPONSE: &str = r#"{"role":"assistant","content":[{"type":"server_tool_use","id":"srvtoolu_1","name":"web_search","input":{}},{"type":"web_search_tool_result","tool_use_id":"srvtoolu_1","content":[{"type":"web_search_result","url":"https://example.com","title":"Example","encrypted_content":"abc","page_age":"April 30, 2025"}]}]}"#;
+
+        let message: Message = serde_json::from_str(RESPONSE).unwrap();
+        let blocks = &message.content.0;
+        let Block::ServerToolUse { call } = &blocks[0] else {
+            panic!("Expected server tool use.");
+        };
+        assert_eq!(call.name, "web_search");
+        // Server tool uses are not for the client to run.
+        assert!(blocks[0].tool_use().is_none());
+
+        // The query is streamed as json deltas, like a regular tool use.
+        let mut block = blocks[0].clone();
+        block
+            .merge_deltas([Delta::Json {
+                partial_json: r#"{"query":"weather"}"#.into(),
+            }])
+            .unwrap();
+        block.finish().unwrap();
+        let Block::ServerToolUse { call } = &block else {
+            unreachable!();
+        };
+        assert_eq!(call.input, serde_json::json!({"query": "weather"}));
+
+        assert_eq!(
+            blocks[1],
+            Block::WebSearchToolResult {
+                tool_use_id: "srvtoolu_1".into(),
+                content: Outcome::Results(vec![SearchResult {
+                    url: "https://example.com".into(),
+                    title: "Example".into(),
+                    encrypted_content: "abc".into(),
+                    page_age: Some("April 30, 2025".into()),
+                }]),
+            }
+        );
+        assert_eq!(blocks[1].clone().into_static(), blocks[1]);
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::from_str::<serde_json::Value>(RESPONSE).unwrap()
+        );
+
+        let block: Block = serde_json::from_str(
+            r#"{"type":"web_search_tool_result","tool_use_id":"srvtoolu_2","content":{"type":"web_search_tool_result_error","error_code":"max_uses_exceeded"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            block,
+            Block::WebSearchToolResult {
+                tool_use_id: "srvtoolu_2".into(),
+                content: Outcome::Error(SearchError {
+                    error_code: "max_uses_exceeded".into(),
+                }),
+            }
+        );
+    }
+
     #[test]
     fn test_block_from_str() {
         let block: Block = "Hello, world!".into();
@@ -1509,11 +2790,38 @@ mod tests {
     #[test]
     #[cfg(feature = "png")]
     fn test_block_from_image() {
-        let image = Image::from_parts(MediaType::Png, "data".to_string());
+        let image = Image::from_parts(MediaType::Png, "data").unwrap();
         let block: Block = image.into();
         assert_eq!(block.to_string(), "![Image](data:image/png;base64,data)");
     }
 
+    #[test]
+    fn test_image_serde() {
+        let image = Image::from_compressed(MediaType::Png, vec![1, 2, 3]);
+        assert_eq!(image.len(), 4);
+
+        let json = serde_json::to_string(&image).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"base64","media_type":"image/png","data":"AQID"}"#
+        );
+        let parsed: Image = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, image);
+        assert_eq!(parsed.data().unwrap().as_ref(), &[1, 2, 3]);
+
+        // Clones share the data.
+        assert_eq!(
+            image.clone().data().unwrap().as_ptr(),
+            image.data().unwrap().as_ptr()
+        );
+
+        assert!(serde_json::from_str::<Image>(
+            r#"{"type":"base64","media_type":"image/png","data":"!"}"#
+        )
+        .is_err());
+        assert!(Image::from_parts(MediaType::Png, "!").is_err());
+    }
+
     // TODO: Image tests
     #[test]
     #[cfg(feature = "png")]
@@ -1531,6 +2839,87 @@ mod tests {
         assert!(matches!(block, Block::Image { .. }));
     }
 
+    #[test]
+    fn test_user_image() {
+        let image = Image::from_url("https://example.com/cat.png");
+        let message = Message::user_image(image.clone(), "What is this?");
+        assert_eq!(message.role, Role::User);
+        assert_eq!(
+            message.content,
+            Content(vec![image.into(), "What is this?".into()])
+        );
+
+        let mut content = Content::text("Two images.");
+        content.push_image_with_caption(
+            Block::image_url("https://example.com/dog.png"),
+            "A dog.",
+        );
+        assert_eq!(content.len(), 11 + 27 + 6);
+        assert_eq!(content.last().unwrap().to_string(), "A dog.");
+    }
+
+    #[test]
+    fn test_image_url() {
+        let block = Block::image_url("https://example.com/cat.png");
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"image","source":{"type":"url","url":"https://example.com/cat.png"}}"#
+        );
+        let parsed: Block = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, block);
+
+        let image = Image::from_url("https://example.com/cat.png");
+        assert_eq!(image.url(), Some("https://example.com/cat.png"));
+        assert!(image.data().is_none());
+        assert_eq!(image.len(), 27);
+        assert_eq!(image.to_string(), "![Image](https://example.com/cat.png)");
+        #[cfg(feature = "image")]
+        assert!(matches!(image.decode(), Err(ImageDecodeError::Url)));
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn test_image_encode_fit() {
+        let gradient = image::RgbaImage::from_fn(2000, 1000, |x, y| {
+            image::Rgba([x as u8, y as u8, 0, 255])
+        });
+        let image = Image::encode_fit(
+            MediaType::Png,
+            gradient,
+            Image::MAX_DIMENSION,
+            Image::MAX_BYTES,
+        )
+        .unwrap();
+        assert_eq!(image.decode().unwrap().dimensions(), (1568, 784));
+
+        // Noise, which compresses poorly.
+        let mut state = 1u32;
+        let noise = image::RgbaImage::from_fn(400, 200, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            image::Rgba(state.to_le_bytes())
+        });
+
+        let image = Image::encode_fit(
+            MediaType::Png,
+            noise.clone(),
+            Image::MAX_DIMENSION,
+            100_000,
+        )
+        .unwrap();
+        assert!(image.data().unwrap().len() <= 100_000);
+        let (width, height) = image.decode().unwrap().dimensions();
+        assert!(width < 400);
+        assert!((width / height).abs_diff(2) <= 1);
+
+        assert!(matches!(
+            Image::encode_fit(MediaType::Png, noise, 1568, 10),
+            Err(ImageEncodeError::TooLarge { max_bytes: 10 })
+        ));
+    }
+
     #[test]
     #[cfg(feature = "png")]
     fn test_image_from_compressed() {
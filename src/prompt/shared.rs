@@ -0,0 +1,146 @@
+//! [`SharedPrompt`] for cheap "clone and tweak" variants of a large
+//! [`Prompt`].
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::{Message, Prompt};
+use crate::client::Overrides;
+
+/// A [`Prompt`] shared with an [`Arc`], so clones are cheap no matter how
+/// large the prompt is. Variants are made by adding [`Overrides`] and
+/// messages to a clone instead of modifying the prompt. They are applied
+/// when it is serialized, which happens once per request anyway.
+///
+/// ```
+/// use misanthropic::{
+///     client::Overrides,
+///     prompt::{Message, OutOfRange, SharedPrompt, Temperature},
+///     Model, Prompt,
+/// };
+///
+/// let base = SharedPrompt::new(
+///     Prompt::default().add_message(Message::user("A very long document...")),
+/// );
+/// let variants = [0.0, 0.5, 1.0]
+///     .into_iter()
+///     .map(Temperature::try_from)
+///     .map(|t| {
+///         Ok(base
+///             .clone()
+///             .overrides(Overrides::new().temperature(t?))
+///             .add_message(Message::assistant("Summary:")))
+///     })
+///     .collect::<Result<Vec<SharedPrompt>, OutOfRange>>()?;
+/// // `client.message(&variants[0])`
+/// # Ok::<(), OutOfRange>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedPrompt {
+    prompt: Arc<Prompt<'static>>,
+    overrides: Overrides,
+    messages: Vec<Message<'static>>,
+}
+
+impl SharedPrompt {
+    /// Share `prompt`.
+    pub fn new(prompt: Prompt<'static>) -> Self {
+        Self {
+            prompt: Arc::new(prompt),
+            overrides: Overrides::default(),
+            messages: vec![],
+        }
+    }
+
+    /// The shared [`Prompt`], without the [`Overrides`] or added messages.
+    pub fn prompt(&self) -> &Prompt<'static> {
+        &self.prompt
+    }
+
+    /// Set [`Overrides`] for this variant, replacing any set before.
+    pub fn overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Add a [`Message`] after those of the shared [`Prompt`] for this
+    /// variant.
+    pub fn add_message<M>(mut self, message: M) -> Self
+    where
+        M: Into<Message<'static>>,
+    {
+        self.messages.push(message.into());
+        self
+    }
+}
+
+impl From<Prompt<'static>> for SharedPrompt {
+    fn from(prompt: Prompt<'static>) -> Self {
+        Self::new(prompt)
+    }
+}
+
+impl Serialize for SharedPrompt {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::Error;
+
+        let mut json =
+            serde_json::to_value(&*self.prompt).map_err(S::Error::custom)?;
+        self.overrides.apply(&mut json).map_err(S::Error::custom)?;
+        if let Some(serde_json::Value::Array(messages)) =
+            json.get_mut("messages")
+        {
+            for message in &self.messages {
+                messages.push(
+                    serde_json::to_value(message).map_err(S::Error::custom)?,
+                );
+            }
+        }
+
+        json.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prompt::Temperature, Model};
+
+    #[test]
+    fn test_shared_prompt() {
+        let prompt = Prompt::default()
+            .model(Model::Sonnet35)
+            .system("Be brief.")
+            .add_message(Message::user("Hi!"));
+        let base = SharedPrompt::new(prompt.clone());
+
+        let variant = base
+            .clone()
+            .overrides(
+                Overrides::new()
+                    .model(Model::Haiku35)
+                    .temperature(Temperature::try_from(0.5).unwrap()),
+            )
+            .add_message(Message::assistant("Hello"));
+        assert!(std::ptr::eq(base.prompt(), variant.prompt()));
+
+        let expected = prompt
+            .model(Model::Haiku35)
+            .temperature(Temperature::try_from(0.5).ok())
+            .add_message(Message::assistant("Hello"));
+        assert_eq!(
+            serde_json::to_value(&variant).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+
+        // The shared prompt is unchanged.
+        assert_eq!(base.prompt().messages.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&base).unwrap(),
+            serde_json::to_value(base.prompt()).unwrap()
+        );
+    }
+}
@@ -5,7 +5,9 @@
 use derive_more::derive::IsVariant;
 
 pub(crate) mod message;
-pub use message::{Message, StopReason, Usage};
+#[cfg(feature = "prompt-caching")]
+pub use message::CacheCreation;
+pub use message::{Message, ServerToolUsage, ServiceTier, StopReason, Usage};
 
 use crate::prompt;
 
@@ -13,6 +15,9 @@ use crate::prompt;
 ///
 /// [Anthropic Messages API]: <https://docs.anthropic.com/en/api/messages>
 #[derive(IsVariant)]
+// A `Response` is short-lived, usually unwrapped right away, so boxing the
+// message isn't worth the allocation.
+#[allow(clippy::large_enum_variant)]
 pub enum Response<'a> {
     /// Single [`response::Message`] from the API.
     ///
@@ -65,7 +70,7 @@ impl<'a> Response<'a> {
     /// this if you don't care about [`response::Message`] metadata.
     ///
     /// [`response::Message`]: self::Message
-    pub fn message(&self) -> Option<&prompt::Message> {
+    pub fn message(&self) -> Option<&prompt::Message<'_>> {
         match self {
             Self::Message { message, .. } => Some(&message.message),
             _ => None,
@@ -113,6 +118,20 @@ impl<'a> Response<'a> {
         self.into_response_message()
             .expect("`Response` is not a `Message` variant.")
     }
+    /// Convert to a `'static` lifetime. See [`Message::into_static`] and
+    /// [`Stream::into_owned`].
+    ///
+    /// [`Stream::into_owned`]: crate::Stream::into_owned
+    pub fn into_static(self) -> Response<'static> {
+        match self {
+            Self::Message { message } => Response::Message {
+                message: message.into_static(),
+            },
+            Self::Stream { stream } => Response::Stream {
+                stream: stream.into_owned(),
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -129,9 +148,7 @@ mod tests {
                 id: TEST_ID.into(),
                 message: prompt::Message {
                     role: prompt::message::Role::User,
-                    content: prompt::message::Content::SinglePart(
-                        CONTENT.into(),
-                    ),
+                    content: prompt::message::Content::text(CONTENT),
                 },
                 model: crate::Model::Sonnet35,
                 stop_reason: None,
@@ -143,7 +160,11 @@ mod tests {
                     #[cfg(feature = "prompt-caching")]
                     cache_read_input_tokens: Some(3),
                     output_tokens: 4,
+                    server_tool_use: None,
+                    service_tier: None,
+                    ..Default::default()
                 },
+                container: None,
             },
         }
     }
@@ -4,7 +4,7 @@ use crate::{prompt, stream::MessageDelta, Model};
 use serde::{Deserialize, Serialize};
 
 /// A [`prompt::message`] with additional response metadata.
-#[derive(Debug, Serialize, Deserialize, derive_more::Display)]
+#[derive(Clone, Debug, Serialize, Deserialize, derive_more::Display)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 #[display("{}", message)]
 pub struct Message<'a> {
@@ -24,15 +24,129 @@ pub struct Message<'a> {
     pub stop_sequence: Option<Cow<'a, str>>,
     /// Usage statistics for the message.
     pub usage: Usage,
+    /// [`Container`] used by the [code execution] tool, if any. Pass its `id`
+    /// to [`Prompt::container`] to reuse it.
+    ///
+    /// [`Container`]: crate::tool::code_execution::Container
+    /// [code execution]: crate::tool::code_execution
+    /// [`Prompt::container`]: crate::Prompt::container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<crate::tool::code_execution::Container>,
 }
 
-impl Message<'_> {
+impl<'a> Message<'a> {
     /// Apply a [`MessageDelta`] with metadata to the message.
     pub fn apply_delta(&mut self, delta: MessageDelta) {
         self.stop_reason = delta.stop_reason;
         self.stop_sequence = delta.stop_sequence;
         if let Some(usage) = delta.usage {
-            self.usage = usage;
+            self.apply_usage(usage);
+        }
+    }
+
+    /// Apply cumulative [`Usage`] from an [`Event::MessageDelta`]. The output
+    /// tokens are replaced. Other counts are replaced only if they are sent,
+    /// since deltas may leave them out.
+    ///
+    /// [`Event::MessageDelta`]: crate::stream::Event::MessageDelta
+    pub fn apply_usage(&mut self, usage: Usage) {
+        if usage.input_tokens > 0 {
+            self.usage.input_tokens = usage.input_tokens;
+        }
+        #[cfg(feature = "prompt-caching")]
+        {
+            self.usage.cache_creation_input_tokens = usage
+                .cache_creation_input_tokens
+                .or(self.usage.cache_creation_input_tokens);
+            self.usage.cache_read_input_tokens = usage
+                .cache_read_input_tokens
+                .or(self.usage.cache_read_input_tokens);
+            self.usage.cache_creation =
+                usage.cache_creation.or(self.usage.cache_creation.take());
+        }
+        self.usage.output_tokens = usage.output_tokens;
+        if usage.server_tool_use.is_some() {
+            self.usage.server_tool_use = usage.server_tool_use;
+        }
+        // Deltas don't repeat the service tier.
+        self.usage.service_tier =
+            usage.service_tier.or(self.usage.service_tier);
+        self.usage.extra.extend(usage.extra);
+    }
+
+    /// Returns true if the [`StopReason`] is [`StopReason::ToolUse`], so the
+    /// tool calls should be run and their results sent back. See
+    /// [`Message::tool_uses`].
+    pub fn stopped_because_tool_use(&self) -> bool {
+        matches!(self.stop_reason, Some(StopReason::ToolUse))
+    }
+
+    /// Returns true if the [`StopReason`] is [`StopReason::MaxTokens`], so the
+    /// response was cut off and may be continued.
+    pub fn hit_max_tokens(&self) -> bool {
+        matches!(self.stop_reason, Some(StopReason::MaxTokens))
+    }
+
+    /// Returns true if the [`StopReason`] is [`StopReason::Refusal`] or the
+    /// text starts like a refusal, such as "I'm sorry, but I can't help with
+    /// that." Use this to route refusals to a fallback or human review.
+    ///
+    /// The text check is a heuristic on the first sentence in English. It
+    /// can miss refusals and, rarely, flag answers which only start like one.
+    ///
+    /// ```
+    /// use misanthropic::{prompt::message::Role, response::Message};
+    ///
+    /// # let json = r#"{"id": "msg_1", "type": "message", "role": "assistant", "content": [], "model": "claude-3-5-sonnet-20241022", "stop_reason": "end_turn", "stop_sequence": null, "usage": {"input_tokens": 1, "output_tokens": 1}}"#;
+    /// let mut message: Message = serde_json::from_str(json).unwrap();
+    /// message.message = (Role::Assistant, "I can't help with that.").into();
+    /// assert!(message.looks_like_refusal());
+    ///
+    /// message.message = (Role::Assistant, "I can't wait to help!").into();
+    /// assert!(!message.looks_like_refusal());
+    /// ```
+    pub fn looks_like_refusal(&self) -> bool {
+        matches!(self.stop_reason, Some(StopReason::Refusal))
+            || is_refusal_text(&self.text())
+    }
+
+    /// Stitch a `continuation` of this message onto it. If this message ends
+    /// with text and the continuation starts with text, the two are joined
+    /// into one [`Block`]. Token counts are summed and the stop reason, stop
+    /// sequence, `id`, and [`Model`] are taken from the continuation.
+    ///
+    /// See [`Client::message_continued`].
+    ///
+    /// [`Block`]: crate::prompt::message::Block
+    /// [`Client::message_continued`]: crate::Client::message_continued
+    pub fn append_continuation(&mut self, continuation: Message<'a>) {
+        use prompt::message::Block;
+
+        let mut blocks = continuation.message.content.into_iter();
+        if let (Some(Block::Text { text, .. }), Some(Block::Text { .. })) =
+            (self.message.content.last_mut(), blocks.as_slice().first())
+        {
+            if let Some(Block::Text { text: next, .. }) = blocks.next() {
+                #[cfg(not(feature = "langsan"))]
+                {
+                    text.to_mut().push_str(&next);
+                }
+                #[cfg(feature = "langsan")]
+                {
+                    text.push_str(&next);
+                }
+            }
+        }
+        self.message.content.extend(blocks);
+
+        self.id = continuation.id;
+        self.model = continuation.model;
+        self.stop_reason = continuation.stop_reason;
+        self.stop_sequence = continuation.stop_sequence;
+        self.usage.input_tokens += continuation.usage.input_tokens;
+        self.usage.output_tokens += continuation.usage.output_tokens;
+        if continuation.container.is_some() {
+            self.container = continuation.container;
         }
     }
 
@@ -44,14 +158,40 @@ impl Message<'_> {
     /// [`Block`]: crate::prompt::message::Block
     /// [`tool::Use`]: crate::tool::Use
     /// [`ToolUse`]: crate::prompt::message::Block::ToolUse
-    pub fn tool_use(&self) -> Option<&crate::tool::Use> {
-        if !matches!(self.stop_reason, Some(StopReason::ToolUse)) {
+    pub fn tool_use(&self) -> Option<&crate::tool::Use<'_>> {
+        if !self.stopped_because_tool_use() {
             return None;
         }
 
         self.message.content.last()?.tool_use()
     }
 
+    /// Returns an iterator over every [`tool::Use`] in the message, regardless
+    /// of the [`StopReason`]. See [`prompt::Message::tool_uses`].
+    ///
+    /// [`tool::Use`]: crate::tool::Use
+    pub fn tool_uses(&self) -> impl Iterator<Item = &crate::tool::Use<'a>> {
+        self.message.tool_uses()
+    }
+
+    /// Returns only the text of the message. See [`prompt::Message::text`].
+    pub fn text(&self) -> Cow<'_, str> {
+        self.message.text()
+    }
+
+    /// Parse a response body, borrowing text from `json` rather than copying
+    /// it. Strings with JSON escapes, such as
+    /// newlines, are still copied. Use [`into_static`] to detach the message
+    /// from `json`.
+    ///
+    /// Deserializing with [`serde`] directly always copies, so that messages
+    /// can be parsed from temporary buffers, such as stream events.
+    ///
+    /// [`into_static`]: Message::into_static
+    pub fn from_slice(json: &'a [u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice::<borrowed::Message>(json).map(Into::into)
+    }
+
     /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
     /// fields.
     pub fn into_static(self) -> Message<'static> {
@@ -64,12 +204,13 @@ impl Message<'_> {
                 .stop_sequence
                 .map(|s| Cow::Owned(s.into_owned())),
             usage: self.usage,
+            container: self.container,
         }
     }
 }
 
 /// Reason the model stopped generating tokens.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 #[serde(rename_all = "snake_case")]
 pub enum StopReason {
@@ -81,14 +222,85 @@ pub enum StopReason {
     StopSequence,
     /// A tool was used.
     ToolUse,
+    /// The model declined to respond for safety reasons. See
+    /// [`Message::looks_like_refusal`].
+    Refusal,
+    /// A long running turn with server tools, such as [`web_search`], was
+    /// paused. Send the response back as is to let the model continue.
+    ///
+    /// [`web_search`]: crate::tool::web_search
+    PauseTurn,
+    /// A reason this crate does not know about, for example one added to the
+    /// API after this version was released. Like [`Block::Unknown`], this
+    /// keeps responses from failing to parse when the API adds a value.
+    ///
+    /// [`Block::Unknown`]: crate::prompt::message::Block::Unknown
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Apologies which may come before a refusal.
+const APOLOGIES: &[&str] = &[
+    "i'm sorry, but ",
+    "i'm sorry, ",
+    "i am sorry, but ",
+    "i apologize, but ",
+    "sorry, but ",
+    "unfortunately, ",
+];
+
+/// Phrases which start a refusal on their own.
+const REFUSALS: &[&str] = &[
+    "i must decline",
+    "i'm not comfortable",
+    "i am not comfortable",
+    "i don't feel comfortable",
+];
+
+/// Phrases which start a refusal if followed by one of [`REFUSED`].
+const DECLINES: &[&str] = &[
+    "i can't ",
+    "i cannot ",
+    "i won't ",
+    "i will not ",
+    "i'm not able to ",
+    "i am not able to ",
+    "i'm unable to ",
+    "i am unable to ",
+];
+
+/// What is refused, after one of [`DECLINES`].
+const REFUSED: &[&str] = &[
+    "help", "assist", "provide", "create", "write", "generate", "comply",
+    "engage", "fulfill", "support", "share", "do that", "continue",
+];
+
+/// Whether `text` starts like a refusal. See [`Message::looks_like_refusal`].
+fn is_refusal_text(text: &str) -> bool {
+    let text = text.trim_start().to_lowercase().replace('\u{2019}', "'");
+    let mut text = text.as_str();
+    if let Some(rest) = APOLOGIES.iter().find_map(|a| text.strip_prefix(a)) {
+        text = rest;
+    }
+
+    REFUSALS.iter().any(|refusal| text.starts_with(refusal))
+        || DECLINES.iter().any(|decline| {
+            text.strip_prefix(decline).is_some_and(|rest| {
+                REFUSED.iter().any(|refused| rest.starts_with(refused))
+            })
+        })
 }
 
 /// Usage statistics from the API. This is used in multiple contexts, not just
 /// for messages.
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
 pub struct Usage {
-    /// Number of input tokens used.
+    /// Number of input tokens used. This may be left out of streamed
+    /// [`Event::MessageDelta`]s.
+    ///
+    /// [`Event::MessageDelta`]: crate::stream::Event::MessageDelta
+    #[serde(default)]
     pub input_tokens: u64,
     /// Number of input tokens used to create the cache entry.
     #[cfg(feature = "prompt-caching")]
@@ -96,8 +308,196 @@ pub struct Usage {
     /// Number of input tokens read from the cache.
     #[cfg(feature = "prompt-caching")]
     pub cache_read_input_tokens: Option<u64>,
+    /// Breakdown of [`Self::cache_creation_input_tokens`] by how long the
+    /// cache entries live.
+    #[cfg(feature = "prompt-caching")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_creation: Option<CacheCreation>,
     /// Number of output tokens generated.
     pub output_tokens: u64,
+    /// Server [`Tool`] usage, such as web searches, which are billed
+    /// separately from tokens.
+    ///
+    /// [`Tool`]: crate::Tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUsage>,
+    /// [`ServiceTier`] used for the request. See [`Prompt::service_tier`].
+    ///
+    /// [`Prompt::service_tier`]: crate::Prompt::service_tier
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+    /// Fields this crate does not know about, for example counters added to
+    /// the API after this version was released. They are kept and serialized
+    /// as is, so no counter is lost when usage is stored or forwarded.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Usage {
+    /// Number of web searches performed. See [`web_search`].
+    ///
+    /// [`web_search`]: crate::tool::web_search
+    pub fn web_search_requests(&self) -> u64 {
+        self.server_tool_use
+            .as_ref()
+            .map(|usage| usage.web_search_requests)
+            .unwrap_or_default()
+    }
+}
+
+/// Input tokens used to create cache entries, by how long the entries live.
+/// See [`Usage::cache_creation`].
+#[cfg(feature = "prompt-caching")]
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct CacheCreation {
+    /// Input tokens written to entries which live for 5 minutes.
+    #[serde(default)]
+    pub ephemeral_5m_input_tokens: u64,
+    /// Input tokens written to entries which live for 1 hour.
+    #[serde(default)]
+    pub ephemeral_1h_input_tokens: u64,
+}
+
+/// Service tier used for a request. See [`Usage::service_tier`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    /// Standard capacity.
+    Standard,
+    /// Priority tier capacity.
+    Priority,
+    /// Message Batches API.
+    Batch,
+}
+
+/// Server [`Tool`] usage statistics. See [`Usage::server_tool_use`].
+///
+/// [`Tool`]: crate::Tool
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct ServerToolUsage {
+    /// Number of web searches performed.
+    #[serde(default)]
+    pub web_search_requests: u64,
+}
+
+/// Mirrors of the response types which borrow from the input, for
+/// [`Message::from_slice`]. Only text [`Block`]s are mirrored. Others, such
+/// as images, which are decoded from base64 anyway, are deserialized as usual.
+///
+/// [`Block`]: prompt::message::Block
+mod borrowed {
+    use std::borrow::Cow;
+
+    use serde::Deserialize;
+
+    #[cfg(feature = "prompt-caching")]
+    use crate::prompt::message::CacheControl;
+    use crate::{
+        prompt::{self, message::Role},
+        tool::code_execution::Container,
+        Model,
+    };
+
+    use super::{StopReason, Usage};
+
+    #[derive(Deserialize)]
+    pub(super) struct Message<'a> {
+        #[serde(borrow)]
+        id: Cow<'a, str>,
+        role: Role,
+        #[serde(borrow)]
+        content: Content<'a>,
+        model: Model,
+        stop_reason: Option<StopReason>,
+        #[serde(borrow)]
+        stop_sequence: Option<Cow<'a, str>>,
+        usage: Usage,
+        #[serde(default)]
+        container: Option<Container>,
+    }
+
+    impl<'a> From<Message<'a>> for super::Message<'a> {
+        fn from(message: Message<'a>) -> Self {
+            Self {
+                id: message.id,
+                message: prompt::Message {
+                    role: message.role,
+                    content: message.content.into(),
+                },
+                model: message.model,
+                stop_reason: message.stop_reason,
+                stop_sequence: message.stop_sequence,
+                usage: message.usage,
+                container: message.container,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Content<'a> {
+        SinglePart(#[serde(borrow)] Cow<'a, str>),
+        MultiPart(#[serde(borrow)] Vec<Block<'a>>),
+    }
+
+    impl<'a> From<Content<'a>> for prompt::message::Content<'a> {
+        fn from(content: Content<'a>) -> Self {
+            match content {
+                Content::SinglePart(text) => Self::text(cow_str(text)),
+                Content::MultiPart(blocks) => {
+                    Self(blocks.into_iter().map(Into::into).collect())
+                }
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Block<'a> {
+        Text {
+            #[serde(rename = "type")]
+            _kind: TextTag,
+            #[serde(borrow)]
+            text: Cow<'a, str>,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: Option<CacheControl>,
+        },
+        Other(prompt::message::Block<'a>),
+    }
+
+    impl<'a> From<Block<'a>> for prompt::message::Block<'a> {
+        fn from(block: Block<'a>) -> Self {
+            match block {
+                Block::Text {
+                    text,
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control,
+                    ..
+                } => Self::Text {
+                    text: cow_str(text),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control,
+                },
+                Block::Other(block) => block,
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum TextTag {
+        Text,
+    }
+
+    fn cow_str(cow: Cow<'_, str>) -> crate::CowStr<'_> {
+        match cow {
+            Cow::Borrowed(s) => s.into(),
+            Cow::Owned(s) => s.into(),
+        }
+    }
 }
 
 #[cfg(feature = "markdown")]
@@ -111,7 +511,7 @@ impl crate::markdown::ToMarkdown for Message<'_> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     // FIXME: This is Copilot generated JSON. It should be replaced with actual
@@ -148,6 +548,131 @@ mod tests {
         assert_eq!(message.usage.output_tokens, 503);
     }
 
+    #[test]
+    fn test_from_slice() {
+        use crate::prompt::message::Block;
+
+        const JSON: &str = r#"{
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "model": "claude-3-5-sonnet-20240620",
+            "content": [
+                {"type": "text", "text": "A cat:", "citations": null},
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "AAAA"}},
+                {"type": "text", "text": "Escaped\n"},
+                {"type": "tool_use", "id": "id", "name": "look", "input": {}}
+            ],
+            "stop_reason": "tool_use",
+            "stop_sequence": null,
+            "usage": {"input_tokens": 1, "output_tokens": 2}
+        }"#;
+
+        let message = Message::from_slice(JSON.as_bytes()).unwrap();
+        let expected: Message = serde_json::from_str(JSON).unwrap();
+        assert_eq!(message, expected);
+
+        let blocks = message.message.content.as_slice();
+        assert!(matches!(message.id, Cow::Borrowed(_)));
+        #[cfg(not(feature = "langsan"))]
+        {
+            assert!(matches!(
+                &blocks[0],
+                Block::Text {
+                    text: Cow::Borrowed(_),
+                    ..
+                }
+            ));
+            // Escaped strings can't be borrowed.
+            assert!(matches!(
+                &blocks[2],
+                Block::Text {
+                    text: Cow::Owned(_),
+                    ..
+                }
+            ));
+        }
+        assert!(
+            matches!(&blocks[1], Block::Image { image, .. } if image.data().unwrap().as_ref() == [0; 3])
+        );
+        assert!(message.tool_use().is_some());
+
+        let message: Message<'static> = message.into_static();
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn test_usage_service_tier() {
+        let mut message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
+        assert!(message.usage.service_tier.is_none());
+        message.usage.service_tier = Some(ServiceTier::Priority);
+
+        let usage: Usage =
+            serde_json::from_str(r#"{"input_tokens":1,"output_tokens":2}"#)
+                .unwrap();
+        message.apply_delta(MessageDelta {
+            stop_reason: None,
+            stop_sequence: None,
+            usage: Some(usage),
+        });
+        assert_eq!(message.usage.service_tier, Some(ServiceTier::Priority));
+
+        let usage: Usage = serde_json::from_str(
+            r#"{"input_tokens":1,"output_tokens":2,"service_tier":"batch"}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.service_tier, Some(ServiceTier::Batch));
+    }
+
+    #[test]
+    fn test_usage_extra() {
+        const JSON: &str = r#"{"input_tokens":1,"cache_creation_input_tokens":5,"cache_read_input_tokens":0,"cache_creation":{"ephemeral_5m_input_tokens":2,"ephemeral_1h_input_tokens":3},"output_tokens":2,"new_counter":7}"#;
+
+        let usage: Usage = serde_json::from_str(JSON).unwrap();
+        assert_eq!(usage.extra["new_counter"], 7);
+        #[cfg(feature = "prompt-caching")]
+        {
+            let cache_creation = usage.cache_creation.as_ref().unwrap();
+            assert_eq!(cache_creation.ephemeral_5m_input_tokens, 2);
+            assert_eq!(cache_creation.ephemeral_1h_input_tokens, 3);
+            assert!(!usage.extra.contains_key("cache_creation"));
+        }
+        // Without the feature, the cache fields are kept as extra fields.
+        #[cfg(not(feature = "prompt-caching"))]
+        assert_eq!(usage.extra["cache_creation_input_tokens"], 5);
+
+        // Every field survives a round trip.
+        let json: serde_json::Value = serde_json::to_value(&usage).unwrap();
+        assert_eq!(
+            json,
+            serde_json::from_str::<serde_json::Value>(JSON).unwrap()
+        );
+
+        // Deltas add extra fields.
+        let mut message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
+        assert!(message.usage.extra.is_empty());
+        message.apply_usage(usage);
+        assert_eq!(message.usage.extra["new_counter"], 7);
+    }
+
+    #[test]
+    fn test_usage_server_tool_use() {
+        let usage: Usage = serde_json::from_str(
+            r#"{"input_tokens":1,"output_tokens":2,"server_tool_use":{"web_search_requests":3}}"#,
+        )
+        .unwrap();
+        assert_eq!(usage.web_search_requests(), 3);
+
+        let usage: Usage =
+            serde_json::from_str(r#"{"input_tokens":1,"output_tokens":2}"#)
+                .unwrap();
+        assert!(usage.server_tool_use.is_none());
+        assert_eq!(usage.web_search_requests(), 0);
+        assert!(!serde_json::to_string(&usage)
+            .unwrap()
+            .contains("server_tool_use"));
+    }
+
     #[test]
     fn test_apply_delta() {
         let mut message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
@@ -167,6 +692,48 @@ mod tests {
         assert_eq!(message.stop_sequence, Some("sequence".into()));
         assert_eq!(message.usage.input_tokens, 100);
         assert_eq!(message.usage.output_tokens, 200);
+        assert!(message.hit_max_tokens());
+        assert!(!message.stopped_because_tool_use());
+
+        // Streamed usage may only have the output tokens.
+        let usage: Usage =
+            serde_json::from_str(r#"{"output_tokens":300}"#).unwrap();
+        message.apply_usage(usage);
+        assert_eq!(message.usage.input_tokens, 100);
+        assert_eq!(message.usage.output_tokens, 300);
+    }
+
+    #[test]
+    fn test_append_continuation() {
+        use prompt::message::Block;
+
+        let mut message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
+        message.stop_reason = Some(StopReason::MaxTokens);
+
+        let mut continuation = message.clone();
+        continuation.id = "msg_2".into();
+        continuation.stop_reason = Some(StopReason::EndTurn);
+        continuation.message.content =
+            prompt::message::Content::text(" How can I help?");
+        continuation.message.content.push(Block::text("Bye."));
+
+        message.append_continuation(continuation);
+
+        assert_eq!(message.id, "msg_2");
+        assert_eq!(message.stop_reason, Some(StopReason::EndTurn));
+        assert!(!message.hit_max_tokens());
+        assert_eq!(message.usage.input_tokens, 4190);
+        assert_eq!(message.usage.output_tokens, 1006);
+        // The adjacent text is joined into one block.
+        assert_eq!(message.message.content.iter().count(), 2);
+        assert_eq!(
+            message.message.content.get(0).unwrap(),
+            &Block::text("Hi! My name is Claude. How can I help?")
+        );
+        assert_eq!(
+            message.message.content.get(1).unwrap(),
+            &Block::text("Bye.")
+        );
     }
 
     #[test]
@@ -187,6 +754,73 @@ mod tests {
         assert!(message.tool_use().is_some());
     }
 
+    #[test]
+    fn test_stop_reason() {
+        for (json, reason) in [
+            ("\"end_turn\"", StopReason::EndTurn),
+            ("\"max_tokens\"", StopReason::MaxTokens),
+            ("\"stop_sequence\"", StopReason::StopSequence),
+            ("\"tool_use\"", StopReason::ToolUse),
+            ("\"refusal\"", StopReason::Refusal),
+            ("\"pause_turn\"", StopReason::PauseTurn),
+            (
+                "\"model_context_window_exceeded\"",
+                StopReason::Other("model_context_window_exceeded".into()),
+            ),
+        ] {
+            assert_eq!(
+                serde_json::from_str::<StopReason>(json).unwrap(),
+                reason
+            );
+            assert_eq!(serde_json::to_string(&reason).unwrap(), json);
+        }
+
+        // A whole response with an unknown reason still parses.
+        let json = RESPONSE_JSON.replace("\"end_turn\"", "\"new_reason\"");
+        let message: Message = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            message.stop_reason,
+            Some(StopReason::Other("new_reason".into()))
+        );
+        assert!(Message::from_slice(json.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_refusal() {
+        let mut message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
+        assert!(!message.looks_like_refusal());
+
+        message.stop_reason = Some(StopReason::Refusal);
+        assert!(message.looks_like_refusal());
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["stop_reason"], "refusal");
+
+        for text in [
+            "I can't help with that.",
+            "  I\u{2019}m sorry, but I cannot assist with this request.",
+            "Sorry, but I won't write that.",
+            "I apologize, but I'm unable to provide instructions for that.",
+            "I must decline this request.",
+        ] {
+            assert!(is_refusal_text(text), "{text}");
+        }
+        for text in [
+            "",
+            "Sure! Here's how to help your friend.",
+            "I can't find any bugs in this code.",
+            "I'm sorry to hear that. Here are some tips.",
+            "You can't help with that, but I can.",
+        ] {
+            assert!(!is_refusal_text(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_text() {
+        let message: Message = serde_json::from_str(RESPONSE_JSON).unwrap();
+        assert_eq!(message.text(), "Hi! My name is Claude.");
+    }
+
     #[test]
     fn test_into_static() {
         // Refers to json:
@@ -214,9 +848,7 @@ mod tests {
             id: "id".into(),
             message: prompt::Message {
                 role: prompt::message::Role::User,
-                content: prompt::message::Content::SinglePart(
-                    "Hello, **world**!".into(),
-                ),
+                content: prompt::message::Content::text("Hello, **world**!"),
             },
             model: crate::Model::Sonnet35,
             stop_reason: None,
@@ -228,7 +860,11 @@ mod tests {
                 #[cfg(feature = "prompt-caching")]
                 cache_read_input_tokens: Some(3),
                 output_tokens: 4,
+                server_tool_use: None,
+                service_tier: None,
+                ..Default::default()
             },
+            container: None,
         };
 
         let expected = "### User\n\nHello, **world**!";
@@ -0,0 +1,126 @@
+//! Persistence for [`Conversation`]s with a [`ConversationStore`]. With the
+//! `sqlite` feature, `SqliteStore` keeps conversations in a local SQLite
+//! database. Conversations can be found by [`Metadata`] or by the text of
+//! their messages with a [`SearchIndex`].
+//!
+//! ```no_run
+//! # #[cfg(feature = "sqlite")]
+//! # fn example() -> Result<(), misanthropic::store::Error> {
+//! use misanthropic::{
+//!     conversation::Conversation,
+//!     store::{ConversationStore, Metadata, SqliteStore},
+//!     Model,
+//! };
+//!
+//! let store = SqliteStore::open("conversations.db")?;
+//! let conversation = Conversation::new(Model::default());
+//! let metadata = Metadata::from([("project".into(), "demo".into())]);
+//! store.save("chat-1", &conversation, &metadata)?;
+//!
+//! for record in store.search_metadata("project", "demo")? {
+//!     let conversation = store.load(&record.id)?;
+//!     # drop(conversation);
+//! }
+//!
+//! for hit in store.search("capital of France")? {
+//!     println!("{}: {}", hit.conversation, hit.highlight("**", "**"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::{collections::BTreeMap, time::SystemTime};
+
+use crate::conversation::{self, Conversation};
+
+mod search;
+pub use search::{Hit, SearchIndex};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Arbitrary string metadata for a stored [`Conversation`], such as a title or
+/// project.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Error from a [`ConversationStore`] provided by this crate.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The [`Conversation`] could not be written or read.
+    #[error("Conversation error: {0}")]
+    Conversation(#[from] conversation::Error),
+    /// SQLite error.
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// Summary of a stored [`Conversation`] from [`ConversationStore::list`] and
+/// [`ConversationStore::search`].
+#[derive(Clone, Debug)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Record {
+    /// Id the conversation was saved with.
+    pub id: String,
+    /// Metadata the conversation was saved with.
+    pub metadata: Metadata,
+    /// When the conversation was created, if known.
+    pub created_at: Option<SystemTime>,
+    /// When the conversation was last saved.
+    pub updated_at: SystemTime,
+}
+
+/// Saves and loads [`Conversation`]s by id, with [`Metadata`] for finding them
+/// again.
+pub trait ConversationStore {
+    /// Error type for the store.
+    type Error: std::error::Error;
+
+    /// Save a `conversation` under an `id`, replacing any existing
+    /// conversation and metadata with that `id`.
+    fn save(
+        &self,
+        id: &str,
+        conversation: &Conversation,
+        metadata: &Metadata,
+    ) -> Result<(), Self::Error>;
+
+    /// Load the conversation with an `id`, if it exists.
+    fn load(
+        &self,
+        id: &str,
+    ) -> Result<Option<Conversation<'static>>, Self::Error>;
+
+    /// Delete the conversation with an `id`. Returns `false` if it did not
+    /// exist.
+    fn delete(&self, id: &str) -> Result<bool, Self::Error>;
+
+    /// [`Record`]s for every stored conversation, most recently saved first.
+    fn list(&self) -> Result<Vec<Record>, Self::Error>;
+
+    /// [`Record`]s for stored conversations with metadata `key` set to
+    /// `value`, most recently saved first.
+    fn search_metadata(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Result<Vec<Record>, Self::Error>;
+
+    /// Search the text of every stored conversation for messages containing
+    /// all the words in a `query`. See [`SearchIndex::search`].
+    ///
+    /// The default implementation loads every conversation into a new
+    /// [`SearchIndex`] with the default options. To search repeatedly, or to
+    /// include thoughts or tool blocks, keep a [`SearchIndex`] instead.
+    fn search(&self, query: &str) -> Result<Vec<Hit>, Self::Error> {
+        let mut index = SearchIndex::new();
+        for record in self.list()? {
+            if let Some(conversation) = self.load(&record.id)? {
+                index.insert(&record.id, &conversation);
+            }
+        }
+
+        Ok(index.search(query))
+    }
+}
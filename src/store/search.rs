@@ -0,0 +1,432 @@
+//! In-memory full-text search over [`Conversation`]s.
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::Range,
+};
+
+use crate::{
+    conversation::Conversation,
+    prompt::{
+        self,
+        message::{Block, Content, Role},
+    },
+};
+
+/// Characters of context before the first match in a [`Hit::snippet`]. Twice
+/// as many are kept after it.
+const CONTEXT: usize = 40;
+
+/// Key of an indexed message: conversation id and message index.
+type Key = (String, usize);
+
+/// A message matching a search. See [`SearchIndex::search`].
+#[derive(Clone, Debug)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Hit {
+    /// Id of the conversation.
+    pub conversation: String,
+    /// Index of the message in [`Conversation::entries`].
+    pub message: usize,
+    /// [`Role`] of the message author.
+    pub role: Role,
+    /// Excerpt of the message text around the first match, with `…` where
+    /// it was cut. Newlines are replaced with spaces.
+    pub snippet: String,
+    /// Byte ranges of the matched words in the [`snippet`](Hit::snippet).
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl Hit {
+    /// The [`snippet`](Hit::snippet) with each match wrapped in `open` and
+    /// `close`, for example `**` and `**` for markdown.
+    pub fn highlight(&self, open: &str, close: &str) -> String {
+        let mut highlighted = String::with_capacity(self.snippet.len());
+        let mut last = 0;
+        for range in &self.highlights {
+            let (Some(before), Some(word)) = (
+                self.snippet.get(last..range.start),
+                self.snippet.get(range.clone()),
+            ) else {
+                continue;
+            };
+            highlighted.push_str(before);
+            highlighted.push_str(open);
+            highlighted.push_str(word);
+            highlighted.push_str(close);
+            last = range.end;
+        }
+        highlighted.push_str(self.snippet.get(last..).unwrap_or_default());
+
+        highlighted
+    }
+}
+
+/// An indexed message.
+#[derive(Clone, Debug)]
+struct Document {
+    role: Role,
+    text: String,
+}
+
+/// In-memory full-text index over the messages of [`Conversation`]s. Words are
+/// matched case-insensitively. By default `<thinking>` spans and tool blocks
+/// are not indexed.
+///
+/// ```
+/// use misanthropic::{
+///     conversation::Conversation, prompt::message::Role, store::SearchIndex,
+///     Model,
+/// };
+///
+/// let mut conversation = Conversation::new(Model::default());
+/// conversation.push(misanthropic::prompt::Message::from((
+///     Role::Assistant,
+///     "The capital of France is Paris.",
+/// )));
+///
+/// let mut index = SearchIndex::new();
+/// index.insert("chat-1", &conversation);
+///
+/// let hits = index.search("paris");
+/// assert_eq!(hits[0].conversation, "chat-1");
+/// assert_eq!(
+///     hits[0].highlight("[", "]"),
+///     "The capital of France is [Paris]."
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    thinking: bool,
+    tools: bool,
+    documents: BTreeMap<Key, Document>,
+    words: HashMap<String, BTreeSet<Key>>,
+}
+
+impl SearchIndex {
+    /// Create an empty index with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index text in `<thinking>` spans. The default is `false`. This only
+    /// applies to conversations inserted afterwards.
+    pub fn thinking(mut self, thinking: bool) -> Self {
+        self.thinking = thinking;
+        self
+    }
+
+    /// Index tool use inputs as JSON and the text of tool results. The
+    /// default is `false`. This only applies to conversations inserted
+    /// afterwards.
+    pub fn tools(mut self, tools: bool) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Number of indexed messages.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns true if no messages are indexed.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Index a `conversation` under an `id`, replacing any conversation
+    /// already indexed with that `id`.
+    pub fn insert(&mut self, id: &str, conversation: &Conversation) {
+        self.remove(id);
+
+        for (index, entry) in conversation.entries.iter().enumerate() {
+            let text = self.text(&entry.message);
+            if text.is_empty() {
+                continue;
+            }
+
+            let key = (id.to_string(), index);
+            for (_, word) in words(&text) {
+                self.words.entry(word).or_default().insert(key.clone());
+            }
+            self.documents.insert(
+                key,
+                Document {
+                    role: entry.message.role,
+                    text,
+                },
+            );
+        }
+    }
+
+    /// Remove the conversation with an `id` from the index. Returns `false`
+    /// if it was not indexed.
+    pub fn remove(&mut self, id: &str) -> bool {
+        let keys: Vec<Key> = self
+            .documents
+            .range((id.to_string(), 0)..=(id.to_string(), usize::MAX))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in &keys {
+            let Some(document) = self.documents.remove(key) else {
+                continue;
+            };
+            for (_, word) in words(&document.text) {
+                if let Some(keys) = self.words.get_mut(&word) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        self.words.remove(&word);
+                    }
+                }
+            }
+        }
+
+        !keys.is_empty()
+    }
+
+    /// Messages containing every word in a `query`, with the most matches
+    /// first. An empty `query` matches nothing.
+    pub fn search(&self, query: &str) -> Vec<Hit> {
+        let terms: BTreeSet<String> =
+            words(query).map(|(_, word)| word).collect();
+
+        let mut candidates: Option<BTreeSet<&Key>> = None;
+        for term in &terms {
+            let Some(keys) = self.words.get(term) else {
+                return vec![];
+            };
+            candidates = Some(match candidates {
+                None => keys.iter().collect(),
+                Some(candidates) => candidates
+                    .into_iter()
+                    .filter(|k| keys.contains(k))
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<(usize, Hit)> = candidates
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|key| {
+                let document = self.documents.get(key)?;
+                let matches: Vec<Range<usize>> = words(&document.text)
+                    .filter(|(_, word)| terms.contains(word))
+                    .map(|(range, _)| range)
+                    .collect();
+                let (snippet, highlights) = snippet(&document.text, &matches)?;
+
+                Some((
+                    matches.len(),
+                    Hit {
+                        conversation: key.0.clone(),
+                        message: key.1,
+                        role: document.role,
+                        snippet,
+                        highlights,
+                    },
+                ))
+            })
+            .collect();
+
+        // Stable, so ties stay in conversation and message order.
+        hits.sort_by(|(a, _), (b, _)| b.cmp(a));
+        hits.into_iter().map(|(_, hit)| hit).collect()
+    }
+
+    /// Searchable text of a `message`, according to the options.
+    fn text(&self, message: &prompt::Message) -> String {
+        let stripped;
+        let content = if self.thinking {
+            &message.content
+        } else {
+            stripped = message.content.strip_thoughts();
+            &stripped
+        };
+
+        let mut text = String::new();
+        self.push_content(&mut text, content);
+        text
+    }
+
+    fn push_content(&self, text: &mut String, content: &Content) {
+        for block in content.iter() {
+            match block {
+                Block::Text { text: part, .. } => push_line(text, part),
+                Block::ToolUse { call } | Block::ServerToolUse { call }
+                    if self.tools =>
+                {
+                    push_line(text, &call.input.to_string())
+                }
+                Block::ToolResult { result } if self.tools => {
+                    self.push_content(text, &result.content)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Append a `line` to `text`, separated by a newline.
+fn push_line(text: &mut String, line: &str) {
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text.push_str(line);
+}
+
+/// Lowercase words in `text` with their byte ranges. A word is a run of
+/// alphanumeric characters.
+fn words(text: &str) -> impl Iterator<Item = (Range<usize>, String)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while chars.next_if(|(_, c)| !c.is_alphanumeric()).is_some() {}
+        let (start, _) = *chars.peek()?;
+        let mut end = start;
+        while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric()) {
+            end = i + c.len_utf8();
+        }
+        let word = text.get(start..end)?.to_lowercase();
+
+        Some((start..end, word))
+    })
+}
+
+/// Excerpt of `text` around the first of the `matches` and the ranges of the
+/// `matches` within it.
+fn snippet(
+    text: &str,
+    matches: &[Range<usize>],
+) -> Option<(String, Vec<Range<usize>>)> {
+    const ELLIPSIS: &str = "…";
+
+    let first = matches.first()?;
+    let start = text
+        .get(..first.start)?
+        .char_indices()
+        .rev()
+        .take(CONTEXT)
+        .last()
+        .map_or(first.start, |(i, _)| i);
+    let end = text
+        .get(first.end..)?
+        .char_indices()
+        .nth(CONTEXT * 2)
+        .map_or(text.len(), |(i, _)| first.end + i);
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push_str(ELLIPSIS);
+    }
+    let offset = snippet.len();
+    snippet.push_str(&text.get(start..end)?.replace('\n', " "));
+    if end < text.len() {
+        snippet.push_str(ELLIPSIS);
+    }
+
+    let highlights = matches
+        .iter()
+        .filter(|range| range.start >= start && range.end <= end)
+        .map(|range| range.start - start + offset..range.end - start + offset)
+        .collect();
+
+    Some((snippet, highlights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tool, Model};
+
+    fn conversation() -> Conversation<'static> {
+        let mut conversation = Conversation::new(Model::default());
+        conversation.push(prompt::Message::from((
+            Role::User,
+            "What is the capital of France?",
+        )));
+        conversation.push(prompt::Message::from((
+            Role::Assistant,
+            "<thinking>Easy, Lyon. No, wait.</thinking>The capital of France \
+             is Paris. Paris is also its largest city.",
+        )));
+        conversation.push(prompt::Message {
+            role: Role::Assistant,
+            content: Content::from(vec![Block::ToolUse {
+                call: tool::Use {
+                    id: "toolu_1".into(),
+                    name: "map".into(),
+                    input: serde_json::json!({"city": "Lyon"}),
+                    #[cfg(feature = "prompt-caching")]
+                    cache_control: None,
+                },
+            }]),
+        });
+        conversation
+    }
+
+    #[test]
+    fn test_words() {
+        let words: Vec<_> = words("Héllo, wörld! 42").collect();
+        assert_eq!(
+            words,
+            [
+                (0..6, "héllo".to_string()),
+                (8..14, "wörld".to_string()),
+                (16..18, "42".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let mut index = SearchIndex::new();
+        index.insert("a", &conversation());
+        // The tool use message has no text.
+        assert_eq!(index.len(), 2);
+
+        let hits = index.search("CAPITAL france");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].message, 0);
+        assert_eq!(hits[0].role, Role::User);
+        assert_eq!(
+            hits[0].highlight("*", "*"),
+            "What is the *capital* of *France*?"
+        );
+
+        // Every match in the snippet is highlighted.
+        let hits = index.search("paris");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].highlights.len(), 2);
+
+        // Thinking and tools are excluded by default.
+        assert!(index.search("lyon").is_empty());
+        assert!(index.search("").is_empty());
+        assert!(index.search("capital berlin").is_empty());
+
+        let mut index = SearchIndex::new().thinking(true).tools(true);
+        index.insert("a", &conversation());
+        index.insert("b", &conversation());
+        let hits = index.search("lyon");
+        assert_eq!(hits.len(), 4);
+        assert_eq!(hits[0].conversation, "a");
+
+        assert!(index.remove("a"));
+        assert!(!index.remove("a"));
+        assert_eq!(index.search("lyon").len(), 2);
+        assert!(index
+            .words
+            .values()
+            .all(|keys| keys.iter().all(|k| k.0 == "b")));
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = format!("{} needle {}", "a ".repeat(50), "b ".repeat(50));
+        let start = text.find("needle").unwrap();
+        let needle = start..start + "needle".len();
+        let (snippet, highlights) =
+            snippet(&text, std::slice::from_ref(&needle)).unwrap();
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert_eq!(&snippet[highlights[0].clone()], "needle");
+    }
+}
@@ -0,0 +1,252 @@
+//! [`ConversationStore`] backed by SQLite.
+use std::{path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{ConversationStore, Error, Metadata, Record};
+use crate::conversation::{from_millis, now, to_millis, Conversation};
+
+/// Tables for conversations, stored in the JSONL session format, and their
+/// metadata. See the [`conversation`](crate::conversation) module.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS conversations (
+    id TEXT PRIMARY KEY NOT NULL,
+    session TEXT NOT NULL,
+    created_at INTEGER,
+    updated_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS metadata (
+    conversation_id TEXT NOT NULL
+        REFERENCES conversations(id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (conversation_id, key)
+);
+CREATE INDEX IF NOT EXISTS metadata_key_value ON metadata (key, value);
+";
+
+/// A [`ConversationStore`] in a local SQLite database. This requires the
+/// `sqlite` feature. See the [module](super) documentation.
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open or create a database at `path`.
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(Connection::open(path)?)
+    }
+
+    /// Create an in-memory database, for tests.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    /// Use an existing [`Connection`]. The tables are created if they do not
+    /// exist.
+    pub fn new(connection: Connection) -> Result<Self, Error> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave a transaction open, so
+        // the connection is still usable.
+        self.connection
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// [`Record`]s for the conversation ids returned by a `query`, most
+    /// recently saved first.
+    fn records<P>(
+        connection: &Connection,
+        query: &str,
+        params: P,
+    ) -> Result<Vec<Record>, Error>
+    where
+        P: rusqlite::Params,
+    {
+        let mut statement = connection.prepare(query)?;
+        let rows = statement.query_map(params, |row| {
+            Ok(Record {
+                id: row.get(0)?,
+                metadata: Metadata::new(),
+                created_at: row.get::<_, Option<i64>>(1)?.map(millis),
+                updated_at: millis(row.get(2)?),
+            })
+        })?;
+        let mut records = rows.collect::<Result<Vec<_>, _>>()?;
+
+        let mut statement = connection.prepare(
+            "SELECT key, value FROM metadata WHERE conversation_id = ?1",
+        )?;
+        for record in &mut records {
+            record.metadata = statement
+                .query_map([&record.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(records)
+    }
+}
+
+impl ConversationStore for SqliteStore {
+    type Error = Error;
+
+    fn save(
+        &self,
+        id: &str,
+        conversation: &Conversation,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let mut session = vec![];
+        conversation.to_jsonl(&mut session)?;
+        // `to_jsonl` writes JSON, which is always UTF-8.
+        let session = String::from_utf8_lossy(&session);
+
+        let mut connection = self.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO conversations (id, session, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET
+                session = excluded.session,
+                created_at = excluded.created_at,
+                updated_at = excluded.updated_at",
+            params![
+                id,
+                session,
+                conversation.created_at.map(to_millis).map(|m| m as i64),
+                to_millis(now()) as i64,
+            ],
+        )?;
+        transaction
+            .execute("DELETE FROM metadata WHERE conversation_id = ?1", [id])?;
+        for (key, value) in metadata {
+            transaction.execute(
+                "INSERT INTO metadata (conversation_id, key, value)
+                 VALUES (?1, ?2, ?3)",
+                params![id, key, value],
+            )?;
+        }
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Option<Conversation<'static>>, Error> {
+        let session: Option<String> = self
+            .connection()
+            .query_row(
+                "SELECT session FROM conversations WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match session {
+            Some(session) => {
+                Ok(Some(Conversation::from_jsonl(session.as_bytes())?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&self, id: &str) -> Result<bool, Error> {
+        let deleted = self
+            .connection()
+            .execute("DELETE FROM conversations WHERE id = ?1", [id])?;
+
+        Ok(deleted > 0)
+    }
+
+    fn list(&self) -> Result<Vec<Record>, Error> {
+        Self::records(
+            &self.connection(),
+            "SELECT id, created_at, updated_at FROM conversations
+             ORDER BY updated_at DESC, id",
+            [],
+        )
+    }
+
+    fn search_metadata(
+        &self,
+        key: &str,
+        value: &str,
+    ) -> Result<Vec<Record>, Error> {
+        Self::records(
+            &self.connection(),
+            "SELECT c.id, c.created_at, c.updated_at
+             FROM conversations c
+             JOIN metadata m ON m.conversation_id = c.id
+             WHERE m.key = ?1 AND m.value = ?2
+             ORDER BY c.updated_at DESC, c.id",
+            [key, value],
+        )
+    }
+}
+
+/// Convert stored milliseconds to a time. Negative values are clamped to the
+/// epoch.
+fn millis(millis: i64) -> std::time::SystemTime {
+    from_millis(u64::try_from(millis).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prompt::{self, message::Role},
+        Model,
+    };
+
+    fn conversation() -> Conversation<'static> {
+        let mut conversation =
+            Conversation::new(Model::default()).system("Be brief.");
+        conversation.push(prompt::Message::from((Role::User, "Hi!")));
+        conversation
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let conversation = conversation();
+        let metadata =
+            Metadata::from([("project".to_string(), "demo".to_string())]);
+
+        store.save("a", &conversation, &metadata).unwrap();
+        store.save("b", &conversation, &Metadata::new()).unwrap();
+
+        let loaded = store.load("a").unwrap().unwrap();
+        assert_eq!(loaded.entries.len(), 1);
+        assert_eq!(loaded.system, conversation.system);
+        assert!(store.load("missing").unwrap().is_none());
+
+        assert_eq!(store.list().unwrap().len(), 2);
+        let found = store.search_metadata("project", "demo").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, "a");
+        assert_eq!(found[0].metadata, metadata);
+        assert!(store
+            .search_metadata("project", "other")
+            .unwrap()
+            .is_empty());
+
+        // Saving again replaces the metadata.
+        store.save("a", &conversation, &Metadata::new()).unwrap();
+        assert!(store.search_metadata("project", "demo").unwrap().is_empty());
+
+        assert!(store.delete("a").unwrap());
+        assert!(!store.delete("a").unwrap());
+        assert_eq!(store.list().unwrap().len(), 1);
+    }
+}
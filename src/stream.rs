@@ -2,23 +2,46 @@
 //! associated types and errors only used when streaming.
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, pin::Pin, task::Poll};
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::Instant;
 
 #[allow(unused_imports)] // `Content`, `request` Used in docs.
 use crate::{
-    client::AnthropicError,
+    client::{AnthropicError, Limit, Limits, TooLarge},
+    conversation::now,
     prompt::{
         self,
         message::{Block, Content},
     },
     response::{self, StopReason, Usage},
+    MaybeSend,
 };
 
+mod meta;
+pub use meta::Meta;
+mod resume;
+pub mod sse;
+pub use sse::to_sse;
+
 /// Sucessful Event from the API. See [`stream::Error`] for errors.
 ///
 /// [`stream::Error`]: Error
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
+#[serde(expecting = "an event with valid fields for its `type`")]
 pub enum Event<'a> {
     /// Periodic ping.
     Ping,
@@ -31,10 +54,6 @@ pub enum Event<'a> {
     /// [`Content`] [`Block`] with empty content.
     ContentBlockStart {
         /// Index of the [`Content`] [`Block`] in [`prompt::message::Content`].
-        // TODO: Indexing. Issue is the Content::SinglePart is a String and
-        // Content::MultiPart is a Vec of Block. This is for serialization
-        // purposes. We should probably just use a Vec for both and write a
-        // custom serializer for that field.
         index: usize,
         /// Empty content block.
         content_block: Block<'a>,
@@ -56,23 +75,106 @@ pub enum Event<'a> {
     MessageDelta {
         /// Delta to apply to the [`response::Message`].
         delta: MessageDelta,
+        /// Cumulative token [`Usage`]. The API sends this next to the
+        /// `delta`, not in it, and may only include the `output_tokens`. See
+        /// [`response::Message::apply_usage`].
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
     },
     /// Message end.
     MessageStop,
+    /// The connection dropped mid-message and the request was re-issued with
+    /// the partial message as prefill. Events after this continue the same
+    /// message. This is never sent by the API. See
+    /// [`Client::stream_resumable`].
+    ///
+    /// [`Client::stream_resumable`]: crate::Client::stream_resumable
+    Resumed {
+        /// Number of times the message has been resumed, starting at 1.
+        attempt: usize,
+    },
+    /// An event type this crate does not know about, for example one added to
+    /// the API after this version was released. The raw JSON is preserved so
+    /// it can be logged, recorded, or handled by the application. Handling is
+    /// up to the caller. It is safe to ignore unknown events.
+    ///
+    /// Only the `type` is unknown. An event of a known `type` which fails to
+    /// parse is an [`Error::Parse`].
+    #[serde(untagged, deserialize_with = "unknown_event")]
+    Unknown(serde_json::Value),
+}
+
+/// `type`s of the known [`Event`]s.
+const EVENT_TYPES: &[&str] = &[
+    "ping",
+    "message_start",
+    "content_block_start",
+    "content_block_delta",
+    "content_block_stop",
+    "message_delta",
+    "message_stop",
+    "resumed",
+];
+
+/// Deserialize [`Event::Unknown`] only if the `type` is unknown.
+fn unknown_event<'de, D>(deserializer: D) -> Result<serde_json::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    crate::parse::unknown_variant(deserializer, EVENT_TYPES)
+}
+
+impl Event<'_> {
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Event<'static> {
+        match self {
+            Event::Ping => Event::Ping,
+            Event::MessageStart { message } => Event::MessageStart {
+                message: message.into_static(),
+            },
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => Event::ContentBlockStart {
+                index,
+                content_block: content_block.into_static(),
+            },
+            Event::ContentBlockDelta { index, delta } => {
+                Event::ContentBlockDelta {
+                    index,
+                    delta: delta.into_static(),
+                }
+            }
+            Event::ContentBlockStop { index } => {
+                Event::ContentBlockStop { index }
+            }
+            Event::MessageDelta { delta, usage } => {
+                Event::MessageDelta { delta, usage }
+            }
+            Event::MessageStop => Event::MessageStop,
+            Event::Resumed { attempt } => Event::Resumed { attempt },
+            Event::Unknown(value) => Event::Unknown(value),
+        }
+    }
 }
 
 /// Internal enum for the API result so we don't have to add an error variant to
 /// the `Event` enum.
 #[derive(Serialize, Deserialize)]
 #[serde(untagged)]
+#[serde(expecting = "an error or an event with valid fields for its `type`")]
+// Only lives while an event is parsed, so there's no point in boxing.
+#[allow(clippy::large_enum_variant)]
 enum ApiResult<'a> {
+    /// Error Event. This must come first because [`Event::Unknown`] will
+    /// otherwise accept anything, including errors.
+    Error { error: AnthropicError },
     /// Successful Event.
     Event {
         #[serde(flatten)]
         event: Event<'a>,
     },
-    /// Error Event.
-    Error { error: AnthropicError },
 }
 
 /// [`Text`] or [`Json`] to be applied to a [`Block::Text`] or
@@ -80,8 +182,9 @@ enum ApiResult<'a> {
 ///
 /// [`Text`]: Delta::Text
 /// [`Json`]: Delta::Json
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case", tag = "type")]
+#[serde(expecting = "a delta with valid fields for its `type`")]
 pub enum Delta<'a> {
     /// Text delta for a [`Text`] [`Content`] [`Block`].
     ///
@@ -99,6 +202,21 @@ pub enum Delta<'a> {
         /// The JSON delta.
         partial_json: Cow<'a, str>,
     },
+    /// A delta type this crate does not know about. The raw JSON is preserved.
+    /// Unknown deltas cannot be merged or applied.
+    #[serde(untagged, deserialize_with = "unknown_delta")]
+    Unknown(serde_json::Value),
+}
+
+/// `type`s of the known [`Delta`]s.
+const DELTA_TYPES: &[&str] = &["text", "text_delta", "input_json_delta"];
+
+/// Deserialize [`Delta::Unknown`] only if the `type` is unknown.
+fn unknown_delta<'de, D>(deserializer: D) -> Result<serde_json::Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    crate::parse::unknown_variant(deserializer, DELTA_TYPES)
 }
 
 /// Error when applying a [`Delta`] to a [`Content`] [`Block`] and the types do
@@ -135,9 +253,26 @@ pub enum DeltaError<'a> {
         "Cannot apply delta because deserialization failed because: {error}"
     )]
     Parse { error: String },
+    #[error("Cannot apply event because no `MessageStart` was received.")]
+    #[from(skip)]
+    NoMessage,
 }
 
 impl Delta<'_> {
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Delta<'static> {
+        match self {
+            Delta::Text { text } => Delta::Text {
+                text: Cow::Owned(text.into_owned()),
+            },
+            Delta::Json { partial_json } => Delta::Json {
+                partial_json: Cow::Owned(partial_json.into_owned()),
+            },
+            Delta::Unknown(value) => Delta::Unknown(value),
+        }
+    }
+
     /// Merge another [`Delta`] onto the end of `self`.
     pub fn merge(mut self, delta: Delta) -> Result<Self, ContentMismatch> {
         match (&mut self, delta) {
@@ -158,6 +293,7 @@ impl Delta<'_> {
                     to: match to {
                         Delta::Text { .. } => stringify!(Delta::Text),
                         Delta::Json { .. } => stringify!(Delta::Json),
+                        Delta::Unknown(_) => stringify!(Delta::Unknown),
                     },
                 });
             }
@@ -169,7 +305,7 @@ impl Delta<'_> {
 
 /// Metadata about a message in progress. This does not contain actual text
 /// deltas. That's the [`Delta`] in [`Event::ContentBlockDelta`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MessageDelta {
     /// Stop reason.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -177,7 +313,7 @@ pub struct MessageDelta {
     /// Stop sequence.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_sequence: Option<Cow<'static, str>>,
-    /// Token usage.
+    /// Token usage. The API sends this in the [`Event::MessageDelta`] instead.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<Usage>,
 }
@@ -192,11 +328,15 @@ pub enum Error {
         /// Error from the `eventsource_stream` crate.
         error: eventsource_stream::EventStreamError<reqwest::Error>,
     },
-    /// JSON parsing error.
-    #[error("JSON error: {error}")]
+    /// JSON parsing error. An event of an unknown `type`, or with a block or
+    /// delta of an unknown `type`, is not an error. It is parsed as an
+    /// `Unknown` variant instead.
+    #[error("JSON error in {context}: {error}")]
     Parse {
         /// Error from [`serde_json`].
         error: serde_json::Error,
+        /// What failed to parse. Boxed to keep the error small.
+        context: Box<ParseContext>,
         /// [`eventsource_stream::Event`] that did not parse.
         event: eventsource_stream::Event,
     },
@@ -208,20 +348,192 @@ pub enum Error {
         /// [`eventsource_stream::Event`] containing the error.
         event: eventsource_stream::Event,
     },
+    /// One of the [`Limits`] was exceeded. The stream ends after this.
+    #[error("Too large: {error}")]
+    TooLarge {
+        /// Which limit was exceeded and by how much.
+        #[from]
+        error: TooLarge,
+    },
+    /// The request to resume a dropped message failed. The stream ends after
+    /// this. See [`Client::stream_resumable`].
+    ///
+    /// [`Client::stream_resumable`]: crate::Client::stream_resumable
+    #[error("Could not resume: {error}")]
+    Resume {
+        /// Error from the request.
+        error: crate::client::Error,
+    },
 }
 
-/// Stream of [`Event`]s or [`Error`]s.
-pub struct Stream<'a> {
-    inner: Pin<
-        Box<
-            dyn futures::Stream<Item = Result<Event<'a>, Error>>
-                + Send
-                + 'static,
+/// The `type`s of an event which failed to parse. See [`Error::Parse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParseContext {
+    /// `type` of the [`Event`], such as `content_block_start`.
+    pub event_type: Option<String>,
+    /// `type` of the [`Block`] or [`Delta`] in the event, if any, such as
+    /// `server_tool_use`.
+    pub inner_type: Option<String>,
+}
+
+impl ParseContext {
+    /// Find the `type`s in the raw `data` of an event, if it's JSON.
+    fn new(data: &str) -> Self {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(data) else {
+            return Self::default();
+        };
+        let type_of = |value: &serde_json::Value| {
+            value.get("type")?.as_str().map(str::to_string)
+        };
+
+        Self {
+            event_type: type_of(&value),
+            inner_type: ["content_block", "delta"]
+                .into_iter()
+                .find_map(|key| type_of(value.get(key)?)),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.event_type, &self.inner_type) {
+            (Some(event), Some(inner)) => write!(f, "`{event}` (`{inner}`)"),
+            (Some(event), None) => write!(f, "`{event}`"),
+            (None, _) => f.write_str("event"),
+        }
+    }
+}
+
+/// Enforces [`Limits`] on a [`Stream`].
+struct Guard {
+    limits: Limits,
+    /// Bytes of event data received so far.
+    received: usize,
+    /// Index and size of the current content [`Block`].
+    block: Option<(usize, usize)>,
+    /// Set once a limit is exceeded, to end the stream.
+    exceeded: bool,
+}
+
+impl Guard {
+    /// Parse an event and check it against the [`Limits`]. Returns [`None`]
+    /// to end the stream after a limit has been exceeded.
+    fn check(
+        &mut self,
+        event: Result<
+            eventsource_stream::Event,
+            eventsource_stream::EventStreamError<reqwest::Error>,
         >,
-    >,
+    ) -> Option<Result<Timed<Event<'static>>, Error>> {
+        if self.exceeded {
+            return None;
+        }
+
+        let received = event.as_ref().map(|e| e.data.len()).unwrap_or(0);
+        let result = Stream::parse(event);
+        let parsed = result.as_ref().map(|timed| &timed.event);
+        match self.measure(received, parsed) {
+            Ok(()) => Some(result),
+            Err(error) => {
+                self.exceeded = true;
+                Some(Err(error.into()))
+            }
+        }
+    }
+
+    fn measure(
+        &mut self,
+        received: usize,
+        result: Result<&Event<'_>, &Error>,
+    ) -> Result<(), TooLarge> {
+        self.received += received;
+        if let Some(max) = self.limits.response_body {
+            Limits::check(Limit::ResponseBody, self.received, max)?;
+        }
+
+        let Some(max) = self.limits.content_block else {
+            return Ok(());
+        };
+        let (index, size) = match result {
+            Ok(Event::ContentBlockStart {
+                index,
+                content_block,
+            }) => (*index, Limits::block_size(content_block)),
+            Ok(Event::ContentBlockDelta { index, delta }) => {
+                let size = match delta {
+                    Delta::Text { text } => text.len(),
+                    Delta::Json { partial_json } => partial_json.len(),
+                    Delta::Unknown(_) => 0,
+                };
+                (*index, size)
+            }
+            _ => return Ok(()),
+        };
+
+        // Blocks are streamed one at a time, so only the current one needs
+        // to be tracked.
+        let total = match self.block {
+            Some((current, total)) if current == index => total + size,
+            _ => size,
+        };
+        self.block = Some((index, total));
+
+        Limits::check(Limit::ContentBlock { index }, total, max)
+    }
+}
+
+/// An item from a [`Stream`] with the time it was received and the raw
+/// [`eventsource_stream::Event`] it was parsed from. See [`Stream::timed`].
+#[derive(Clone, Debug)]
+pub struct Timed<T> {
+    /// The parsed item.
+    pub event: T,
+    /// When the event was received, by the wall clock so it can be correlated
+    /// with logs. Use the difference between two of these for latencies.
+    pub received_at: SystemTime,
+    /// The event as received. The `data` is the JSON the item was parsed
+    /// from.
+    pub raw: eventsource_stream::Event,
+}
+
+impl<T> Timed<T> {
+    /// Discard the time and the raw event.
+    pub fn into_inner(self) -> T {
+        self.event
+    }
+}
+
+/// Boxed inner stream of a [`Stream`]. [`Send`] with the `send` feature.
+/// Events are always owned. They are shortened to the lifetime of the
+/// [`Stream`] when yielded.
+#[cfg(feature = "send")]
+type BoxStream =
+    futures::stream::BoxStream<'static, Result<Timed<Event<'static>>, Error>>;
+/// Boxed inner stream of a [`Stream`]. [`Send`] with the `send` feature.
+/// Events are always owned. They are shortened to the lifetime of the
+/// [`Stream`] when yielded.
+#[cfg(not(feature = "send"))]
+type BoxStream = futures::stream::LocalBoxStream<
+    'static,
+    Result<Timed<Event<'static>>, Error>,
+>;
+
+/// Stream of [`Event`]s or [`Error`]s. It is [`Send`] with the `send` feature
+/// (the default).
+///
+/// The events are always owned, so the lifetime can be extended with
+/// [`Stream::into_owned`].
+pub struct Stream<'a> {
+    inner: BoxStream,
+    meta: Option<Meta>,
+    lifetime: PhantomData<Event<'a>>,
 }
 
+#[cfg(feature = "send")]
 static_assertions::assert_impl_all!(Stream<'_>: futures::Stream, Send);
+#[cfg(not(feature = "send"))]
+static_assertions::assert_impl_all!(Stream<'_>: futures::Stream);
 
 impl Stream<'_> {
     /// Create a new stream from an [`eventsource_stream::EventStream`] or
@@ -233,29 +545,77 @@ impl Stream<'_> {
                     eventsource_stream::Event,
                     eventsource_stream::EventStreamError<reqwest::Error>,
                 >,
-            > + Send
+            > + MaybeSend
+            + 'static,
+    {
+        Self::with_limits(stream, Limits::default())
+    }
+
+    /// Create a new stream like [`Stream::new`] which ends with an
+    /// [`Error::TooLarge`] if the event data exceeds the
+    /// [`Limits::response_body`] or a content [`Block`] exceeds the
+    /// [`Limits::content_block`].
+    pub fn with_limits<S>(stream: S, limits: Limits) -> Self
+    where
+        S: futures::Stream<
+                Item = Result<
+                    eventsource_stream::Event,
+                    eventsource_stream::EventStreamError<reqwest::Error>,
+                >,
+            > + MaybeSend
             + 'static,
     {
+        let guard = Guard {
+            limits,
+            received: 0,
+            block: None,
+            exceeded: false,
+        };
+
         Self {
-            inner: Box::pin(stream.map(|event| match event {
-                Ok(event) => {
-                    #[cfg(feature = "log")]
-                    log::trace!("Event: {:?}", event);
-
-                    match serde_json::from_str::<ApiResult>(&event.data) {
-                        Ok(ApiResult::Event { event }) => Ok(event),
-                        Ok(ApiResult::Error { error }) => {
-                            Err(Error::Anthropic { error, event })
-                        }
-                        Err(error) => Err(Error::Parse { error, event }),
+            inner: Box::pin(stream.scan(guard, |guard, event| {
+                futures::future::ready(guard.check(event))
+            })),
+            meta: None,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Parse an [`eventsource_stream::Event`], keeping it and the time it was
+    /// received.
+    fn parse<'a>(
+        event: Result<
+            eventsource_stream::Event,
+            eventsource_stream::EventStreamError<reqwest::Error>,
+        >,
+    ) -> Result<Timed<Event<'a>>, Error> {
+        let received_at = now();
+        match event {
+            Ok(event) => {
+                #[cfg(feature = "log")]
+                log::trace!("Event: {:?}", event);
+
+                match crate::parse::from_str::<ApiResult>(&event.data) {
+                    Ok(ApiResult::Event { event: parsed }) => Ok(Timed {
+                        event: parsed,
+                        received_at,
+                        raw: event,
+                    }),
+                    Ok(ApiResult::Error { error }) => {
+                        Err(Error::Anthropic { error, event })
                     }
+                    Err(error) => Err(Error::Parse {
+                        error,
+                        context: Box::new(ParseContext::new(&event.data)),
+                        event,
+                    }),
                 }
-                Err(error) => {
-                    #[cfg(feature = "log")]
-                    log::error!("Stream error: {:?}", error);
-                    Err(Error::Stream { error })
-                }
-            })),
+            }
+            Err(error) => {
+                #[cfg(feature = "log")]
+                log::error!("Stream error: {:?}", error);
+                Err(Error::Stream { error })
+            }
         }
     }
 
@@ -273,6 +633,29 @@ impl Stream<'_> {
 impl<'a> futures::Stream for Stream<'a> {
     type Item = Result<Event<'a>, Error>;
 
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context,
+    ) -> Poll<Option<Self::Item>> {
+        self.inner
+            .as_mut()
+            .poll_next(cx)
+            .map(|item| item.map(|result| result.map(Timed::into_inner)))
+    }
+}
+
+/// A [`Stream`] of [`Timed`] [`Event`]s. See [`Stream::timed`].
+pub struct TimedStream<'a> {
+    inner: BoxStream,
+    lifetime: PhantomData<Event<'a>>,
+}
+
+#[cfg(feature = "send")]
+static_assertions::assert_impl_all!(TimedStream<'_>: futures::Stream, Send);
+
+impl<'a> futures::Stream for TimedStream<'a> {
+    type Item = Result<Timed<Event<'a>>, Error>;
+
     fn poll_next(
         mut self: Pin<&mut Self>,
         cx: &mut std::task::Context,
@@ -281,6 +664,227 @@ impl<'a> futures::Stream for Stream<'a> {
     }
 }
 
+impl<'a> Stream<'a> {
+    /// Yield each [`Event`] with the time it was received and the raw
+    /// [`eventsource_stream::Event`] it was parsed from. This is useful to
+    /// measure latency between events or to log exactly what the API sent.
+    /// [`Error`]s are unchanged; they already include the raw event where
+    /// there is one.
+    pub fn timed(self) -> TimedStream<'a> {
+        TimedStream {
+            inner: self.inner,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Convert to a `'static` lifetime so the stream and its [`Event`]s can be
+    /// stored without borrowing. This is free since the events are always
+    /// owned. See also [`Event::into_static`].
+    pub fn into_owned(self) -> Stream<'static> {
+        Stream {
+            inner: self.inner,
+            meta: self.meta,
+            lifetime: PhantomData,
+        }
+    }
+
+    /// Split the stream into `n` independent [`Tee`]s. Every [`Tee`] receives
+    /// every [`Event`] (cloned) and every [`Error`] (shared through an
+    /// [`Arc`]) in the original order. This is useful to, for example, feed a
+    /// UI, an accumulator, and metrics from the same response.
+    ///
+    /// ## Notes:
+    /// - Items are buffered for each [`Tee`] until it is polled, so a [`Tee`]
+    ///   that is never polled will buffer the entire response. Drop any
+    ///   [`Tee`] you do not intend to consume.
+    /// - If `n` is zero, the stream is dropped and an empty [`Vec`] returned.
+    pub fn tee(self, n: usize) -> Vec<Tee<'a>> {
+        if n == 0 {
+            return vec![];
+        }
+
+        let shared = Arc::new(Mutex::new(TeeShared {
+            inner: self,
+            buffers: (0..n).map(|_| Some(VecDeque::new())).collect(),
+            done: false,
+        }));
+        let notifier = Arc::new(TeeNotifier {
+            wakers: Mutex::new(vec![None; n]),
+        });
+
+        (0..n)
+            .map(|index| Tee {
+                index,
+                shared: shared.clone(),
+                notifier: notifier.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Receiver from [`Stream::into_channel`].
+#[cfg(feature = "tokio")]
+pub type Receiver = tokio::sync::mpsc::Receiver<Result<Event<'static>, Error>>;
+
+#[cfg(feature = "tokio")]
+impl Stream<'static> {
+    /// Drive the stream from a task on the current Tokio runtime, sending each
+    /// item to a [`tokio::sync::mpsc`] channel with capacity `buffer` (at least
+    /// one). This is useful for apps built around channels or actors. This
+    /// requires the `tokio` feature.
+    ///
+    /// Returns the receiver and the handle of the driving task. The task ends
+    /// when the stream ends or the receiver is dropped, which drops the stream
+    /// and closes the connection. Aborting the task does the same.
+    ///
+    /// Fails if not called from within a Tokio runtime.
+    pub fn into_channel(
+        mut self,
+        buffer: usize,
+    ) -> Result<
+        (Receiver, tokio::task::JoinHandle<()>),
+        tokio::runtime::TryCurrentError,
+    > {
+        let runtime = tokio::runtime::Handle::try_current()?;
+        let (sender, receiver) = tokio::sync::mpsc::channel(buffer.max(1));
+
+        let task = runtime.spawn(async move {
+            while let Some(item) = self.next().await {
+                if sender.send(item).await.is_err() {
+                    // The receiver was dropped.
+                    break;
+                }
+            }
+        });
+
+        Ok((receiver, task))
+    }
+}
+
+/// Item yielded by a [`Tee`]. [`Error`]s are not [`Clone`] so they are shared
+/// between [`Tee`]s with an [`Arc`].
+pub type TeeItem<'a> = Result<Event<'a>, Arc<Error>>;
+
+/// State shared by all [`Tee`]s created by [`Stream::tee`].
+struct TeeShared<'a> {
+    /// The source stream.
+    inner: Stream<'a>,
+    /// Pending items for each [`Tee`]. [`None`] if the [`Tee`] was dropped.
+    buffers: Vec<Option<VecDeque<TeeItem<'a>>>>,
+    /// Whether the source stream is exhausted.
+    done: bool,
+}
+
+/// Wakes every [`Tee`] waiting on the source stream. The source stream only
+/// remembers the last [`Waker`] it was polled with, so we poll it with this
+/// instead, otherwise a [`Tee`] could wait forever on an item another [`Tee`]
+/// already received.
+struct TeeNotifier {
+    wakers: Mutex<Vec<Option<Waker>>>,
+}
+
+impl Wake for TeeNotifier {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut wakers = self.wakers.lock().unwrap_or_else(|e| e.into_inner());
+        for waker in wakers.iter_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+    }
+}
+
+/// One of several independent consumers of the same [`Stream`]. See
+/// [`Stream::tee`].
+pub struct Tee<'a> {
+    index: usize,
+    shared: Arc<Mutex<TeeShared<'a>>>,
+    notifier: Arc<TeeNotifier>,
+}
+
+#[cfg(feature = "send")]
+static_assertions::assert_impl_all!(Tee<'_>: futures::Stream, Send);
+
+impl<'a> futures::Stream for Tee<'a> {
+    type Item = TeeItem<'a>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        // A poisoned lock just means another `Tee` panicked while holding it.
+        // The state itself is still consistent, so we carry on.
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        let shared = &mut *shared;
+
+        if let Some(item) = shared.buffers[self.index]
+            .as_mut()
+            .and_then(VecDeque::pop_front)
+        {
+            return Poll::Ready(Some(item));
+        }
+
+        if shared.done {
+            return Poll::Ready(None);
+        }
+
+        // Register our interest before polling the source so we don't miss a
+        // wakeup that happens in between.
+        self.notifier
+            .wakers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())[self.index] =
+            Some(cx.waker().clone());
+        let waker = Waker::from(self.notifier.clone());
+
+        match shared
+            .inner
+            .poll_next_unpin(&mut Context::from_waker(&waker))
+        {
+            Poll::Ready(Some(item)) => {
+                let item = item.map_err(Arc::new);
+                for (index, buffer) in shared.buffers.iter_mut().enumerate() {
+                    if index == self.index {
+                        continue;
+                    }
+                    if let Some(buffer) = buffer {
+                        buffer.push_back(item.clone());
+                    }
+                }
+                // We already have our item, so only the others need waking.
+                self.notifier
+                    .wakers
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())[self.index] = None;
+                self.notifier.wake_by_ref();
+
+                Poll::Ready(Some(item))
+            }
+            Poll::Ready(None) => {
+                shared.done = true;
+                self.notifier.wake_by_ref();
+
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Tee<'_> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        // Stop buffering items for this `Tee`.
+        shared.buffers[self.index] = None;
+        self.notifier
+            .wakers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())[self.index] = None;
+    }
+}
+
 /// Extension trait for our crate [`Event`] [`Stream`]s to filter out
 /// [`RateLimit`] and [`Overloaded`] [`AnthropicError`]s, as well as several
 /// other common use cases.
@@ -290,7 +894,7 @@ impl<'a> futures::Stream for Stream<'a> {
 /// [`RateLimit`]: AnthropicError::RateLimit
 /// [`Overloaded`]: AnthropicError::Overloaded
 pub trait FilterExt<'a>:
-    futures::stream::Stream<Item = Result<Event<'a>, Error>> + Sized + Send
+    futures::stream::Stream<Item = Result<Event<'a>, Error>> + Sized + MaybeSend
 {
     /// Filter out rate limit and overload errors. Because the server sends
     /// these events there isn't a need to retry or backoff. The stream will
@@ -299,7 +903,7 @@ pub trait FilterExt<'a>:
     /// This is recommended for most use cases.
     fn filter_rate_limit(
         self,
-    ) -> impl futures::Stream<Item = Result<Event<'a>, Error>> + Send {
+    ) -> impl futures::Stream<Item = Result<Event<'a>, Error>> + MaybeSend {
         self.filter_map(|result| async move {
             match result {
                 Ok(event) => Some(Ok(event)),
@@ -318,7 +922,7 @@ pub trait FilterExt<'a>:
     /// text, JSON, and tool use.
     fn deltas(
         self,
-    ) -> impl futures::Stream<Item = Result<Delta<'a>, Error>> + Send {
+    ) -> impl futures::Stream<Item = Result<Delta<'a>, Error>> + MaybeSend {
         self.filter_map(|result| async move {
             match result {
                 Ok(Event::ContentBlockDelta { delta, .. }) => Some(Ok(delta)),
@@ -330,7 +934,8 @@ pub trait FilterExt<'a>:
     /// Filter out everything but text pieces.
     fn text(
         self,
-    ) -> impl futures::Stream<Item = Result<Cow<'a, str>, Error>> + Send {
+    ) -> impl futures::Stream<Item = Result<Cow<'a, str>, Error>> + MaybeSend
+    {
         self.deltas().filter_map(|result| async move {
             match result {
                 Ok(Delta::Text { text }) => Some(Ok(text)),
@@ -338,63 +943,717 @@ pub trait FilterExt<'a>:
             }
         })
     }
+
+    /// Filter out everything but [`Event::Ping`]s, yielding when each was
+    /// received. The server sends pings periodically, so these can be used to
+    /// tell a slow response from a dead connection, for example to reset a
+    /// watchdog. Other filters drop pings. Use [`Stream::tee`] to watch for
+    /// pings while consuming the rest of the stream.
+    fn heartbeats(self) -> impl futures::Stream<Item = Instant> + MaybeSend {
+        self.filter_map(|result| async move {
+            match result {
+                Ok(Event::Ping) => Some(Instant::now()),
+                _ => None,
+            }
+        })
+    }
+
+    /// Record every [`Event`] to a `writer` as [JSON Lines] while passing the
+    /// events through unchanged. API errors and events that failed to parse
+    /// are recorded as the raw event data so they replay faithfully. The
+    /// recording can be loaded with [`mock_stream_from_jsonl`].
+    ///
+    /// Writes are blocking, so `writer` should be something cheap like a
+    /// buffered file or a [`Vec<u8>`]. See [`Recorder`] for details on write
+    /// errors.
+    ///
+    /// [JSON Lines]: <https://jsonlines.org/>
+    fn record<W>(self, writer: W) -> Recorder<Self, W>
+    where
+        W: std::io::Write + MaybeSend,
+    {
+        Recorder {
+            inner: Box::pin(self),
+            writer: Some(writer),
+            error: None,
+        }
+    }
+
+    /// Measure time to first token, latency between [`Delta`]s, tokens per
+    /// second, and total duration while passing the events through
+    /// unchanged. The [`Timings`] are available from [`Metrics::timings`]
+    /// once the stream completes.
+    fn metrics(self) -> Metrics<Self> {
+        Metrics {
+            inner: Box::pin(self),
+            start: None,
+            first_delta: None,
+            last_delta: None,
+            intervals: vec![],
+            output_tokens: None,
+            timings: None,
+        }
+    }
 }
 
 impl<'a, S> FilterExt<'a> for S where
-    S: futures::Stream<Item = Result<Event<'a>, Error>> + Send
+    S: futures::Stream<Item = Result<Event<'a>, Error>> + MaybeSend
 {
 }
 
-#[cfg(test)]
-pub(crate) mod tests {
-    use futures::TryStreamExt;
+/// Stream adapter that records [`Event`]s as JSON Lines. See
+/// [`FilterExt::record`].
+///
+/// If a write fails, recording stops but events continue to pass through. The
+/// error can be retrieved with [`Recorder::take_error`].
+pub struct Recorder<S, W> {
+    inner: Pin<Box<S>>,
+    writer: Option<W>,
+    error: Option<std::io::Error>,
+}
 
-    use super::*;
+// `W` is never pinned, so this is fine regardless of `W`.
+impl<S, W> Unpin for Recorder<S, W> {}
 
-    // Actual JSON from the API.
+impl<S, W> Recorder<S, W> {
+    /// Take the write error that stopped recording, if any.
+    pub fn take_error(&mut self) -> Option<std::io::Error> {
+        self.error.take()
+    }
 
-    pub const CONTENT_BLOCK_START: &str = "{\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"} }";
-    pub const CONTENT_BLOCK_DELTA: &str = "{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Certainly! I\"}     }";
+    /// Consume the [`Recorder`] and return the writer. Returns [`None`] if
+    /// recording was stopped because of a write error.
+    pub fn into_writer(self) -> Option<W> {
+        self.writer
+    }
+}
 
-    /// Creates a mock stream from a string (likely `include_str!`). The string
-    /// should be a series of `event`, `data`, and empty lines (a SSE stream).
-    /// Anthropic provides such example data in the API documentation.
-    pub fn mock_stream(text: &'static str) -> Stream<'static> {
-        use itertools::Itertools;
+impl<'a, S, W> Recorder<S, W>
+where
+    S: futures::Stream<Item = Result<Event<'a>, Error>>,
+    W: std::io::Write,
+{
+    /// Write a single line for the item, if there is anything to write.
+    fn write_line(
+        writer: &mut W,
+        item: &Result<Event<'a>, Error>,
+    ) -> std::io::Result<()> {
+        match item {
+            Ok(event) => {
+                serde_json::to_writer(&mut *writer, event)?;
+            }
+            Err(
+                Error::Parse { event, .. } | Error::Anthropic { event, .. },
+            ) => {
+                // JSON does not allow raw newlines in strings so this only
+                // affects insignificant whitespace (or garbage, which we
+                // preserve as best we can).
+                writer.write_all(event.data.replace('\n', " ").as_bytes())?;
+            }
+            // There is no event data for HTTP errors or exceeded limits.
+            Err(
+                Error::Stream { .. }
+                | Error::TooLarge { .. }
+                | Error::Resume { .. },
+            ) => return Ok(()),
+        }
 
-        // TODO: one of every possible variants, even if it doesn't make sense.
-        let inner = futures::stream::iter(
-            // first line should be `event`, second line should be `data`, third
-            // line should be empty.
-            text.lines().tuples().map(|(event, data, _empty)| {
-                assert!(_empty.is_empty());
+        writer.write_all(b"\n")
+    }
+}
 
-                Ok(eventsource_stream::Event {
-                    event: event.strip_prefix("event: ").unwrap().into(),
-                    data: data.strip_prefix("data: ").unwrap().into(),
-                    id: "".into(),
-                    retry: None,
-                })
-            }),
-        );
+impl<'a, S, W> futures::Stream for Recorder<S, W>
+where
+    S: futures::Stream<Item = Result<Event<'a>, Error>>,
+    W: std::io::Write,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let poll = this.inner.as_mut().poll_next(cx);
+
+        if let Some(writer) = this.writer.as_mut() {
+            let result = match &poll {
+                Poll::Ready(Some(item)) => Self::write_line(writer, item),
+                Poll::Ready(None) => writer.flush(),
+                Poll::Pending => Ok(()),
+            };
+
+            if let Err(error) = result {
+                #[cfg(feature = "log")]
+                log::error!("Stopped recording stream because: {}", error);
+                this.writer = None;
+                this.error = Some(error);
+            }
+        }
 
-        Stream::new(inner)
+        poll
     }
+}
 
-    #[test]
-    fn test_content_block_start() {
-        let event: Event = serde_json::from_str(CONTENT_BLOCK_START).unwrap();
-        match event {
-            Event::ContentBlockStart {
-                index,
-                content_block,
-            } => {
-                assert_eq!(index, 0);
-                #[cfg(feature = "prompt-caching")]
-                if let Block::Text {
-                    text,
-                    cache_control,
-                } = content_block
+/// Stream adapter that measures [`Timings`]. See [`FilterExt::metrics`].
+pub struct Metrics<S> {
+    inner: Pin<Box<S>>,
+    /// When the stream was first polled.
+    start: Option<Instant>,
+    first_delta: Option<Instant>,
+    last_delta: Option<Instant>,
+    /// Time between consecutive [`Delta`]s.
+    intervals: Vec<Duration>,
+    /// Output tokens from the final [`MessageDelta`] usage.
+    output_tokens: Option<u64>,
+    timings: Option<Timings>,
+}
+
+impl<S> Metrics<S> {
+    /// The [`Timings`], once the stream has completed.
+    pub fn timings(&self) -> Option<&Timings> {
+        self.timings.as_ref()
+    }
+}
+
+impl<'a, S> futures::Stream for Metrics<S>
+where
+    S: futures::Stream<Item = Result<Event<'a>, Error>>,
+{
+    type Item = S::Item;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let start = *this.start.get_or_insert_with(Instant::now);
+        let poll = this.inner.as_mut().poll_next(cx);
+
+        match &poll {
+            Poll::Ready(Some(Ok(Event::ContentBlockDelta { .. }))) => {
+                let now = Instant::now();
+                if let Some(last) = this.last_delta.replace(now) {
+                    this.intervals.push(now - last);
+                }
+                this.first_delta.get_or_insert(now);
+            }
+            Poll::Ready(Some(Ok(Event::MessageDelta { delta, usage }))) => {
+                if let Some(usage) = usage.as_ref().or(delta.usage.as_ref()) {
+                    this.output_tokens = Some(usage.output_tokens);
+                }
+            }
+            Poll::Ready(None) if this.timings.is_none() => {
+                let now = Instant::now();
+                let mut intervals = std::mem::take(&mut this.intervals);
+                intervals.sort_unstable();
+                this.timings = Some(Timings {
+                    time_to_first_token: this.first_delta.map(|t| t - start),
+                    generation: this
+                        .first_delta
+                        .zip(this.last_delta)
+                        .map(|(first, last)| last - first),
+                    total: now - start,
+                    deltas: intervals.len()
+                        + this.first_delta.is_some() as usize,
+                    output_tokens: this.output_tokens,
+                    intervals,
+                });
+            }
+            _ => {}
+        }
+
+        poll
+    }
+}
+
+/// Timings of a streamed response, measured by [`Metrics`].
+///
+/// Latency is measured between [`Delta`]s, which usually contain one token
+/// but may contain several.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timings {
+    /// Time from first polling the stream to the first [`Delta`]. This does
+    /// not include the time to receive the response headers, which happens
+    /// before the [`Stream`] is returned.
+    pub time_to_first_token: Option<Duration>,
+    /// Time from the first to the last [`Delta`].
+    pub generation: Option<Duration>,
+    /// Time from first polling the stream to the end of the stream.
+    pub total: Duration,
+    /// Number of [`Delta`]s received.
+    pub deltas: usize,
+    /// Output tokens reported by the API in the final [`MessageDelta`].
+    pub output_tokens: Option<u64>,
+    /// Time between consecutive [`Delta`]s, sorted.
+    intervals: Vec<Duration>,
+}
+
+impl Timings {
+    /// Latency between [`Delta`]s at percentile `p` (0 to 100), using the
+    /// nearest rank. Returns [`None`] if there were fewer than two
+    /// [`Delta`]s.
+    pub fn inter_token_latency(&self, p: f64) -> Option<Duration> {
+        let last = self.intervals.len().checked_sub(1)?;
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * last as f64).round();
+        self.intervals.get(rank as usize).copied()
+    }
+
+    /// Output tokens per second during [`generation`]. Uses
+    /// [`output_tokens`] if reported, otherwise the number of [`Delta`]s.
+    ///
+    /// [`generation`]: Timings::generation
+    /// [`output_tokens`]: Timings::output_tokens
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let seconds = self.generation?.as_secs_f64();
+        let tokens = self.output_tokens.unwrap_or(self.deltas as u64);
+        (seconds > 0.0).then(|| tokens as f64 / seconds)
+    }
+}
+
+/// Load a [`Stream`] from [JSON Lines], for example one written by
+/// [`FilterExt::record`]. Each non-empty line is one event's data, exactly as
+/// the API would send it. This is useful to replay recorded responses in tests
+/// without making requests.
+///
+/// [JSON Lines]: <https://jsonlines.org/>
+pub fn mock_stream_from_jsonl<R>(reader: R) -> std::io::Result<Stream<'static>>
+where
+    R: std::io::BufRead,
+{
+    let mut lines = vec![];
+    for line in reader.lines() {
+        let data = line?;
+        if !data.trim().is_empty() {
+            lines.push(data);
+        }
+    }
+
+    Ok(mock_stream_from_data(lines))
+}
+
+/// Create a [`Stream`] from each event's data, exactly as the API would send
+/// it.
+pub(crate) fn mock_stream_from_data<I>(data: I) -> Stream<'static>
+where
+    I: IntoIterator<Item = String>,
+{
+    let events: Vec<_> = data
+        .into_iter()
+        .map(|data| {
+            // The SSE event name is the same as the `type` in the data. This
+            // isn't used by the `Stream`, but we might as well be accurate.
+            let event = serde_json::from_str::<serde_json::Value>(&data)
+                .ok()
+                .and_then(|v| v.get("type")?.as_str().map(ToString::to_string))
+                .unwrap_or_default();
+
+            Ok(eventsource_stream::Event {
+                event,
+                data,
+                id: String::new(),
+                retry: None,
+            })
+        })
+        .collect();
+
+    Stream::new(futures::stream::iter(events))
+}
+
+/// Accumulates [`Event`]s into a [`response::Message`].
+///
+/// Text is applied as it arrives, so [`Accumulator::message`] can be used to
+/// show progress. JSON for tool use is kept as text until the block stops,
+/// since partial JSON is not useful (or parseable). See [`Block::finish`].
+/// [`Delta`]s are applied to the [`Block`] at their `index`, so interleaved
+/// blocks are handled.
+///
+/// ```
+/// use misanthropic::stream::{Accumulator, Event};
+///
+/// let mut acc = Accumulator::new().trim_stop_sequences(["\n\nHuman:"]);
+/// # let events: Vec<Event> = vec![];
+/// for event in events {
+///     acc.apply_event(event)?;
+/// }
+/// let message = acc.finish();
+/// # assert!(message.is_err());
+/// # Ok::<(), misanthropic::stream::DeltaError>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct Accumulator<'a> {
+    message: Option<response::Message<'a>>,
+    /// Index in the [`Content`] of the API's block `0`. This is not `0` if
+    /// there is a prefill.
+    offset: usize,
+    /// Index of the last [`Block`] with JSON [`Delta`]s, until it stops.
+    json_index: Option<usize>,
+    stop_sequences: Vec<Cow<'a, str>>,
+    /// Minimum characters of a partial stop sequence to trim. [`None`] for
+    /// [`Accumulator::MIN_PARTIAL_STOP_LEN`].
+    min_partial_stop_len: Option<usize>,
+    prefill: Option<crate::CowStr<'a>>,
+    /// True until the first [`Block`] starts, if there is a prefill.
+    merge_prefill: bool,
+    /// Called on every [`Event::Ping`].
+    on_ping: Option<OnPing<'a>>,
+}
+
+/// Callback for [`Accumulator::on_ping`]. [`Send`] with the `send` feature.
+#[cfg(feature = "send")]
+struct OnPing<'a>(Box<dyn FnMut() + Send + 'a>);
+/// Callback for [`Accumulator::on_ping`]. [`Send`] with the `send` feature.
+#[cfg(not(feature = "send"))]
+struct OnPing<'a>(Box<dyn FnMut() + 'a>);
+
+impl std::fmt::Debug for OnPing<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnPing")
+    }
+}
+
+impl<'a> Accumulator<'a> {
+    /// Create an empty [`Accumulator`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trim a trailing partial stop sequence from the final text when
+    /// [`finish`]ing, for example `"\n\nHum"` when generation was cut off
+    /// before `"\n\nHuman:"` could complete. This only applies if the
+    /// [`StopReason`] is [`MaxTokens`] or the stream ended without one. Use
+    /// the same sequences as [`Prompt::stop_sequences`].
+    ///
+    /// A stop sequence that fired is never part of the text, so nothing is
+    /// trimmed for [`StopSequence`]. It is in
+    /// [`response::Message::stop_sequence`].
+    ///
+    /// Short or whitespace-only partial matches are kept, since they are more
+    /// likely real output, such as the `"\n"` of `"Hello!\n"` or the `"#"`
+    /// of `"Issue #"` with `"###"`. See [`min_partial_stop_len`].
+    ///
+    /// [`finish`]: Accumulator::finish
+    /// [`min_partial_stop_len`]: Accumulator::min_partial_stop_len
+    /// [`StopSequence`]: StopReason::StopSequence
+    /// [`MaxTokens`]: StopReason::MaxTokens
+    /// [`Prompt::stop_sequences`]: crate::Prompt::stop_sequences
+    pub fn trim_stop_sequences<S, Ss>(mut self, stop_sequences: Ss) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+        Ss: IntoIterator<Item = S>,
+    {
+        self.stop_sequences =
+            stop_sequences.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Default for [`min_partial_stop_len`].
+    ///
+    /// [`min_partial_stop_len`]: Accumulator::min_partial_stop_len
+    pub const MIN_PARTIAL_STOP_LEN: usize = 2;
+
+    /// Set the minimum number of characters of a partial stop sequence to
+    /// trim. See [`trim_stop_sequences`]. The default is
+    /// [`MIN_PARTIAL_STOP_LEN`]. Whitespace-only matches are never trimmed.
+    ///
+    /// [`trim_stop_sequences`]: Accumulator::trim_stop_sequences
+    /// [`MIN_PARTIAL_STOP_LEN`]: Accumulator::MIN_PARTIAL_STOP_LEN
+    pub fn min_partial_stop_len(mut self, len: usize) -> Self {
+        self.min_partial_stop_len = Some(len);
+        self
+    }
+
+    /// Prepend the `prefill` text to the response, so the [`message`] has the
+    /// full text. Use the same text as [`Prompt::prefill`], since the API only
+    /// returns the continuation.
+    ///
+    /// [`message`]: Accumulator::message
+    /// [`Prompt::prefill`]: crate::Prompt::prefill
+    pub fn prefill<T>(mut self, prefill: T) -> Self
+    where
+        T: Into<crate::CowStr<'a>>,
+    {
+        self.prefill = Some(prefill.into());
+        self
+    }
+
+    /// Call `callback` on every [`Event::Ping`], for example to reset a
+    /// watchdog. Pings don't otherwise change the [`message`].
+    ///
+    /// [`message`]: Accumulator::message
+    pub fn on_ping<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut() + MaybeSend + 'a,
+    {
+        self.on_ping = Some(OnPing(Box::new(callback)));
+        self
+    }
+
+    /// Apply an [`Event`]. See [`apply_event`].
+    ///
+    /// [`apply_event`]: Accumulator::apply_event
+    pub fn push(&mut self, event: Event<'a>) -> Result<(), DeltaError<'a>> {
+        self.apply_event(event)
+    }
+
+    /// Apply an [`Event`]. [`Event::Ping`], [`Event::Resumed`], and
+    /// [`Event::Unknown`] are ignored,
+    /// other than calling the [`on_ping`] callback.
+    ///
+    /// An [`Event::ContentBlockStart`] creates a [`Block`] and must be for the
+    /// next `index`. [`Delta`]s are applied to the [`Block`] at their `index`.
+    /// Otherwise, this returns an [`OutOfBounds`] error. A [`Delta::Unknown`],
+    /// or any [`Delta`] for a [`Block::Unknown`], is skipped, so blocks this
+    /// crate doesn't know about, such as thinking, are kept as they started.
+    ///
+    /// [`on_ping`]: Accumulator::on_ping
+    pub fn apply_event(
+        &mut self,
+        event: Event<'a>,
+    ) -> Result<(), DeltaError<'a>> {
+        if let (Event::Ping, Some(OnPing(callback))) =
+            (&event, &mut self.on_ping)
+        {
+            callback();
+        }
+
+        if let Event::MessageStart { mut message } = event {
+            if let Some(prefill) = &self.prefill {
+                message.message.content.push(Block::text(prefill.clone()));
+            }
+            self.merge_prefill = self.prefill.is_some();
+            self.offset = message.message.content.iter().len();
+            self.message = Some(message);
+            self.json_index = None;
+            return Ok(());
+        }
+
+        let message = match (&mut self.message, &event) {
+            (
+                _,
+                Event::Ping
+                | Event::Resumed { .. }
+                | Event::Unknown(_)
+                | Event::MessageStop,
+            ) => return Ok(()),
+            (Some(message), _) => message,
+            (None, _) => return Err(DeltaError::NoMessage),
+        };
+
+        let blocks = &mut message.message.content.0;
+        match event {
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                match content_block {
+                    // The continuation of the prefill goes in the same block.
+                    Block::Text { text, .. }
+                        if std::mem::take(&mut self.merge_prefill) =>
+                    {
+                        self.offset = self.offset.saturating_sub(1);
+                        let block = block_at(blocks, self.offset, index)?;
+                        if !text.is_empty() {
+                            block.merge_deltas([Delta::Text {
+                                text: Cow::Owned(text.to_string()),
+                            }])?;
+                        }
+                    }
+                    content_block => {
+                        self.merge_prefill = false;
+                        let next = blocks.len().saturating_sub(self.offset);
+                        if index != next {
+                            return Err(OutOfBounds { index, max: next }.into());
+                        }
+                        blocks.push(content_block);
+                    }
+                }
+            }
+            Event::ContentBlockDelta { index, delta } => {
+                let block = block_at(blocks, self.offset, index)?;
+                // Such as `thinking_delta`s for a thinking block, which this
+                // crate doesn't know about, or a `citations_delta` for text.
+                if matches!(delta, Delta::Unknown(_))
+                    || matches!(block, Block::Unknown(_))
+                {
+                    return Ok(());
+                }
+                if let Delta::Json { .. } = delta {
+                    self.json_index = Some(index);
+                }
+                block.merge_deltas([delta])?;
+            }
+            Event::ContentBlockStop { index } => {
+                if self.json_index == Some(index) {
+                    self.json_index = None;
+                }
+                block_at(blocks, self.offset, index)?.finish()?;
+            }
+            Event::MessageDelta { delta, usage } => {
+                message.apply_delta(delta);
+                if let Some(usage) = usage {
+                    message.apply_usage(usage);
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// The [`response::Message`] so far, if a [`Event::MessageStart`] has
+    /// been received.
+    pub fn message(&self) -> Option<&response::Message<'a>> {
+        self.message.as_ref()
+    }
+
+    /// Preview the input of the tool use [`Block`] being streamed, parsed from
+    /// the JSON [`Delta`]s so far with [`partial_json::parse`]. Returns
+    /// [`None`] if there is no pending JSON or nothing could be parsed yet.
+    ///
+    /// [`partial_json::parse`]: crate::partial_json::parse
+    pub fn partial_input(&self) -> Option<crate::partial_json::Partial> {
+        let block = self
+            .message
+            .as_ref()?
+            .message
+            .content
+            .get(self.offset + self.json_index?)?;
+        match block {
+            Block::ToolUse { call } | Block::ServerToolUse { call } => {
+                crate::partial_json::parse(call.input.as_str()?)
+            }
+            _ => None,
+        }
+    }
+
+    /// Finish accumulating and return the [`response::Message`], trimming stop
+    /// sequences if configured. Returns [`DeltaError::NoMessage`] if no
+    /// [`Event::MessageStart`] was received.
+    ///
+    /// If the stream ended before the tool use [`Block`] being streamed was
+    /// stopped, its JSON is parsed as by [`Block::finish`], so the input is
+    /// never left as a string of partial JSON. If the JSON is incomplete, this
+    /// returns [`DeltaError::Parse`].
+    pub fn finish(self) -> Result<response::Message<'a>, DeltaError<'a>> {
+        let mut message = self.message.ok_or(DeltaError::NoMessage)?;
+
+        if let Some(index) = self.json_index {
+            block_at(&mut message.message.content.0, self.offset, index)?
+                .finish()?;
+        }
+
+        // The API never includes a stop sequence that fired in the text, so
+        // only text which was cut off can end with part of one.
+        if matches!(message.stop_reason, Some(StopReason::MaxTokens) | None) {
+            if let Some(Block::Text { text, .. }) =
+                message.message.content.last_mut()
+            {
+                let min = self
+                    .min_partial_stop_len
+                    .unwrap_or(Self::MIN_PARTIAL_STOP_LEN);
+                let trim = self
+                    .stop_sequences
+                    .iter()
+                    .map(|seq| partial_suffix_len(text, seq))
+                    .filter(|&len| {
+                        let partial = &text[text.len() - len..];
+                        partial.chars().count() >= min
+                            && !partial.trim().is_empty()
+                    })
+                    .max()
+                    .unwrap_or_default();
+                if trim > 0 {
+                    let keep = text.len() - trim;
+                    *text = text[..keep].to_string().into();
+                }
+            }
+        }
+
+        Ok(message)
+    }
+}
+
+/// The [`Block`] at the API's `index`, where the API's block `0` is at
+/// `offset` in `blocks`.
+fn block_at<'b, 'a>(
+    blocks: &'b mut [Block<'a>],
+    offset: usize,
+    index: usize,
+) -> Result<&'b mut Block<'a>, DeltaError<'a>> {
+    let max = blocks.len().saturating_sub(offset + 1);
+    blocks
+        .get_mut(offset + index)
+        .ok_or_else(|| OutOfBounds { index, max }.into())
+}
+
+/// Length of the longest prefix of `seq` that `text` ends with.
+fn partial_suffix_len(text: &str, seq: &str) -> usize {
+    seq.char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .rev()
+        .find(|&len| text.ends_with(&seq[..len]))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    // Actual JSON from the API.
+
+    pub const CONTENT_BLOCK_START: &str = "{\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"} }";
+    pub const CONTENT_BLOCK_DELTA: &str = "{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Certainly! I\"}     }";
+
+    /// Creates a mock stream from a string (likely `include_str!`). The string
+    /// should be a series of `event`, `data`, and empty lines (a SSE stream).
+    /// Anthropic provides such example data in the API documentation.
+    pub fn mock_stream(text: &'static str) -> Stream<'static> {
+        mock_stream_with_limits(text, Limits::default())
+    }
+
+    /// Like [`mock_stream`] but with [`Limits`].
+    pub fn mock_stream_with_limits(
+        text: &'static str,
+        limits: Limits,
+    ) -> Stream<'static> {
+        use itertools::Itertools;
+
+        // TODO: one of every possible variants, even if it doesn't make sense.
+        let inner = futures::stream::iter(
+            // first line should be `event`, second line should be `data`, third
+            // line should be empty.
+            text.lines().tuples().map(|(event, data, _empty)| {
+                assert!(_empty.is_empty());
+
+                Ok(eventsource_stream::Event {
+                    event: event.strip_prefix("event: ").unwrap().into(),
+                    data: data.strip_prefix("data: ").unwrap().into(),
+                    id: "".into(),
+                    retry: None,
+                })
+            }),
+        );
+
+        Stream::with_limits(inner, limits)
+    }
+
+    #[test]
+    fn test_content_block_start() {
+        let event: Event = serde_json::from_str(CONTENT_BLOCK_START).unwrap();
+        match event {
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                assert_eq!(index, 0);
+                #[cfg(feature = "prompt-caching")]
+                if let Block::Text {
+                    text,
+                    cache_control,
+                } = content_block
                 {
                     assert_eq!(text.as_ref(), "");
                     assert!(cache_control.is_none());
@@ -429,6 +1688,144 @@ pub(crate) mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_unknown_event() {
+        const UNKNOWN_EVENT: &str = "{\"type\":\"future_event\",\"foo\":42}";
+        const UNKNOWN_BLOCK: &str = "{\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"future_block\",\"id\":\"future_1\"}}";
+        // A known block type with missing fields is an error, not unknown.
+        const MALFORMED_BLOCK: &str = "{\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"server_tool_use\",\"id\":\"srvtoolu_1\"}}";
+        const UNKNOWN_DELTA: &str = "{\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"future_delta\"}}";
+
+        // The raw JSON is preserved and round-trips (key order aside).
+        let event: Event = serde_json::from_str(UNKNOWN_EVENT).unwrap();
+        assert!(matches!(event, Event::Unknown(_)));
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::from_str::<serde_json::Value>(UNKNOWN_EVENT).unwrap()
+        );
+
+        let event: Event = serde_json::from_str(UNKNOWN_BLOCK).unwrap();
+        assert!(matches!(
+            event,
+            Event::ContentBlockStart {
+                index: 1,
+                content_block: Block::Unknown(_)
+            }
+        ));
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::from_str::<serde_json::Value>(UNKNOWN_BLOCK).unwrap()
+        );
+
+        let event: Event = serde_json::from_str(UNKNOWN_DELTA).unwrap();
+        assert!(matches!(
+            event,
+            Event::ContentBlockDelta {
+                index: 1,
+                delta: Delta::Unknown(_)
+            }
+        ));
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::from_str::<serde_json::Value>(UNKNOWN_DELTA).unwrap()
+        );
+
+        // Unknown deltas can't be merged.
+        assert!(Delta::Unknown(serde_json::json!({}))
+            .merge(Delta::Text { text: "".into() })
+            .is_err());
+
+        // Malformed known types are not unknown.
+        assert!(serde_json::from_str::<Event>(MALFORMED_BLOCK).is_err());
+        assert!(serde_json::from_str::<Event>(
+            "{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\"}}"
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Event>(
+            "{\"type\":\"content_block_stop\"}"
+        )
+        .is_err());
+
+        // Unknown events don't break the stream and errors are still errors.
+        let data = [
+            UNKNOWN_EVENT,
+            UNKNOWN_BLOCK,
+            MALFORMED_BLOCK,
+            "{\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}",
+        ]
+        .join("\n");
+        let results: Vec<_> = mock_stream_from_jsonl(data.as_bytes())
+            .unwrap()
+            .collect()
+            .await;
+        assert!(matches!(results[0], Ok(Event::Unknown(_))));
+        assert!(matches!(results[1], Ok(Event::ContentBlockStart { .. })));
+        let Err(error @ Error::Parse { context, .. }) = &results[2] else {
+            panic!("Expected a parse error.");
+        };
+        assert_eq!(
+            **context,
+            ParseContext {
+                event_type: Some("content_block_start".into()),
+                inner_type: Some("server_tool_use".into()),
+            }
+        );
+        assert!(error.to_string().starts_with(
+            "JSON error in `content_block_start` (`server_tool_use`)"
+        ));
+        assert!(matches!(results[3], Err(Error::Anthropic { .. })));
+    }
+
+    #[test]
+    fn test_event_into_static() {
+        let text = String::from("Hello");
+        let event = Event::ContentBlockDelta {
+            index: 0,
+            delta: Delta::Text {
+                text: Cow::Borrowed(&text),
+            },
+        };
+        let event: Event<'static> = event.into_static();
+        drop(text);
+
+        assert!(matches!(
+            event,
+            Event::ContentBlockDelta {
+                index: 0,
+                delta: Delta::Text {
+                    text: Cow::Owned(text)
+                },
+            } if text == "Hello"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stream_into_owned() {
+        struct Holder {
+            stream: Stream<'static>,
+        }
+
+        fn hold(stream: Stream<'_>) -> Holder {
+            Holder {
+                stream: stream.into_owned(),
+            }
+        }
+
+        let holder =
+            hold(mock_stream(include_str!("../test/data/sse.stream.txt")));
+        let text: String = holder
+            .stream
+            .filter_rate_limit()
+            .text()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            text,
+            "Okay, let's check the weather for San Francisco, CA:"
+        );
+    }
+
     #[test]
     fn test_content_block_delta_merge() {
         // Merge text deltas.
@@ -521,4 +1918,620 @@ pub(crate) mod tests {
             "Okay, let's check the weather for San Francisco, CA:"
         );
     }
+
+    #[tokio::test]
+    async fn test_timed() {
+        let before = SystemTime::now();
+        let events: Vec<Timed<Event>> =
+            mock_stream(include_str!("../test/data/sse.stream.txt"))
+                .timed()
+                // The mock data includes a rate limit error.
+                .filter_map(|result| async move { result.ok() })
+                .collect()
+                .await;
+
+        let first = events.first().unwrap();
+        assert_eq!(first.raw.event, "message_start");
+        assert!(first.raw.data.starts_with(r#"{"type":"message_start""#));
+        assert!(first.received_at >= before);
+        assert!(events
+            .windows(2)
+            .all(|pair| pair[0].received_at <= pair[1].received_at));
+        assert!(matches!(
+            first.clone().into_inner(),
+            Event::MessageStart { .. }
+        ));
+    }
+
+    /// Collect the text from a [`Tee`].
+    async fn tee_text(tee: Tee<'_>) -> String {
+        tee.filter_map(|result| async move {
+            match result {
+                Ok(Event::ContentBlockDelta {
+                    delta: Delta::Text { text },
+                    ..
+                }) => Some(text),
+                _ => None,
+            }
+        })
+        .collect()
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_heartbeats() {
+        let start = Instant::now();
+        let pings: Vec<Instant> =
+            mock_stream(include_str!("../test/data/sse.stream.txt"))
+                .heartbeats()
+                .collect()
+                .await;
+        assert_eq!(pings.len(), 1);
+        assert!(pings[0] >= start);
+
+        let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = count.clone();
+        let mut acc = Accumulator::new().on_ping(move || {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        });
+        acc.push(Event::Ping).unwrap();
+        acc.push(Event::Ping).unwrap();
+        assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 2);
+        assert!(acc.message().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_accumulator() {
+        let events: Vec<_> =
+            mock_stream(include_str!("../test/data/sse.stream.txt"))
+                .filter_rate_limit()
+                .try_collect()
+                .await
+                .unwrap();
+
+        let mut acc = Accumulator::new();
+        assert!(matches!(
+            acc.push(Event::ContentBlockStop { index: 0 }),
+            Err(DeltaError::NoMessage)
+        ));
+        let mut previews = vec![];
+        for event in events {
+            acc.push(event).unwrap();
+            previews.extend(acc.partial_input());
+        }
+        let message = acc.finish().unwrap();
+
+        // The JSON is previewed as it streams in.
+        assert!(previews.len() > 1);
+        assert!(!previews[0].is_complete());
+        assert!(previews.last().unwrap().is_complete());
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+        assert!(message.stopped_because_tool_use());
+        // The usage is next to the delta, not in it.
+        assert_eq!(message.usage.output_tokens, 89);
+        assert_eq!(
+            message.text(),
+            "Okay, let's check the weather for San Francisco, CA:"
+        );
+        let call = message.tool_use().unwrap();
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(
+            call.input,
+            serde_json::json!({"location": "San Francisco, CA", "unit": "fahrenheit"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_accumulator_prefill() {
+        let events: Vec<_> =
+            mock_stream(include_str!("../test/data/sse.stream.txt"))
+                .filter_rate_limit()
+                .try_collect()
+                .await
+                .unwrap();
+
+        let mut acc = Accumulator::new().prefill("Sure!");
+        for event in events {
+            acc.push(event).unwrap();
+        }
+        let message = acc.finish().unwrap();
+
+        assert_eq!(
+            message.text(),
+            "Sure!Okay, let's check the weather for San Francisco, CA:"
+        );
+        // The prefill and its continuation are a single block.
+        let blocks = message.message.content.as_slice();
+        assert_eq!(blocks.len(), 2);
+        assert!(message.tool_use().is_some());
+    }
+
+    #[test]
+    fn test_accumulator_interleaved() {
+        let start: Event = serde_json::from_str(
+            r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-haiku-20240307","stop_sequence":null,"usage":{"input_tokens":1,"output_tokens":1},"content":[],"stop_reason":null}}"#,
+        )
+        .unwrap();
+        let text = |index, text: &'static str| Event::ContentBlockDelta {
+            index,
+            delta: Delta::Text { text: text.into() },
+        };
+        let json = |index, json: &'static str| Event::ContentBlockDelta {
+            index,
+            delta: Delta::Json {
+                partial_json: json.into(),
+            },
+        };
+
+        let mut acc = Accumulator::new().prefill("Sure! ");
+        for event in [
+            start,
+            Event::ContentBlockStart {
+                index: 0,
+                content_block: Block::text(""),
+            },
+            Event::ContentBlockStart {
+                index: 1,
+                content_block: Block::ToolUse {
+                    call: crate::tool::Use {
+                        id: "toolu_1".into(),
+                        name: "ping".into(),
+                        input: serde_json::json!({}),
+                        #[cfg(feature = "prompt-caching")]
+                        cache_control: None,
+                    },
+                },
+            },
+            text(0, "Pinging"),
+            json(1, r#"{"host": "#),
+            text(0, " now."),
+            json(1, r#""example.com"}"#),
+            Event::ContentBlockStop { index: 1 },
+            Event::ContentBlockStop { index: 0 },
+        ] {
+            acc.apply_event(event).unwrap();
+        }
+
+        // Blocks must start in order and deltas need a block.
+        assert!(matches!(
+            acc.apply_event(Event::ContentBlockStart {
+                index: 3,
+                content_block: Block::text(""),
+            }),
+            Err(DeltaError::OutOfBounds {
+                error: OutOfBounds { index: 3, max: 2 }
+            })
+        ));
+        assert!(matches!(
+            acc.apply_event(text(2, "Oops.")),
+            Err(DeltaError::OutOfBounds {
+                error: OutOfBounds { index: 2, max: 1 }
+            })
+        ));
+
+        let message = acc.finish().unwrap();
+        assert_eq!(message.message.content.iter().count(), 2);
+        assert_eq!(message.text(), "Sure! Pinging now.");
+        assert_eq!(
+            message.tool_uses().next().unwrap().input,
+            serde_json::json!({"host": "example.com"})
+        );
+    }
+
+    #[test]
+    fn test_accumulator_thinking() {
+        // Thinking blocks and their deltas are unknown to this crate, as are
+        // citations deltas. They must not stop the rest of the message.
+        const EVENTS: &[&str] = &[
+            r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-5-sonnet-20241022","stop_sequence":null,"usage":{"input_tokens":1,"output_tokens":1},"content":[],"stop_reason":null}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":"","signature":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"The user wants a ping."}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"EqQBCgIYAhIM"}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Pinging."}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"citations_delta","citation":{"type":"char_location","cited_text":"ping","document_index":0,"start_char_index":0,"end_char_index":4}}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"toolu_1","name":"ping","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{\"host\": \"example.com\"}"}}"#,
+            r#"{"type":"content_block_stop","index":2}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":30}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut acc = Accumulator::new();
+        for event in EVENTS {
+            acc.apply_event(serde_json::from_str(event).unwrap())
+                .unwrap();
+        }
+
+        // Deltas for unknown blocks still need a block.
+        assert!(matches!(
+            acc.apply_event(
+                serde_json::from_str(
+                    r#"{"type":"content_block_delta","index":3,"delta":{"type":"thinking_delta","thinking":"Hmm."}}"#
+                )
+                .unwrap()
+            ),
+            Err(DeltaError::OutOfBounds { .. })
+        ));
+
+        let message = acc.finish().unwrap();
+        let blocks: Vec<_> = message.message.content.iter().collect();
+        assert_eq!(blocks.len(), 3);
+        assert!(matches!(blocks[0], Block::Unknown(_)));
+        assert_eq!(message.text(), "Pinging.");
+        assert_eq!(
+            message.tool_use().unwrap().input,
+            serde_json::json!({"host": "example.com"})
+        );
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+    }
+
+    #[test]
+    fn test_accumulator_unstopped_tool_use() {
+        let events = |json: &'static str| {
+            let mut acc = Accumulator::new();
+            for event in [
+                Event::MessageStart {
+                    message: response::Message::fake().build(),
+                },
+                Event::ContentBlockStart {
+                    index: 0,
+                    content_block: Block::ToolUse {
+                        call: crate::tool::Use {
+                            id: "toolu_1".into(),
+                            name: "ping".into(),
+                            input: serde_json::json!({}),
+                            #[cfg(feature = "prompt-caching")]
+                            cache_control: None,
+                        },
+                    },
+                },
+                Event::ContentBlockDelta {
+                    index: 0,
+                    delta: Delta::Json {
+                        partial_json: json.into(),
+                    },
+                },
+                // No `ContentBlockStop`.
+            ] {
+                acc.apply_event(event).unwrap();
+            }
+            acc
+        };
+
+        // Complete JSON is parsed.
+        let message = events(r#"{"host": "example.com"}"#).finish().unwrap();
+        assert_eq!(
+            message.tool_uses().next().unwrap().input,
+            serde_json::json!({"host": "example.com"})
+        );
+
+        // Partial JSON is an error rather than a string input.
+        assert!(matches!(
+            events(r#"{"host": "exa"#).finish(),
+            Err(DeltaError::Parse { .. })
+        ));
+
+        assert!(matches!(
+            Accumulator::new().finish(),
+            Err(DeltaError::NoMessage)
+        ));
+    }
+
+    #[test]
+    fn test_accumulator_trim_stop_sequences() {
+        fn accumulate(
+            text: &'static str,
+            stop_reason: Option<StopReason>,
+        ) -> response::Message<'static> {
+            accumulate_with(Accumulator::new(), text, stop_reason)
+        }
+        fn accumulate_with(
+            acc: Accumulator<'static>,
+            text: &'static str,
+            stop_reason: Option<StopReason>,
+        ) -> response::Message<'static> {
+            let mut acc = acc.trim_stop_sequences(["\n\nHuman:", "###"]);
+            let start: Event = serde_json::from_str(
+                r#"{"type":"message_start","message":{"id":"msg_1","type":"message","role":"assistant","model":"claude-3-haiku-20240307","stop_sequence":null,"usage":{"input_tokens":1,"output_tokens":1},"content":[],"stop_reason":null}}"#,
+            )
+            .unwrap();
+            acc.push(start).unwrap();
+            acc.push(Event::ContentBlockStart {
+                index: 0,
+                content_block: Block::text(""),
+            })
+            .unwrap();
+            acc.push(Event::ContentBlockDelta {
+                index: 0,
+                delta: Delta::Text { text: text.into() },
+            })
+            .unwrap();
+            acc.push(Event::ContentBlockStop { index: 0 }).unwrap();
+            acc.push(Event::MessageDelta {
+                delta: MessageDelta {
+                    stop_reason,
+                    stop_sequence: None,
+                    usage: None,
+                },
+                usage: None,
+            })
+            .unwrap();
+            acc.push(Event::MessageStop).unwrap();
+            acc.finish().unwrap()
+        }
+
+        let message = accumulate("Hello!\n\nHum", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Hello!");
+        // The stop sequence fired, so the text is real output.
+        let message = accumulate("Hello!##", Some(StopReason::StopSequence));
+        assert_eq!(message.text(), "Hello!##");
+        let message = accumulate("Hello!\n", Some(StopReason::StopSequence));
+        assert_eq!(message.text(), "Hello!\n");
+        // Short and whitespace-only partial matches are likely real output.
+        let message = accumulate("Hello!\n", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Hello!\n");
+        let message = accumulate("Hello!\n\n", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Hello!\n\n");
+        let message = accumulate("Issue #", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Issue #");
+        let message = accumulate("Hello!\n\nH", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Hello!");
+        let message = accumulate("Issue ##", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Issue ");
+        // The minimum is configurable.
+        let message = accumulate_with(
+            Accumulator::new().min_partial_stop_len(1),
+            "Issue #",
+            Some(StopReason::MaxTokens),
+        );
+        assert_eq!(message.text(), "Issue ");
+        let message = accumulate_with(
+            Accumulator::new().min_partial_stop_len(4),
+            "Hello!\n\nHu",
+            Some(StopReason::MaxTokens),
+        );
+        assert_eq!(message.text(), "Hello!");
+        let message = accumulate_with(
+            Accumulator::new().min_partial_stop_len(4),
+            "Issue ##",
+            Some(StopReason::MaxTokens),
+        );
+        assert_eq!(message.text(), "Issue ##");
+        // Nothing to trim.
+        let message = accumulate("Hello!", Some(StopReason::MaxTokens));
+        assert_eq!(message.text(), "Hello!");
+        // The stream ended early, so the text was cut off.
+        let message = accumulate("Hello!\n\nHum", None);
+        assert_eq!(message.text(), "Hello!");
+        // The model finished on its own, so the text is left alone.
+        let message = accumulate("Hello!\n\nHum", Some(StopReason::EndTurn));
+        assert_eq!(message.text(), "Hello!\n\nHum");
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        let mut recorder =
+            mock_stream(include_str!("../test/data/sse.stream.txt"))
+                .record(Vec::new());
+
+        // The test data includes a rate limit error, which is recorded too.
+        let results: Vec<_> = recorder.by_ref().collect().await;
+        assert!(recorder.take_error().is_none());
+
+        let recording = recorder.into_writer().unwrap();
+        assert_eq!(
+            recording.iter().filter(|&&b| b == b'\n').count(),
+            results.len()
+        );
+
+        // Replay the recording.
+        let text: String = mock_stream_from_jsonl(recording.as_slice())
+            .unwrap()
+            .filter_rate_limit()
+            .text()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            text,
+            "Okay, let's check the weather for San Francisco, CA:"
+        );
+
+        // Errors are recorded as raw data and replayed as errors.
+        const DATA: &str = "{\"type\":\"error\",\"error\":{\"type\":\"overloaded_error\",\"message\":\"Overloaded\"}}\n\nnot json\n";
+        let mut recorder = mock_stream_from_jsonl(DATA.as_bytes())
+            .unwrap()
+            .record(Vec::new());
+        let results: Vec<_> = recorder.by_ref().collect().await;
+        assert!(matches!(
+            results[0],
+            Err(Error::Anthropic {
+                error: AnthropicError::Overloaded { .. },
+                ..
+            })
+        ));
+        assert!(matches!(results[1], Err(Error::Parse { .. })));
+        assert_eq!(
+            String::from_utf8(recorder.into_writer().unwrap()).unwrap(),
+            DATA.replace("\n\n", "\n")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let mut metrics =
+            mock_stream(include_str!("../test/data/sse.stream.txt")).metrics();
+        assert!(metrics.timings().is_none());
+
+        let text: String = metrics
+            .by_ref()
+            .filter_rate_limit()
+            .text()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            text,
+            "Okay, let's check the weather for San Francisco, CA:"
+        );
+
+        let timings = metrics.timings().unwrap();
+        assert!(timings.deltas > 2);
+        assert!(timings.time_to_first_token.unwrap() <= timings.total);
+        assert!(timings.generation.unwrap() <= timings.total);
+        assert!(
+            timings.inter_token_latency(50.0)
+                <= timings.inter_token_latency(99.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_limits() {
+        const SSE: &str = include_str!("../test/data/sse.stream.txt");
+
+        // "Okay" + "," + " let" + "'s" is 11 bytes.
+        let limits = Limits::default().content_block(10);
+        let results: Vec<_> =
+            mock_stream_with_limits(SSE, limits).collect().await;
+        let (last, rest) = results.split_last().unwrap();
+        assert!(rest.iter().all(Result::is_ok));
+        match last {
+            Err(Error::TooLarge { error }) => assert_eq!(
+                error,
+                &TooLarge {
+                    limit: Limit::ContentBlock { index: 0 },
+                    size: 11,
+                    max: 10,
+                }
+            ),
+            _ => panic!("expected TooLarge, got {:?}", last),
+        }
+
+        // The first event is larger than this, so the stream ends at once.
+        let limits = Limits::default().response_body(100);
+        let results: Vec<_> =
+            mock_stream_with_limits(SSE, limits).collect().await;
+        assert_eq!(results.len(), 1);
+        assert!(matches!(
+            results[0],
+            Err(Error::TooLarge {
+                error: TooLarge {
+                    limit: Limit::ResponseBody,
+                    ..
+                }
+            })
+        ));
+
+        // Within limits, everything passes through.
+        let limits = Limits::default().response_body(1 << 20).content_block(52);
+        let text: String = mock_stream_with_limits(SSE, limits)
+            .filter_rate_limit()
+            .text()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(text.len(), 52);
+    }
+
+    #[test]
+    fn test_timings() {
+        let timings = Timings {
+            generation: Some(Duration::from_secs(2)),
+            deltas: 5,
+            intervals: (1..=4).map(Duration::from_millis).collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            timings.inter_token_latency(0.0),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(
+            timings.inter_token_latency(50.0),
+            Some(Duration::from_millis(3))
+        );
+        assert_eq!(
+            timings.inter_token_latency(100.0),
+            Some(Duration::from_millis(4))
+        );
+        assert_eq!(timings.tokens_per_second(), Some(2.5));
+        assert_eq!(Timings::default().inter_token_latency(50.0), None);
+        assert_eq!(Timings::default().tokens_per_second(), None);
+    }
+
+    #[tokio::test]
+    async fn test_tee() {
+        const EXPECTED: &str =
+            "Okay, let's check the weather for San Francisco, CA:";
+
+        // Concurrently.
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        let texts =
+            futures::future::join_all(stream.tee(3).into_iter().map(tee_text))
+                .await;
+
+        assert_eq!(texts.len(), 3);
+        for text in texts {
+            assert_eq!(text, EXPECTED);
+        }
+
+        // Sequentially. The first `Tee` drives the source to completion and
+        // the rest are served from their buffers.
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        let mut tees = stream.tee(2);
+        let second = tees.pop().unwrap();
+        let first = tees.pop().unwrap();
+        assert_eq!(tee_text(first).await, EXPECTED);
+        assert_eq!(tee_text(second).await, EXPECTED);
+
+        // A dropped `Tee` does not affect the others.
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        let mut tees = stream.tee(2);
+        drop(tees.pop());
+        assert_eq!(tee_text(tees.pop().unwrap()).await, EXPECTED);
+
+        // Zero `Tee`s.
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        assert!(stream.tee(0).is_empty());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_into_channel() {
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        let (mut receiver, task) = stream.into_channel(1).unwrap();
+
+        let mut text = String::new();
+        while let Some(item) = receiver.recv().await {
+            // The recording includes rate limit errors.
+            if let Ok(Event::ContentBlockDelta {
+                delta: Delta::Text { text: delta },
+                ..
+            }) = item
+            {
+                text.push_str(&delta);
+            }
+        }
+        assert_eq!(
+            text,
+            "Okay, let's check the weather for San Francisco, CA:"
+        );
+        task.await.unwrap();
+
+        // The task ends when the receiver is dropped.
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        let (receiver, task) = stream.into_channel(0).unwrap();
+        drop(receiver);
+        task.await.unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_into_channel_no_runtime() {
+        let stream = mock_stream(include_str!("../test/data/sse.stream.txt"));
+        assert!(stream.into_channel(1).is_err());
+    }
 }
@@ -0,0 +1,135 @@
+//! HTTP [`Meta`]data of the response a [`Stream`] was created from. See
+//! [`Stream::meta`].
+use reqwest::{header::HeaderMap, StatusCode};
+
+use super::Stream;
+
+/// Prefix of the rate limit headers.
+const RATE_LIMIT_PREFIX: &str = "anthropic-ratelimit-";
+
+/// Status and headers of the HTTP response a [`Stream`] was created from,
+/// captured before the body is read as Server-Sent Events.
+///
+/// ```no_run
+/// use misanthropic::{prompt::message::Role, Client, Prompt};
+///
+/// # async fn example(client: Client) -> misanthropic::client::Result<()> {
+/// let prompt = Prompt::default().add_message((Role::User, "Hi!"));
+/// let stream = client.stream(prompt).await?;
+/// if let Some(meta) = stream.meta() {
+///     println!("{} {:?}", meta.status, meta.request_id());
+///     println!("{:?}", meta.rate_limit("requests-remaining"));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Meta {
+    /// HTTP status. Streams are only created from successful responses, so
+    /// this is `200 OK` unless the [`Stream`] was created some other way.
+    pub status: StatusCode,
+    /// Every response header, including the `request-id` and the rate limit
+    /// headers.
+    pub headers: HeaderMap,
+}
+
+impl Meta {
+    /// Capture the status and headers of a `response`.
+    pub fn from_response(response: &reqwest::Response) -> Self {
+        Self {
+            status: response.status(),
+            headers: response.headers().clone(),
+        }
+    }
+
+    /// Value of the header `name` as a string, if present and valid.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// Id of the request, from the `request-id` header. Include this when
+    /// reporting an issue to Anthropic.
+    pub fn request_id(&self) -> Option<&str> {
+        self.header("request-id")
+    }
+
+    /// Value of the `anthropic-ratelimit-{name}` header as a number, such as
+    /// `requests-remaining` or `input-tokens-limit`.
+    pub fn rate_limit(&self, name: &str) -> Option<u64> {
+        self.header(&format!("{RATE_LIMIT_PREFIX}{name}"))?
+            .trim()
+            .parse()
+            .ok()
+    }
+
+    /// Every `anthropic-ratelimit-*` header, with the prefix removed, such as
+    /// `("tokens-reset", "2024-10-01T00:00:00Z")`.
+    pub fn rate_limits(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().filter_map(|(name, value)| {
+            Some((
+                name.as_str().strip_prefix(RATE_LIMIT_PREFIX)?,
+                value.to_str().ok()?,
+            ))
+        })
+    }
+}
+
+impl Stream<'_> {
+    /// Status and headers of the HTTP response the stream was created from,
+    /// if it was created by a [`Client`].
+    ///
+    /// [`Client`]: crate::Client
+    pub fn meta(&self) -> Option<&Meta> {
+        self.meta.as_ref()
+    }
+
+    /// Set the [`Meta`].
+    pub(crate) fn with_meta(mut self, meta: Meta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_meta() {
+        let mut headers = HeaderMap::new();
+        headers.insert("request-id", HeaderValue::from_static("req_123"));
+        headers.insert(
+            "anthropic-ratelimit-requests-remaining",
+            HeaderValue::from_static("49"),
+        );
+        headers.insert(
+            "anthropic-ratelimit-tokens-reset",
+            HeaderValue::from_static("2024-10-01T00:00:00Z"),
+        );
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        let meta = Meta {
+            status: StatusCode::OK,
+            headers,
+        };
+
+        assert_eq!(meta.request_id(), Some("req_123"));
+        assert_eq!(meta.rate_limit("requests-remaining"), Some(49));
+        assert_eq!(meta.rate_limit("tokens-reset"), None);
+        assert_eq!(meta.rate_limit("input-tokens-limit"), None);
+        let mut limits: Vec<_> = meta.rate_limits().collect();
+        limits.sort();
+        assert_eq!(
+            limits,
+            [
+                ("requests-remaining", "49"),
+                ("tokens-reset", "2024-10-01T00:00:00Z")
+            ]
+        );
+
+        let stream = Stream::new(futures::stream::empty());
+        assert!(stream.meta().is_none());
+        let stream = stream.with_meta(meta).into_owned();
+        assert_eq!(stream.meta().unwrap().request_id(), Some("req_123"));
+    }
+}
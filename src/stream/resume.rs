@@ -0,0 +1,415 @@
+//! Resume a [`Stream`] when the connection drops mid-message. See
+//! [`Client::stream_resumable`].
+use futures::StreamExt;
+use std::marker::PhantomData;
+
+use super::{Accumulator, Delta, Error, Event, Stream, Timed};
+use crate::{
+    client,
+    prompt::message::{Block, Content, Message, Role},
+    Client, Prompt,
+};
+
+impl Client {
+    /// Like [`stream`] but if the connection drops mid-message, the request is
+    /// re-issued with the partial message as prefill, up to `max_resumes`
+    /// times. An [`Event::Resumed`] is yielded in place of the connection
+    /// error and the events that follow continue the same message, with
+    /// indices as if it was never interrupted, so a single [`Accumulator`]
+    /// can be used for the whole stream.
+    ///
+    /// ## Notes:
+    /// - Resuming is only possible if the partial message ends with text,
+    ///   since a partial tool use can't be prefilled. Otherwise the
+    ///   connection error is yielded as usual.
+    /// - The API rejects a prefill ending in whitespace, so trailing
+    ///   whitespace is trimmed from the prefill and leading whitespace from
+    ///   the continuation.
+    /// - If the request to resume fails, an [`Error::Resume`] is yielded and
+    ///   the stream ends.
+    /// - [`Usage`] is that of the last request.
+    ///
+    /// [`stream`]: Self::stream
+    /// [`Usage`]: crate::response::Usage
+    pub async fn stream_resumable(
+        &self,
+        prompt: &Prompt<'_>,
+        max_resumes: usize,
+    ) -> client::Result<Stream<'static>> {
+        let stream = self.stream(prompt).await?;
+        let meta = stream.meta.clone();
+        let state = Resume::new(prompt.clone().into_static(), max_resumes);
+
+        let inner = futures::stream::unfold(
+            (self.clone(), state, Some(stream)),
+            |(client, mut state, mut stream)| async move {
+                loop {
+                    let item = stream.as_mut()?.inner.next().await;
+                    let error = match item {
+                        Some(Ok(Timed {
+                            event,
+                            received_at,
+                            mut raw,
+                        })) => {
+                            let resumed = state.attempts > 0;
+                            let Some(event) = state.rewrite(event) else {
+                                continue;
+                            };
+                            // The indices may have changed.
+                            if resumed {
+                                if let Ok(data) = serde_json::to_string(&event)
+                                {
+                                    raw.data = data;
+                                }
+                            }
+                            let timed = Timed {
+                                event,
+                                received_at,
+                                raw,
+                            };
+                            return Some((Ok(timed), (client, state, stream)));
+                        }
+                        Some(Err(error @ Error::Stream { .. })) => Some(error),
+                        Some(Err(error)) => {
+                            // The limits apply to the whole message.
+                            if let Error::TooLarge { .. } = error {
+                                state.stopped = true;
+                            }
+                            return Some((Err(error), (client, state, stream)));
+                        }
+                        None => None,
+                    };
+
+                    let Some(prompt) = state.next_prompt() else {
+                        // Not resumable, so end as the stream would have.
+                        return error
+                            .map(|error| (Err(error), (client, state, None)));
+                    };
+
+                    #[cfg(feature = "log")]
+                    log::warn!(
+                        "Resuming stream (attempt {}) after: {:?}",
+                        state.attempts,
+                        error
+                    );
+
+                    let item = match client.stream(&prompt).await {
+                        Ok(next) => {
+                            stream = Some(next);
+                            let event = Event::Resumed {
+                                attempt: state.attempts,
+                            };
+                            Ok(Timed {
+                                raw: eventsource_stream::Event {
+                                    event: "resumed".into(),
+                                    data: serde_json::to_string(&event)
+                                        .unwrap_or_default(),
+                                    id: String::new(),
+                                    retry: None,
+                                },
+                                event,
+                                received_at: crate::conversation::now(),
+                            })
+                        }
+                        Err(error) => {
+                            stream = None;
+                            Err(Error::Resume { error })
+                        }
+                    };
+                    return Some((item, (client, state, stream)));
+                }
+            },
+        );
+
+        Ok(Stream {
+            inner: Box::pin(inner),
+            meta,
+            lifetime: PhantomData,
+        })
+    }
+}
+
+/// State to resume a message. Events of each request are rewritten so they
+/// continue the message so far.
+struct Resume {
+    /// The prompt without its prefill.
+    base: Prompt<'static>,
+    /// The prefill of the prompt, if any.
+    prefill: Content<'static>,
+    /// The message so far, from the rewritten events.
+    acc: Accumulator<'static>,
+    /// Added to the block indices of the current request.
+    offset: usize,
+    /// Whether block `0` of the current request continues the last block.
+    continues: bool,
+    /// Whether whitespace trimmed from the prefill should be trimmed from the
+    /// start of the continuation.
+    trim_start: bool,
+    /// Set once the message has stopped or can't be resumed.
+    stopped: bool,
+    attempts: usize,
+    max_attempts: usize,
+}
+
+impl Resume {
+    fn new(mut base: Prompt<'static>, max_attempts: usize) -> Self {
+        let prefill = match base.messages.last() {
+            Some(last) if last.role == Role::Assistant => {
+                base.messages.pop().map(|m| m.content).unwrap_or_default()
+            }
+            _ => Content::default(),
+        };
+
+        Self {
+            base,
+            prefill,
+            acc: Accumulator::new(),
+            offset: 0,
+            continues: false,
+            trim_start: false,
+            stopped: false,
+            attempts: 0,
+            max_attempts,
+        }
+    }
+
+    /// The [`Prompt`] to resume the message with, or [`None`] if it can't be
+    /// resumed. The next events are rewritten to continue the message.
+    fn next_prompt(&mut self) -> Option<Prompt<'static>> {
+        if self.stopped || self.attempts >= self.max_attempts {
+            return None;
+        }
+
+        let blocks = match self.acc.message() {
+            Some(message) if message.stop_reason.is_some() => return None,
+            Some(message) => message.message.content.as_slice(),
+            // Nothing was received, so this is a retry.
+            None => &[],
+        };
+
+        match blocks.last() {
+            None => {
+                self.offset = 0;
+                self.continues = false;
+                self.trim_start = false;
+            }
+            Some(Block::Text { text, .. }) if !text.trim().is_empty() => {
+                self.offset = blocks.len() - 1;
+                self.continues = true;
+                self.trim_start = text.trim_end().len() != text.len();
+            }
+            _ => return None,
+        }
+
+        // The API continues the last text block of the prefill, so join them.
+        let mut partial = self.prefill.clone();
+        for block in blocks {
+            match (partial.last_mut(), block) {
+                (
+                    Some(Block::Text { text, .. }),
+                    Block::Text { text: more, .. },
+                ) => {
+                    #[cfg(not(feature = "langsan"))]
+                    {
+                        text.to_mut().push_str(more);
+                    }
+                    #[cfg(feature = "langsan")]
+                    {
+                        text.push_str(more);
+                    }
+                }
+                _ => partial.push(block.clone()),
+            }
+        }
+        if let Some(Block::Text { text, .. }) = partial.last_mut() {
+            let len = text.trim_end().len();
+            *text = text[..len].to_string().into();
+        }
+
+        self.attempts += 1;
+        let mut prompt = self.base.clone();
+        if !partial.is_empty() {
+            prompt = prompt.add_message(Message {
+                role: Role::Assistant,
+                content: partial,
+            });
+        }
+
+        Some(prompt)
+    }
+
+    /// Rewrite an event of the current request to continue the message, and
+    /// apply it. Returns [`None`] if it should be dropped.
+    fn rewrite(&mut self, event: Event<'static>) -> Option<Event<'static>> {
+        let event = match event {
+            // The message already started.
+            Event::MessageStart { .. } if self.acc.message().is_some() => {
+                return None
+            }
+            Event::ContentBlockStart {
+                index: 0,
+                content_block,
+            } if std::mem::take(&mut self.continues) => match content_block {
+                Block::Text { text, .. } => self.continue_text(text)?,
+                content_block => {
+                    // A new block instead of the continuation.
+                    self.offset += 1;
+                    self.trim_start = false;
+                    Event::ContentBlockStart {
+                        index: self.offset,
+                        content_block,
+                    }
+                }
+            },
+            Event::ContentBlockStart {
+                index,
+                content_block,
+            } => Event::ContentBlockStart {
+                index: index + self.offset,
+                content_block,
+            },
+            Event::ContentBlockDelta {
+                index: 0,
+                delta: Delta::Text { text },
+            } if self.trim_start => self.continue_text(text)?,
+            Event::ContentBlockDelta { index, delta } => {
+                Event::ContentBlockDelta {
+                    index: index + self.offset,
+                    delta,
+                }
+            }
+            Event::ContentBlockStop { index } => Event::ContentBlockStop {
+                index: index + self.offset,
+            },
+            Event::MessageStop => {
+                self.stopped = true;
+                Event::MessageStop
+            }
+            event => event,
+        };
+
+        // Errors are left to the caller's accumulator.
+        self.acc.apply_event(event.clone()).ok();
+
+        Some(event)
+    }
+
+    /// Continuation `text` of the last block, with leading whitespace trimmed
+    /// if it was trimmed from the prefill.
+    fn continue_text(
+        &mut self,
+        text: impl Into<std::borrow::Cow<'static, str>>,
+    ) -> Option<Event<'static>> {
+        let text = text.into();
+        let text = match self.trim_start {
+            true => text.trim_start().to_string().into(),
+            false => text,
+        };
+        if text.is_empty() {
+            return None;
+        }
+        self.trim_start = false;
+
+        Some(Event::ContentBlockDelta {
+            index: self.offset,
+            delta: Delta::Text { text },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(data: &[&str]) -> Vec<Event<'static>> {
+        data.iter()
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect()
+    }
+
+    const MESSAGE_START: &str = r#"{"type":"message_start","message":{"id":"msg_01","type":"message","role":"assistant","content":[],"model":"claude-3-5-sonnet-20241022","stop_reason":null,"stop_sequence":null,"usage":{"input_tokens":10,"output_tokens":1}}}"#;
+    const TEXT_START: &str = r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#;
+
+    #[test]
+    fn test_resume() {
+        let prompt = Prompt::default()
+            .add_message(Message::user("Finish the sentence."))
+            .prefill("The");
+        let mut state = Resume::new(prompt, 2);
+        let mut acc = Accumulator::new().prefill("The");
+
+        // The connection drops mid-sentence.
+        for event in events(&[
+            MESSAGE_START,
+            TEXT_START,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" quick"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" brown "}}"#,
+        ]) {
+            acc.apply_event(state.rewrite(event).unwrap()).unwrap();
+        }
+
+        let prompt = state.next_prompt().unwrap();
+        assert_eq!(prompt.messages.len(), 2);
+        assert_eq!(prompt.messages[0].text(), "Finish the sentence.");
+        assert_eq!(prompt.messages[1].role, Role::Assistant);
+        assert_eq!(
+            prompt.messages[1].content,
+            Content::text("The quick brown")
+        );
+
+        // The continuation, with the duplicate start and whitespace dropped.
+        let continuation = events(&[
+            MESSAGE_START,
+            TEXT_START,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" "}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" fox jumps."}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_01","name":"jump","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use","stop_sequence":null},"usage":{"output_tokens":20}}"#,
+            r#"{"type":"message_stop"}"#,
+        ]);
+        let rewritten: Vec<_> = continuation
+            .into_iter()
+            .filter_map(|event| state.rewrite(event))
+            .collect();
+        assert_eq!(rewritten.len(), 7);
+        for event in rewritten {
+            acc.apply_event(event).unwrap();
+        }
+
+        let message = acc.finish().unwrap();
+        assert_eq!(message.message.content.iter().count(), 2);
+        assert_eq!(message.text(), "The quick brown fox jumps.");
+        assert_eq!(message.tool_uses().next().unwrap().name, "jump");
+
+        // The message is done.
+        assert!(state.next_prompt().is_none());
+    }
+
+    #[test]
+    fn test_resume_not_resumable() {
+        let prompt =
+            Prompt::default().add_message(Message::user("Call a tool."));
+
+        // Nothing was received, so the request is retried as is.
+        let mut state = Resume::new(prompt.clone(), 1);
+        assert_eq!(state.next_prompt().unwrap().messages.len(), 1);
+        let event = state.rewrite(events(&[MESSAGE_START]).remove(0));
+        assert!(matches!(event, Some(Event::MessageStart { .. })));
+        // Out of attempts.
+        assert!(state.next_prompt().is_none());
+
+        // A partial tool use can't be prefilled.
+        let mut state = Resume::new(prompt, 1);
+        for event in events(&[
+            MESSAGE_START,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"tool_use","id":"toolu_01","name":"jump","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{\"hei"}}"#,
+        ]) {
+            state.rewrite(event).unwrap();
+        }
+        assert!(state.next_prompt().is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Re-emit a [`Stream`] as [Server-Sent Events], for example to proxy a
+//! response to a browser. See [`to_sse`].
+//!
+//! This is framework agnostic. An [`Event`] can be written as is with its
+//! [`Display`] implementation or converted to a web framework's own event
+//! type from its [`event`] and [`data`] fields.
+//!
+//! [Server-Sent Events]: <https://html.spec.whatwg.org/multipage/server-sent-events.html>
+//! [`Stream`]: super::Stream
+//! [`Display`]: std::fmt::Display
+//! [`event`]: Event::event
+//! [`data`]: Event::data
+use futures::StreamExt;
+use serde_json::json;
+
+use super::Error;
+use crate::MaybeSend;
+
+/// Server-Sent Event frame.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Event {
+    /// Event name. Empty for the default `message` event.
+    pub event: String,
+    /// Event data. For API events, this is the same JSON the API sends.
+    pub data: String,
+}
+
+impl Event {
+    /// Name of error events.
+    pub const ERROR: &'static str = "error";
+
+    /// An [`ERROR`](Self::ERROR) event in the same format as API errors, with
+    /// an error `kind` and a `message`.
+    pub fn error(kind: &str, message: impl std::fmt::Display) -> Self {
+        Self {
+            event: Self::ERROR.to_string(),
+            data: json!({
+                "type": "error",
+                "error": {"type": kind, "message": message.to_string()},
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Writes the event in wire format, including the blank line ending it.
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.event.is_empty() {
+            writeln!(f, "event: {}", self.event)?;
+        }
+        // Each line of the data must be a separate field.
+        for line in self.data.split('\n') {
+            writeln!(f, "data: {}", line.strip_suffix('\r').unwrap_or(line))?;
+        }
+        writeln!(f)
+    }
+}
+
+/// The event name is the `type` of the API event.
+impl From<super::Event<'_>> for Event {
+    fn from(event: super::Event<'_>) -> Self {
+        let data = match serde_json::to_value(&event) {
+            Ok(data) => data,
+            Err(error) => return Self::error("serialization_error", error),
+        };
+
+        Self {
+            event: data
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            data: data.to_string(),
+        }
+    }
+}
+
+/// API errors are re-emitted as the API sent them. Other errors have these
+/// types:
+///
+/// - `stream_error` for [`Error::Stream`]
+/// - `parse_error` for [`Error::Parse`]
+/// - `too_large_error` for [`Error::TooLarge`]
+/// - `resume_error` for [`Error::Resume`]
+///
+/// The message is the error's [`Display`](std::fmt::Display) text. The raw
+/// data of events which did not parse is not included.
+impl From<&Error> for Event {
+    fn from(error: &Error) -> Self {
+        match error {
+            Error::Anthropic { error, .. } => Self {
+                event: Self::ERROR.to_string(),
+                data: json!({"type": "error", "error": error}).to_string(),
+            },
+            Error::Stream { .. } => Self::error("stream_error", error),
+            Error::Parse { .. } => Self::error("parse_error", error),
+            Error::TooLarge { .. } => Self::error("too_large_error", error),
+            Error::Resume { .. } => Self::error("resume_error", error),
+        }
+    }
+}
+
+impl From<Error> for Event {
+    fn from(error: Error) -> Self {
+        Self::from(&error)
+    }
+}
+
+/// Map a [`Stream`] of API events and errors to Server-Sent [`Event`]s. API
+/// events keep their name and data, so a browser can handle them like the
+/// API's own stream. Errors become [`Event::ERROR`] events in the format of
+/// API errors. See [`Event`] for the error types.
+///
+/// ```
+/// use futures::StreamExt;
+/// use misanthropic::stream::{to_sse, Stream};
+///
+/// async fn proxy(stream: Stream<'_>) -> String {
+///     // In a web backend, send each frame to the client instead.
+///     to_sse(stream).map(|event| event.to_string()).collect().await
+/// }
+/// ```
+///
+/// [`Stream`]: super::Stream
+pub fn to_sse<'a, S>(
+    stream: S,
+) -> impl futures::Stream<Item = Event> + MaybeSend + 'a
+where
+    S: futures::Stream<Item = Result<super::Event<'a>, Error>> + MaybeSend + 'a,
+{
+    stream.map(|result| match result {
+        Ok(event) => Event::from(event),
+        Err(error) => Event::from(error),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::mock_stream_from_data;
+
+    #[test]
+    fn test_display() {
+        let event = Event {
+            event: "ping".into(),
+            data: "line 1\r\nline 2".into(),
+        };
+        assert_eq!(
+            event.to_string(),
+            "event: ping\ndata: line 1\ndata: line 2\n\n"
+        );
+        assert_eq!(Event::default().to_string(), "data: \n\n");
+    }
+
+    #[tokio::test]
+    async fn test_to_sse() {
+        let stream = mock_stream_from_data(
+            [
+                r#"{"type": "ping"}"#,
+                r#"{"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Hi"}}"#,
+                r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#,
+                "not json",
+            ]
+            .map(String::from),
+        );
+
+        let events: Vec<Event> = to_sse(stream).collect().await;
+        assert_eq!(events.len(), 4);
+
+        assert_eq!(events[0].event, "ping");
+        assert_eq!(events[0].data, r#"{"type":"ping"}"#);
+
+        assert_eq!(events[1].event, "content_block_delta");
+        let data: serde_json::Value =
+            serde_json::from_str(&events[1].data).unwrap();
+        assert_eq!(data["delta"]["text"], "Hi");
+
+        assert_eq!(events[2].event, Event::ERROR);
+        let data: serde_json::Value =
+            serde_json::from_str(&events[2].data).unwrap();
+        assert_eq!(
+            data,
+            json!({
+                "type": "error",
+                "error": {"type": "overloaded_error", "message": "Overloaded"}
+            })
+        );
+
+        assert_eq!(events[3].event, Event::ERROR);
+        let data: serde_json::Value =
+            serde_json::from_str(&events[3].data).unwrap();
+        assert_eq!(data["error"]["type"], "parse_error");
+    }
+}
@@ -0,0 +1,322 @@
+//! Fake responses for testing code which uses this crate, such as agent
+//! loops, without making requests or constructing every field by hand. Start
+//! with [`response::Message::fake`].
+//!
+//! ```
+//! use misanthropic::{json, response, stream::Accumulator};
+//! use futures::TryStreamExt;
+//!
+//! let fake = response::Message::fake()
+//!     .text("Let me check the weather.")
+//!     .tool_use("toolu_1", "get_weather", json!({"city": "Paris"}));
+//!
+//! // A complete message, as from `Client::message`.
+//! let message = fake.clone().build();
+//! assert_eq!(message.tool_use().unwrap().name, "get_weather");
+//!
+//! // The same message as a stream, as from `Client::stream`.
+//! # futures::executor::block_on(async {
+//! let mut acc = Accumulator::new();
+//! let mut stream = fake.stream();
+//! while let Some(event) = stream.try_next().await? {
+//!     acc.push(event)?;
+//! }
+//! assert_eq!(acc.finish().unwrap().text(), "Let me check the weather.");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! # }).unwrap();
+//! ```
+//!
+//! This requires the `test-util` feature. Enable it in `dev-dependencies`.
+use std::borrow::Cow;
+
+use crate::{
+    prompt::{
+        self,
+        message::{Block, Content, Role},
+    },
+    response::{self, StopReason, Usage},
+    stream::{self, Delta, Event, MessageDelta, Stream},
+    tool, Model,
+};
+
+/// Builder for a fake [`response::Message`] and the [`Event`]s which stream
+/// it. See the [module](self) documentation.
+#[derive(Clone, Debug)]
+pub struct FakeMessage<'a> {
+    message: response::Message<'a>,
+}
+
+impl Default for FakeMessage<'_> {
+    fn default() -> Self {
+        Self {
+            message: response::Message {
+                id: "msg_fake".into(),
+                message: prompt::Message {
+                    role: Role::Assistant,
+                    content: Content::default(),
+                },
+                model: Model::default(),
+                stop_reason: Some(StopReason::EndTurn),
+                stop_sequence: None,
+                usage: Usage::default(),
+                container: None,
+            },
+        }
+    }
+}
+
+impl<'a> FakeMessage<'a> {
+    /// An empty [`Assistant`] message which stopped at the end of its turn.
+    ///
+    /// [`Assistant`]: Role::Assistant
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the message `id`. The default is `msg_fake`.
+    pub fn id<S>(mut self, id: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        self.message.id = id.into();
+        self
+    }
+
+    /// Set the [`Model`]. The default is [`Model::default`].
+    pub fn model(mut self, model: Model) -> Self {
+        self.message.model = model;
+        self
+    }
+
+    /// Add a text [`Block`].
+    pub fn text<T>(self, text: T) -> Self
+    where
+        T: Into<crate::CowStr<'a>>,
+    {
+        self.block(Block::text(text))
+    }
+
+    /// Add a [`Block::ToolUse`] and set the [`StopReason`] to
+    /// [`StopReason::ToolUse`].
+    pub fn tool_use<I, N>(
+        mut self,
+        id: I,
+        name: N,
+        input: serde_json::Value,
+    ) -> Self
+    where
+        I: Into<Cow<'a, str>>,
+        N: Into<Cow<'a, str>>,
+    {
+        self.message.stop_reason = Some(StopReason::ToolUse);
+        self.block(tool::Use {
+            id: id.into(),
+            name: name.into(),
+            input,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        })
+    }
+
+    /// Add any [`Block`].
+    pub fn block<B>(mut self, block: B) -> Self
+    where
+        B: Into<Block<'a>>,
+    {
+        self.message.message.content.push(block);
+        self
+    }
+
+    /// Set the [`StopReason`]. The default is [`StopReason::EndTurn`], or
+    /// [`StopReason::ToolUse`] after adding a [`tool_use`].
+    ///
+    /// [`tool_use`]: FakeMessage::tool_use
+    pub fn stop_reason(mut self, stop_reason: Option<StopReason>) -> Self {
+        self.message.stop_reason = stop_reason;
+        self
+    }
+
+    /// Stop because of a stop `sequence`.
+    pub fn stop_sequence<S>(mut self, sequence: S) -> Self
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        self.message.stop_reason = Some(StopReason::StopSequence);
+        self.message.stop_sequence = Some(sequence.into());
+        self
+    }
+
+    /// Set the input and output token [`Usage`]. The default is zero.
+    pub fn usage(mut self, input_tokens: u64, output_tokens: u64) -> Self {
+        self.message.usage.input_tokens = input_tokens;
+        self.message.usage.output_tokens = output_tokens;
+        self
+    }
+
+    /// Build the [`response::Message`].
+    pub fn build(self) -> response::Message<'a> {
+        self.message
+    }
+
+    /// The [`Event`]s the API would stream for the message, in order. Text is
+    /// split into a [`Delta`] per word and tool use input is sent as a single
+    /// JSON [`Delta`]. There are no [`Event::Ping`]s.
+    pub fn events(self) -> Vec<Event<'a>> {
+        let response::Message {
+            id,
+            message,
+            model,
+            stop_reason,
+            stop_sequence,
+            usage,
+            container,
+        } = self.message;
+
+        let blocks = message.content;
+
+        let mut events = vec![Event::MessageStart {
+            message: response::Message {
+                id,
+                message: prompt::Message {
+                    role: message.role,
+                    content: Content::default(),
+                },
+                model,
+                stop_reason: None,
+                stop_sequence: None,
+                usage: Usage {
+                    input_tokens: usage.input_tokens,
+                    ..Default::default()
+                },
+                container,
+            },
+        }];
+
+        for (index, block) in blocks.into_iter().enumerate() {
+            let (content_block, deltas) = match block {
+                Block::Text { text, .. } => {
+                    let deltas = text
+                        .split_inclusive(' ')
+                        .map(|word| Delta::Text {
+                            text: Cow::Owned(word.to_string()),
+                        })
+                        .collect();
+                    (Block::text(""), deltas)
+                }
+                Block::ToolUse { mut call } => {
+                    let input = std::mem::replace(
+                        &mut call.input,
+                        serde_json::json!({}),
+                    );
+                    let delta = Delta::Json {
+                        partial_json: Cow::Owned(input.to_string()),
+                    };
+                    (Block::ToolUse { call }, vec![delta])
+                }
+                block => (block, vec![]),
+            };
+
+            events.push(Event::ContentBlockStart {
+                index,
+                content_block,
+            });
+            events.extend(
+                deltas
+                    .into_iter()
+                    .map(|delta| Event::ContentBlockDelta { index, delta }),
+            );
+            events.push(Event::ContentBlockStop { index });
+        }
+
+        events.push(Event::MessageDelta {
+            delta: MessageDelta {
+                stop_reason,
+                stop_sequence: stop_sequence
+                    .map(|s| Cow::Owned(s.into_owned())),
+                usage: None,
+            },
+            usage: Some(usage),
+        });
+        events.push(Event::MessageStop);
+
+        events
+    }
+
+    /// A [`Stream`] of the [`events`], as if from the API.
+    ///
+    /// [`events`]: FakeMessage::events
+    pub fn stream(self) -> Stream<'static> {
+        stream::mock_stream_from_data(
+            self.events()
+                .iter()
+                .filter_map(|event| serde_json::to_string(event).ok()),
+        )
+    }
+}
+
+impl<'a> response::Message<'a> {
+    /// Start building a fake message for tests. See [`FakeMessage`]. This
+    /// requires the `test-util` feature.
+    pub fn fake() -> FakeMessage<'a> {
+        FakeMessage::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt;
+
+    use super::*;
+    use crate::stream::Accumulator;
+
+    fn fake() -> FakeMessage<'static> {
+        response::Message::fake()
+            .id("msg_1")
+            .usage(10, 20)
+            .text("Checking the weather.")
+            .tool_use("toolu_1", "get_weather", serde_json::json!({"a": 1}))
+    }
+
+    #[test]
+    fn test_build() {
+        let message = fake().build();
+        assert_eq!(message.id, "msg_1");
+        assert_eq!(message.text(), "Checking the weather.");
+        assert_eq!(message.stop_reason, Some(StopReason::ToolUse));
+        assert_eq!(message.usage.output_tokens, 20);
+        assert_eq!(message.tool_use().unwrap().id, "toolu_1");
+
+        let message = FakeMessage::new().stop_sequence("STOP").build();
+        assert_eq!(message.stop_reason, Some(StopReason::StopSequence));
+        assert_eq!(message.stop_sequence.as_deref(), Some("STOP"));
+    }
+
+    #[test]
+    fn test_events() {
+        let events = fake().events();
+        // Start, text block start, 3 words, and stop, tool use start, JSON,
+        // and stop, message delta, and message stop.
+        assert_eq!(events.len(), 1 + 5 + 3 + 2);
+        assert!(matches!(events[0], Event::MessageStart { .. }));
+        assert!(matches!(
+            &events[2],
+            Event::ContentBlockDelta {
+                index: 0,
+                delta: Delta::Text { text },
+            } if text == "Checking "
+        ));
+        assert!(matches!(events[10], Event::MessageStop));
+    }
+
+    #[tokio::test]
+    async fn test_stream() {
+        let mut acc = Accumulator::new();
+        let mut stream = fake().stream();
+        while let Some(event) = stream.try_next().await.unwrap() {
+            acc.push(event).unwrap();
+        }
+
+        // Streaming gives the same message.
+        assert_eq!(acc.finish().unwrap(), fake().build());
+    }
+}
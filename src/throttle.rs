@@ -0,0 +1,268 @@
+//! Client-side rate limiting with a [`RateLimiter`], so bursts of requests
+//! are queued instead of rejected with rate limit errors.
+//!
+//! ```
+//! use misanthropic::{throttle::RateLimiter, Client};
+//!
+//! # let key = "x".repeat(108);
+//! let client = Client::new(key)?
+//!     .with_rate_limiter(RateLimiter::new(50, 40_000));
+//! # Ok::<(), misanthropic::key::InvalidKeyLength>(())
+//! ```
+use std::{sync::Mutex, time::Duration};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::Instant;
+
+/// Token bucket which is allowed to go into debt. Callers take what they need
+/// immediately and wait until the debt is paid, so they are served in order.
+#[derive(Debug)]
+struct Bucket {
+    /// Maximum (and initial) number of tokens.
+    capacity: f64,
+    /// Current number of tokens. Negative if in debt.
+    tokens: f64,
+    /// Tokens added per second.
+    rate: f64,
+    /// When `tokens` was last updated.
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(per_minute);
+        Self {
+            capacity,
+            tokens: capacity,
+            rate: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Add the tokens earned since the last update.
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.rate)
+            .min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take `n` tokens and return how long to wait until they are available.
+    /// More than the capacity is never taken, otherwise a request could wait
+    /// forever.
+    fn take(&mut self, n: f64, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= n.min(self.capacity);
+        match self.tokens < 0.0 && self.rate > 0.0 {
+            true => Duration::from_secs_f64(-self.tokens / self.rate),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// Update from the limit and remaining tokens reported by the API.
+    fn update(&mut self, limit: Option<f64>, remaining: Option<f64>) {
+        if let Some(limit) = limit.filter(|&limit| limit > 0.0) {
+            self.capacity = limit;
+            self.rate = limit / 60.0;
+        }
+        if let Some(remaining) = remaining {
+            self.tokens = self.tokens.min(remaining);
+        }
+    }
+
+    /// Go into debt so the next request waits for `duration`.
+    fn pause(&mut self, duration: Duration) {
+        self.tokens = self.tokens.min(-duration.as_secs_f64() * self.rate);
+    }
+}
+
+/// Token bucket rate limiter for requests per minute and input tokens per
+/// minute. Set it on a [`Client`] with [`Client::with_rate_limiter`] and
+/// requests will wait their turn. It is updated from the rate limit response
+/// headers when the API sends them.
+///
+/// Input tokens are estimated before the request is sent. See
+/// [`estimate_input_tokens`].
+///
+/// [`Client`]: crate::Client
+/// [`Client::with_rate_limiter`]: crate::Client::with_rate_limiter
+#[derive(Debug)]
+pub struct RateLimiter {
+    buckets: Mutex<(Bucket, Bucket)>,
+}
+
+impl RateLimiter {
+    /// Create a new [`RateLimiter`] with your organization's [rate limits].
+    ///
+    /// [rate limits]: <https://docs.anthropic.com/en/api/rate-limits>
+    pub fn new(requests_per_minute: u32, input_tokens_per_minute: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            buckets: Mutex::new((
+                Bucket::new(requests_per_minute, now),
+                Bucket::new(input_tokens_per_minute, now),
+            )),
+        }
+    }
+
+    /// Reserve one request and `input_tokens`, returning how long to wait
+    /// before sending it. The reservation is made even if the request is
+    /// never sent. [`RateLimiter::acquire`] waits for you.
+    pub fn reserve(&self, input_tokens: u64) -> Duration {
+        let now = Instant::now();
+        // The buckets are always consistent, even if another thread panicked.
+        let mut buckets =
+            self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let (requests, tokens) = &mut *buckets;
+
+        requests
+            .take(1.0, now)
+            .max(tokens.take(input_tokens as f64, now))
+    }
+
+    /// Wait until one request and `input_tokens` are available.
+    pub async fn acquire(&self, input_tokens: u64) {
+        let delay = self.reserve(input_tokens);
+        if !delay.is_zero() {
+            #[cfg(feature = "log")]
+            log::debug!("Rate limited. Waiting {:?}.", delay);
+            futures_timer::Delay::new(delay).await;
+        }
+    }
+
+    /// Update from the `anthropic-ratelimit-requests-*`,
+    /// `anthropic-ratelimit-input-tokens-*`, and `retry-after` headers of a
+    /// response.
+    pub fn update(&self, headers: &reqwest::header::HeaderMap) {
+        let get = |name: &str| -> Option<f64> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        };
+
+        let now = Instant::now();
+        let mut buckets =
+            self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let (requests, tokens) = &mut *buckets;
+        requests.refill(now);
+        tokens.refill(now);
+
+        requests.update(
+            get("anthropic-ratelimit-requests-limit"),
+            get("anthropic-ratelimit-requests-remaining"),
+        );
+        tokens.update(
+            get("anthropic-ratelimit-input-tokens-limit"),
+            get("anthropic-ratelimit-input-tokens-remaining"),
+        );
+
+        if let Some(seconds) = get("retry-after").filter(|&s| s > 0.0) {
+            let duration = Duration::from_secs_f64(seconds);
+            requests.pause(duration);
+            tokens.pause(duration);
+        }
+    }
+}
+
+/// Estimate the input tokens of a request body, at about four characters per
+/// token of text. Base64 `data` is skipped since images and documents are
+/// billed by size rather than encoded length.
+pub fn estimate_input_tokens(json: &serde_json::Value) -> u64 {
+    fn chars(value: &serde_json::Value) -> usize {
+        match value {
+            serde_json::Value::String(s) => s.chars().count(),
+            serde_json::Value::Array(values) => values.iter().map(chars).sum(),
+            serde_json::Value::Object(map) => map
+                .iter()
+                .filter(|(key, _)| key.as_str() != "data")
+                .map(|(_, value)| chars(value))
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    let messages = json.get("messages").map(chars).unwrap_or_default();
+    let system = json.get("system").map(chars).unwrap_or_default();
+    let tools = json.get("tools").map(chars).unwrap_or_default();
+
+    (messages + system + tools).div_ceil(4) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    #[test]
+    fn test_bucket() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(60, start);
+
+        // A burst up to the capacity is allowed.
+        for _ in 0..60 {
+            assert_eq!(bucket.take(1.0, start), Duration::ZERO);
+        }
+        // Then each request waits its turn, at one per second.
+        assert_eq!(bucket.take(1.0, start), Duration::from_secs(1));
+        assert_eq!(bucket.take(1.0, start), Duration::from_secs(2));
+        // Time passes.
+        let later = start + Duration::from_secs(2);
+        assert_eq!(bucket.take(1.0, later), Duration::from_secs(1));
+        // More than the capacity waits for a full bucket, not forever.
+        assert_eq!(bucket.take(1000.0, later), Duration::from_secs(61));
+    }
+
+    #[test]
+    fn test_update() {
+        let limiter = RateLimiter::new(1000, 1000);
+        assert_eq!(limiter.reserve(10), Duration::ZERO);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "anthropic-ratelimit-requests-limit",
+            HeaderValue::from_static("60"),
+        );
+        headers.insert(
+            "anthropic-ratelimit-requests-remaining",
+            HeaderValue::from_static("0"),
+        );
+        limiter.update(&headers);
+        // Nothing remaining at 60 per minute is a one second wait.
+        let delay = limiter.reserve(10);
+        assert!(delay > Duration::from_millis(900), "{delay:?}");
+        assert!(delay <= Duration::from_secs(1), "{delay:?}");
+
+        headers.clear();
+        headers.insert("retry-after", HeaderValue::from_static("30"));
+        limiter.update(&headers);
+        assert!(limiter.reserve(10) > Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_acquire() {
+        let limiter = RateLimiter::new(6000, 1_000_000);
+        let start = Instant::now();
+        for _ in 0..101 {
+            limiter.acquire(1).await;
+        }
+        // 100 requests per second, with a burst of 6000.
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_estimate_input_tokens() {
+        let json = serde_json::json!({
+            "model": "ignored",
+            "system": "12345678",
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "1234"},
+                    {"type": "image", "source": {"type": "base64", "data": "AAAAAAAAAAAA"}},
+                ],
+            }],
+        });
+        // "12345678" + "user" + "text" + "1234" + "image" + "base64".
+        assert_eq!(estimate_input_tokens(&json), 31usize.div_ceil(4) as u64);
+    }
+}
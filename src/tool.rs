@@ -1,4 +1,5 @@
-//! [`Tool`] and tool [`Choice`] types for the Anthropic Messages API.
+//! [`Tool`] and tool [`Choice`] types for the Anthropic Messages API as well
+//! as a [`ToolBox`] to dispatch [`Use`]s to their [`Handler`]s.
 use std::borrow::Cow;
 
 use crate::prompt::message::Content;
@@ -7,43 +8,130 @@ use crate::Prompt; // without this rustdoc doesn't link to Prompt, even with the
                    // full path and all features enabled. Rustdoc bug?
 use serde::{Deserialize, Serialize};
 
+mod toolbox;
+pub use toolbox::{Handler, HandlerFuture, ToolBox};
+
+pub mod code_execution;
+pub mod computer;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod text_editor;
+pub mod web_search;
+
 /// Choice of [`Tool`] for a specific [`prompt::message`].
 ///
+/// By default the model may call several tools at once. Use
+/// [`Choice::disable_parallel_tool_use`] to limit it to one (or, with
+/// [`Auto`], at most one).
+///
 /// [`prompt::message`]: crate::prompt::message
-#[derive(Serialize, Deserialize)]
+/// [`Auto`]: Choice::Auto
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case", tag = "type")]
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
-#[cfg_attr(test, derive(Debug))]
 pub enum Choice {
     /// Model chooses which tool to use, or no tool at all.
-    Auto,
+    Auto {
+        /// Use at most one tool.
+        #[serde(default, skip_serializing_if = "is_false")]
+        disable_parallel_tool_use: bool,
+    },
     /// Model must use at least one of the tools provided.
-    Any,
+    Any {
+        /// Use exactly one tool.
+        #[serde(default, skip_serializing_if = "is_false")]
+        disable_parallel_tool_use: bool,
+    },
     /// Model must use a specific tool.
     Tool {
         /// Name of the tool.
         name: String,
+        /// Use exactly one tool.
+        #[serde(default, skip_serializing_if = "is_false")]
+        disable_parallel_tool_use: bool,
     },
 }
 
+impl Choice {
+    /// Model chooses which tool to use, or no tool at all.
+    pub const fn auto() -> Self {
+        Self::Auto {
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Model must use at least one of the tools provided.
+    pub const fn any() -> Self {
+        Self::Any {
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Model must use the [`Tool`] with the given `name`.
+    pub fn tool<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Tool {
+            name: name.into(),
+            disable_parallel_tool_use: false,
+        }
+    }
+
+    /// Prevent the model from calling more than one tool at once.
+    pub fn disable_parallel_tool_use(mut self) -> Self {
+        match &mut self {
+            Self::Auto {
+                disable_parallel_tool_use,
+            }
+            | Self::Any {
+                disable_parallel_tool_use,
+            }
+            | Self::Tool {
+                disable_parallel_tool_use,
+                ..
+            } => *disable_parallel_tool_use = true,
+        }
+        self
+    }
+}
+
+/// For `skip_serializing_if` on flags that default to false.
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
 /// A tool a model can use while completing a [`prompt::Message`].
 ///
 /// [`prompt::Message`]: crate::prompt::Message
 #[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(try_from = "ToolBuilder<'a>")]
 pub struct Tool<'a> {
+    /// Type of a server tool, such as [`code_execution::TYPE`]. Server tools
+    /// are run by Anthropic and have no `description` or `input_schema`.
+    /// [`None`] for ordinary tools you handle yourself.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<Cow<'a, str>>,
     /// Name of the tool.
     pub name: Cow<'a, str>,
     /// Description of the tool. The model will use this as documentation.
+    #[serde(skip_serializing_if = "str_is_empty")]
     pub description: Cow<'a, str>,
     /// Input schema for the tool. See [tool use guide] for more information.
-    /// The schema is not validated by this crate but should conform to the
-    /// [JSON Schema] specification.
+    /// The schema should conform to the [JSON Schema] specification. Inputs
+    /// can be validated against it with `Tool::validate_input` if the
+    /// `validate-schema` feature is enabled.
     ///
     /// [tool use guide]: <https://docs.anthropic.com/en/docs/build-with-claude/tool-use>
     /// [JSON Schema]: <https://json-schema.org/>
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
     pub input_schema: serde_json::Value,
+    /// Other fields, serialized alongside the rest. This allows using new API
+    /// fields (such as display metadata) before this crate supports them.
+    /// Keys should not be the same as those of other fields.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
     /// Set a cache breakpoint. See [`Prompt::cache`] for more information.
     ///
     /// [`Prompt::cache`] crate::Prompt::cache
@@ -79,28 +167,38 @@ impl<'de> Deserialize<'de> for ToolBuilder<'_> {
     {
         #[derive(Deserialize)]
         struct Foreign {
+            #[serde(rename = "type", default)]
+            kind: Option<Cow<'static, str>>,
             name: Cow<'static, str>,
+            #[serde(default)]
             description: Cow<'static, str>,
+            #[serde(default)]
             input_schema: serde_json::Value,
             #[cfg(feature = "prompt-caching")]
             cache_control: Option<crate::prompt::message::CacheControl>,
+            #[serde(flatten)]
+            extra: serde_json::Map<String, serde_json::Value>,
         }
 
         let foreign = Foreign::deserialize(deserializer)?;
 
         let Foreign {
+            kind,
             name,
             description,
             input_schema,
             #[cfg(feature = "prompt-caching")]
             cache_control,
+            extra,
         } = foreign;
 
         Ok(ToolBuilder {
             tool: Tool {
+                kind,
                 name,
                 description,
                 input_schema,
+                extra,
                 #[cfg(feature = "prompt-caching")]
                 cache_control,
             },
@@ -163,6 +261,228 @@ impl<'a> ToolBuilder<'a> {
         self
     }
 
+    /// Add a parameter to the [`Tool::input_schema`] with a JSON Schema for
+    /// its value. This creates the `properties` and `required` structure
+    /// checked on [`build`], so small tools don't need a hand-written
+    /// [`schema`]. Adding a parameter with the same `name` replaces it.
+    ///
+    /// The typed helpers like [`param_string`] are more convenient for simple
+    /// parameters. For complex cases, use [`schema`], with or without adding
+    /// parameters afterwards.
+    ///
+    /// ```
+    /// use misanthropic::{json, Tool};
+    ///
+    /// let tool = Tool::builder("ping")
+    ///     .description("Ping a server.")
+    ///     .param_string("host", "The host to ping.", true)
+    ///     .param_integer("count", "Number of pings.", false)
+    ///     .param_enum("protocol", "IP version.", ["ipv4", "ipv6"], false)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(tool.input_schema["required"], json!(["host"]));
+    /// assert_eq!(
+    ///     tool.input_schema["properties"]["protocol"]["enum"],
+    ///     json!(["ipv4", "ipv6"])
+    /// );
+    /// ```
+    ///
+    /// [`build`]: ToolBuilder::build
+    /// [`schema`]: ToolBuilder::schema
+    /// [`param_string`]: ToolBuilder::param_string
+    pub fn param<S>(
+        mut self,
+        name: S,
+        schema: serde_json::Value,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+
+        if !self.tool.input_schema.is_object() {
+            self.tool.input_schema = serde_json::json!({"type": "object"});
+        }
+        let Some(input_schema) = self.tool.input_schema.as_object_mut() else {
+            return self;
+        };
+
+        let required_names = input_schema
+            .entry("required")
+            .and_modify(|r| {
+                if !r.is_array() {
+                    *r = serde_json::json!([]);
+                }
+            })
+            .or_insert_with(|| serde_json::json!([]));
+        if let Some(required_names) = required_names.as_array_mut() {
+            required_names.retain(|n| n.as_str() != Some(&name));
+            if required {
+                required_names.push(name.clone().into());
+            }
+        }
+
+        let properties = input_schema
+            .entry("properties")
+            .and_modify(|p| {
+                if !p.is_object() {
+                    *p = serde_json::json!({});
+                }
+            })
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(properties) = properties.as_object_mut() {
+            properties.insert(name, schema);
+        }
+
+        self
+    }
+
+    /// Add a string parameter. See [`param`].
+    ///
+    /// [`param`]: ToolBuilder::param
+    pub fn param_string<S, D>(
+        self,
+        name: S,
+        description: D,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.param_typed(name, "string", description, required)
+    }
+
+    /// Add a number parameter. See [`param`].
+    ///
+    /// [`param`]: ToolBuilder::param
+    pub fn param_number<S, D>(
+        self,
+        name: S,
+        description: D,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.param_typed(name, "number", description, required)
+    }
+
+    /// Add an integer parameter. See [`param`].
+    ///
+    /// [`param`]: ToolBuilder::param
+    pub fn param_integer<S, D>(
+        self,
+        name: S,
+        description: D,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.param_typed(name, "integer", description, required)
+    }
+
+    /// Add a boolean parameter. See [`param`].
+    ///
+    /// [`param`]: ToolBuilder::param
+    pub fn param_bool<S, D>(
+        self,
+        name: S,
+        description: D,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.param_typed(name, "boolean", description, required)
+    }
+
+    /// Add a string parameter which must be one of `values`. See [`param`].
+    ///
+    /// [`param`]: ToolBuilder::param
+    pub fn param_enum<S, D, V, Vs>(
+        self,
+        name: S,
+        description: D,
+        values: Vs,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+        V: Into<String>,
+        Vs: IntoIterator<Item = V>,
+    {
+        let values: Vec<String> = values.into_iter().map(Into::into).collect();
+        let mut schema = Self::typed_schema("string", description);
+        if let Some(schema) = schema.as_object_mut() {
+            schema.insert("enum".into(), values.into());
+        }
+        self.param(name, schema, required)
+    }
+
+    fn param_typed<S, D>(
+        self,
+        name: S,
+        kind: &str,
+        description: D,
+        required: bool,
+    ) -> Self
+    where
+        S: Into<String>,
+        D: Into<String>,
+    {
+        self.param(name, Self::typed_schema(kind, description), required)
+    }
+
+    /// Schema for a value of a `kind` such as `"string"`. An empty
+    /// `description` is left out.
+    fn typed_schema<D>(kind: &str, description: D) -> serde_json::Value
+    where
+        D: Into<String>,
+    {
+        let description = description.into();
+        match description.is_empty() {
+            true => serde_json::json!({"type": kind}),
+            false => serde_json::json!({
+                "type": kind,
+                "description": description,
+            }),
+        }
+    }
+
+    /// Set an [`extra`] field, such as a new API field this crate doesn't
+    /// support yet. Setting the same `key` again replaces the value.
+    ///
+    /// ```
+    /// use misanthropic::{json, Tool};
+    ///
+    /// let tool = Tool::builder("ping")
+    ///     .description("Ping a server.")
+    ///     .param_string("host", "The host to ping.", true)
+    ///     .extra("strict", true)
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(json!(tool)["strict"], true);
+    /// ```
+    ///
+    /// [`extra`]: Tool::extra
+    pub fn extra<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<serde_json::Value>,
+    {
+        self.tool.extra.insert(key.into(), value.into());
+        self
+    }
+
     /// This will build the [`Tool`] without checking any of the fields. This is
     /// recommended only with static strings.
     pub fn build_unchecked(self) -> Tool<'a> {
@@ -240,6 +560,11 @@ impl<'a> ToolBuilder<'a> {
             return Err(ToolBuildError::EmptyName);
         }
 
+        // Server tools are defined by Anthropic, not by us.
+        if self.tool.is_server() {
+            return Ok(self.tool);
+        }
+
         if self.tool.description.is_empty() {
             return Err(ToolBuildError::EmptyDescription);
         }
@@ -278,20 +603,89 @@ pub enum ToolBuildError {
     },
 }
 
+/// Errors from [`Tool::validate_input`].
+#[cfg(feature = "validate-schema")]
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum InvalidInput {
+    #[error("Tool `{expected}` can't validate a call to tool `{name}`.")]
+    WrongTool { expected: String, name: String },
+    #[error("Invalid input schema: {message}")]
+    Schema { message: String },
+    #[error("Invalid input for tool `{name}`: {}", errors.join("; "))]
+    Input { name: String, errors: Vec<String> },
+}
+
+#[cfg(feature = "validate-schema")]
+impl InvalidInput {
+    /// An error [`Result`] for the `call`, describing what is wrong with the
+    /// input and asking the model to try again.
+    pub fn to_result(&self, call: &Use<'_>) -> Result<'static> {
+        Result {
+            tool_use_id: Cow::Owned(call.id.to_string()),
+            content: Content::text(format!(
+                "{self} Fix the input and try again."
+            )),
+            is_error: true,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }
+    }
+}
+
 impl<'a> Tool<'a> {
     /// Use a builder to create a new tool with some very basic validation.
     pub fn builder(name: impl Into<Cow<'a, str>>) -> ToolBuilder<'a> {
         ToolBuilder {
             tool: Tool {
+                kind: None,
                 name: name.into(),
                 description: Cow::Owned(String::new()),
                 input_schema: serde_json::Value::Null,
+                extra: Default::default(),
                 #[cfg(feature = "prompt-caching")]
                 cache_control: None,
             },
         }
     }
 
+    /// Create a server [`Tool`] of a given `kind` (type) and `name`. Server
+    /// tools are run by Anthropic. See [`Tool::code_execution`] for an example.
+    pub fn server(
+        kind: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        Tool {
+            kind: Some(kind.into()),
+            ..Self::builder(name).build_unchecked()
+        }
+    }
+
+    /// Anthropic's code execution server [`Tool`]. Results are returned as
+    /// [`Block::CodeExecutionToolResult`]. This requires the `code-execution`
+    /// feature, which enables the beta.
+    ///
+    /// [`Block::CodeExecutionToolResult`]: crate::prompt::message::Block::CodeExecutionToolResult
+    pub fn code_execution() -> Self {
+        Self::server(code_execution::TYPE, code_execution::NAME)
+    }
+
+    /// Anthropic's web search server [`Tool`]. Searches are returned as
+    /// [`Block::ServerToolUse`] and results as [`Block::WebSearchToolResult`].
+    ///
+    /// [`Block::ServerToolUse`]: crate::prompt::message::Block::ServerToolUse
+    /// [`Block::WebSearchToolResult`]: crate::prompt::message::Block::WebSearchToolResult
+    pub fn web_search() -> Self {
+        Self::server(web_search::TYPE, web_search::NAME)
+    }
+
+    /// Returns true if this is a server [`Tool`] (if [`kind`] is [`Some`]).
+    ///
+    /// [`kind`]: Tool::kind
+    pub fn is_server(&self) -> bool {
+        self.kind.is_some()
+    }
+
     /// Create a cache breakpoint at this [`Tool`] by setting [`cache_control`]
     /// to [`Ephemeral`] See [`Prompt::cache`] for more information.
     ///
@@ -312,6 +706,77 @@ impl<'a> Tool<'a> {
         self.cache_control.is_some()
     }
 
+    /// Validate a [`Use`] of this [`Tool`] against the [`input_schema`], so
+    /// malformed input can be rejected before calling the tool. The error can
+    /// be sent back to the model with [`InvalidInput::to_result`] so it can
+    /// try again.
+    ///
+    /// Tools without a schema, such as server tools, accept any input. Remote
+    /// `$ref`s in the schema are not resolved.
+    ///
+    /// ```
+    /// use misanthropic::{json, tool::Use, Tool};
+    ///
+    /// let tool = Tool::builder("shout")
+    ///     .description("Returns the input in uppercase.")
+    ///     .schema(json!({
+    ///         "type": "object",
+    ///         "properties": {"text": {"type": "string"}},
+    ///         "required": ["text"],
+    ///     }))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let call = Use::try_from(json!({
+    ///     "id": "toolu_1",
+    ///     "name": "shout",
+    ///     "input": {"text": 42},
+    /// }))
+    /// .unwrap();
+    /// let error = tool.validate_input(&call).unwrap_err();
+    /// assert!(error.to_result(&call).is_error);
+    /// ```
+    ///
+    /// [`input_schema`]: Tool::input_schema
+    #[cfg(feature = "validate-schema")]
+    pub fn validate_input(
+        &self,
+        call: &Use<'_>,
+    ) -> std::result::Result<(), InvalidInput> {
+        if call.name != self.name {
+            return Err(InvalidInput::WrongTool {
+                expected: self.name.to_string(),
+                name: call.name.to_string(),
+            });
+        }
+
+        if self.input_schema.is_null() {
+            return Ok(());
+        }
+
+        let validator =
+            jsonschema::validator_for(&self.input_schema).map_err(|error| {
+                InvalidInput::Schema {
+                    message: error.to_string(),
+                }
+            })?;
+        let errors: Vec<String> = validator
+            .iter_errors(&call.input)
+            .map(|error| match error.instance_path.as_str() {
+                "" => error.to_string(),
+                path => format!("`{path}`: {error}"),
+            })
+            .collect();
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(InvalidInput::Input {
+                name: call.name.to_string(),
+                errors,
+            }),
+        }
+    }
+
     /// Try to convert from a serializable value to a [`Tool`].
     // A blanket impl for TryFrom<T> where T: Serialize would be nice but it
     // would conflict with the blanket impl for TryFrom<Value> where Value:
@@ -325,6 +790,26 @@ impl<'a> Tool<'a> {
         let value = serde_json::to_value(value)?;
         value.try_into()
     }
+
+    /// Convert to a `'static` lifetime by taking ownership of the [`Cow`]
+    /// fields.
+    pub fn into_static(self) -> Tool<'static> {
+        Tool {
+            kind: self.kind.map(|kind| Cow::Owned(kind.into_owned())),
+            name: Cow::Owned(self.name.into_owned()),
+            description: Cow::Owned(self.description.into_owned()),
+            input_schema: self.input_schema,
+            extra: self.extra,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: self.cache_control,
+        }
+    }
+}
+
+/// For `skip_serializing_if` on [`Cow`] fields.
+#[allow(clippy::ptr_arg)] // serde passes a reference to the field.
+fn str_is_empty(s: &Cow<'_, str>) -> bool {
+    s.is_empty()
 }
 
 impl TryFrom<serde_json::Value> for Tool<'static> {
@@ -496,6 +981,67 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "validate-schema")]
+    fn test_validate_input() {
+        let tool = Tool::builder("ping")
+            .description("Ping a server.")
+            .schema(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "host": {"type": "string"},
+                    "count": {"type": "integer", "minimum": 1}
+                },
+                "required": ["host"]
+            }))
+            .build()
+            .unwrap();
+        let call = |input| Use {
+            id: "toolu_1".into(),
+            name: "ping".into(),
+            input,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+
+        let valid = call(serde_json::json!({"host": "example.com"}));
+        assert!(tool.validate_input(&valid).is_ok());
+
+        let invalid = call(serde_json::json!({"count": 0}));
+        let error = tool.validate_input(&invalid).unwrap_err();
+        let InvalidInput::Input { name, errors } = &error else {
+            panic!("Expected invalid input, got {error:?}");
+        };
+        assert_eq!(name, "ping");
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.contains("\"host\" is a required")));
+        assert!(errors.iter().any(|e| e.starts_with("`/count`:")));
+
+        let result = error.to_result(&invalid);
+        assert_eq!(result.tool_use_id, "toolu_1");
+        assert!(result.is_error);
+        assert!(result.content.to_string().ends_with("try again."));
+
+        let mut other = valid.clone();
+        other.name = "pong".into();
+        assert!(matches!(
+            tool.validate_input(&other),
+            Err(InvalidInput::WrongTool { .. })
+        ));
+
+        // Server tools have no schema, so anything goes.
+        let mut search = valid;
+        search.name = "web_search".into();
+        assert!(Tool::web_search().validate_input(&search).is_ok());
+
+        let mut broken = tool;
+        broken.input_schema = serde_json::json!({"type": 42});
+        assert!(matches!(
+            broken.validate_input(&call(serde_json::json!({}))),
+            Err(InvalidInput::Schema { .. })
+        ));
+    }
+
     #[test]
     #[cfg(feature = "markdown")]
     fn test_use_markdown() {
@@ -562,6 +1108,128 @@ mod tests {
         assert!(ToolBuilder::is_valid_input_schema(&schema).is_err());
     }
 
+    #[test]
+    fn test_tool_builder_params() {
+        let tool = Tool::builder("ping")
+            .description("Ping a server.")
+            .param_string("host", "The host to ping.", true)
+            .param_number("timeout", "", false)
+            .param_bool("verbose", "Print more.", false)
+            .param_enum("protocol", "IP version.", ["ipv4", "ipv6"], true)
+            // Replacing a parameter can make it optional.
+            .param_integer("count", "Number of pings.", true)
+            .param_integer("count", "Number of pings.", false)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            tool.input_schema,
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "host": {
+                        "type": "string",
+                        "description": "The host to ping."
+                    },
+                    "timeout": {"type": "number"},
+                    "verbose": {
+                        "type": "boolean",
+                        "description": "Print more."
+                    },
+                    "protocol": {
+                        "type": "string",
+                        "description": "IP version.",
+                        "enum": ["ipv4", "ipv6"]
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": "Number of pings."
+                    }
+                },
+                "required": ["host", "protocol"]
+            })
+        );
+
+        // Parameters can be added to a raw schema.
+        let tool = Tool::builder("ping")
+            .description("Ping a server.")
+            .schema(serde_json::json!({
+                "type": "object",
+                "properties": {"host": {"type": "string", "format": "hostname"}},
+                "required": ["host"]
+            }))
+            .param("ports", serde_json::json!({"type": "array"}), false)
+            .build()
+            .unwrap();
+        assert_eq!(tool.input_schema["required"], serde_json::json!(["host"]));
+        assert_eq!(
+            tool.input_schema["properties"]["host"]["format"],
+            "hostname"
+        );
+        assert!(tool.input_schema["properties"]["ports"].is_object());
+
+        // Without parameters or a schema, the build still fails.
+        assert!(matches!(
+            Tool::builder("ping").description("Ping a server.").build(),
+            Err(ToolBuildError::EmptyInputSchema)
+        ));
+    }
+
+    #[test]
+    fn test_server_tool() {
+        let tool = Tool::code_execution();
+        assert!(tool.is_server());
+
+        let json = serde_json::to_value(&tool).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": code_execution::TYPE,
+                "name": code_execution::NAME,
+            })
+        );
+
+        // Server tools skip validation of description and schema.
+        let tool: Tool = json.try_into().unwrap();
+        assert_eq!(tool, Tool::code_execution());
+
+        let tool = Tool::web_search();
+        assert!(tool.is_server());
+        assert_eq!(tool.kind.as_deref(), Some(web_search::TYPE));
+        assert_eq!(tool.name, web_search::NAME);
+    }
+
+    #[test]
+    fn test_tool_extra() {
+        let json = serde_json::json!({
+            "name": "ping",
+            "description": "Ping a server.",
+            "input_schema": {
+                "type": "object",
+                "properties": {"host": {"type": "string"}},
+                "required": ["host"]
+            },
+            "strict": true,
+            "display": {"icon": "network"}
+        });
+
+        // Unknown fields are kept and serialized again.
+        let tool: Tool = json.clone().try_into().unwrap();
+        assert_eq!(tool.extra.len(), 2);
+        assert_eq!(tool.extra["strict"], true);
+        assert_eq!(serde_json::to_value(&tool).unwrap(), json);
+
+        let tool = Tool::builder("ping")
+            .description("Ping a server.")
+            .param_string("host", "", true)
+            .extra("strict", false)
+            .extra("strict", true)
+            .extra("display", serde_json::json!({"icon": "network"}))
+            .build()
+            .unwrap();
+        assert_eq!(serde_json::to_value(&tool).unwrap(), json);
+    }
+
     #[test]
     fn test_build() {
         let tool = Tool::builder("test_name")
@@ -790,24 +1458,48 @@ mod tests {
 
     #[test]
     fn test_choice_serde() {
-        let choice = Choice::Auto;
+        let choice = Choice::auto();
         let json = serde_json::to_string(&choice).unwrap();
+        assert_eq!(json, r#"{"type":"auto"}"#);
         let choice2: Choice = serde_json::from_str(&json).unwrap();
         assert_eq!(choice, choice2);
 
-        let choice = Choice::Any;
+        let choice = Choice::any();
         let json = serde_json::to_string(&choice).unwrap();
         let choice2: Choice = serde_json::from_str(&json).unwrap();
         assert_eq!(choice, choice2);
 
-        let choice = Choice::Tool {
-            name: "test_name".into(),
-        };
+        let choice = Choice::tool("test_name");
         let json = serde_json::to_string(&choice).unwrap();
         let choice2: Choice = serde_json::from_str(&json).unwrap();
         assert_eq!(choice, choice2);
     }
 
+    #[test]
+    fn test_choice_disable_parallel_tool_use() {
+        let choice = Choice::tool("test_name").disable_parallel_tool_use();
+        let json = serde_json::to_string(&choice).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"tool","name":"test_name","disable_parallel_tool_use":true}"#
+        );
+        let choice2: Choice = serde_json::from_str(&json).unwrap();
+        assert_eq!(choice, choice2);
+
+        assert_eq!(
+            Choice::auto().disable_parallel_tool_use(),
+            Choice::Auto {
+                disable_parallel_tool_use: true
+            }
+        );
+        assert_eq!(
+            Choice::any().disable_parallel_tool_use(),
+            Choice::Any {
+                disable_parallel_tool_use: true
+            }
+        );
+    }
+
     #[test]
     fn test_result_serde() {
         let result = Result {
@@ -837,7 +1529,7 @@ mod tests {
 
         assert_eq!(result.tool_use_id, "test_id");
         assert_eq!(result.content.to_string(), "test_content");
-        assert_eq!(result.is_error, false);
+        assert!(!result.is_error);
     }
 
     #[test]
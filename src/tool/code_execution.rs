@@ -0,0 +1,84 @@
+//! Types for Anthropic's [code execution] server tool. Add the tool with
+//! [`Tool::code_execution`]. The model's code runs in a sandboxed container
+//! and the results come back as a [`Block::CodeExecutionToolResult`]. The
+//! container can be reused across requests with [`Prompt::container`].
+//!
+//! [code execution]: <https://docs.anthropic.com/en/docs/agents-and-tools/tool-use/code-execution-tool>
+//! [`Tool::code_execution`]: crate::Tool::code_execution
+//! [`Block::CodeExecutionToolResult`]: crate::prompt::message::Block::CodeExecutionToolResult
+//! [`Prompt::container`]: crate::Prompt::container
+use serde::{Deserialize, Serialize};
+
+/// Name of the code execution tool.
+pub const NAME: &str = "code_execution";
+/// Type of the code execution tool.
+pub const TYPE: &str = "code_execution_20250522";
+/// Beta required to use the code execution tool. This is sent by the
+/// [`Client`] when the `code-execution` feature is enabled.
+///
+/// [`Client`]: crate::Client
+pub const BETA: &str = beta!();
+
+/// [`BETA`] as a literal, for use in `concat!`.
+macro_rules! beta {
+    () => {
+        "code-execution-2025-05-22"
+    };
+}
+pub(crate) use beta;
+
+/// Outcome of running code with the code execution tool.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Outcome {
+    /// The code ran. It may still have failed. Check the `return_code`.
+    #[serde(rename = "code_execution_result")]
+    Result {
+        /// Standard output.
+        stdout: String,
+        /// Standard error.
+        stderr: String,
+        /// Exit code of the process.
+        return_code: i32,
+        /// Files created by the code.
+        #[serde(rename = "content", default)]
+        files: Vec<File>,
+    },
+    /// The code could not be run.
+    #[serde(rename = "code_execution_tool_result_error")]
+    Error {
+        /// Why, for example `unavailable` or `execution_time_exceeded`.
+        error_code: String,
+    },
+}
+
+impl Outcome {
+    /// Returns true if the code ran and returned zero.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Result { return_code: 0, .. })
+    }
+}
+
+/// A file created by the code, which can be downloaded with the Files API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(tag = "type", rename = "code_execution_output")]
+pub struct File {
+    /// Id of the file.
+    pub file_id: String,
+}
+
+/// A container the code ran in. Pass the [`id`] to [`Prompt::container`] to
+/// reuse the container, and its files, in the next request.
+///
+/// [`id`]: Container::id
+/// [`Prompt::container`]: crate::Prompt::container
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+pub struct Container {
+    /// Id of the container.
+    pub id: String,
+    /// When the container expires, as an RFC 3339 timestamp.
+    pub expires_at: String,
+}
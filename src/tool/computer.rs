@@ -0,0 +1,456 @@
+//! Typed [`Action`]s for Anthropic's [computer use] tool. These serialize to
+//! and from the tool's [`Use::input`] so a handler can `match` on the action
+//! instead of digging through [`serde_json::Value`]s.
+//!
+//! ```
+//! use misanthropic::{json, tool::computer::{Action, Coordinate}};
+//!
+//! let action: Action = serde_json::from_value(json!({
+//!     "action": "left_click",
+//!     "coordinate": [100, 200],
+//! }))
+//! .unwrap();
+//!
+//! match action {
+//!     Action::Click { coordinate: Some(Coordinate { x, y }), .. } => {
+//!         assert_eq!((x, y), (100, 200));
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+//!
+//! [computer use]: <https://docs.anthropic.com/en/docs/build-with-claude/computer-use>
+use serde::{Deserialize, Serialize};
+
+use super::Use;
+
+/// A point on the screen, in pixels, serialized as `[x, y]`.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+#[serde(from = "[u32; 2]", into = "[u32; 2]")]
+pub struct Coordinate {
+    /// Pixels from the left.
+    pub x: u32,
+    /// Pixels from the top.
+    pub y: u32,
+}
+
+impl From<[u32; 2]> for Coordinate {
+    fn from([x, y]: [u32; 2]) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Coordinate> for [u32; 2] {
+    fn from(Coordinate { x, y }: Coordinate) -> Self {
+        [x, y]
+    }
+}
+
+impl From<(u32, u32)> for Coordinate {
+    fn from((x, y): (u32, u32)) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Mouse button for [`Action::Click`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    /// Left (primary) button.
+    #[default]
+    Left,
+    /// Right (secondary) button.
+    Right,
+    /// Middle button.
+    Middle,
+}
+
+/// Direction for [`Action::Scroll`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// An action requested by the model through the computer use tool.
+///
+/// Coordinates are optional where the API allows them to be omitted, in which
+/// case the action happens at the current cursor position.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawAction", into = "RawAction")]
+pub enum Action {
+    /// Take a screenshot. The result should be an image of the screen.
+    Screenshot,
+    /// Report the current cursor position.
+    CursorPosition,
+    /// Move the cursor.
+    MouseMove {
+        /// Where to move the cursor.
+        coordinate: Coordinate,
+    },
+    /// Single click.
+    Click {
+        /// Which button to click.
+        button: MouseButton,
+        /// Where to click.
+        coordinate: Option<Coordinate>,
+        /// Keys to hold while clicking, for example `shift`.
+        modifiers: Option<String>,
+    },
+    /// Double left click.
+    DoubleClick {
+        /// Where to click.
+        coordinate: Option<Coordinate>,
+    },
+    /// Triple left click.
+    TripleClick {
+        /// Where to click.
+        coordinate: Option<Coordinate>,
+    },
+    /// Drag with the left button held from the cursor to `coordinate`.
+    Drag {
+        /// Where to release the button.
+        coordinate: Coordinate,
+    },
+    /// Press the left mouse button.
+    LeftMouseDown,
+    /// Release the left mouse button.
+    LeftMouseUp,
+    /// Scroll.
+    Scroll {
+        /// Where to scroll.
+        coordinate: Option<Coordinate>,
+        /// Which way to scroll.
+        direction: ScrollDirection,
+        /// How many "clicks" of the scroll wheel.
+        amount: u32,
+        /// Keys to hold while scrolling.
+        modifiers: Option<String>,
+    },
+    /// Type a string of text.
+    Type {
+        /// Text to type.
+        text: String,
+    },
+    /// Press a key or key combination in xdotool syntax, like `ctrl+s`.
+    Key {
+        /// Key combination.
+        combo: String,
+    },
+    /// Hold a key or key combination down for a while.
+    HoldKey {
+        /// Key combination.
+        combo: String,
+        /// How long to hold it, in seconds.
+        duration: f64,
+    },
+    /// Do nothing for a while.
+    Wait {
+        /// How long to wait, in seconds.
+        duration: f64,
+    },
+}
+
+/// Error converting computer use input into an [`Action`].
+#[derive(Debug, thiserror::Error)]
+#[allow(missing_docs)]
+pub enum ActionError {
+    #[error("Unknown computer use action `{action}`.")]
+    Unknown { action: String },
+    #[error("Computer use action `{action}` is missing `{field}`.")]
+    MissingField {
+        action: &'static str,
+        field: &'static str,
+    },
+}
+
+/// The tool input as it is sent by the API. All fields but `action` are
+/// optional and depend on the action.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct RawAction {
+    action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    coordinate: Option<Coordinate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scroll_direction: Option<ScrollDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scroll_amount: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+}
+
+impl TryFrom<RawAction> for Action {
+    type Error = ActionError;
+
+    fn try_from(raw: RawAction) -> Result<Self, Self::Error> {
+        use ActionError::MissingField;
+
+        let RawAction {
+            action,
+            coordinate,
+            text,
+            scroll_direction,
+            scroll_amount,
+            duration,
+        } = raw;
+
+        macro_rules! require {
+            ($field:ident, $action:literal) => {
+                $field.ok_or(MissingField {
+                    action: $action,
+                    field: stringify!($field),
+                })?
+            };
+        }
+
+        fn click(
+            button: MouseButton,
+            coordinate: Option<Coordinate>,
+            modifiers: Option<String>,
+        ) -> Action {
+            Action::Click {
+                button,
+                coordinate,
+                modifiers,
+            }
+        }
+
+        Ok(match action.as_str() {
+            "screenshot" => Self::Screenshot,
+            "cursor_position" => Self::CursorPosition,
+            "mouse_move" => Self::MouseMove {
+                coordinate: require!(coordinate, "mouse_move"),
+            },
+            "left_click" => click(MouseButton::Left, coordinate, text),
+            "right_click" => click(MouseButton::Right, coordinate, text),
+            "middle_click" => click(MouseButton::Middle, coordinate, text),
+            "double_click" => Self::DoubleClick { coordinate },
+            "triple_click" => Self::TripleClick { coordinate },
+            "left_click_drag" => Self::Drag {
+                coordinate: require!(coordinate, "left_click_drag"),
+            },
+            "left_mouse_down" => Self::LeftMouseDown,
+            "left_mouse_up" => Self::LeftMouseUp,
+            "scroll" => Self::Scroll {
+                coordinate,
+                direction: require!(scroll_direction, "scroll"),
+                amount: require!(scroll_amount, "scroll"),
+                modifiers: text,
+            },
+            "type" => Self::Type {
+                text: require!(text, "type"),
+            },
+            "key" => Self::Key {
+                combo: require!(text, "key"),
+            },
+            "hold_key" => Self::HoldKey {
+                combo: require!(text, "hold_key"),
+                duration: require!(duration, "hold_key"),
+            },
+            "wait" => Self::Wait {
+                duration: require!(duration, "wait"),
+            },
+            _ => return Err(ActionError::Unknown { action }),
+        })
+    }
+}
+
+impl From<Action> for RawAction {
+    fn from(action: Action) -> Self {
+        let raw = |action: &str| RawAction {
+            action: action.to_string(),
+            ..Default::default()
+        };
+
+        match action {
+            Action::Screenshot => raw("screenshot"),
+            Action::CursorPosition => raw("cursor_position"),
+            Action::MouseMove { coordinate } => RawAction {
+                coordinate: Some(coordinate),
+                ..raw("mouse_move")
+            },
+            Action::Click {
+                button,
+                coordinate,
+                modifiers,
+            } => RawAction {
+                coordinate,
+                text: modifiers,
+                ..raw(match button {
+                    MouseButton::Left => "left_click",
+                    MouseButton::Right => "right_click",
+                    MouseButton::Middle => "middle_click",
+                })
+            },
+            Action::DoubleClick { coordinate } => RawAction {
+                coordinate,
+                ..raw("double_click")
+            },
+            Action::TripleClick { coordinate } => RawAction {
+                coordinate,
+                ..raw("triple_click")
+            },
+            Action::Drag { coordinate } => RawAction {
+                coordinate: Some(coordinate),
+                ..raw("left_click_drag")
+            },
+            Action::LeftMouseDown => raw("left_mouse_down"),
+            Action::LeftMouseUp => raw("left_mouse_up"),
+            Action::Scroll {
+                coordinate,
+                direction,
+                amount,
+                modifiers,
+            } => RawAction {
+                coordinate,
+                text: modifiers,
+                scroll_direction: Some(direction),
+                scroll_amount: Some(amount),
+                ..raw("scroll")
+            },
+            Action::Type { text } => RawAction {
+                text: Some(text),
+                ..raw("type")
+            },
+            Action::Key { combo } => RawAction {
+                text: Some(combo),
+                ..raw("key")
+            },
+            Action::HoldKey { combo, duration } => RawAction {
+                text: Some(combo),
+                duration: Some(duration),
+                ..raw("hold_key")
+            },
+            Action::Wait { duration } => RawAction {
+                duration: Some(duration),
+                ..raw("wait")
+            },
+        }
+    }
+}
+
+impl TryFrom<&Use<'_>> for Action {
+    type Error = serde_json::Error;
+
+    fn try_from(call: &Use<'_>) -> Result<Self, Self::Error> {
+        Action::deserialize(&call.input)
+    }
+}
+
+impl From<Action> for serde_json::Value {
+    fn from(action: Action) -> Self {
+        // Unwrap can never panic because `RawAction` is always representable
+        // as JSON.
+        serde_json::to_value(action).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_action_roundtrip() {
+        let cases = [
+            (json!({"action": "screenshot"}), Action::Screenshot),
+            (json!({"action": "cursor_position"}), Action::CursorPosition),
+            (
+                json!({"action": "mouse_move", "coordinate": [1, 2]}),
+                Action::MouseMove {
+                    coordinate: (1, 2).into(),
+                },
+            ),
+            (
+                json!({"action": "right_click", "coordinate": [3, 4], "text": "shift"}),
+                Action::Click {
+                    button: MouseButton::Right,
+                    coordinate: Some((3, 4).into()),
+                    modifiers: Some("shift".into()),
+                },
+            ),
+            (
+                json!({"action": "left_click"}),
+                Action::Click {
+                    button: MouseButton::Left,
+                    coordinate: None,
+                    modifiers: None,
+                },
+            ),
+            (
+                json!({"action": "scroll", "coordinate": [5, 6], "scroll_direction": "down", "scroll_amount": 3}),
+                Action::Scroll {
+                    coordinate: Some((5, 6).into()),
+                    direction: ScrollDirection::Down,
+                    amount: 3,
+                    modifiers: None,
+                },
+            ),
+            (
+                json!({"action": "type", "text": "hello"}),
+                Action::Type {
+                    text: "hello".into(),
+                },
+            ),
+            (
+                json!({"action": "key", "text": "ctrl+s"}),
+                Action::Key {
+                    combo: "ctrl+s".into(),
+                },
+            ),
+            (
+                json!({"action": "hold_key", "text": "shift", "duration": 1.5}),
+                Action::HoldKey {
+                    combo: "shift".into(),
+                    duration: 1.5,
+                },
+            ),
+            (
+                json!({"action": "wait", "duration": 2.0}),
+                Action::Wait { duration: 2.0 },
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let action: Action = serde_json::from_value(input.clone()).unwrap();
+            assert_eq!(action, expected);
+            assert_eq!(serde_json::Value::from(action), input);
+        }
+    }
+
+    #[test]
+    fn test_action_errors() {
+        let err = serde_json::from_value::<Action>(json!({"action": "dance"}))
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown computer use action"));
+
+        let err = serde_json::from_value::<Action>(json!({"action": "type"}))
+            .unwrap_err();
+        assert!(err.to_string().contains("missing `text`"));
+    }
+
+    #[test]
+    fn test_action_from_use() {
+        let call = Use {
+            id: "toolu_1".into(),
+            name: "computer".into(),
+            input: json!({"action": "left_click_drag", "coordinate": [7, 8]}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+
+        assert_eq!(
+            Action::try_from(&call).unwrap(),
+            Action::Drag {
+                coordinate: (7, 8).into()
+            }
+        );
+    }
+}
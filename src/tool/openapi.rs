@@ -0,0 +1,624 @@
+//! [`Tool`]s from [OpenAPI] operations. [`Tool::from_openapi`] converts an
+//! operation's parameters and JSON request body into an input schema and
+//! returns an [`Operation`], a [`Handler`] which makes the HTTP request.
+//!
+//! ```
+//! use misanthropic::{json, tool::ToolBox, Tool};
+//!
+//! let spec = json!({
+//!     "openapi": "3.0.0",
+//!     "servers": [{"url": "https://petstore.example.com/v1"}],
+//!     "paths": {
+//!         "/pets/{petId}": {
+//!             "get": {
+//!                 "operationId": "showPetById",
+//!                 "summary": "Info for a specific pet",
+//!                 "parameters": [{
+//!                     "name": "petId",
+//!                     "in": "path",
+//!                     "required": true,
+//!                     "schema": {"type": "string"}
+//!                 }]
+//!             }
+//!         }
+//!     }
+//! });
+//!
+//! let (tool, operation) = Tool::from_openapi(&spec, "showPetById")?;
+//! let operation = operation.try_header("authorization", "Bearer secret")?;
+//! let toolbox = ToolBox::new().add(tool, operation);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Only what is needed to call an operation is supported. The spec must be
+//! JSON (or converted to a [`serde_json::Value`]). Local `$ref`s are resolved
+//! but remote ones are not. Cookie parameters are ignored. Array query
+//! parameters are sent as repeated keys.
+//!
+//! [OpenAPI]: <https://www.openapis.org/>
+use std::borrow::Cow;
+
+use serde_json::{json, Map, Value};
+
+use super::{Handler, HandlerFuture, Tool, ToolBuildError};
+use crate::client::InvalidHeader;
+
+/// HTTP methods an OpenAPI path item can have.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Name of the input property for the request body.
+pub const BODY: &str = "body";
+
+/// Errors from [`Tool::from_openapi`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// No operation has the requested `operationId`.
+    #[error("Operation `{operation_id}` not found.")]
+    #[allow(missing_docs)]
+    NotFound { operation_id: String },
+    /// The generated [`Tool`] is invalid.
+    #[error("Invalid tool: {error}")]
+    Build {
+        #[from]
+        /// Error building the [`Tool`].
+        error: ToolBuildError,
+    },
+}
+
+/// Where a parameter goes in the request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Location {
+    Path,
+    Query,
+    Header,
+}
+
+/// An OpenAPI operation. This is a [`Handler`] which makes the HTTP request
+/// with the [`Use::input`] and returns the response body. Responses with an
+/// error status are returned as errors, with the status. See the [module]
+/// documentation.
+///
+/// [`Use::input`]: crate::tool::Use::input
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct Operation {
+    client: reqwest::Client,
+    method: reqwest::Method,
+    base_url: String,
+    path: String,
+    params: Vec<(String, Location)>,
+    headers: reqwest::header::HeaderMap,
+}
+
+impl Operation {
+    /// Set the base URL which the operation's path is appended to. The
+    /// default is the first of the spec's `servers`, if any.
+    pub fn base_url<S>(mut self, base_url: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Use a [`reqwest::Client`], for example one with a timeout.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Add a header to every request, such as `authorization`.
+    pub fn header(
+        mut self,
+        name: reqwest::header::HeaderName,
+        value: reqwest::header::HeaderValue,
+    ) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Parse and add a header to every request. See [`Self::header`].
+    pub fn try_header<K, V>(
+        self,
+        name: K,
+        value: V,
+    ) -> std::result::Result<Self, InvalidHeader>
+    where
+        reqwest::header::HeaderName: TryFrom<K>,
+        <reqwest::header::HeaderName as TryFrom<K>>::Error:
+            Into<reqwest::header::InvalidHeaderName>,
+        reqwest::header::HeaderValue: TryFrom<V>,
+        <reqwest::header::HeaderValue as TryFrom<V>>::Error:
+            Into<reqwest::header::InvalidHeaderValue>,
+    {
+        let name = reqwest::header::HeaderName::try_from(name)
+            .map_err(|e| InvalidHeader::Name(e.into()))?;
+        let value = reqwest::header::HeaderValue::try_from(value)
+            .map_err(|e| InvalidHeader::Value(e.into()))?;
+
+        Ok(self.header(name, value))
+    }
+
+    /// Build the request for an `input`. The error is for the model.
+    fn request(
+        &self,
+        input: &Value,
+    ) -> std::result::Result<reqwest::RequestBuilder, String> {
+        let mut path = self.path.clone();
+        let mut query = vec![];
+        let mut headers = self.headers.clone();
+
+        for (name, location) in &self.params {
+            let Some(value) = input.get(name).filter(|v| !v.is_null()) else {
+                continue;
+            };
+
+            match location {
+                Location::Path => {
+                    path = path.replace(
+                        &format!("{{{name}}}"),
+                        &encode_segment(&to_param(value)),
+                    );
+                }
+                Location::Query => match value {
+                    Value::Array(values) => query.extend(
+                        values.iter().map(|v| (name.as_str(), to_param(v))),
+                    ),
+                    value => query.push((name.as_str(), to_param(value))),
+                },
+                Location::Header => {
+                    let value = to_param(value);
+                    match (
+                        reqwest::header::HeaderName::try_from(name.as_str()),
+                        reqwest::header::HeaderValue::try_from(value.as_ref()),
+                    ) {
+                        (Ok(name), Ok(value)) => {
+                            headers.insert(name, value);
+                        }
+                        _ => return Err(format!("Invalid header `{name}`.")),
+                    }
+                }
+            }
+        }
+
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        let url = reqwest::Url::parse(&url)
+            .map_err(|e| format!("Invalid URL `{url}`: {e}"))?;
+
+        let mut request = self
+            .client
+            .request(self.method.clone(), url)
+            .headers(headers)
+            .query(&query);
+        if let Some(body) = input.get(BODY) {
+            request = request.json(body);
+        }
+
+        Ok(request)
+    }
+}
+
+impl Handler for Operation {
+    fn call(&self, input: Value) -> HandlerFuture {
+        let request = self.request(&input);
+
+        Box::pin(async move {
+            let response = request?.send().await.map_err(|e| e.to_string())?;
+            let status = response.status();
+            let text = response.text().await.map_err(|e| e.to_string())?;
+
+            match status.is_success() {
+                true => Ok(text.into()),
+                false => Err(format!("HTTP {status}: {text}").into()),
+            }
+        })
+    }
+}
+
+impl Tool<'static> {
+    /// Create a [`Tool`] from the [OpenAPI] operation with the given
+    /// `operation_id` and an [`Operation`] to handle it. The input schema has
+    /// a property for each parameter and a [`BODY`] property for a JSON
+    /// request body. See the [module] documentation for an example.
+    ///
+    /// [OpenAPI]: <https://www.openapis.org/>
+    /// [module]: crate::tool::openapi
+    pub fn from_openapi(
+        spec: &Value,
+        operation_id: &str,
+    ) -> std::result::Result<(Self, Operation), Error> {
+        let not_found = || Error::NotFound {
+            operation_id: operation_id.to_string(),
+        };
+
+        let (path, item, method, operation) = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .flat_map(|(path, item)| {
+                METHODS.iter().filter_map(move |method| {
+                    Some((path, item, *method, item.get(*method)?))
+                })
+            })
+            .find(|(_, _, _, operation)| {
+                operation.get("operationId").and_then(Value::as_str)
+                    == Some(operation_id)
+            })
+            .ok_or_else(not_found)?;
+
+        let mut properties = Map::new();
+        let mut required = vec![];
+        let mut params = vec![];
+
+        // Operation parameters override path item parameters.
+        let all_params: Vec<Value> = [item, operation]
+            .iter()
+            .filter_map(|v| v.get("parameters")?.as_array())
+            .flatten()
+            .map(|param| resolve(spec, param, &mut vec![]))
+            .collect();
+        for param in &all_params {
+            let (Some(name), Some(location)) = (
+                param.get("name").and_then(Value::as_str),
+                param.get("in").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            let location = match location {
+                "path" => Location::Path,
+                "query" => Location::Query,
+                "header" => Location::Header,
+                _ => continue,
+            };
+            let overridden = all_params
+                .iter()
+                .rev()
+                .find(|p| {
+                    p.get("name").and_then(Value::as_str) == Some(name)
+                        && p.get("in") == param.get("in")
+                })
+                .is_some_and(|p| !std::ptr::eq(p, param));
+            if overridden {
+                continue;
+            }
+
+            let mut schema = param
+                .get("schema")
+                .or_else(|| media_schema(param.get("content")?))
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            if let (Some(schema), Some(description)) =
+                (schema.as_object_mut(), param.get("description"))
+            {
+                schema
+                    .entry("description")
+                    .or_insert_with(|| description.clone());
+            }
+
+            if location == Location::Path
+                || param.get("required").and_then(Value::as_bool) == Some(true)
+            {
+                required.push(Value::from(name));
+            }
+            properties.insert(name.to_string(), schema);
+            params.push((name.to_string(), location));
+        }
+
+        if let Some(body) = operation.get("requestBody") {
+            let body = resolve(spec, body, &mut vec![]);
+            if let Some(schema) =
+                body.get("content").and_then(media_schema).cloned()
+            {
+                if body.get("required").and_then(Value::as_bool) == Some(true) {
+                    required.push(Value::from(BODY));
+                }
+                properties.insert(BODY.to_string(), schema);
+            }
+        }
+
+        let text = |key| operation.get(key).and_then(Value::as_str);
+        let description = match (text("summary"), text("description")) {
+            (Some(summary), Some(description)) => {
+                format!("{summary}\n\n{description}")
+            }
+            (Some(text), None) | (None, Some(text)) => text.to_string(),
+            (None, None) => format!("{} {}", method.to_uppercase(), path),
+        };
+
+        let tool = Tool::builder(tool_name(operation_id))
+            .description(description)
+            .schema(json!({
+                "type": "object",
+                "properties": properties,
+                "required": required,
+            }))
+            .build()?;
+
+        let base_url = spec
+            .pointer("/servers/0/url")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let operation = Operation {
+            client: reqwest::Client::new(),
+            method: reqwest::Method::from_bytes(
+                method.to_uppercase().as_bytes(),
+            )
+            // All `METHODS` are valid.
+            .unwrap_or(reqwest::Method::GET),
+            base_url,
+            path: path.clone(),
+            params,
+            headers: reqwest::header::HeaderMap::new(),
+        };
+
+        Ok((tool, operation))
+    }
+}
+
+/// The schema of the JSON media type in a `content` map, or of the first
+/// media type if there is no JSON one.
+fn media_schema(content: &Value) -> Option<&Value> {
+    let content = content.as_object()?;
+    content
+        .get("application/json")
+        .or_else(|| content.values().next())?
+        .get("schema")
+}
+
+/// Replace local `$ref`s (like `#/components/schemas/Pet`) with what they
+/// refer to. Refs which can't be resolved, or refer to a schema which is
+/// already being resolved (a recursive schema), are replaced with an empty
+/// schema, which accepts anything. `resolving` is the stack of refs being
+/// resolved.
+fn resolve<'a>(
+    spec: &'a Value,
+    value: &'a Value,
+    resolving: &mut Vec<&'a str>,
+) -> Value {
+    match value {
+        Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(Value::as_str)
+            {
+                let target = reference
+                    .strip_prefix('#')
+                    .filter(|_| !resolving.contains(&reference))
+                    .and_then(|pointer| spec.pointer(pointer));
+                let Some(target) = target else {
+                    return json!({});
+                };
+
+                resolving.push(reference);
+                let resolved = resolve(spec, target, resolving);
+                resolving.pop();
+                return resolved;
+            }
+
+            Value::Object(
+                object
+                    .iter()
+                    .map(|(k, v)| (k.clone(), resolve(spec, v, resolving)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(
+            values.iter().map(|v| resolve(spec, v, resolving)).collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Tool names must match `^[a-zA-Z0-9_-]{1,64}$`.
+fn tool_name(operation_id: &str) -> String {
+    operation_id
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            false => '_',
+        })
+        .take(64)
+        .collect()
+}
+
+/// A parameter value as a string. Strings are not quoted.
+fn to_param(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Percent-encode a path segment.
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => encoded.push(byte as char),
+            byte => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "servers": [{"url": "https://petstore.example.com/v1/"}],
+            "paths": {
+                "/pets": {
+                    "parameters": [{"$ref": "#/components/parameters/Trace"}],
+                    "post": {
+                        "operationId": "create.pet",
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "$ref": "#/components/schemas/Pet"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "/pets/{petId}": {
+                    "get": {
+                        "operationId": "showPetById",
+                        "summary": "Info for a specific pet",
+                        "parameters": [
+                            {
+                                "name": "petId",
+                                "in": "path",
+                                "description": "The pet's id",
+                                "schema": {"type": "string"}
+                            },
+                            {
+                                "name": "tags",
+                                "in": "query",
+                                "schema": {
+                                    "type": "array",
+                                    "items": {"type": "string"}
+                                }
+                            },
+                            {
+                                "name": "session",
+                                "in": "cookie",
+                                "schema": {"type": "string"}
+                            }
+                        ]
+                    }
+                }
+            },
+            "components": {
+                "parameters": {
+                    "Trace": {
+                        "name": "x-trace",
+                        "in": "header",
+                        "schema": {"type": "string"}
+                    }
+                },
+                "schemas": {
+                    "Pet": {
+                        "type": "object",
+                        "properties": {
+                            "name": {"type": "string"},
+                            "parent": {"$ref": "#/components/schemas/Pet"}
+                        },
+                        "required": ["name"]
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_from_openapi() {
+        let (tool, operation) =
+            Tool::from_openapi(&spec(), "showPetById").unwrap();
+        assert_eq!(tool.name, "showPetById");
+        assert_eq!(tool.description, "Info for a specific pet");
+        assert_eq!(
+            tool.input_schema,
+            json!({
+                "type": "object",
+                "properties": {
+                    "petId": {
+                        "type": "string",
+                        "description": "The pet's id"
+                    },
+                    "tags": {
+                        "type": "array",
+                        "items": {"type": "string"}
+                    }
+                },
+                // Path parameters are always required.
+                "required": ["petId"]
+            })
+        );
+        assert_eq!(operation.method, reqwest::Method::GET);
+
+        let (tool, operation) =
+            Tool::from_openapi(&spec(), "create.pet").unwrap();
+        assert_eq!(tool.name, "create_pet");
+        assert_eq!(tool.description, "POST /pets");
+        assert_eq!(tool.input_schema["required"], json!([BODY]));
+        assert_eq!(
+            tool.input_schema["properties"]["x-trace"],
+            json!({"type": "string"})
+        );
+        assert_eq!(
+            tool.input_schema["properties"][BODY]["properties"]["name"],
+            json!({"type": "string"})
+        );
+        assert_eq!(operation.params, [("x-trace".into(), Location::Header)]);
+
+        assert!(matches!(
+            Tool::from_openapi(&spec(), "deletePet"),
+            Err(Error::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_request() {
+        let (_, operation) =
+            Tool::from_openapi(&spec(), "showPetById").unwrap();
+        let operation = operation.try_header("authorization", "key").unwrap();
+
+        let request = operation
+            .request(&json!({"petId": "a b/c", "tags": ["x", "y"]}))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.method(), reqwest::Method::GET);
+        assert_eq!(
+            request.url().as_str(),
+            "https://petstore.example.com/v1/pets/a%20b%2Fc?tags=x&tags=y"
+        );
+        assert_eq!(request.headers()["authorization"], "key");
+
+        let (_, operation) = Tool::from_openapi(&spec(), "create.pet").unwrap();
+        let request = operation
+            .base_url("http://localhost:8080")
+            .request(&json!({"x-trace": 42, "body": {"name": "Rex"}}))
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:8080/pets");
+        assert_eq!(request.headers()["x-trace"], "42");
+        assert_eq!(
+            request.body().and_then(|b| b.as_bytes()),
+            Some(br#"{"name":"Rex"}"#.as_slice())
+        );
+
+        // A spec without servers needs a base URL.
+        let (_, operation) = Tool::from_openapi(&spec(), "create.pet").unwrap();
+        let operation = operation.base_url("");
+        assert!(operation.request(&json!({})).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let spec = spec();
+        let reference = json!({"$ref": "#/components/schemas/Pet"});
+        let pet = resolve(&spec, &reference, &mut vec![]);
+        assert_eq!(pet["required"], json!(["name"]));
+        // Recursion stops at the first repeated ref.
+        assert_eq!(pet["properties"]["parent"], json!({}));
+
+        let remote = json!({"$ref": "other.json#/Pet"});
+        assert_eq!(resolve(&spec, &remote, &mut vec![]), json!({}));
+    }
+}
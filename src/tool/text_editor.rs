@@ -0,0 +1,587 @@
+//! Implementation of Anthropic's [text editor tool]
+//! (`str_replace_based_edit_tool`) over an abstract [`FileStore`]. The
+//! [`TextEditor`] parses the model's [`Command`]s, applies them, and returns
+//! ready-made [`tool::Result`]s, including the error messages the model
+//! expects when a command can't be applied.
+//!
+//! [text editor tool]: <https://docs.anthropic.com/en/docs/agents-and-tools/tool-use/text-editor-tool>
+//! [`tool::Result`]: super::Result
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Handler, Use};
+
+/// Name of the text editor tool. The model uses this name in its [`Use`]s.
+pub const NAME: &str = "str_replace_based_edit_tool";
+
+/// Storage for the files a [`TextEditor`] edits. Paths are as given by the
+/// model.
+pub trait FileStore {
+    /// Read a file.
+    fn read(&self, path: &str) -> std::io::Result<String>;
+    /// Write a file, creating it if necessary.
+    fn write(&mut self, path: &str, contents: &str) -> std::io::Result<()>;
+    /// Returns true if a file (or directory) exists at `path`.
+    fn exists(&self, path: &str) -> bool;
+    /// List the entries of a directory, if `path` is one. The default
+    /// implementation has no directories.
+    fn list(&self, path: &str) -> Option<std::io::Result<Vec<String>>> {
+        let _ = path;
+        None
+    }
+}
+
+/// An in-memory [`FileStore`]. Useful for tests and sandboxes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryStore {
+    /// Files by path.
+    pub files: BTreeMap<String, String>,
+}
+
+impl FileStore for MemoryStore {
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, path)
+        })
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> std::io::Result<()> {
+        self.files.insert(path.to_string(), contents.to_string());
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+}
+
+/// A [`FileStore`] backed by a directory on disk. Paths are relative to the
+/// `root` and may not escape it. Absolute paths are treated as relative to
+/// the `root`. Not available in the browser.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Clone, Debug)]
+pub struct Directory {
+    root: PathBuf,
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl Directory {
+    /// Create a new [`Directory`] store rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolve a model provided `path` inside the root.
+    fn resolve(&self, path: &str) -> std::io::Result<PathBuf> {
+        let mut resolved = self.root.clone();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => resolved.push(part),
+                Component::RootDir | Component::CurDir => {}
+                Component::ParentDir | Component::Prefix(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::PermissionDenied,
+                        format!(
+                            "`{}` is outside the editable directory.",
+                            path
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+impl FileStore for Directory {
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        std::fs::read_to_string(self.resolve(path)?)
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> std::io::Result<()> {
+        let path = self.resolve(path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, contents)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.resolve(path).map(|p| p.exists()).unwrap_or(false)
+    }
+
+    fn list(&self, path: &str) -> Option<std::io::Result<Vec<String>>> {
+        let resolved = self.resolve(path).ok()?;
+        if !resolved.is_dir() {
+            return None;
+        }
+
+        Some(
+            std::fs::read_dir(resolved)
+                .and_then(|entries| {
+                    entries
+                        .map(|entry| {
+                            Ok(entry?
+                                .file_name()
+                                .to_string_lossy()
+                                .into_owned())
+                        })
+                        .collect::<std::io::Result<Vec<_>>>()
+                })
+                .map(|mut entries| {
+                    entries.sort();
+                    entries
+                }),
+        )
+    }
+}
+
+/// A text editor command from the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "command")]
+pub enum Command {
+    /// View a file, or list a directory.
+    View {
+        /// Path to view.
+        path: String,
+        /// Optional 1-indexed, inclusive, line range. An end of `-1` means the
+        /// end of the file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        view_range: Option<[i64; 2]>,
+    },
+    /// Create a new file.
+    Create {
+        /// Path of the new file.
+        path: String,
+        /// Contents of the new file.
+        file_text: String,
+    },
+    /// Replace exactly one occurrence of `old_str` with `new_str`.
+    StrReplace {
+        /// Path of the file.
+        path: String,
+        /// Text to replace. Must appear exactly once.
+        old_str: String,
+        /// Replacement text.
+        #[serde(default)]
+        new_str: String,
+    },
+    /// Insert text after a line.
+    Insert {
+        /// Path of the file.
+        path: String,
+        /// Line to insert after. `0` inserts at the beginning.
+        insert_line: usize,
+        /// Text to insert.
+        #[serde(alias = "insert_text")]
+        new_str: String,
+    },
+}
+
+/// Applies [`Command`]s to a [`FileStore`].
+#[derive(Clone, Debug, Default)]
+pub struct TextEditor<S> {
+    /// The files being edited.
+    pub store: S,
+}
+
+impl<S: FileStore> TextEditor<S> {
+    /// Create a new [`TextEditor`] over `store`.
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Execute a [`Command`]. On success, returns a message for the model. On
+    /// failure, returns an error message for the model.
+    pub fn execute(&mut self, command: &Command) -> Result<String, String> {
+        match command {
+            Command::View { path, view_range } => {
+                if let Some(entries) = self.store.list(path) {
+                    let entries = entries.map_err(|e| io_error(path, e))?;
+                    return Ok(format!(
+                        "Here are the files and directories in {}:\n{}",
+                        path,
+                        entries.join("\n")
+                    ));
+                }
+
+                let text = self.read(path)?;
+                let lines: Vec<&str> = text.lines().collect();
+                let (start, end) = match view_range {
+                    None => (1, lines.len()),
+                    Some([start, end]) => {
+                        let end =
+                            if *end == -1 { lines.len() as i64 } else { *end };
+                        if *start < 1
+                            || end < *start
+                            || end > lines.len() as i64
+                        {
+                            return Err(format!(
+                                "Invalid `view_range` [{}, {}]. The file has \
+                                {} lines.",
+                                start,
+                                end,
+                                lines.len()
+                            ));
+                        }
+                        (*start as usize, end as usize)
+                    }
+                };
+
+                let mut output = format!(
+                    "Here's the result of running `cat -n` on {}:\n",
+                    path
+                );
+                for (n, line) in lines
+                    .iter()
+                    .enumerate()
+                    .take(end)
+                    .skip(start.saturating_sub(1))
+                {
+                    output.push_str(&format!("{:6}\t{}\n", n + 1, line));
+                }
+
+                Ok(output)
+            }
+            Command::Create { path, file_text } => {
+                if self.store.exists(path) {
+                    return Err(format!(
+                        "File already exists at {}. Cannot overwrite files \
+                        using `create`.",
+                        path
+                    ));
+                }
+
+                self.write(path, file_text)?;
+                Ok(format!("File created successfully at {}.", path))
+            }
+            Command::StrReplace {
+                path,
+                old_str,
+                new_str,
+            } => {
+                let text = self.read(path)?;
+                let matches: Vec<usize> = text
+                    .match_indices(old_str.as_str())
+                    .map(|(i, _)| i)
+                    .collect();
+
+                match matches.as_slice() {
+                    [] => Err(format!(
+                        "No replacement was performed. `old_str` did not \
+                        appear verbatim in {}.",
+                        path
+                    )),
+                    [i] => {
+                        let mut edited = String::with_capacity(
+                            text.len() - old_str.len() + new_str.len(),
+                        );
+                        edited.push_str(&text[..*i]);
+                        edited.push_str(new_str);
+                        edited.push_str(&text[i + old_str.len()..]);
+                        self.write(path, &edited)?;
+
+                        Ok(format!("The file {} has been edited.", path))
+                    }
+                    many => {
+                        let lines: Vec<String> = many
+                            .iter()
+                            .map(|i| {
+                                (text[..*i].matches('\n').count() + 1)
+                                    .to_string()
+                            })
+                            .collect();
+                        Err(format!(
+                            "No replacement was performed. Multiple \
+                            occurrences of `old_str` in lines {}. Please \
+                            ensure it is unique.",
+                            lines.join(", ")
+                        ))
+                    }
+                }
+            }
+            Command::Insert {
+                path,
+                insert_line,
+                new_str,
+            } => {
+                let text = self.read(path)?;
+                let mut lines: Vec<&str> = text.lines().collect();
+                if *insert_line > lines.len() {
+                    return Err(format!(
+                        "Invalid `insert_line` {}. The file has {} lines.",
+                        insert_line,
+                        lines.len()
+                    ));
+                }
+
+                lines.splice(*insert_line..*insert_line, new_str.lines());
+                let mut edited = lines.join("\n");
+                if text.ends_with('\n') {
+                    edited.push('\n');
+                }
+                self.write(path, &edited)?;
+
+                Ok(format!("The file {} has been edited.", path))
+            }
+        }
+    }
+
+    /// Handle a [`Use`] of the text editor tool, returning the
+    /// [`tool::Result`] to send back to the model.
+    ///
+    /// [`tool::Result`]: super::Result
+    pub fn handle(&mut self, call: &Use) -> super::Result<'static> {
+        let result = Command::deserialize(&call.input)
+            .map_err(|e| format!("Invalid command: {}", e))
+            .and_then(|command| self.execute(&command));
+
+        let is_error = result.is_err();
+        super::Result {
+            tool_use_id: Cow::Owned(call.id.to_string()),
+            content: result.unwrap_or_else(|e| e).into(),
+            is_error,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<String, String> {
+        self.store.read(path).map_err(|e| io_error(path, e))
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<(), String> {
+        self.store
+            .write(path, contents)
+            .map_err(|e| io_error(path, e))
+    }
+}
+
+impl<S> TextEditor<S>
+where
+    S: FileStore + Send + 'static,
+{
+    /// Convert into a [`Handler`] for a [`ToolBox`]. The editor is shared
+    /// behind a [`Mutex`], so commands are applied one at a time.
+    ///
+    /// [`ToolBox`]: super::ToolBox
+    pub fn into_handler(self) -> impl Handler {
+        let editor = Arc::new(Mutex::new(self));
+        move |input: serde_json::Value| {
+            let result = Command::deserialize(&input)
+                .map_err(|e| format!("Invalid command: {}", e))
+                .and_then(|command| {
+                    editor
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .execute(&command)
+                });
+            std::future::ready(result)
+        }
+    }
+}
+
+fn io_error(path: &str, error: std::io::Error) -> String {
+    match error.kind() {
+        std::io::ErrorKind::NotFound => {
+            format!("The path {} does not exist.", path)
+        }
+        _ => format!("Could not access {}: {}", path, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn editor() -> TextEditor<MemoryStore> {
+        let mut store = MemoryStore::default();
+        store.write("a.txt", "one\ntwo\nthree\ntwo\n").unwrap();
+        TextEditor::new(store)
+    }
+
+    fn command(input: serde_json::Value) -> Command {
+        serde_json::from_value(input).unwrap()
+    }
+
+    #[test]
+    fn test_view() {
+        let mut editor = editor();
+        let output = editor
+            .execute(&command(json!({"command": "view", "path": "a.txt"})))
+            .unwrap();
+        assert!(output.ends_with(
+            "     1\tone\n     2\ttwo\n     3\tthree\n     4\ttwo\n"
+        ));
+
+        let output = editor
+            .execute(&command(json!({
+                "command": "view",
+                "path": "a.txt",
+                "view_range": [2, -1],
+            })))
+            .unwrap();
+        assert!(output.ends_with("     2\ttwo\n     3\tthree\n     4\ttwo\n"));
+        assert!(!output.contains("one"));
+
+        assert!(editor
+            .execute(&command(json!({
+                "command": "view",
+                "path": "a.txt",
+                "view_range": [3, 9],
+            })))
+            .is_err());
+
+        assert_eq!(
+            editor
+                .execute(&command(json!({"command": "view", "path": "b.txt"})))
+                .unwrap_err(),
+            "The path b.txt does not exist."
+        );
+    }
+
+    #[test]
+    fn test_create() {
+        let mut editor = editor();
+        editor
+            .execute(&command(json!({
+                "command": "create",
+                "path": "b.txt",
+                "file_text": "new",
+            })))
+            .unwrap();
+        assert_eq!(editor.store.files["b.txt"], "new");
+
+        assert!(editor
+            .execute(&command(json!({
+                "command": "create",
+                "path": "a.txt",
+                "file_text": "overwrite",
+            })))
+            .is_err());
+    }
+
+    #[test]
+    fn test_str_replace() {
+        let mut editor = editor();
+        editor
+            .execute(&command(json!({
+                "command": "str_replace",
+                "path": "a.txt",
+                "old_str": "three",
+                "new_str": "3",
+            })))
+            .unwrap();
+        assert_eq!(editor.store.files["a.txt"], "one\ntwo\n3\ntwo\n");
+
+        let err = editor
+            .execute(&command(json!({
+                "command": "str_replace",
+                "path": "a.txt",
+                "old_str": "two",
+                "new_str": "2",
+            })))
+            .unwrap_err();
+        assert!(err.contains("lines 2, 4"), "{}", err);
+
+        assert!(editor
+            .execute(&command(json!({
+                "command": "str_replace",
+                "path": "a.txt",
+                "old_str": "four",
+            })))
+            .is_err());
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut editor = editor();
+        editor
+            .execute(&command(json!({
+                "command": "insert",
+                "path": "a.txt",
+                "insert_line": 0,
+                "new_str": "zero",
+            })))
+            .unwrap();
+        editor
+            .execute(&command(json!({
+                "command": "insert",
+                "path": "a.txt",
+                "insert_line": 5,
+                "insert_text": "five\nsix",
+            })))
+            .unwrap();
+        assert_eq!(
+            editor.store.files["a.txt"],
+            "zero\none\ntwo\nthree\ntwo\nfive\nsix\n"
+        );
+
+        assert!(editor
+            .execute(&command(json!({
+                "command": "insert",
+                "path": "a.txt",
+                "insert_line": 100,
+                "new_str": "nope",
+            })))
+            .is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let mut editor = editor();
+        let call = Use {
+            id: "toolu_1".into(),
+            name: NAME.into(),
+            input: json!({"command": "delete", "path": "a.txt"}),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        };
+
+        let result = editor.handle(&call);
+        assert!(result.is_error);
+        assert_eq!(result.tool_use_id, "toolu_1");
+    }
+
+    #[test]
+    fn test_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut editor = TextEditor::new(Directory::new(dir.path()));
+
+        editor
+            .execute(&command(json!({
+                "command": "create",
+                "path": "/sub/a.txt",
+                "file_text": "hello",
+            })))
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("sub/a.txt")).unwrap(),
+            "hello"
+        );
+
+        let output = editor
+            .execute(&command(json!({"command": "view", "path": "sub"})))
+            .unwrap();
+        assert!(output.ends_with("a.txt"));
+
+        assert!(editor
+            .execute(&command(json!({
+                "command": "create",
+                "path": "../escape.txt",
+                "file_text": "nope",
+            })))
+            .is_err());
+    }
+}
@@ -0,0 +1,718 @@
+//! A [`ToolBox`] of [`Tool`]s and the [`Handler`]s that implement them.
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{
+    future::{BoxFuture, Either},
+    FutureExt,
+};
+
+use crate::prompt::{message::Content, Message};
+
+use super::{Result, Tool, Use};
+
+/// [`Future`] returned by a [`Handler`]. On success, the [`Content`] is sent
+/// back to the model. On failure, the [`Content`] is also sent back to the
+/// model, but with [`is_error`] set.
+///
+/// [`is_error`]: Result::is_error
+pub type HandlerFuture =
+    BoxFuture<'static, std::result::Result<Content<'static>, Content<'static>>>;
+
+/// Implementation of a [`Tool`]. This is implemented for async closures (and
+/// functions) taking the [`Use::input`] and returning a [`std::result::Result`]
+/// where both variants can be converted into [`Content`].
+///
+/// ```
+/// use misanthropic::{exports::serde_json::Value, json, tool::ToolBox, Tool};
+///
+/// let tool = Tool::builder("shout")
+///     .description("Returns the input in uppercase.")
+///     .schema(json!({
+///         "type": "object",
+///         "properties": {"text": {"type": "string"}},
+///         "required": ["text"],
+///     }))
+///     .build()
+///     .unwrap();
+///
+/// let toolbox = ToolBox::new().add(tool, |input: Value| async move {
+///     match input["text"].as_str() {
+///         Some(text) => Ok(text.to_uppercase()),
+///         None => Err("`text` must be a string.".to_string()),
+///     }
+/// });
+///
+/// assert!(toolbox.get("shout").is_some());
+/// ```
+pub trait Handler: Send + Sync {
+    /// Call the tool with the [`Use::input`].
+    fn call(&self, input: serde_json::Value) -> HandlerFuture;
+}
+
+impl<F, Fut, T, E> Handler for F
+where
+    F: Fn(serde_json::Value) -> Fut + Send + Sync,
+    Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+    T: Into<Content<'static>>,
+    E: Into<Content<'static>>,
+{
+    fn call(&self, input: serde_json::Value) -> HandlerFuture {
+        let fut = self(input);
+        Box::pin(async move { fut.await.map(Into::into).map_err(Into::into) })
+    }
+}
+
+static_assertions::assert_obj_safe!(Handler);
+
+/// A [`Tool`] and its [`Handler`].
+#[derive(Clone)]
+struct Entry {
+    tool: Tool<'static>,
+    handler: Arc<dyn Handler>,
+    timeout: Option<Duration>,
+}
+
+/// Successful results by [`call_hash`], with the input to check for hash
+/// collisions. See [`ToolBox::dedup_calls`].
+type CallCache =
+    HashMap<u64, Vec<(String, serde_json::Value, Content<'static>)>>;
+
+/// A collection of [`Tool`]s and their [`Handler`]s. Use this to provide the
+/// [`Prompt::tools`] and to dispatch the model's [`Use`]s to the right
+/// [`Handler`].
+///
+/// Tools are kept in the order they were added. Adding a [`Tool`] with the
+/// same name as an existing one replaces it.
+///
+/// When the model calls several tools at once, [`handle`] runs them
+/// concurrently. A slow [`Tool`] can be given a [`timeout`], or all of them a
+/// [`default_timeout`]. With [`catch_panics`], a panicking [`Handler`] becomes
+/// an error [`Result`] rather than taking down the agent. With
+/// [`dedup_calls`], a repeated call is answered with the earlier result
+/// instead of running the [`Handler`] again.
+///
+/// [`handle`]: ToolBox::handle
+/// [`timeout`]: ToolBox::timeout
+/// [`default_timeout`]: ToolBox::default_timeout
+/// [`catch_panics`]: ToolBox::catch_panics
+/// [`dedup_calls`]: ToolBox::dedup_calls
+///
+/// [`Prompt::tools`]: crate::Prompt::tools
+#[derive(Clone, Default)]
+pub struct ToolBox {
+    entries: Vec<Entry>,
+    default_timeout: Option<Duration>,
+    catch_panics: bool,
+    #[cfg(feature = "validate-schema")]
+    validate_inputs: bool,
+    call_cache: Option<Arc<Mutex<CallCache>>>,
+}
+
+static_assertions::assert_impl_all!(ToolBox: Send, Sync);
+
+impl ToolBox {
+    /// Create an empty [`ToolBox`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a [`Tool`] and its [`Handler`]. If a [`Tool`] with the same name
+    /// already exists, it is replaced.
+    pub fn add<H>(mut self, tool: Tool<'static>, handler: H) -> Self
+    where
+        H: Handler + 'static,
+    {
+        self.insert(tool, handler);
+        self
+    }
+
+    /// Insert a [`Tool`] and its [`Handler`]. If a [`Tool`] with the same name
+    /// already exists, it is replaced and the old [`Tool`] returned.
+    pub fn insert<H>(
+        &mut self,
+        tool: Tool<'static>,
+        handler: H,
+    ) -> Option<Tool<'static>>
+    where
+        H: Handler + 'static,
+    {
+        let entry = Entry {
+            tool,
+            handler: Arc::new(handler),
+            timeout: None,
+        };
+
+        match self.position(&entry.tool.name) {
+            Some(i) => {
+                Some(std::mem::replace(&mut self.entries[i], entry).tool)
+            }
+            None => {
+                self.entries.push(entry);
+                None
+            }
+        }
+    }
+
+    /// Set a `timeout` for calls to the [`Tool`] named `name`. If a call takes
+    /// longer, it is abandoned and the model is told it timed out. Does
+    /// nothing if there is no such [`Tool`]. Replacing the [`Tool`] clears the
+    /// timeout.
+    pub fn timeout(mut self, name: &str, timeout: Duration) -> Self {
+        if let Some(i) = self.position(name) {
+            self.entries[i].timeout = Some(timeout);
+        }
+        self
+    }
+
+    /// Set a `timeout` for calls to any [`Tool`] without its own [`timeout`].
+    ///
+    /// [`timeout`]: ToolBox::timeout
+    pub fn default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// If `catch` is true, a [`Handler`] that panics is reported to the model
+    /// as an error [`Result`] instead of unwinding into the caller. The default
+    /// is false.
+    ///
+    /// This has no effect if panics abort, as they do in this crate's release
+    /// profile.
+    pub fn catch_panics(mut self, catch: bool) -> Self {
+        self.catch_panics = catch;
+        self
+    }
+
+    /// If `validate` is true, each [`Use::input`] is validated with
+    /// [`Tool::validate_input`] before calling the [`Handler`]. Invalid input
+    /// is reported to the model as an error [`Result`] so it can try again,
+    /// and the [`Handler`] is not called. The default is false.
+    #[cfg(feature = "validate-schema")]
+    pub fn validate_inputs(mut self, validate: bool) -> Self {
+        self.validate_inputs = validate;
+        self
+    }
+
+    /// If `dedup` is true, a call with the same [`Tool`] name and input as an
+    /// earlier successful call is answered with the earlier [`Result`]
+    /// content instead of calling the [`Handler`] again. This keeps a model
+    /// retrying a call from repeating side effects, like sending an email
+    /// twice. Input is compared as JSON, so the order of object keys does not
+    /// matter. The default is false.
+    ///
+    /// Errors are not remembered, so failed calls can be retried. Identical
+    /// calls made at the same time may both run. Clones of the [`ToolBox`]
+    /// share the remembered results. See [`ToolBox::clear_calls`].
+    pub fn dedup_calls(mut self, dedup: bool) -> Self {
+        self.call_cache = dedup.then(Default::default);
+        self
+    }
+
+    /// Forget the results remembered by [`ToolBox::dedup_calls`], for example
+    /// at the start of a new task.
+    pub fn clear_calls(&self) {
+        if let Some(cache) = &self.call_cache {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+    }
+
+    /// The remembered result of an identical earlier `call`, if any.
+    fn remembered(&self, call: &Use<'_>) -> Option<Content<'static>> {
+        let cache = self.call_cache.as_ref()?;
+        // The cache is always consistent, even if another thread panicked.
+        let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(&call_hash(&call.name, &call.input))?
+            .iter()
+            .find(|(name, input, _)| *name == call.name && *input == call.input)
+            .map(|(_, _, content)| content.clone())
+    }
+
+    /// Remember the `content` of a successful `call`.
+    fn remember(&self, call: &Use<'_>, content: &Content<'static>) {
+        let Some(cache) = &self.call_cache else {
+            return;
+        };
+        let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .entry(call_hash(&call.name, &call.input))
+            .or_default()
+            .push((call.name.to_string(), call.input.clone(), content.clone()));
+    }
+
+    /// Remove a [`Tool`] by name, returning it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Tool<'static>> {
+        let i = self.position(name)?;
+        Some(self.entries.remove(i).tool)
+    }
+
+    /// Get a [`Tool`] by name.
+    pub fn get(&self, name: &str) -> Option<&Tool<'static>> {
+        self.position(name).map(|i| &self.entries[i].tool)
+    }
+
+    /// Iterate over the [`Tool`]s, for example to pass to [`Prompt::tools`]
+    /// with `toolbox.tools().cloned()`.
+    ///
+    /// [`Prompt::tools`]: crate::Prompt::tools
+    pub fn tools(&self) -> impl Iterator<Item = &Tool<'static>> {
+        self.entries.iter().map(|entry| &entry.tool)
+    }
+
+    /// Returns the number of [`Tool`]s.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if there are no [`Tool`]s.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.tool.name == name)
+    }
+
+    /// Call the [`Handler`] for a [`Use`] and return the [`Result`]. If there
+    /// is no such [`Tool`], the [`Result`] is an error telling the model so.
+    pub async fn call(&self, call: &Use<'_>) -> Result<'static> {
+        if let Some(content) = self.remembered(call) {
+            #[cfg(feature = "log")]
+            log::debug!(
+                "Repeated call to `{}`. Using earlier result.",
+                call.name
+            );
+            return Result {
+                tool_use_id: Cow::Owned(call.id.to_string()),
+                content,
+                is_error: false,
+                #[cfg(feature = "prompt-caching")]
+                cache_control: None,
+            };
+        }
+
+        let result = match self.position(&call.name) {
+            Some(i) => {
+                let entry = &self.entries[i];
+                #[cfg(feature = "validate-schema")]
+                if self.validate_inputs {
+                    if let Err(error) = entry.tool.validate_input(call) {
+                        #[cfg(feature = "log")]
+                        log::warn!("{error}");
+                        return error.to_result(call);
+                    }
+                }
+                let handler = &entry.handler;
+                let input = call.input.clone();
+                // The handler is called inside the future so a panic before
+                // the handler's own future is created is also caught.
+                let fut = async move { handler.call(input).await };
+                let fut = if self.catch_panics {
+                    std::panic::AssertUnwindSafe(fut)
+                        .catch_unwind()
+                        .map(|result| {
+                            result.unwrap_or_else(|payload| {
+                                Err(panic_message(&call.name, payload).into())
+                            })
+                        })
+                        .boxed()
+                } else {
+                    fut.boxed()
+                };
+                match entry.timeout.or(self.default_timeout) {
+                    Some(timeout) => {
+                        let delay = futures_timer::Delay::new(timeout);
+                        match futures::future::select(fut, delay).await {
+                            Either::Left((result, _)) => result,
+                            Either::Right(_) => {
+                                #[cfg(feature = "log")]
+                                log::warn!(
+                                    "Tool `{}` timed out after {:?}.",
+                                    call.name,
+                                    timeout
+                                );
+                                Err(format!(
+                                    "Tool `{}` timed out after {:?}.",
+                                    call.name, timeout
+                                )
+                                .into())
+                            }
+                        }
+                    }
+                    None => fut.await,
+                }
+            }
+            None => {
+                #[cfg(feature = "log")]
+                log::warn!("Model called unknown tool `{}`.", call.name);
+                Err(format!("Tool `{}` not found.", call.name).into())
+            }
+        };
+
+        let is_error = result.is_err();
+        let content = result.unwrap_or_else(|content| content);
+        if !is_error {
+            self.remember(call, &content);
+        }
+
+        Result {
+            tool_use_id: Cow::Owned(call.id.to_string()),
+            content,
+            is_error,
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }
+    }
+
+    /// Call every [`Use`] in a [`Message`] concurrently and return a [`User`]
+    /// [`Message`] with the [`Result`]s in the same order. Returns [`None`] if
+    /// there are no [`Use`]s in the [`Message`]. The calls run on the current
+    /// task, so this takes about as long as the slowest call (or its
+    /// [`timeout`]).
+    ///
+    /// [`timeout`]: ToolBox::timeout
+    ///
+    /// [`User`]: crate::prompt::message::Role::User
+    pub async fn handle(
+        &self,
+        message: &Message<'_>,
+    ) -> Option<Message<'static>> {
+        let calls: Vec<&Use> = message.tool_uses().collect();
+
+        if calls.is_empty() {
+            return None;
+        }
+
+        let results =
+            futures::future::join_all(calls.into_iter().map(|c| self.call(c)))
+                .await;
+
+        Some(Message::tool_results(results))
+    }
+}
+
+/// Hash a call's tool `name` and `input`. Object keys are hashed in sorted
+/// order, so equal inputs hash equally regardless of key order.
+fn call_hash(name: &str, input: &serde_json::Value) -> u64 {
+    fn hash_value(value: &serde_json::Value, hasher: &mut DefaultHasher) {
+        use serde_json::Value;
+
+        std::mem::discriminant(value).hash(hasher);
+        match value {
+            Value::Null => {}
+            Value::Bool(b) => b.hash(hasher),
+            Value::Number(n) => n.to_string().hash(hasher),
+            Value::String(s) => s.hash(hasher),
+            Value::Array(values) => {
+                values.len().hash(hasher);
+                values.iter().for_each(|v| hash_value(v, hasher));
+            }
+            Value::Object(map) => {
+                let mut fields: Vec<_> = map.iter().collect();
+                fields.sort_unstable_by_key(|(key, _)| *key);
+                fields.len().hash(hasher);
+                for (key, value) in fields {
+                    key.hash(hasher);
+                    hash_value(value, hasher);
+                }
+            }
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    hash_value(input, &mut hasher);
+    hasher.finish()
+}
+
+/// Describe a caught panic for the model.
+fn panic_message(name: &str, payload: Box<dyn std::any::Any + Send>) -> String {
+    let reason = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+
+    #[cfg(feature = "log")]
+    log::error!("Tool `{}` panicked: {:?}", name, reason);
+
+    match reason {
+        Some(reason) => format!("Tool `{}` panicked: {}", name, reason),
+        None => format!("Tool `{}` panicked.", name),
+    }
+}
+
+impl std::fmt::Debug for ToolBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.tools().map(|tool| &tool.name))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt::message::{Block, Role};
+
+    fn tool(name: &'static str) -> Tool<'static> {
+        Tool::builder(name)
+            .description("A test tool.")
+            .schema(serde_json::json!({
+                "type": "object",
+                "properties": {"text": {"type": "string"}},
+                "required": ["text"],
+            }))
+            .build()
+            .unwrap()
+    }
+
+    fn call(id: &'static str, name: &'static str, text: &str) -> Use<'static> {
+        Use {
+            id: id.into(),
+            name: name.into(),
+            input: serde_json::json!({ "text": text }),
+            #[cfg(feature = "prompt-caching")]
+            cache_control: None,
+        }
+    }
+
+    async fn shout(
+        input: serde_json::Value,
+    ) -> std::result::Result<String, String> {
+        match input["text"].as_str() {
+            Some(text) => Ok(text.to_uppercase()),
+            None => Err("`text` must be a string.".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_toolbox_insert_remove() {
+        let mut toolbox =
+            ToolBox::new().add(tool("a"), shout).add(tool("b"), shout);
+        assert_eq!(toolbox.len(), 2);
+
+        // Replacing keeps the order.
+        assert!(toolbox.insert(tool("a"), shout).is_some());
+        assert_eq!(
+            toolbox.tools().map(|t| t.name.as_ref()).collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+        assert_eq!(format!("{:?}", toolbox), "[\"a\", \"b\"]");
+
+        assert!(toolbox.remove("a").is_some());
+        assert!(toolbox.remove("a").is_none());
+        assert!(toolbox.get("a").is_none());
+        assert!(toolbox.get("b").is_some());
+        assert!(!toolbox.is_empty());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "validate-schema")]
+    async fn test_toolbox_validate_inputs() {
+        let toolbox = ToolBox::new()
+            .add(tool("shout"), |_: serde_json::Value| async move {
+                Err::<String, _>("Handler called.")
+            })
+            .validate_inputs(true);
+
+        let mut bad = call("1", "shout", "");
+        bad.input = serde_json::json!({"text": 42});
+        let result = toolbox.call(&bad).await;
+        assert!(result.is_error);
+        assert!(result
+            .content
+            .to_string()
+            .starts_with("Invalid input for tool `shout`: `/text`:"));
+
+        // Valid input reaches the handler.
+        let result = toolbox.call(&call("2", "shout", "hi")).await;
+        assert_eq!(result.content.to_string(), "Handler called.");
+    }
+
+    #[tokio::test]
+    async fn test_toolbox_call() {
+        let toolbox = ToolBox::new().add(tool("shout"), shout);
+
+        let result = toolbox.call(&call("1", "shout", "hi")).await;
+        assert_eq!(result.tool_use_id, "1");
+        assert_eq!(result.content.to_string(), "HI");
+        assert!(!result.is_error);
+
+        let mut bad = call("2", "shout", "");
+        bad.input = serde_json::json!({});
+        let result = toolbox.call(&bad).await;
+        assert!(result.is_error);
+
+        let result = toolbox.call(&call("3", "whisper", "hi")).await;
+        assert!(result.is_error);
+        assert_eq!(result.content.to_string(), "Tool `whisper` not found.");
+    }
+
+    #[tokio::test]
+    async fn test_toolbox_handle() {
+        let toolbox = ToolBox::new().add(tool("shout"), shout);
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content::from(vec![
+                Block::text("Let me shout."),
+                call("1", "shout", "a").into(),
+                call("2", "shout", "b").into(),
+            ]),
+        };
+
+        let response = toolbox.handle(&message).await.unwrap();
+        assert_eq!(response.role, Role::User);
+        let blocks = response.content;
+        let ids: Vec<_> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::ToolResult { result } => result.tool_use_id.as_ref(),
+                _ => panic!("Expected tool result."),
+            })
+            .collect();
+        assert_eq!(ids, ["1", "2"]);
+
+        let message = Message {
+            role: Role::Assistant,
+            content: "No tools here.".into(),
+        };
+        assert!(toolbox.handle(&message).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_toolbox_timeout() {
+        async fn sleep(
+            input: serde_json::Value,
+        ) -> std::result::Result<String, String> {
+            futures_timer::Delay::new(Duration::from_secs(60)).await;
+            shout(input).await
+        }
+
+        let toolbox = ToolBox::new()
+            .add(tool("sleep"), sleep)
+            .add(tool("shout"), shout)
+            .timeout("sleep", Duration::from_millis(10))
+            .timeout("missing", Duration::from_millis(10));
+
+        let message = Message {
+            role: Role::Assistant,
+            content: Content::from(vec![
+                Block::from(call("1", "sleep", "a")),
+                call("2", "shout", "b").into(),
+            ]),
+        };
+
+        let response = toolbox.handle(&message).await.unwrap();
+        let blocks = response.content;
+        let results: Vec<_> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::ToolResult { result } => result,
+                _ => panic!("Expected tool result."),
+            })
+            .collect();
+        assert_eq!(results[0].tool_use_id, "1");
+        assert!(results[0].is_error);
+        assert_eq!(
+            results[0].content.to_string(),
+            "Tool `sleep` timed out after 10ms."
+        );
+        assert_eq!(results[1].tool_use_id, "2");
+        assert!(!results[1].is_error);
+        assert_eq!(results[1].content.to_string(), "B");
+
+        // Tools without their own timeout use the default.
+        let toolbox = ToolBox::new()
+            .add(tool("sleep"), sleep)
+            .default_timeout(Duration::from_millis(10));
+        let result = toolbox.call(&call("3", "sleep", "c")).await;
+        assert!(result.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_toolbox_catch_panics() {
+        async fn panics(
+            _: serde_json::Value,
+        ) -> std::result::Result<String, String> {
+            panic!("oops");
+        }
+
+        let toolbox = ToolBox::new()
+            .add(tool("panics"), panics)
+            .add(tool("shout"), shout)
+            .catch_panics(true);
+
+        let result = toolbox.call(&call("1", "panics", "a")).await;
+        assert!(result.is_error);
+        assert_eq!(result.content.to_string(), "Tool `panics` panicked: oops");
+
+        // Other tools still work.
+        let result = toolbox.call(&call("2", "shout", "b")).await;
+        assert!(!result.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_toolbox_dedup_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let toolbox = ToolBox::new()
+            .add(tool("send"), move |input: serde_json::Value| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match input["text"].as_str() {
+                        Some("fail") => Err("Failed."),
+                        _ => Ok("Sent."),
+                    }
+                }
+            })
+            .dedup_calls(true);
+
+        let mut first = call("1", "send", "hi");
+        first.input = serde_json::json!({"text": "hi", "to": "bob"});
+        let mut retry = call("2", "send", "hi");
+        retry.input = serde_json::json!({"to": "bob", "text": "hi"});
+
+        let result = toolbox.call(&first).await;
+        assert_eq!(result.content.to_string(), "Sent.");
+        let result = toolbox.call(&retry).await;
+        assert_eq!(result.tool_use_id, "2");
+        assert_eq!(result.content.to_string(), "Sent.");
+        assert!(!result.is_error);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Different input runs again.
+        toolbox.call(&call("3", "send", "bye")).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Errors are not remembered.
+        toolbox.call(&call("4", "send", "fail")).await;
+        let result = toolbox.call(&call("5", "send", "fail")).await;
+        assert!(result.is_error);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        toolbox.clear_calls();
+        toolbox.call(&first).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+
+        assert_eq!(
+            call_hash("send", &first.input),
+            call_hash("send", &retry.input)
+        );
+        assert_ne!(
+            call_hash("send", &first.input),
+            call_hash("other", &first.input)
+        );
+    }
+}
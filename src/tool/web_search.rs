@@ -0,0 +1,54 @@
+//! Types for Anthropic's [web search] server tool. Add the tool with
+//! [`Tool::web_search`]. The model's searches appear as
+//! [`Block::ServerToolUse`] and the results as [`Block::WebSearchToolResult`].
+//! Searches are billed separately from tokens. See
+//! [`Usage::web_search_requests`].
+//!
+//! [web search]: <https://docs.anthropic.com/en/docs/agents-and-tools/tool-use/web-search-tool>
+//! [`Tool::web_search`]: crate::Tool::web_search
+//! [`Block::ServerToolUse`]: crate::prompt::message::Block::ServerToolUse
+//! [`Block::WebSearchToolResult`]: crate::prompt::message::Block::WebSearchToolResult
+//! [`Usage::web_search_requests`]: crate::response::Usage::web_search_requests
+use serde::{Deserialize, Serialize};
+
+/// Name of the web search tool.
+pub const NAME: &str = "web_search";
+/// Type of the web search tool.
+pub const TYPE: &str = "web_search_20250305";
+
+/// Outcome of a web search: either the results or an error.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(untagged)]
+pub enum Outcome {
+    /// The search results.
+    Results(Vec<SearchResult>),
+    /// The search failed.
+    Error(SearchError),
+}
+
+/// A single web search result.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(tag = "type", rename = "web_search_result")]
+pub struct SearchResult {
+    /// URL of the page.
+    pub url: String,
+    /// Title of the page.
+    pub title: String,
+    /// Encrypted page content. This must be sent back unchanged in
+    /// multi-turn conversations for citations to work.
+    pub encrypted_content: String,
+    /// How old the page is, if known. For example `April 30, 2025`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_age: Option<String>,
+}
+
+/// Error from a web search.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(any(feature = "partial-eq", test), derive(PartialEq))]
+#[serde(tag = "type", rename = "web_search_tool_result_error")]
+pub struct SearchError {
+    /// Why, for example `max_uses_exceeded` or `too_many_requests`.
+    pub error_code: String,
+}